@@ -1,4 +1,5 @@
 use criterion::{Criterion, criterion_group, criterion_main};
+use docs_mcp::internal::chunking::ChunkSizer;
 use docs_mcp::internal::chunking::ChunkingConfig;
 use docs_mcp::internal::chunking::chunk_content;
 use docs_mcp::internal::extractor::extract_content;
@@ -12,8 +13,9 @@ pub fn criterion_benchmark(c: &mut Criterion) {
     let test_page = fs::read_to_string(test_page_path).expect("can read test file");
     let content = extract_content(&test_page).unwrap();
     let config = ChunkingConfig::default();
+    let sizer = ChunkSizer::default();
     c.bench_function("chunking", |b| {
-        b.iter(|| chunk_content(black_box(&content), black_box(&config)))
+        b.iter(|| chunk_content(black_box(&content), black_box(&config), &sizer))
     });
 }
 
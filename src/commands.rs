@@ -1,21 +1,35 @@
 use std::borrow::Cow;
+use std::time::Duration;
 
-use anyhow::{Context, Result, bail};
+use anyhow::{Context, Result, anyhow, bail};
 use itertools::Itertools;
-use modelcontextprotocol_server::ServerBuilder;
 use modelcontextprotocol_server::transport::StdioTransport;
-use serde_json::from_value;
-use tokio::runtime::Handle;
-use tokio::task::block_in_place;
+use modelcontextprotocol_server::{ServerBuilder, Transport};
 use tracing::{error, info, warn};
 
 use crate::config::Config;
+use crate::config::settings::EmbeddingBackend;
 use crate::crawler::{CrawlerConfig, INDEX_REGEX, SiteCrawler, validate_url};
-use crate::database::lancedb::vector_store::VectorStore;
+use crate::database::lancedb::vector_store::{MIN_INDEXED_EMBEDDINGS, VectorStore};
 use crate::database::sqlite::Database;
 use crate::database::sqlite::models::{NewSite, Site, SiteStatus, SiteUpdate};
-use crate::database::sqlite::queries::SiteQueries;
-use crate::mcp::tools::{CallToolParams, ToolHandler};
+use crate::database::sqlite::queries::{EmbeddingCacheQueries, SiteQueries};
+use crate::embeddings::create_provider;
+use crate::indexer::{
+    Indexer, chunk_sizer, describe_oversized_chunks, embedded_differently, prune_embedding_cache,
+};
+use crate::mcp::dispatch::{ToolDispatcher, ToolRegistry, ToolTransport};
+use crate::mcp::http::{HttpServerOptions, HttpTransport, MCP_PATH, SSE_PATH};
+use crate::mcp::jobs::IndexingJobs;
+use crate::mcp::query_cache::QueryEmbeddingCache;
+use crate::mcp::reload::{RELOAD_POLL_INTERVAL, Reloadable, ServerReloader};
+use crate::mcp::resources::{
+    COMPLETED_SITES_POLL_INTERVAL, PageResources, ResourceTransport, watch_completed_sites,
+};
+use crate::mcp::tools::{
+    BuildContextHandler, GetContextHandler, GetPageHandler, IndexSiteHandler,
+    IndexingStatusHandler, ListSitesHandler, SearchDocsHandler, ServerStatsHandler,
+};
 
 /// Validation functions for CLI commands
 pub mod validation {
@@ -106,29 +120,52 @@ pub mod validation {
     }
 }
 
-/// Add a new documentation site for indexing with comprehensive progress display
+/// Normalize a documentation index URL
+///
+/// Strips any trailing `index.html`-esque component and ensures directory URLs
+/// end with a trailing slash.
 #[inline]
-pub async fn add_site(
-    url: &str,
-    name: Option<String>,
-    version: Option<String>,
-    base_url: &str,
-    config: &Config,
-    verbose: bool,
-) -> Result<Site> {
-    // Normalize the index url
-    // First strip any trailing `index.html`-esque component
+pub fn normalize_index_url(url: &str) -> Result<Cow<'_, str>> {
     let mut url = Cow::Borrowed(url);
     if INDEX_REGEX.is_match(&url)? {
         url = Cow::Owned(format!("{}/", url.rsplit_once('/').expect("has /").0));
     }
-    // Next ensure we have a trailing slash
     if !url.ends_with('/')
         && let Some((_, path)) = url.rsplit_once('/')
         && !path.contains('.')
     {
-        url = Cow::Owned(format!("{}/", path));
+        url = Cow::Owned(format!("{}/", url));
     }
+    Ok(url)
+}
+
+/// Generate a site name from its URL, used when no name is given
+#[inline]
+pub fn default_site_name(url: &url::Url) -> String {
+    let host = url.host_str().unwrap_or("unknown");
+    let path_segments: Vec<&str> = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+
+    if path_segments.is_empty() {
+        host.to_string()
+    } else {
+        format!("{} {}", host, path_segments.join(" "))
+    }
+}
+
+/// Add a new documentation site for indexing with comprehensive progress display
+#[inline]
+pub async fn add_site(
+    url: &str,
+    name: Option<String>,
+    version: Option<String>,
+    base_url: &str,
+    config: &Config,
+    verbose: bool,
+) -> Result<Site> {
+    let url = normalize_index_url(url)?;
 
     eprintln!("🚀 Adding new documentation site");
     eprintln!("   URL: {}", url);
@@ -153,19 +190,7 @@ pub async fn add_site(
     eprintln!("✅");
 
     // Generate name if not provided
-    let site_name = name.unwrap_or_else(|| {
-        let host = parsed_url.host_str().unwrap_or("unknown");
-        let path_segments: Vec<&str> = parsed_url
-            .path_segments()
-            .map(|segments| segments.collect())
-            .unwrap_or_default();
-
-        if path_segments.is_empty() {
-            host.to_string()
-        } else {
-            format!("{} {}", host, path_segments.join(" "))
-        }
-    });
+    let site_name = name.unwrap_or_else(|| default_site_name(&parsed_url));
     // Default site version to "latest" if not provided
     let site_version = version.as_deref().unwrap_or("latest");
 
@@ -379,6 +404,26 @@ pub async fn list_sites(config: &Config, verbose: bool) -> Result<()> {
                 Err(e) => eprintln!("   Statistics: Error - {}", e),
             }
 
+            if let Some(model) = &site.embedding_model {
+                let stale = if embedded_differently(site, &config.ollama) {
+                    " (differs from configured model, run 'docs-mcp reembed')"
+                } else {
+                    ""
+                };
+                match site.embedding_dimension {
+                    Some(dimension) => eprintln!(
+                        "   Embedding Model: {} ({} dimensions){}",
+                        model, dimension, stale
+                    ),
+                    None => eprintln!("   Embedding Model: {}{}", model, stale),
+                }
+                if let Some(prefix) = &site.embedding_document_prefix
+                    && !prefix.is_empty()
+                {
+                    eprintln!("   Document Prefix: {:?}", prefix);
+                }
+            }
+
             // Show indexing dates
             if let Some(indexed_date) = site.indexed_date {
                 eprintln!(
@@ -608,7 +653,7 @@ pub async fn update_site(site_identifier: String, config: &Config, verbose: bool
     eprintln!("   • Clear all existing crawl queue entries");
     eprintln!("   • Clear all existing indexed content and embeddings");
     eprintln!("   • Re-crawl the entire site from scratch");
-    eprintln!("   • Re-generate all embeddings");
+    eprintln!("   • Re-generate embeddings, reusing cached ones for unchanged content");
     eprintln!();
 
     // Get user confirmation for destructive operation
@@ -679,6 +724,9 @@ pub async fn update_site(site_identifier: String, config: &Config, verbose: bool
         error_message: None,
         last_heartbeat: None,
         indexed_date: None,
+        embedding_model: None,
+        embedding_dimension: None,
+        embedding_document_prefix: None,
     };
 
     SiteQueries::update(database.pool(), site.id, update)
@@ -750,6 +798,9 @@ pub async fn show_status(config: &Config) -> Result<()> {
     let database = match Database::new(&config.database_path()?).await {
         Ok(db) => {
             eprintln!("   ✅ SQLite: Connected");
+            if let Ok(cached) = EmbeddingCacheQueries::count(db.pool()).await {
+                eprintln!("   💾 Embedding Cache: {} embeddings", cached);
+            }
             Some(db)
         }
         Err(e) => {
@@ -758,32 +809,78 @@ pub async fn show_status(config: &Config) -> Result<()> {
         }
     };
 
-    // Ollama connectivity
-    eprintln!("🤖 Ollama Status:");
-    match crate::embeddings::ollama::OllamaClient::new(config.ollama.clone()) {
-        Ok(client) => match client.health_check() {
+    // Embedding server connectivity
+    let server_name = config.ollama.provider.server_name();
+    eprintln!("🤖 Embedding Server Status:");
+    match create_provider(&config.ollama) {
+        Ok(_) if config.ollama.provider == EmbeddingBackend::Hashing => {
+            eprintln!(
+                "   ⚠️  {}: No embedding model, semantic search only matches shared words",
+                server_name
+            );
+            eprintln!(
+                "   🔢 Dimension: {} (searches are best in hybrid or keyword mode)",
+                config.ollama.embedding_dimension
+            );
+        }
+        Ok(embedder) => match embedder.health_check().await {
             Ok(()) => {
                 eprintln!(
-                    "   ✅ Ollama: Connected ({}:{})",
-                    config.ollama.host, config.ollama.port
+                    "   ✅ {}: Connected ({})",
+                    server_name,
+                    config.ollama.location()
                 );
-                eprintln!("   📋 Model: {}", config.ollama.model);
+                eprintln!("   📋 Model: {}", config.ollama.model_name());
                 eprintln!("   🔢 Batch Size: {}", config.ollama.batch_size);
+                match chunk_sizer(&config.ollama, embedder.as_ref()).await {
+                    Ok(sizer) => {
+                        if let Some(window) = sizer.window() {
+                            eprintln!(
+                                "   📏 Chunk Limit: {} tokens ({})",
+                                window,
+                                if sizer.has_tokenizer() {
+                                    "counted by tokenizer"
+                                } else {
+                                    "estimated"
+                                }
+                            );
+                        }
+                        if let Some(warning) = describe_oversized_chunks(
+                            &config.chunking,
+                            &sizer,
+                            config.ollama.model_name(),
+                        ) {
+                            eprintln!("   ⚠️  {}", warning);
+                        }
+                    }
+                    Err(e) => eprintln!("   ❌ Tokenizer: Failed to load - {:#}", e),
+                }
             }
             Err(e) => {
-                eprintln!("   ⚠️  Ollama: Connected but unhealthy - {}", e);
+                eprintln!("   ⚠️  {}: Connected but unhealthy - {}", server_name, e);
             }
         },
         Err(e) => {
-            eprintln!("   ❌ Ollama: Failed to connect - {}", e);
+            eprintln!("   ❌ {}: Failed to connect - {}", server_name, e);
         }
     }
 
     // Vector database status
     eprintln!("🔍 Vector Database Status:");
     match VectorStore::new(config).await {
-        Ok(_store) => {
+        Ok(store) => {
             eprintln!("   ✅ LanceDB: Connected");
+            match store.vector_index_status().await {
+                Ok(Some(index)) => eprintln!(
+                    "   📇 Index: {} ({} indexed, {} unindexed embeddings)",
+                    index.index_type, index.indexed_rows, index.unindexed_rows
+                ),
+                Ok(None) => eprintln!(
+                    "   📇 Index: none, built after {} embeddings",
+                    config.search.index_threshold
+                ),
+                Err(e) => eprintln!("   ⚠️  Index: Failed to read status - {}", e),
+            }
         }
         Err(e) => {
             eprintln!("   ❌ LanceDB: Failed to connect - {}", e);
@@ -844,37 +941,225 @@ pub async fn show_status(config: &Config) -> Result<()> {
     Ok(())
 }
 
+/// Re-embed indexed chunks with the configured embedding model
+///
+/// Every site is re-embedded unless `site_identifier` selects one. Searches
+/// keep using the existing embeddings until the new ones are complete.
+#[inline]
+pub async fn reembed(
+    site_identifier: Option<String>,
+    config: &Config,
+    verbose: bool,
+) -> Result<()> {
+    let site = match site_identifier {
+        Some(site_identifier) => {
+            validation::validate_site_identifier(&site_identifier)
+                .context("Invalid site identifier")?;
+
+            let database = Database::new(config.database_path()?)
+                .await
+                .context("Failed to initialize database")?;
+
+            // Try to find site by ID first, then by name
+            let site = if let Ok(id) = site_identifier.parse::<i64>() {
+                SiteQueries::get_by_id(database.pool(), id).await?
+            } else {
+                let sites = SiteQueries::list_all(database.pool()).await?;
+                sites.into_iter().find(|s| {
+                    s.name
+                        .to_lowercase()
+                        .contains(&site_identifier.to_lowercase())
+                })
+            };
+            Some(site.ok_or_else(|| anyhow!("Site not found: {}", site_identifier))?)
+        }
+        None => None,
+    };
+
+    match &site {
+        Some(site) => eprintln!(
+            "🔁 Re-embedding {} {} (ID: {}) with {}",
+            site.name,
+            site.version,
+            site.id,
+            config.ollama.model_name()
+        ),
+        None => eprintln!(
+            "🔁 Re-embedding all sites with {}",
+            config.ollama.model_name()
+        ),
+    }
+    eprintln!("   Searches keep using the current embeddings until this finishes.");
+    eprintln!();
+
+    let summary = Indexer::new(config.clone(), verbose)
+        .await?
+        .reembed(site.as_ref())
+        .await?;
+
+    eprintln!();
+    eprintln!(
+        "✅ Re-embedded {} chunks of {} sites",
+        summary.chunks, summary.sites
+    );
+    if summary.cached > 0 {
+        eprintln!(
+            "   Reused {} of {} embeddings from the embedding cache ({:.1}%)",
+            summary.cached,
+            summary.chunks,
+            summary.cached as f64 / summary.chunks as f64 * 100.0
+        );
+    }
+    if summary.kept > 0 {
+        eprintln!("   Kept {} embeddings of other sites", summary.kept);
+    }
+
+    Ok(())
+}
+
+/// Rebuild the vector index from all stored embeddings
+///
+/// Indexes are built automatically once enough embeddings are stored, and
+/// later embeddings are merged into them. Rebuilding retrains the index on the
+/// current embeddings, which restores recall after the table has grown a lot.
+#[inline]
+pub async fn rebuild_index(config: &Config) -> Result<()> {
+    let vector_store = VectorStore::new(config)
+        .await
+        .context("Failed to initialize vector store")?;
+
+    let embeddings = vector_store.count_embeddings().await?;
+    if embeddings < MIN_INDEXED_EMBEDDINGS {
+        bail!(
+            "An index needs at least {} embeddings, but only {} are stored",
+            MIN_INDEXED_EMBEDDINGS,
+            embeddings
+        );
+    }
+
+    eprintln!(
+        "🔨 Rebuilding {} vector index for {} embeddings...",
+        config.search.distance_metric, embeddings
+    );
+    vector_store
+        .optimize()
+        .await
+        .context("Failed to optimize vector database")?;
+    vector_store
+        .create_vector_index()
+        .await
+        .context("Failed to rebuild vector index")?;
+
+    if let Some(index) = vector_store.vector_index_status().await? {
+        eprintln!(
+            "✅ Built {} index over {} embeddings",
+            index.index_type, index.indexed_rows
+        );
+    }
+    if embeddings < config.search.index_threshold {
+        eprintln!(
+            "💡 Fewer than index_threshold ({}) embeddings are stored, so small tables are usually faster to scan",
+            config.search.index_threshold
+        );
+    }
+
+    Ok(())
+}
+
+/// Remove cached embeddings which re-indexing can't reuse
+///
+/// Embeddings stay cached after their site is deleted, their page changes or
+/// the model does, so the cache only grows. Pruning keeps the embeddings of
+/// stored chunks under the configured model and prefix.
+#[inline]
+pub async fn prune_cache(config: &Config) -> Result<()> {
+    let database = Database::new(&config.database_path()?).await?;
+
+    let cached = EmbeddingCacheQueries::count(database.pool()).await?;
+    let deleted = prune_embedding_cache(&database, &config.ollama).await?;
+    eprintln!(
+        "🧹 Removed {} of {} cached embeddings not used by {}",
+        deleted,
+        cached,
+        config.ollama.model_name()
+    );
+
+    Ok(())
+}
+
+/// Remove every cached embedding
+#[inline]
+pub async fn clear_cache(config: &Config) -> Result<()> {
+    let database = Database::new(&config.database_path()?).await?;
+
+    let deleted = database.clear_embedding_cache().await?;
+    eprintln!("🧹 Removed {} cached embeddings", deleted);
+
+    Ok(())
+}
+
 /// Start MCP server
+///
+/// Serves over stdio by default, or over HTTP when `http` options are given.
+/// Tool calls run concurrently, each limited to `tool_timeout`.
 #[inline]
-pub async fn serve_mcp(config: &Config) -> Result<()> {
-    info!("Starting MCP server with stdio transport");
+pub async fn serve_mcp(
+    config: &Config,
+    http: Option<HttpServerOptions>,
+    tool_timeout: Duration,
+) -> Result<()> {
+    info!(
+        "Starting MCP server with {} transport",
+        if http.is_some() { "HTTP" } else { "stdio" }
+    );
 
-    // Verify Ollama connectivity before starting
-    match crate::embeddings::ollama::OllamaClient::new(config.ollama.clone()) {
-        Ok(client) => match client.health_check() {
-            Ok(()) => {
-                info!(
-                    "✅ Ollama connected at {}:{} with model {}",
-                    config.ollama.host, config.ollama.port, config.ollama.model
-                );
-            }
-            Err(e) => {
-                warn!("⚠️  Ollama is reachable but unhealthy: {}", e);
-                eprintln!("Warning: Ollama may not be ready. Background indexing may fail.");
-            }
-        },
+    // Verify embedding server connectivity before starting
+    let server_name = config.ollama.provider.server_name();
+    let embedder = match create_provider(&config.ollama) {
+        Ok(embedder) => embedder,
         Err(e) => {
-            error!("❌ Failed to connect to Ollama: {}", e);
+            error!("❌ Failed to connect to {}: {}", server_name, e);
             eprintln!(
-                "Error: Cannot connect to Ollama at {}:{}",
-                config.ollama.host, config.ollama.port
+                "Error: Cannot connect to {} at {}",
+                server_name,
+                config.ollama.location()
             );
-            eprintln!("Please ensure Ollama is running and accessible.");
+            eprintln!("Please ensure {} is running and accessible.", server_name);
             eprintln!("Use 'docs-mcp config' to update connection settings.");
             return Err(e);
         }
+    };
+    match embedder.health_check().await {
+        Ok(()) if config.ollama.provider == EmbeddingBackend::Hashing => {
+            warn!(
+                "⚠️  Using the {}: embeddings are hashed words rather than a model's, so semantic search is only approximate",
+                server_name.to_lowercase()
+            );
+        }
+        Ok(()) => {
+            info!(
+                "✅ {} connected at {} with model {}",
+                server_name,
+                config.ollama.location(),
+                config.ollama.model_name()
+            );
+        }
+        Err(e) => {
+            warn!("⚠️  {} is reachable but unhealthy: {}", server_name, e);
+            eprintln!(
+                "Warning: {} may not be ready. Background indexing may fail.",
+                server_name
+            );
+        }
     }
 
+    // build_context measures its budget in the tokens chunks were measured in
+    let sizer = Reloadable::new(
+        chunk_sizer(&config.ollama, embedder.as_ref())
+            .await
+            .context("Failed to load the tokenizer")?,
+    );
+
     // Initialize MCP server components
     eprintln!("🌐 Initializing MCP server...");
 
@@ -891,65 +1176,155 @@ pub async fn serve_mcp(config: &Config) -> Result<()> {
             .context("Failed to initialize vector store")?,
     );
 
-    let ollama_client = std::sync::Arc::new(
-        crate::embeddings::ollama::OllamaClient::new(config.ollama.clone())
-            .context("Failed to create Ollama client")?,
+    let embedder = Reloadable::new(embedder);
+
+    // Pick up config edits and indexing by other processes without a restart
+    let shared_config = Reloadable::new(config.clone());
+    tokio::spawn(
+        ServerReloader::new(
+            shared_config.clone(),
+            embedder.clone(),
+            sizer.clone(),
+            std::sync::Arc::clone(&sqlite_db),
+            std::sync::Arc::clone(&vector_store),
+        )
+        .watch(RELOAD_POLL_INTERVAL),
     );
 
+    let jobs = IndexingJobs::new(shared_config.clone(), std::sync::Arc::clone(&sqlite_db));
+    let query_cache = std::sync::Arc::new(QueryEmbeddingCache::default());
+
     // Register tools
-    let search_definition = crate::mcp::tools::SearchDocsHandler::tool_definition();
-    let list_definition = crate::mcp::tools::ListSitesHandler::tool_definition();
-
-    // Create MCP server
-    let server = ServerBuilder::new("docs-mcp", env!("CARGO_PKG_VERSION"))
-        .with_transport(StdioTransport::new())
-        .with_tool(
-            &search_definition.name,
-            search_definition.description.as_deref(),
-            search_definition.input_schema,
-            {
-                let sqlite_db = std::sync::Arc::clone(&sqlite_db);
-                let vector_store = std::sync::Arc::clone(&vector_store);
-                let ollama_client = std::sync::Arc::clone(&ollama_client);
-                move |args| {
-                    let handler = crate::mcp::tools::SearchDocsHandler::new(
-                        std::sync::Arc::clone(&sqlite_db),
-                        std::sync::Arc::clone(&vector_store),
-                        std::sync::Arc::clone(&ollama_client),
-                    );
-                    let params: CallToolParams = from_value(args)?;
-                    block_in_place(move || {
-                        Handle::current().block_on(async move { handler.handle(params).await })
-                    })
-                }
-            },
-        )
-        .with_tool(
-            &list_definition.name,
-            list_definition.description.as_deref(),
-            list_definition.input_schema,
-            {
-                let sqlite_db = std::sync::Arc::clone(&sqlite_db);
-                move |args| {
-                    let handler =
-                        crate::mcp::tools::ListSitesHandler::new(std::sync::Arc::clone(&sqlite_db));
-                    let params: CallToolParams = from_value(args)?;
-                    block_in_place(move || {
-                        Handle::current().block_on(async move { handler.handle(params).await })
-                    })
+    let mut tools = ToolRegistry::new();
+    tools.register(
+        SearchDocsHandler::tool_definition(),
+        SearchDocsHandler::new(
+            shared_config.clone(),
+            std::sync::Arc::clone(&sqlite_db),
+            std::sync::Arc::clone(&vector_store),
+            embedder.clone(),
+            std::sync::Arc::clone(&query_cache),
+        ),
+    );
+    tools.register(
+        ListSitesHandler::tool_definition(),
+        ListSitesHandler::new(std::sync::Arc::clone(&sqlite_db)),
+    );
+    tools.register(
+        GetPageHandler::tool_definition(),
+        GetPageHandler::new(std::sync::Arc::clone(&sqlite_db)),
+    );
+    tools.register(
+        GetContextHandler::tool_definition(),
+        GetContextHandler::new(std::sync::Arc::clone(&sqlite_db)),
+    );
+    tools.register(
+        BuildContextHandler::tool_definition(),
+        BuildContextHandler::new(
+            shared_config,
+            std::sync::Arc::clone(&sqlite_db),
+            std::sync::Arc::clone(&vector_store),
+            embedder.clone(),
+            std::sync::Arc::clone(&query_cache),
+            sizer,
+        ),
+    );
+    tools.register(
+        IndexSiteHandler::tool_definition(),
+        IndexSiteHandler::new(jobs.clone()),
+    );
+    tools.register(
+        IndexingStatusHandler::tool_definition(),
+        IndexingStatusHandler::new(jobs, std::sync::Arc::clone(&sqlite_db)),
+    );
+    tools.register(
+        ServerStatsHandler::tool_definition(),
+        ServerStatsHandler::new(query_cache),
+    );
+
+    // The server only lists the tools, calls are run by the dispatcher
+    let builder = tools.definitions().fold(
+        ServerBuilder::new("docs-mcp", env!("CARGO_PKG_VERSION")),
+        |builder, tool| {
+            builder.with_tool(
+                &tool.name,
+                tool.description.as_deref(),
+                tool.input_schema.clone(),
+                |_| Err(anyhow!("Tool calls are handled by the tool dispatcher")),
+            )
+        },
+    );
+
+    let tool_names = tools
+        .definitions()
+        .map(|tool| tool.name.as_str())
+        .join(", ");
+    let dispatcher = std::sync::Arc::new(ToolDispatcher::new(tools, tool_timeout));
+    let resources = std::sync::Arc::new(PageResources::new(std::sync::Arc::clone(&sqlite_db)));
+    match http {
+        Some(options) => {
+            let addr = options.addr;
+            let authenticated = options.bearer_token.is_some();
+            let transport = ResourceTransport::new(
+                ToolTransport::new(HttpTransport::new(options), dispatcher),
+                resources,
+            );
+            tokio::spawn(watch_completed_sites(
+                std::sync::Arc::clone(&sqlite_db),
+                transport.box_clone(),
+                COMPLETED_SITES_POLL_INTERVAL,
+            ));
+            let server = builder.with_transport(transport).build()?;
+
+            eprintln!("✅ MCP server initialized with tools: {}", tool_names);
+            eprintln!("🌐 Starting MCP server with HTTP transport...");
+            eprintln!("   • Streamable HTTP: http://{}{}", addr, MCP_PATH);
+            eprintln!("   • Legacy SSE:      http://{}{}", addr, SSE_PATH);
+            if authenticated {
+                eprintln!("🔒 Clients must send 'Authorization: Bearer <token>'");
+            } else if !addr.ip().is_loopback() {
+                warn!(
+                    "HTTP server is listening on {} without a bearer token",
+                    addr
+                );
+                eprintln!(
+                    "⚠️  Listening on a non-loopback address without --token; anyone who can reach it can use the server"
+                );
+            }
+            eprintln!("📊 Use 'docs-mcp status' to monitor indexing progress");
+            eprintln!("📚 Use 'docs-mcp list' to see indexed sites");
+            eprintln!();
+            eprintln!("Note: Press Ctrl+C to stop the server.");
+
+            tokio::select! {
+                result = server.run() => result?,
+                _ = tokio::signal::ctrl_c() => {
+                    info!("Received Ctrl+C");
                 }
-            },
-        )
-        .build()?;
+            }
+        }
+        None => {
+            let transport = ResourceTransport::new(
+                ToolTransport::new(StdioTransport::new(), dispatcher),
+                resources,
+            );
+            tokio::spawn(watch_completed_sites(
+                std::sync::Arc::clone(&sqlite_db),
+                transport.box_clone(),
+                COMPLETED_SITES_POLL_INTERVAL,
+            ));
+            let server = builder.with_transport(transport).build()?;
 
-    eprintln!("✅ MCP server initialized with tools: search_docs, list_sites");
-    eprintln!("🌐 Starting MCP server with stdio transport...");
-    eprintln!("📊 Use 'docs-mcp status' to monitor indexing progress");
-    eprintln!("📚 Use 'docs-mcp list' to see indexed sites");
-    eprintln!();
-    eprintln!("Note: Server ready for MCP client connections via stdio.");
+            eprintln!("✅ MCP server initialized with tools: {}", tool_names);
+            eprintln!("🌐 Starting MCP server with stdio transport...");
+            eprintln!("📊 Use 'docs-mcp status' to monitor indexing progress");
+            eprintln!("📚 Use 'docs-mcp list' to see indexed sites");
+            eprintln!();
+            eprintln!("Note: Server ready for MCP client connections via stdio.");
 
-    server.run().await?;
+            server.run().await?;
+        }
+    }
 
     info!("Server shutting down");
     eprintln!("✅ Shutdown complete");
@@ -960,6 +1335,42 @@ pub async fn serve_mcp(config: &Config) -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::validation::*;
+    use super::{default_site_name, normalize_index_url};
+
+    #[test]
+    fn normalize_index_url_works() {
+        let normalize = |url| normalize_index_url(url).expect("valid regex").into_owned();
+
+        assert_eq!(
+            normalize("https://docs.example.com/guide/index.html"),
+            "https://docs.example.com/guide/"
+        );
+        // The slash is appended to the whole URL, not just its last segment
+        assert_eq!(
+            normalize("https://docs.example.com/guide"),
+            "https://docs.example.com/guide/"
+        );
+        assert_eq!(
+            normalize("https://docs.example.com/guide/"),
+            "https://docs.example.com/guide/"
+        );
+        assert_eq!(
+            normalize("https://docs.example.com/guide/intro.html"),
+            "https://docs.example.com/guide/intro.html"
+        );
+    }
+
+    #[test]
+    fn default_site_name_works() {
+        let name = |url| default_site_name(&url::Url::parse(url).expect("valid URL"));
+
+        // The empty segment after a trailing slash isn't part of the name
+        assert_eq!(name("https://docs.example.com/"), "docs.example.com");
+        assert_eq!(
+            name("https://docs.rs/tokio/latest/"),
+            "docs.rs tokio latest"
+        );
+    }
 
     #[test]
     fn validate_site_identifier_works() {
@@ -1,8 +1,14 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use anyhow::{Context, Result};
 use console::style;
 use dialoguer::{Confirm, Input, Select};
 
-use crate::{config::settings::OllamaConfig, embeddings::ollama::DEFAULT_EMBEDDING_DIMENSION};
+use crate::{
+    config::settings::{EmbeddingBackend, OllamaConfig},
+    embeddings::ollama::DEFAULT_EMBEDDING_DIMENSION,
+};
 
 use super::{Config, ConfigError};
 
@@ -11,8 +17,13 @@ pub fn run_interactive_config(mut config: Config) -> Result<()> {
     eprintln!("{}", style("🔧 Docs MCP Configuration Setup").bold().cyan());
     eprintln!();
 
-    eprintln!("{}", style("Ollama Configuration").bold().yellow());
-    eprintln!("Configure your local Ollama instance for embedding generation.");
+    eprintln!(
+        "{}",
+        style("Embedding Server Configuration").bold().yellow()
+    );
+    eprintln!(
+        "Configure your local Ollama instance, or an OpenAI-compatible server, for embedding generation."
+    );
     eprintln!();
 
     configure_ollama(&mut config.ollama)?;
@@ -20,14 +31,40 @@ pub fn run_interactive_config(mut config: Config) -> Result<()> {
     eprintln!();
     eprintln!("{}", style("Testing configuration...").yellow());
 
-    if test_ollama_connection(&config.ollama)? {
-        eprintln!("{}", style("✓ Ollama connection successful!").green());
+    let server_name = config.ollama.provider.server_name();
+    if config.ollama.provider == EmbeddingBackend::Hashing {
+        eprintln!(
+            "{}",
+            style("⚠ The hashing embedder needs no server, but semantic search will only match shared words")
+                .yellow()
+        );
+    } else if config.ollama.provider == EmbeddingBackend::Local {
+        if test_ollama_connection(&config.ollama)? {
+            eprintln!("{}", style("✓ Model files found!").green());
+        } else {
+            eprintln!(
+                "{}",
+                style(format!(
+                    "⚠ Warning: {} is missing config.json, model.safetensors or tokenizer.json",
+                    config.ollama.location()
+                ))
+                .yellow()
+            );
+        }
+    } else if test_ollama_connection(&config.ollama)? {
+        eprintln!(
+            "{}",
+            style(format!("✓ {} connection successful!", server_name)).green()
+        );
     } else {
         eprintln!(
             "{}",
-            style("⚠ Warning: Could not connect to Ollama").yellow()
+            style(format!("⚠ Warning: Could not connect to {}", server_name)).yellow()
+        );
+        eprintln!(
+            "You can continue, but make sure {} is running before indexing.",
+            server_name
         );
-        eprintln!("You can continue, but make sure Ollama is running before indexing.");
     }
 
     eprintln!();
@@ -58,18 +95,93 @@ pub fn show_config(config: &Config) -> Result<()> {
     eprintln!("{}", style("📋 Current Configuration").bold().cyan());
     eprintln!();
 
-    eprintln!("{}", style("Ollama Settings:").bold().yellow());
+    eprintln!("{}", style("Embedding Server Settings:").bold().yellow());
+    eprintln!("  Provider: {}", style(config.ollama.provider).cyan());
     eprintln!("  Host: {}", style(&config.ollama.host).cyan());
     eprintln!("  Port: {}", style(config.ollama.port).cyan());
-    eprintln!("  Model: {}", style(&config.ollama.model).cyan());
+    eprintln!("  Model: {}", style(config.ollama.model_name()).cyan());
     eprintln!("  Batch Size: {}", style(config.ollama.batch_size).cyan());
+    eprintln!("  Concurrency: {}", style(config.ollama.concurrency).cyan());
+    if config.ollama.api_key.is_some() {
+        eprintln!("  API Key: {}", style("set").cyan());
+    }
+    let prompt = config.ollama.prompt_template();
+    let source = if config.ollama.prompt.is_some() {
+        "configured"
+    } else {
+        "built-in"
+    };
+    if !prompt.query.is_empty() {
+        eprintln!(
+            "  Query Prefix: {} ({})",
+            style(format!("{:?}", prompt.query)).cyan(),
+            source
+        );
+    }
+    if !prompt.document.is_empty() {
+        eprintln!(
+            "  Document Prefix: {} ({})",
+            style(format!("{:?}", prompt.document)).cyan(),
+            source
+        );
+    }
 
     eprintln!();
-    match config.ollama.ollama_url() {
-        Ok(url) => eprintln!("  Ollama URL: {}", style(url).cyan()),
-        Err(e) => eprintln!("  Ollama URL: {} ({})", style("Invalid").red(), e),
+    if let (EmbeddingBackend::Local, Some(model_path)) =
+        (config.ollama.provider, &config.ollama.model_path)
+    {
+        eprintln!("  Model Path: {}", style(model_path.display()).cyan());
+    } else {
+        match config.ollama.ollama_url() {
+            Ok(url) => eprintln!("  Server URL: {}", style(url).cyan()),
+            Err(e) => eprintln!("  Server URL: {} ({})", style("Invalid").red(), e),
+        }
+    }
+    if let Some(tokenizer_path) = &config.ollama.tokenizer_path {
+        eprintln!("  Tokenizer: {}", style(tokenizer_path.display()).cyan());
     }
 
+    eprintln!();
+    eprintln!("{}", style("Search Settings:").bold().yellow());
+    eprintln!(
+        "  Distance Metric: {}",
+        style(config.search.distance_metric).cyan()
+    );
+    eprintln!(
+        "  Index Threshold: {} embeddings",
+        style(config.search.index_threshold).cyan()
+    );
+    eprintln!("  Nprobes: {}", style(config.search.nprobes).cyan());
+    eprintln!(
+        "  Refine Factor: {}",
+        style(
+            config
+                .search
+                .refine_factor
+                .map_or_else(|| "none".to_string(), |factor| factor.to_string())
+        )
+        .cyan()
+    );
+
+    eprintln!();
+    eprintln!("{}", style("Reranking Settings:").bold().yellow());
+    eprintln!("  Enabled: {}", style(config.reranking.enabled).cyan());
+    eprintln!(
+        "  Model: {}",
+        style(
+            config
+                .reranking
+                .model
+                .as_deref()
+                .unwrap_or("none (lexical)")
+        )
+        .cyan()
+    );
+    eprintln!(
+        "  Candidate Multiplier: {}",
+        style(config.reranking.candidate_multiplier).cyan()
+    );
+
     let config_path = config
         .config_file_path()
         .context("Failed to get config file path")?;
@@ -80,6 +192,41 @@ pub fn show_config(config: &Config) -> Result<()> {
 }
 
 fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
+    let providers = [
+        EmbeddingBackend::Ollama,
+        EmbeddingBackend::OpenAi,
+        EmbeddingBackend::Local,
+        EmbeddingBackend::Hashing,
+    ];
+    let provider_names = &[
+        "Ollama",
+        "OpenAI-compatible (llama.cpp, vLLM, LM Studio, LocalAI)",
+        "Local model (runs in-process, needs the local-embeddings feature)",
+        "Hashing (no model, rough keyword-like recall for testing)",
+    ];
+    let provider_index = Select::new()
+        .with_prompt("Embedding server")
+        .default(
+            providers
+                .iter()
+                .position(|&p| p == ollama.provider)
+                .unwrap_or(0),
+        )
+        .items(provider_names)
+        .interact()?;
+    let provider = providers[provider_index];
+    let default_port = match provider {
+        EmbeddingBackend::Local => return configure_local_model(ollama),
+        EmbeddingBackend::Hashing => {
+            // Hashing has no model or server to ask about
+            ollama.provider = provider;
+            ollama.api_key = None;
+            return Ok(());
+        }
+        _ if provider == ollama.provider => ollama.port,
+        _ => provider.default_port().unwrap_or(ollama.port),
+    };
+
     let protocols = &["http", "https"];
     let default_index = protocols
         .iter()
@@ -87,7 +234,7 @@ fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
         .unwrap_or(0);
 
     let protocol_index = Select::new()
-        .with_prompt("Ollama protocol")
+        .with_prompt("Server protocol")
         .default(default_index)
         .items(protocols)
         .interact()?;
@@ -95,7 +242,7 @@ fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
     let protocol = protocols[protocol_index].to_string();
 
     let host: String = Input::new()
-        .with_prompt("Ollama host")
+        .with_prompt("Server host")
         .default(ollama.host.clone())
         .validate_with(|input: &String| -> Result<(), ConfigError> {
             let temp_config = OllamaConfig {
@@ -105,6 +252,7 @@ fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
                 model: "test".to_string(),
                 batch_size: 16,
                 embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+                ..Default::default()
             };
             temp_config.validate()?;
             Ok(())
@@ -112,8 +260,8 @@ fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
         .interact_text()?;
 
     let port: u16 = Input::new()
-        .with_prompt("Ollama port")
-        .default(ollama.port)
+        .with_prompt("Server port")
+        .default(default_port)
         .validate_with(|input: &u16| -> Result<(), &str> {
             if *input == 0 {
                 Err("Port must be greater than 0")
@@ -135,18 +283,99 @@ fn configure_ollama(ollama: &mut OllamaConfig) -> Result<()> {
         })
         .interact_text()?;
 
+    let api_key = if provider == EmbeddingBackend::OpenAi {
+        let api_key: String = Input::new()
+            .with_prompt("API key (leave empty if the server needs none)")
+            .default(ollama.api_key.clone().unwrap_or_default())
+            .show_default(false)
+            .allow_empty(true)
+            .interact_text()?;
+        Some(api_key.trim().to_string()).filter(|api_key| !api_key.is_empty())
+    } else {
+        None
+    };
+
+    ollama.provider = provider;
     ollama.set_protocol(protocol)?;
     ollama.set_host(host)?;
     ollama.set_port(port)?;
     ollama.set_model(model)?;
+    ollama.api_key = api_key;
+
+    Ok(())
+}
+
+/// Ask for the directory and name of a model run by the `local` provider
+fn configure_local_model(ollama: &mut OllamaConfig) -> Result<()> {
+    let model_path: String = Input::new()
+        .with_prompt("Model directory (config.json, model.safetensors, tokenizer.json)")
+        .with_initial_text(
+            ollama
+                .model_path
+                .as_ref()
+                .map(|path| path.display().to_string())
+                .unwrap_or_default(),
+        )
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if Path::new(input.trim()).is_dir() {
+                Ok(())
+            } else {
+                Err("Model directory does not exist")
+            }
+        })
+        .interact_text()?;
+    let model_path = PathBuf::from(model_path.trim());
+
+    let default_model = model_path.file_name().map_or_else(
+        || ollama.model.clone(),
+        |name| name.to_string_lossy().to_string(),
+    );
+    let model: String = Input::new()
+        .with_prompt("Model name (recorded with indexed sites)")
+        .default(default_model)
+        .validate_with(|input: &String| -> Result<(), &str> {
+            if input.trim().is_empty() {
+                Err("Model name cannot be empty")
+            } else {
+                Ok(())
+            }
+        })
+        .interact_text()?;
+
+    // The embeddings are as wide as the model's hidden states
+    if let Some(hidden_size) = fs::read_to_string(model_path.join("config.json"))
+        .ok()
+        .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+        .and_then(|config| config.get("hidden_size")?.as_u64())
+        .and_then(|size| u32::try_from(size).ok())
+    {
+        ollama.set_embedding_dimension(hidden_size)?;
+    }
+
+    ollama.provider = EmbeddingBackend::Local;
+    ollama.model_path = Some(model_path);
+    ollama.set_model(model)?;
+    ollama.api_key = None;
 
     Ok(())
 }
 
 fn test_ollama_connection(ollama: &OllamaConfig) -> Result<bool> {
+    let path = match ollama.provider {
+        EmbeddingBackend::Ollama => "/api/version",
+        EmbeddingBackend::OpenAi => "/v1/models",
+        EmbeddingBackend::Hashing => return Ok(true),
+        EmbeddingBackend::Local => {
+            return Ok(ollama.model_path.as_ref().is_some_and(|model_path| {
+                ["config.json", "model.safetensors", "tokenizer.json"]
+                    .iter()
+                    .all(|file| model_path.join(file).is_file())
+            }));
+        }
+    };
     let url = format!(
-        "{}://{}:{}/api/version",
-        ollama.protocol, ollama.host, ollama.port
+        "{}://{}:{}{}",
+        ollama.protocol, ollama.host, ollama.port, path
     );
 
     let agent: ureq::Agent = ureq::Agent::config_builder()
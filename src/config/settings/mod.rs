@@ -9,6 +9,7 @@ use thiserror::Error;
 use url::Url;
 
 use crate::embeddings::chunking::ChunkingConfig;
+use crate::embeddings::hashing::HASHING_MODEL_NAME;
 use crate::embeddings::ollama::DEFAULT_EMBEDDING_DIMENSION;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -16,30 +17,258 @@ pub struct Config {
     pub ollama: OllamaConfig,
     #[serde(default)]
     pub chunking: ChunkingConfig,
+    #[serde(default)]
+    pub search: SearchConfig,
+    #[serde(default)]
+    pub reranking: RerankingConfig,
     #[serde(skip)]
     pub base_dir: PathBuf,
 }
 
+/// Embedding server settings
+///
+/// Despite the section's name, the server may also be any server with an
+/// OpenAI-compatible API, a model run in-process, or no model at all,
+/// selected by `provider`.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 #[serde(default)]
 pub struct OllamaConfig {
+    /// API spoken by the embedding server
+    pub provider: EmbeddingBackend,
     pub protocol: String,
     pub host: String,
     pub port: u16,
     pub model: String,
     pub batch_size: u32,
+    /// Embedding requests in flight at once while indexing
+    pub concurrency: u32,
     pub embedding_dimension: u32,
+    /// Bearer token sent to OpenAI-compatible servers which require one
+    pub api_key: Option<String>,
+    /// Directory of the model run by the `local` provider, holding its
+    /// `config.json`, `model.safetensors` and `tokenizer.json`
+    pub model_path: Option<PathBuf>,
+    /// HuggingFace `tokenizer.json` of the model, used to measure chunks in
+    /// the model's tokens instead of estimating them
+    pub tokenizer_path: Option<PathBuf>,
+    /// Prefixes put before queries and documents, replacing the model's
+    /// built-in ones
+    pub prompt: Option<PromptTemplate>,
 }
 
 impl Default for OllamaConfig {
     fn default() -> Self {
         Self {
+            provider: EmbeddingBackend::default(),
             protocol: "http".to_string(),
             host: "localhost".to_string(),
             port: 11434,
             model: "nomic-embed-text:latest".to_string(),
             batch_size: 16,
+            concurrency: 4,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            api_key: None,
+            model_path: None,
+            tokenizer_path: None,
+            prompt: None,
+        }
+    }
+}
+
+/// Instruction prefixes an embedding model expects before the text it embeds
+///
+/// Models such as nomic-embed-text and e5 are trained to embed queries and
+/// documents differently, telling them apart by a prefix on the text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct PromptTemplate {
+    /// Put before search queries
+    pub query: String,
+    /// Put before the chunks of indexed pages
+    pub document: String,
+}
+
+/// Query instruction of the BGE family of models and those trained like them
+const RETRIEVAL_INSTRUCTION: &str = "Represent this sentence for searching relevant passages: ";
+
+impl PromptTemplate {
+    /// Built-in prefixes of popular models trained with them, and none for
+    /// other models
+    pub fn for_model(model: &str) -> Self {
+        // Ignore the organization of Hugging Face names and the tag of Ollama's
+        let model = model.rsplit('/').next().unwrap_or(model);
+        let model = model.split(':').next().unwrap_or(model).to_lowercase();
+
+        let (query, document) = if model.starts_with("nomic-embed-text") {
+            ("search_query: ", "search_document: ")
+        } else if model.starts_with("e5-") || model.contains("-e5-") {
+            ("query: ", "passage: ")
+        } else if (model.starts_with("bge-") && model.contains("-en"))
+            || model.starts_with("mxbai-embed-large")
+            || (model.starts_with("snowflake-arctic-embed")
+                && !model.starts_with("snowflake-arctic-embed2"))
+        {
+            (RETRIEVAL_INSTRUCTION, "")
+        } else {
+            ("", "")
+        };
+
+        Self {
+            query: query.to_string(),
+            document: document.to_string(),
+        }
+    }
+
+    /// Text to embed for a search query
+    pub fn format_query(&self, query: &str) -> String {
+        format!("{}{}", self.query, query)
+    }
+
+    /// Text to embed for a document chunk
+    pub fn format_document(&self, document: &str) -> String {
+        format!("{}{}", self.document, document)
+    }
+}
+
+/// API used to generate embeddings
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingBackend {
+    /// Ollama's native `/api/embed` endpoint
+    #[default]
+    Ollama,
+    /// The OpenAI-compatible `/v1/embeddings` endpoint, as served by
+    /// llama.cpp server, vLLM, LM Studio and LocalAI
+    OpenAi,
+    /// A BERT-style model run in-process on the CPU, which needs the
+    /// `local-embeddings` feature
+    Local,
+    /// Feature hashing of the text's words, which needs no model and only
+    /// approximates semantic search
+    Hashing,
+}
+
+impl EmbeddingBackend {
+    /// Port the backend's servers usually listen on, if it uses a server
+    pub fn default_port(self) -> Option<u16> {
+        match self {
+            Self::Ollama => Some(11434),
+            Self::OpenAi => Some(8080),
+            Self::Local | Self::Hashing => None,
+        }
+    }
+
+    /// Name of the embedding server for messages
+    pub fn server_name(self) -> &'static str {
+        match self {
+            Self::Ollama => "Ollama",
+            Self::OpenAi => "OpenAI-compatible server",
+            Self::Local => "Local model",
+            Self::Hashing => "Hashing embedder",
+        }
+    }
+}
+
+impl std::fmt::Display for EmbeddingBackend {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Ollama => write!(f, "ollama"),
+            Self::OpenAi => write!(f, "openai"),
+            Self::Local => write!(f, "local"),
+            Self::Hashing => write!(f, "hashing"),
+        }
+    }
+}
+
+/// How embeddings are compared when searching
+///
+/// L2 is the default, as LanceDB searched and built indexes with it before
+/// the metric could be configured.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DistanceMetric {
+    /// Cosine distance, which ignores the length of embeddings
+    Cosine,
+    /// Dot product, equivalent to cosine for normalized embeddings
+    Dot,
+    /// Squared euclidean distance
+    #[default]
+    L2,
+}
+
+impl DistanceMetric {
+    /// Convert a distance reported by the vector store into a relevance
+    /// score between 0 and 1
+    ///
+    /// For normalized embeddings, which Ollama returns, every metric yields
+    /// the cosine similarity, clamped at 0 for unrelated or opposite vectors.
+    pub fn similarity(self, distance: f32) -> f32 {
+        let similarity = match self {
+            // Cosine and dot distances are 1 - similarity
+            Self::Cosine | Self::Dot => 1.0 - distance,
+            // |a - b|² = 2 - 2 cos(a, b) for unit vectors
+            Self::L2 => 1.0 - distance / 2.0,
+        };
+        similarity.clamp(0.0, 1.0)
+    }
+}
+
+impl std::fmt::Display for DistanceMetric {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Cosine => write!(f, "cosine"),
+            Self::Dot => write!(f, "dot"),
+            Self::L2 => write!(f, "l2"),
+        }
+    }
+}
+
+/// Vector search settings
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct SearchConfig {
+    /// Distance metric used for queries and the vector index
+    pub distance_metric: DistanceMetric,
+    /// Number of embeddings from which an approximate nearest neighbour index
+    /// is built, below which searches scan every embedding
+    pub index_threshold: u64,
+    /// Index partitions searched per query, trading speed for recall
+    pub nprobes: u32,
+    /// Fetch `refine_factor` times as many index candidates and rank them by
+    /// exact distance, when set
+    pub refine_factor: Option<u32>,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            distance_metric: DistanceMetric::default(),
+            index_threshold: 10_000,
+            nprobes: 20,
+            refine_factor: None,
+        }
+    }
+}
+
+/// Second-stage reranking of search candidates
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct RerankingConfig {
+    /// Whether search candidates are reranked before being returned
+    pub enabled: bool,
+    /// Reranker model served by the embedding backend. Candidates are
+    /// reranked by lexical overlap with the query when unset.
+    pub model: Option<String>,
+    /// Number of candidates fetched per requested result
+    pub candidate_multiplier: u32,
+}
+
+impl Default for RerankingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            model: None,
+            candidate_multiplier: 5,
         }
     }
 }
@@ -54,6 +283,8 @@ pub enum ConfigError {
     InvalidPort(u16),
     #[error("Invalid batch size: {0} (must be between 1 and 1000)")]
     InvalidBatchSize(u32),
+    #[error("Invalid concurrency: {0} (must be between 1 and 64)")]
+    InvalidConcurrency(u32),
     #[error("Invalid model name: {0} (cannot be empty)")]
     InvalidModel(String),
     #[error("Invalid browser timeout: {0} (must be between 1 and 300 seconds)")]
@@ -64,6 +295,18 @@ pub enum ConfigError {
     InvalidWindowDimensions(u32, u32),
     #[error("Invalid protocol: {0} (must be 'http' or 'https')")]
     InvalidProtocol(String),
+    #[error("Invalid index threshold: {0} (must be at least 256)")]
+    InvalidIndexThreshold(u64),
+    #[error("Invalid nprobes: {0} (must be between 1 and 1000)")]
+    InvalidNprobes(u32),
+    #[error("Invalid refine factor: {0} (must be between 1 and 100)")]
+    InvalidRefineFactor(u32),
+    #[error("Invalid rerank candidate multiplier: {0} (must be between 1 and 20)")]
+    InvalidRerankCandidateMultiplier(u32),
+    #[error("Invalid API key (cannot be empty, remove it instead)")]
+    InvalidApiKey,
+    #[error("The local embedding provider needs a model_path")]
+    MissingModelPath,
     #[error("Invalid embedding dimension: {0} (must be between 64 and 4096)")]
     InvalidEmbeddingDimension(u32),
     #[error("Invalid target chunk size: {0} (must be between 100 and 2048)")]
@@ -95,6 +338,8 @@ impl Config {
             return Ok(Self {
                 ollama: OllamaConfig::default(),
                 chunking: ChunkingConfig::default(),
+                search: SearchConfig::default(),
+                reranking: RerankingConfig::default(),
                 base_dir: config_dir.as_ref().to_path_buf(),
             });
         }
@@ -146,6 +391,8 @@ impl Config {
     pub fn validate(&self) -> Result<(), ConfigError> {
         self.ollama.validate()?;
         self.validate_chunking_config()?;
+        self.search.validate()?;
+        self.reranking.validate()?;
         Ok(())
     }
 
@@ -234,15 +481,70 @@ impl OllamaConfig {
             return Err(ConfigError::InvalidBatchSize(self.batch_size));
         }
 
+        if !(1..=64).contains(&self.concurrency) {
+            return Err(ConfigError::InvalidConcurrency(self.concurrency));
+        }
+
         if !(64..=4096).contains(&self.embedding_dimension) {
             return Err(ConfigError::InvalidEmbeddingDimension(
                 self.embedding_dimension,
             ));
         }
 
+        if let Some(api_key) = &self.api_key
+            && api_key.trim().is_empty()
+        {
+            return Err(ConfigError::InvalidApiKey);
+        }
+
+        if self.provider == EmbeddingBackend::Local && self.model_path.is_none() {
+            return Err(ConfigError::MissingModelPath);
+        }
+
         Ok(())
     }
 
+    /// Where embeddings are generated, for messages: the server's address,
+    /// or the directory of a local model
+    pub fn location(&self) -> String {
+        match (&self.provider, &self.model_path) {
+            (EmbeddingBackend::Local, Some(model_path)) => model_path.display().to_string(),
+            (EmbeddingBackend::Hashing, _) => "in-process".to_string(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+
+    /// Name of the embedding model, as recorded with each site
+    ///
+    /// The hashing embedder ignores `model`, so it has a name of its own, and
+    /// switching to or from it is detected as a model change.
+    pub fn model_name(&self) -> &str {
+        match self.provider {
+            EmbeddingBackend::Hashing => HASHING_MODEL_NAME,
+            _ => &self.model,
+        }
+    }
+
+    /// The `tokenizer.json` chunks are measured with: the configured one, or
+    /// else the local model's own
+    pub fn tokenizer_file(&self) -> Option<PathBuf> {
+        self.tokenizer_path
+            .clone()
+            .or_else(|| match (&self.provider, &self.model_path) {
+                (EmbeddingBackend::Local, Some(model_path)) => {
+                    Some(model_path.join("tokenizer.json"))
+                }
+                _ => None,
+            })
+    }
+
+    /// Prefixes for the model, either configured or built in
+    pub fn prompt_template(&self) -> PromptTemplate {
+        self.prompt
+            .clone()
+            .unwrap_or_else(|| PromptTemplate::for_model(self.model_name()))
+    }
+
     pub fn ollama_url(&self) -> Result<Url, ConfigError> {
         let url_str = format!("{}://{}:{}", self.protocol, self.host, self.port);
         Url::parse(&url_str).map_err(|_| ConfigError::InvalidUrl(url_str))
@@ -298,3 +600,42 @@ impl OllamaConfig {
         Ok(())
     }
 }
+
+impl SearchConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        // Training the index's product quantizer needs 256 embeddings
+        if self.index_threshold < 256 {
+            return Err(ConfigError::InvalidIndexThreshold(self.index_threshold));
+        }
+
+        if !(1..=1000).contains(&self.nprobes) {
+            return Err(ConfigError::InvalidNprobes(self.nprobes));
+        }
+
+        if let Some(refine_factor) = self.refine_factor
+            && !(1..=100).contains(&refine_factor)
+        {
+            return Err(ConfigError::InvalidRefineFactor(refine_factor));
+        }
+
+        Ok(())
+    }
+}
+
+impl RerankingConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if !(1..=20).contains(&self.candidate_multiplier) {
+            return Err(ConfigError::InvalidRerankCandidateMultiplier(
+                self.candidate_multiplier,
+            ));
+        }
+
+        if let Some(model) = &self.model
+            && model.trim().is_empty()
+        {
+            return Err(ConfigError::InvalidModel(model.clone()));
+        }
+
+        Ok(())
+    }
+}
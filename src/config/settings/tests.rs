@@ -23,9 +23,17 @@ fn config_validation() {
     invalid_config.batch_size = 0;
     assert!(invalid_config.validate().is_err());
 
-    let mut invalid_config = config;
+    let mut invalid_config = config.clone();
     invalid_config.batch_size = 1001;
     assert!(invalid_config.validate().is_err());
+
+    let mut invalid_config = config.clone();
+    invalid_config.concurrency = 0;
+    assert!(invalid_config.validate().is_err());
+
+    let mut invalid_config = config;
+    invalid_config.concurrency = 65;
+    assert!(invalid_config.validate().is_err());
 }
 
 #[test]
@@ -46,6 +54,7 @@ fn setter_validation() {
         model: "test-model".to_string(),
         batch_size: 32,
         embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+        ..Default::default()
     };
 
     assert!(config.set_protocol("https".to_string()).is_ok());
@@ -98,6 +107,7 @@ fn protocol_validation() {
         model: "test-model".to_string(),
         batch_size: 32,
         embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+        ..Default::default()
     };
 
     // Valid protocols
@@ -117,6 +127,8 @@ fn chunking_config_validation() {
     let config = Config {
         ollama: OllamaConfig::default(),
         chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: Default::default(),
         base_dir: PathBuf::from("/tmp"),
     };
     assert!(config.validate().is_ok());
@@ -183,6 +195,8 @@ fn config_toml_serialization() {
     let config = Config {
         ollama: OllamaConfig::default(),
         chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: Default::default(),
         base_dir: temp_dir.path().to_path_buf(),
     };
 
@@ -242,3 +256,283 @@ sentence_boundary_splitting = false
     assert!(!loaded_config.chunking.preserve_code_blocks);
     assert!(!loaded_config.chunking.sentence_boundary_splitting);
 }
+
+#[test]
+fn reranking_config_validation() {
+    let config = RerankingConfig::default();
+    assert!(!config.enabled);
+    assert!(config.validate().is_ok());
+
+    for candidate_multiplier in [0, 21] {
+        let invalid_config = RerankingConfig {
+            candidate_multiplier,
+            ..RerankingConfig::default()
+        };
+        assert!(matches!(
+            invalid_config.validate(),
+            Err(ConfigError::InvalidRerankCandidateMultiplier(_))
+        ));
+    }
+
+    let invalid_config = RerankingConfig {
+        model: Some(" ".to_string()),
+        ..RerankingConfig::default()
+    };
+    assert!(matches!(
+        invalid_config.validate(),
+        Err(ConfigError::InvalidModel(_))
+    ));
+}
+
+#[test]
+fn config_toml_reranking_section() {
+    let temp_dir = TempDir::new().expect("should create temp dir");
+    let config = Config {
+        ollama: OllamaConfig::default(),
+        chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: RerankingConfig {
+            enabled: true,
+            model: Some("bge-reranker-v2-m3".to_string()),
+            candidate_multiplier: 8,
+        },
+        base_dir: temp_dir.path().to_path_buf(),
+    };
+    config.save().expect("should save config");
+
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.reranking, config.reranking);
+
+    // A partial section falls back to the defaults
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\n\n[reranking]\nenabled = true\n",
+    )
+    .expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert!(loaded_config.reranking.enabled);
+    assert_eq!(loaded_config.reranking.model, None);
+    assert_eq!(loaded_config.reranking.candidate_multiplier, 5);
+}
+
+#[test]
+fn distance_metric_similarity() {
+    assert_eq!(DistanceMetric::Cosine.similarity(0.0), 1.0);
+    assert_eq!(DistanceMetric::Cosine.similarity(0.25), 0.75);
+    // Opposite vectors are clamped to the bottom of the range
+    assert_eq!(DistanceMetric::Cosine.similarity(2.0), 0.0);
+    assert_eq!(DistanceMetric::Dot.similarity(-0.5), 1.0);
+    assert_eq!(DistanceMetric::Dot.similarity(0.5), 0.5);
+    assert_eq!(DistanceMetric::L2.similarity(1.0), 0.5);
+    assert_eq!(DistanceMetric::L2.similarity(4.0), 0.0);
+}
+
+#[test]
+fn config_toml_search_section() {
+    let temp_dir = TempDir::new().expect("should create temp dir");
+    std::fs::write(temp_dir.path().join("config.toml"), "[ollama]\n").expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.search.distance_metric, DistanceMetric::L2);
+
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\n\n[search]\ndistance_metric = \"cosine\"\n",
+    )
+    .expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.search.distance_metric, DistanceMetric::Cosine);
+
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\n\n[search]\ndistance_metric = \"manhattan\"\n",
+    )
+    .expect("should write config");
+    assert!(Config::load(temp_dir.path()).is_err());
+}
+
+#[test]
+fn config_toml_embedding_provider() {
+    let temp_dir = TempDir::new().expect("should create temp dir");
+    std::fs::write(temp_dir.path().join("config.toml"), "[ollama]\n").expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.ollama.provider, EmbeddingBackend::Ollama);
+    assert_eq!(loaded_config.ollama.api_key, None);
+
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\nprovider = \"openai\"\nport = 8080\nmodel = \"bge-small-en-v1.5\"\napi_key = \"secret\"\n",
+    )
+    .expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.ollama.provider, EmbeddingBackend::OpenAi);
+    assert_eq!(loaded_config.ollama.api_key.as_deref(), Some("secret"));
+
+    // The provider and key survive a save
+    loaded_config.save().expect("should save config");
+    assert_eq!(
+        Config::load(temp_dir.path()).expect("should load config"),
+        loaded_config
+    );
+
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\napi_key = \" \"\n",
+    )
+    .expect("should write config");
+    assert!(Config::load(temp_dir.path()).is_err());
+}
+
+#[test]
+fn config_toml_local_provider() {
+    let temp_dir = TempDir::new().expect("should create temp dir");
+
+    // A local model can't be found without its directory
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\nprovider = \"local\"\n",
+    )
+    .expect("should write config");
+    let error = Config::load(temp_dir.path()).expect_err("model_path is required");
+    assert!(format!("{:#}", error).contains("model_path"), "{:#}", error);
+
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\nprovider = \"local\"\nmodel = \"bge-small-en-v1.5\"\nmodel_path = \"/models/bge-small-en-v1.5\"\nembedding_dimension = 384\n",
+    )
+    .expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.ollama.provider, EmbeddingBackend::Local);
+    assert_eq!(loaded_config.ollama.provider.default_port(), None);
+    assert_eq!(loaded_config.ollama.location(), "/models/bge-small-en-v1.5");
+    assert_eq!(
+        loaded_config.ollama.tokenizer_file(),
+        Some(PathBuf::from("/models/bge-small-en-v1.5/tokenizer.json"))
+    );
+}
+
+#[test]
+fn tokenizer_path_is_only_needed_for_servers() {
+    let mut config = OllamaConfig::default();
+    assert_eq!(config.tokenizer_file(), None);
+
+    config.tokenizer_path = Some(PathBuf::from("/models/nomic/tokenizer.json"));
+    assert_eq!(
+        config.tokenizer_file(),
+        Some(PathBuf::from("/models/nomic/tokenizer.json"))
+    );
+}
+
+#[test]
+fn hashing_provider_records_its_own_model_name() {
+    let mut config = OllamaConfig::default();
+    assert_eq!(config.model_name(), "nomic-embed-text:latest");
+
+    // `model` is ignored, so switching to hashing is a model change
+    config.provider = EmbeddingBackend::Hashing;
+    assert_eq!(config.model_name(), "feature-hashing");
+    assert_eq!(config.provider.default_port(), None);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn popular_models_have_built_in_prefixes() {
+    let prompt = PromptTemplate::for_model("nomic-embed-text:latest");
+    assert_eq!(prompt.format_query("tokio"), "search_query: tokio");
+    assert_eq!(prompt.format_document("tokio"), "search_document: tokio");
+
+    let prompt = PromptTemplate::for_model("intfloat/multilingual-e5-large");
+    assert_eq!(prompt.query, "query: ");
+    assert_eq!(prompt.document, "passage: ");
+
+    for model in ["BAAI/bge-small-en-v1.5", "mxbai-embed-large:335m"] {
+        let prompt = PromptTemplate::for_model(model);
+        assert!(
+            prompt.query.starts_with("Represent this sentence"),
+            "{}",
+            model
+        );
+        assert_eq!(prompt.document, "", "{}", model);
+    }
+
+    for model in [
+        "all-minilm",
+        "bge-m3",
+        "snowflake-arctic-embed2",
+        "feature-hashing",
+    ] {
+        assert_eq!(
+            PromptTemplate::for_model(model),
+            PromptTemplate::default(),
+            "{}",
+            model
+        );
+    }
+}
+
+#[test]
+fn config_toml_prompt_section() {
+    let temp_dir = TempDir::new().expect("should create temp dir");
+
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(loaded_config.ollama.prompt, None);
+    assert_eq!(
+        loaded_config.ollama.prompt_template(),
+        PromptTemplate::for_model("nomic-embed-text:latest")
+    );
+
+    // Configured prefixes replace the built-in ones, and a missing one is empty
+    std::fs::write(
+        temp_dir.path().join("config.toml"),
+        "[ollama]\nmodel = \"nomic-embed-text\"\n\n[ollama.prompt]\nquery = \"Query: \"\n",
+    )
+    .expect("should write config");
+    let loaded_config = Config::load(temp_dir.path()).expect("should load config");
+    assert_eq!(
+        loaded_config.ollama.prompt_template(),
+        PromptTemplate {
+            query: "Query: ".to_string(),
+            document: String::new(),
+        }
+    );
+}
+
+#[test]
+fn search_config_validation() {
+    let config = SearchConfig::default();
+    assert!(config.validate().is_ok());
+
+    let invalid = SearchConfig {
+        index_threshold: 255,
+        ..SearchConfig::default()
+    };
+    assert!(matches!(
+        invalid.validate(),
+        Err(ConfigError::InvalidIndexThreshold(255))
+    ));
+
+    let invalid = SearchConfig {
+        nprobes: 0,
+        ..SearchConfig::default()
+    };
+    assert!(matches!(
+        invalid.validate(),
+        Err(ConfigError::InvalidNprobes(0))
+    ));
+
+    let invalid = SearchConfig {
+        refine_factor: Some(0),
+        ..SearchConfig::default()
+    };
+    assert!(matches!(
+        invalid.validate(),
+        Err(ConfigError::InvalidRefineFactor(0))
+    ));
+
+    let valid = SearchConfig {
+        index_threshold: 256,
+        nprobes: 1000,
+        refine_factor: Some(10),
+        ..SearchConfig::default()
+    };
+    assert!(valid.validate().is_ok());
+}
@@ -38,8 +38,11 @@ mod integration_tests {
                 model: "test".to_string(),
                 batch_size: 1,
                 embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+                ..Default::default()
             },
             chunking: ChunkingConfig::default(),
+            search: Default::default(),
+            reranking: Default::default(),
             base_dir: temp_dir.path().to_path_buf(),
         };
 
@@ -56,6 +59,7 @@ mod integration_tests {
             model: "test".to_string(),
             batch_size: 1,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         };
 
         assert!(config.set_port(1).is_ok());
@@ -72,6 +76,7 @@ mod integration_tests {
             model: "test".to_string(),
             batch_size: 1,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         };
 
         assert!(config.set_batch_size(1).is_ok());
@@ -104,8 +109,11 @@ mod integration_tests {
                     model: "test".to_string(),
                     batch_size: 32,
                     embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+                    ..Default::default()
                 },
                 chunking: ChunkingConfig::default(),
+                search: Default::default(),
+                reranking: Default::default(),
                 base_dir: temp_dir.path().to_path_buf(),
             };
 
@@ -123,6 +131,7 @@ mod integration_tests {
             model: "valid-model".to_string(),
             batch_size: 32,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         };
 
         assert!(config.set_model("valid-model".to_string()).is_ok());
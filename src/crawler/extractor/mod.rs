@@ -90,6 +90,7 @@ pub fn extract_content(html: &str) -> Result<ExtractedContent> {
 }
 
 /// Extract content sections organized by heading hierarchy from markdown
+#[allow(clippy::collapsible_match)]
 fn extract_sections(markdown: &str) -> Result<Vec<ContentSection>> {
     let mut sections = Vec::new();
     let mut heading_stack: Vec<(u8, String)> = Vec::new();
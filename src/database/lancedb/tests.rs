@@ -65,6 +65,8 @@ mod integration_tests {
             base_dir: temp_dir.path().to_path_buf(),
             ollama: OllamaConfig::default(),
             chunking: ChunkingConfig::default(),
+            search: Default::default(),
+            reranking: Default::default(),
         };
         (config, temp_dir)
     }
@@ -199,7 +201,7 @@ mod integration_tests {
         // Test semantic search for Rust-related content
         let rust_query_vector = &dataset[0].vector; // Use Rust getting started vector as query
         let rust_results = store
-            .search_similar(rust_query_vector, 5, Some("rust_docs"))
+            .search_similar(rust_query_vector, 5, Some(&["rust_docs".to_string()]))
             .await
             .expect("search should succeed");
 
@@ -1,20 +1,24 @@
+mod staging;
 #[cfg(test)]
 mod tests;
 
 use super::{ChunkMetadata, EmbeddingRecord};
+use crate::config::settings::{DistanceMetric, SearchConfig};
 use crate::{DocsError, config::Config};
 use arrow::array::{
     Array, FixedSizeListArray, Float32Array, RecordBatchIterator, StringArray, UInt32Array,
 };
-use arrow::datatypes::{DataType, Field, Schema};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
 use arrow::record_batch::RecordBatch;
 use futures::TryStreamExt;
 use lancedb::{
-    Connection,
+    Connection, DistanceType, Table,
+    index::{Index, vector::IvfPqIndexBuilder},
     query::{ExecutableQuery, QueryBase},
 };
+use serde::Serialize;
 use std::path::Path;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, PoisonError};
 use tracing::{debug, error, info, warn};
 
 /// Vector database store using LanceDB for similarity search
@@ -22,6 +26,10 @@ pub struct VectorStore {
     connection: Connection,
     table_name: String,
     vector_dimension: usize,
+    /// Search settings, replaced when the configuration is reloaded
+    search: Mutex<SearchConfig>,
+    /// Table handle reused by searches, see [`VectorStore::refresh_search_table`]
+    search_table: Mutex<Option<Table>>,
 }
 
 /// Search result from vector similarity search
@@ -31,6 +39,33 @@ pub struct SearchResult {
     pub chunk_metadata: ChunkMetadata,
     pub similarity_score: f32,
     pub distance: f32,
+    /// How `similarity_score` was computed, when the result was reranked
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+/// Fewest embeddings an ANN index can be trained on
+pub const MIN_INDEXED_EMBEDDINGS: u64 = 256;
+
+/// State of the approximate nearest neighbour index on the vector column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorIndexStatus {
+    pub index_type: String,
+    /// Metric the index was built for, if it is one of the supported metrics
+    pub distance_metric: Option<DistanceMetric>,
+    pub indexed_rows: usize,
+    /// Embeddings added since the index was last updated, which searches scan
+    pub unindexed_rows: usize,
+}
+
+/// Components of a reranked result's score
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScoreBreakdown {
+    /// Score from the first-stage search, before reranking
+    pub retrieval_score: f32,
+    /// Score assigned by the reranker
+    pub rerank_score: f32,
+    /// Reranker model, or `lexical` for the query term overlap heuristic
+    pub reranker: String,
 }
 
 impl VectorStore {
@@ -89,6 +124,8 @@ impl VectorStore {
             connection,
             table_name,
             vector_dimension: config.ollama.embedding_dimension as usize,
+            search: Mutex::new(config.search.clone()),
+            search_table: Mutex::new(None),
         };
 
         // Initialize the table if it doesn't exist with corruption handling
@@ -155,23 +192,25 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to open existing table: {}", e)))?;
 
-        let schema = table
-            .schema()
-            .await
-            .map_err(|e| DocsError::Database(format!("Failed to get table schema: {}", e)))?;
+        table_vector_dimension(&table).await
+    }
 
-        // Find the vector column and extract its dimension
-        for field in schema.fields() {
-            if field.name() == "vector" {
-                if let DataType::FixedSizeList(_, size) = field.data_type() {
-                    return Ok(*size as usize);
-                }
-            }
-        }
+    /// Dimension of the embeddings stored in the vector table
+    pub fn vector_dimension(&self) -> usize {
+        self.vector_dimension
+    }
 
-        Err(DocsError::Database(
-            "Could not find vector column or determine dimension".to_string(),
-        ))
+    /// The search settings in use
+    pub fn search_config(&self) -> SearchConfig {
+        self.search
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Replace the search settings used by later searches and index builds
+    pub fn set_search_config(&self, search: SearchConfig) {
+        *self.search.lock().unwrap_or_else(PoisonError::into_inner) = search;
     }
 
     /// Create schema with the specified vector dimension
@@ -216,18 +255,26 @@ impl VectorStore {
 
         debug!("Storing batch of {} embeddings", records.len());
 
-        // Auto-detect vector dimension from first record and recreate table if needed
+        // Auto-detect vector dimension from first record. The table is only
+        // recreated while it is empty, so no site's embeddings are lost.
         let vector_dim = records[0].vector.len();
         if self.vector_dimension != vector_dim {
+            let stored = self.count_embeddings().await?;
+            if stored > 0 {
+                return Err(DocsError::Embedding(format!(
+                    "Embeddings have {} dimensions, but the vector table holds {} embeddings with {} dimensions. Run `docs-mcp reembed` to re-embed all sites with the current model",
+                    vector_dim, stored, self.vector_dimension
+                )));
+            }
             info!(
-                "Vector dimension changed from {:?} to {}, recreating table",
+                "Vector dimension changed from {:?} to {}, recreating empty table",
                 self.vector_dimension, vector_dim
             );
             self.recreate_table_with_dimension(vector_dim).await?;
             self.vector_dimension = vector_dim;
         }
 
-        let record_batch = self.create_record_batch(&records)?;
+        let record_batch = self.create_record_batch(&records, self.vector_dimension)?;
 
         let table = self
             .connection
@@ -244,6 +291,7 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to insert embeddings: {}", e)))?;
 
+        self.invalidate_search_table();
         info!("Successfully stored {} embeddings", records.len());
         Ok(())
     }
@@ -272,8 +320,12 @@ impl VectorStore {
         Ok(())
     }
 
-    /// Create a RecordBatch from embedding records
-    fn create_record_batch(&self, records: &[EmbeddingRecord]) -> Result<RecordBatch, DocsError> {
+    /// Create a RecordBatch from embedding records with `vector_dim` dimensions
+    fn create_record_batch(
+        &self,
+        records: &[EmbeddingRecord],
+        vector_dim: usize,
+    ) -> Result<RecordBatch, DocsError> {
         let len = records.len();
 
         let mut ids = Vec::with_capacity(len);
@@ -302,22 +354,20 @@ impl VectorStore {
             created_ats.push(record.metadata.created_at.as_str());
         }
 
-        let schema = self.create_schema(self.vector_dimension);
+        let schema = self.create_schema(vector_dim);
 
         // Create vector array using FixedSizeListArray
-        let mut flat_values = Vec::with_capacity(len * self.vector_dimension);
+        let mut flat_values = Vec::with_capacity(len * vector_dim);
         for vector in &vectors {
             flat_values.extend_from_slice(vector);
         }
         let values_array = Float32Array::from(flat_values);
         let field = Arc::new(Field::new("item", DataType::Float32, false));
-        let vector_array = FixedSizeListArray::try_new(
-            field,
-            self.vector_dimension as i32,
-            Arc::new(values_array),
-            None,
-        )
-        .map_err(|e| DocsError::Database(format!("Failed to create vector array: {}", e)))?;
+        let vector_array =
+            FixedSizeListArray::try_new(field, vector_dim as i32, Arc::new(values_array), None)
+                .map_err(|e| {
+                    DocsError::Database(format!("Failed to create vector array: {}", e))
+                })?;
 
         let arrays: Vec<Arc<dyn arrow::array::Array>> = vec![
             Arc::new(StringArray::from(ids)),
@@ -342,7 +392,7 @@ impl VectorStore {
     /// # Arguments
     /// * `query_vector` - The query vector to search for
     /// * `limit` - Maximum number of results to return
-    /// * `site_filter` - Optional site ID to filter results
+    /// * `site_filter` - Optional set of site IDs to restrict results to
     ///
     /// # Returns
     /// * `Result<Vec<SearchResult>, DocsError>` - Search results or error
@@ -350,26 +400,32 @@ impl VectorStore {
         &self,
         query_vector: &[f32],
         limit: usize,
-        site_filter: Option<&str>,
+        site_filter: Option<&[String]>,
     ) -> Result<Vec<SearchResult>, DocsError> {
         debug!("Searching for similar vectors with limit: {}", limit);
 
-        let table = self
-            .connection
-            .open_table(&self.table_name)
-            .execute()
-            .await
-            .map_err(|e| DocsError::Database(format!("Failed to open table: {}", e)))?;
+        if site_filter.is_some_and(|site_ids| site_ids.is_empty()) {
+            return Ok(Vec::new());
+        }
+
+        let table = self.search_table().await?;
+        let search = self.search_config();
 
         let mut query = table
             .vector_search(query_vector)
             .map_err(|e| DocsError::Database(format!("Failed to create vector search: {}", e)))?
             .column("vector")
+            .distance_type(distance_type(search.distance_metric))
+            .nprobes(search.nprobes as usize)
             .limit(limit);
 
+        if let Some(refine_factor) = search.refine_factor {
+            query = query.refine_factor(refine_factor);
+        }
+
         // Apply site filter if provided
-        if let Some(site_id) = site_filter {
-            query = query.only_if(format!("site_id = '{}'", site_id));
+        if let Some(site_ids) = site_filter {
+            query = query.only_if(site_filter_predicate(site_ids));
         }
 
         let results = query
@@ -377,13 +433,85 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to execute search: {}", e)))?;
 
-        self.parse_search_results_stream(results).await
+        self.parse_search_results_stream(results, search.distance_metric)
+            .await
+    }
+
+    /// Table handle used for searches
+    ///
+    /// Opening the table reads its manifest, so the handle is kept between
+    /// searches. Writes through this store drop it, and long-running readers
+    /// pick up writes from other processes with [`Self::refresh_search_table`].
+    async fn search_table(&self) -> Result<Table, DocsError> {
+        if let Some(table) = self.cached_search_table() {
+            return Ok(table);
+        }
+
+        let table = self.open_search_table().await?;
+        *self
+            .search_table
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(table.clone());
+        Ok(table)
+    }
+
+    /// Reopen the search table if it has been written or replaced since it
+    /// was opened
+    ///
+    /// A table dropped and recreated, such as for a new vector dimension,
+    /// starts its versions again, so the schema is compared as well as the
+    /// version. Returns whether the table handle used for searches changed.
+    pub async fn refresh_search_table(&self) -> Result<bool, DocsError> {
+        let Some(current) = self.cached_search_table() else {
+            // Nothing cached, so the next search opens the latest version
+            return Ok(false);
+        };
+
+        let latest = self.open_search_table().await?;
+        let (current_version, current_schema) = table_identity(&current).await?;
+        let (latest_version, latest_schema) = table_identity(&latest).await?;
+        if current_version == latest_version && current_schema == latest_schema {
+            return Ok(false);
+        }
+
+        debug!(
+            "Reopened search table at version {} (was {})",
+            latest_version, current_version
+        );
+        *self
+            .search_table
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = Some(latest);
+        Ok(true)
+    }
+
+    async fn open_search_table(&self) -> Result<Table, DocsError> {
+        self.connection
+            .open_table(&self.table_name)
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to open table: {}", e)))
+    }
+
+    fn cached_search_table(&self) -> Option<Table> {
+        self.search_table
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn invalidate_search_table(&self) {
+        *self
+            .search_table
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) = None;
     }
 
     /// Parse search results from LanceDB stream into SearchResult structs
     async fn parse_search_results_stream(
         &self,
         mut results: lancedb::arrow::SendableRecordBatchStream,
+        distance_metric: DistanceMetric,
     ) -> Result<Vec<SearchResult>, DocsError> {
         let mut search_results = Vec::new();
 
@@ -392,7 +520,7 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to read result stream: {}", e)))?
         {
-            let parsed_batch = self.parse_search_batch(&batch_result)?;
+            let parsed_batch = self.parse_search_batch(&batch_result, distance_metric)?;
             search_results.extend(parsed_batch);
         }
 
@@ -401,7 +529,11 @@ impl VectorStore {
     }
 
     /// Parse a single record batch from search results
-    fn parse_search_batch(&self, batch: &RecordBatch) -> Result<Vec<SearchResult>, DocsError> {
+    fn parse_search_batch(
+        &self,
+        batch: &RecordBatch,
+        distance_metric: DistanceMetric,
+    ) -> Result<Vec<SearchResult>, DocsError> {
         let mut search_results = Vec::new();
         let num_rows = batch.num_rows();
 
@@ -495,13 +627,14 @@ impl VectorStore {
                 .flatten()
                 .map_or(0.0, |d| if d.is_null(row) { 0.0 } else { d.value(row) });
 
-            // Convert distance to similarity score (higher is better)
-            let similarity_score = 1.0 - distance;
+            // Convert distance to a 0-1 similarity score (higher is better)
+            let similarity_score = distance_metric.similarity(distance);
 
             search_results.push(SearchResult {
                 chunk_metadata,
                 similarity_score,
                 distance,
+                score_breakdown: None,
             });
         }
 
@@ -532,15 +665,32 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to delete site embeddings: {}", e)))?;
 
+        self.invalidate_search_table();
         info!("Deleted embeddings for site: {}", site_id);
         Ok(0) // LanceDB doesn't return count of deleted rows
     }
 
+    /// Delete the embeddings with the given vector IDs
+    pub async fn delete_embeddings(&self, vector_ids: &[String]) -> Result<(), DocsError> {
+        if vector_ids.is_empty() {
+            return Ok(());
+        }
+
+        let table = self.open_search_table().await?;
+        table
+            .delete(&format!("id IN ({})", quoted_list(vector_ids)))
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to delete embeddings: {}", e)))?;
+
+        self.invalidate_search_table();
+        debug!("Deleted {} embeddings", vector_ids.len());
+        Ok(())
+    }
+
     /// Get the total number of embeddings stored
     ///
     /// # Returns
     /// * `Result<u64, DocsError>` - Total count or error
-    #[allow(dead_code)]
     pub async fn count_embeddings(&self) -> Result<u64, DocsError> {
         let table = self
             .connection
@@ -590,15 +740,17 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to optimize table: {}", e)))?;
 
+        self.invalidate_search_table();
         info!("Vector database optimization and old version cleanup completed");
         Ok(())
     }
 
     /// Create index on the vector column for improved search performance
     ///
+    /// Any existing index is replaced, retraining it on all embeddings.
+    ///
     /// # Returns
     /// * `Result<(), DocsError>` - Success or error
-    #[allow(dead_code)]
     pub async fn create_vector_index(&self) -> Result<(), DocsError> {
         debug!("Creating vector index for improved search performance");
 
@@ -609,16 +761,87 @@ impl VectorStore {
             .await
             .map_err(|e| DocsError::Database(format!("Failed to open table: {}", e)))?;
 
+        // The index must use the same metric as queries for them to use it
+        let index = IvfPqIndexBuilder::default()
+            .distance_type(distance_type(self.search_config().distance_metric));
         table
-            .create_index(&["vector"], lancedb::index::Index::Auto)
+            .create_index(&["vector"], Index::IvfPq(index))
+            .replace(true)
             .execute()
             .await
             .map_err(|e| DocsError::Database(format!("Failed to create vector index: {}", e)))?;
 
+        self.invalidate_search_table();
         info!("Vector index created successfully");
         Ok(())
     }
 
+    /// Build the vector index once the table reaches the configured size
+    ///
+    /// An index built for another distance metric is rebuilt. Embeddings added
+    /// to an up to date index are merged into it by [`Self::optimize`] instead.
+    ///
+    /// # Returns
+    /// * `Result<bool, DocsError>` - Whether an index was built
+    pub async fn update_vector_index(&self) -> Result<bool, DocsError> {
+        let search = self.search_config();
+        let rows = self.count_embeddings().await?;
+        if rows < search.index_threshold.max(MIN_INDEXED_EMBEDDINGS) {
+            debug!(
+                "Not indexing {} embeddings, below the threshold of {}",
+                rows, search.index_threshold
+            );
+            return Ok(false);
+        }
+
+        match self.vector_index_status().await? {
+            Some(status) if status.distance_metric == Some(search.distance_metric) => {
+                return Ok(false);
+            }
+            Some(status) => info!(
+                "Rebuilding {} vector index for the {} distance metric",
+                status.index_type, search.distance_metric
+            ),
+            None => info!("Building vector index for {} embeddings", rows),
+        }
+
+        self.create_vector_index().await?;
+        Ok(true)
+    }
+
+    /// Describe the index on the vector column, if there is one
+    pub async fn vector_index_status(&self) -> Result<Option<VectorIndexStatus>, DocsError> {
+        let table = self
+            .connection
+            .open_table(&self.table_name)
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to open table: {}", e)))?;
+
+        let indices = table
+            .list_indices()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to list indices: {}", e)))?;
+        let Some(index) = indices
+            .into_iter()
+            .find(|index| index.columns == ["vector"])
+        else {
+            return Ok(None);
+        };
+
+        let stats = table
+            .index_stats(&index.name)
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to get index statistics: {}", e)))?;
+
+        Ok(stats.map(|stats| VectorIndexStatus {
+            index_type: stats.index_type.to_string(),
+            distance_metric: stats.distance_type.and_then(distance_metric),
+            indexed_rows: stats.num_indexed_rows,
+            unindexed_rows: stats.num_unindexed_rows,
+        }))
+    }
+
     /// Attempt to recover from database corruption
     ///
     /// # Arguments
@@ -694,6 +917,7 @@ impl VectorStore {
                 .drop_table(&self.table_name)
                 .await
                 .map_err(|e| DocsError::Database(format!("Failed to drop table: {}", e)))?;
+            self.invalidate_search_table();
         }
 
         Ok(())
@@ -792,3 +1016,70 @@ impl VectorStore {
         Ok(vec![])
     }
 }
+
+/// Version and schema of `table`, which together tell whether it changed
+async fn table_identity(table: &Table) -> Result<(u64, SchemaRef), DocsError> {
+    let version = table
+        .version()
+        .await
+        .map_err(|e| DocsError::Database(format!("Failed to get table version: {}", e)))?;
+    let schema = table
+        .schema()
+        .await
+        .map_err(|e| DocsError::Database(format!("Failed to get table schema: {}", e)))?;
+    Ok((version, schema))
+}
+
+/// Dimension of the vector column of a table
+async fn table_vector_dimension(table: &Table) -> Result<usize, DocsError> {
+    let schema = table
+        .schema()
+        .await
+        .map_err(|e| DocsError::Database(format!("Failed to get table schema: {}", e)))?;
+
+    // Find the vector column and extract its dimension
+    for field in schema.fields() {
+        if field.name() == "vector" {
+            if let DataType::FixedSizeList(_, size) = field.data_type() {
+                return Ok(*size as usize);
+            }
+        }
+    }
+
+    Err(DocsError::Database(
+        "Could not find vector column or determine dimension".to_string(),
+    ))
+}
+
+/// LanceDB distance type for a configured metric
+fn distance_type(metric: DistanceMetric) -> DistanceType {
+    match metric {
+        DistanceMetric::Cosine => DistanceType::Cosine,
+        DistanceMetric::Dot => DistanceType::Dot,
+        DistanceMetric::L2 => DistanceType::L2,
+    }
+}
+
+/// Configured metric matching a LanceDB distance type
+fn distance_metric(distance_type: DistanceType) -> Option<DistanceMetric> {
+    match distance_type {
+        DistanceType::Cosine => Some(DistanceMetric::Cosine),
+        DistanceType::Dot => Some(DistanceMetric::Dot),
+        DistanceType::L2 => Some(DistanceMetric::L2),
+        _ => None,
+    }
+}
+
+/// Build a LanceDB predicate matching any of the given site IDs
+fn site_filter_predicate(site_ids: &[String]) -> String {
+    format!("site_id IN ({})", quoted_list(site_ids))
+}
+
+/// Quote strings as SQL literals, separated by commas
+fn quoted_list(values: &[String]) -> String {
+    values
+        .iter()
+        .map(|value| format!("'{}'", value.replace('\'', "''")))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,251 @@
+//! Staged Vector Tables
+//!
+//! Re-embedding every chunk takes a long time, and the existing embeddings must
+//! stay searchable meanwhile. New embeddings are therefore written to a staging
+//! table, which replaces the contents of the embeddings table in a single new
+//! table version once it is complete. Readers holding the previous version keep
+//! using it until they refresh.
+
+use std::sync::Arc;
+
+use arrow::array::{RecordBatchIterator, RecordBatchReader};
+use arrow::datatypes::SchemaRef;
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use futures::TryStreamExt;
+use lancedb::Table;
+use lancedb::arrow::SendableRecordBatchStream;
+use lancedb::query::{ExecutableQuery, QueryBase};
+use lancedb::table::AddDataMode;
+use tokio::runtime::Handle;
+use tracing::{debug, info};
+
+use super::{VectorStore, site_filter_predicate, table_vector_dimension};
+use crate::DocsError;
+use crate::database::lancedb::EmbeddingRecord;
+
+impl VectorStore {
+    fn staging_table_name(&self) -> String {
+        format!("{}_staging", self.table_name)
+    }
+
+    /// Start staging a replacement for the embeddings table, discarding any
+    /// staging table left by an interrupted run
+    ///
+    /// Returns the version of the embeddings table being replaced, which
+    /// [`Self::swap_in_staged_table`] checks is still current.
+    pub async fn begin_staging(&self) -> Result<u64, DocsError> {
+        let staging_name = self.staging_table_name();
+        if self.table_exists(&staging_name).await? {
+            info!("Discarding unfinished staging table {}", staging_name);
+            self.connection
+                .drop_table(&staging_name)
+                .await
+                .map_err(|e| DocsError::Database(format!("Failed to drop table: {}", e)))?;
+        }
+        self.table_version().await
+    }
+
+    /// Add embeddings to the staging table
+    ///
+    /// The staging table is created with the dimension of the first embeddings
+    /// stored, which may differ from the current table's.
+    pub async fn store_staged_embeddings(
+        &self,
+        records: Vec<EmbeddingRecord>,
+    ) -> Result<(), DocsError> {
+        let Some(first) = records.first() else {
+            return Ok(());
+        };
+        let vector_dim = first.vector.len();
+
+        let table = self.staging_table(vector_dim).await?;
+        let staged_dim = table_vector_dimension(&table).await?;
+        if staged_dim != vector_dim {
+            return Err(DocsError::Embedding(format!(
+                "Embeddings have {} dimensions, but the staged embeddings have {}",
+                vector_dim, staged_dim
+            )));
+        }
+
+        let record_batch = self.create_record_batch(&records, vector_dim)?;
+        let schema = record_batch.schema();
+        let reader = RecordBatchIterator::new(std::iter::once(Ok(record_batch)), schema);
+        table
+            .add(reader)
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to stage embeddings: {}", e)))?;
+
+        debug!("Staged {} embeddings", records.len());
+        Ok(())
+    }
+
+    /// Copy the current embeddings of every site except `site_ids` into the
+    /// staging table, returning how many were copied
+    pub async fn stage_existing_embeddings(&self, site_ids: &[String]) -> Result<u64, DocsError> {
+        let table = self.open_search_table().await?;
+        let mut query = table.query();
+        if !site_ids.is_empty() {
+            query = query.only_if(format!("NOT ({})", site_filter_predicate(site_ids)));
+        }
+        let mut batches = query
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to read embeddings: {}", e)))?;
+
+        let staging = self.staging_table(self.vector_dimension).await?;
+        let staged_dim = table_vector_dimension(&staging).await?;
+        if staged_dim != self.vector_dimension {
+            return Err(DocsError::Embedding(format!(
+                "The staged embeddings have {} dimensions, but the existing embeddings have {}",
+                staged_dim, self.vector_dimension
+            )));
+        }
+
+        let mut copied = 0;
+        while let Some(batch) = batches
+            .try_next()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to read embeddings: {}", e)))?
+        {
+            if batch.num_rows() == 0 {
+                continue;
+            }
+            copied += batch.num_rows() as u64;
+            let schema = batch.schema();
+            let reader = RecordBatchIterator::new(std::iter::once(Ok(batch)), schema);
+            staging
+                .add(reader)
+                .execute()
+                .await
+                .map_err(|e| DocsError::Database(format!("Failed to stage embeddings: {}", e)))?;
+        }
+
+        debug!("Copied {} existing embeddings to the staging table", copied);
+        Ok(copied)
+    }
+
+    /// Replace the embeddings table's contents with the staging table
+    ///
+    /// The replacement is written as one new version of the embeddings table,
+    /// so searches see either all old or all new embeddings. Staged batches are
+    /// streamed into the write rather than loaded into memory together. Any
+    /// vector index is dropped along with the old embeddings.
+    ///
+    /// The swap is refused if the embeddings table has changed since
+    /// `base_version`, as embeddings written meanwhile, such as by indexing in
+    /// another process, would be lost.
+    pub async fn swap_in_staged_table(&mut self, base_version: u64) -> Result<u64, DocsError> {
+        let current_version = self.table_version().await?;
+        if current_version != base_version {
+            return Err(DocsError::Database(format!(
+                "The embeddings table changed while re-embedding (version {} is now {}), so its new embeddings would be lost",
+                base_version, current_version
+            )));
+        }
+
+        let staging = self.staging_table(self.vector_dimension).await?;
+        let vector_dim = table_vector_dimension(&staging).await?;
+        let rows = staging.count_rows(None).await.map_err(|e| {
+            DocsError::Database(format!("Failed to count staged embeddings: {}", e))
+        })?;
+
+        let batches =
+            staging.query().execute().await.map_err(|e| {
+                DocsError::Database(format!("Failed to read staged embeddings: {}", e))
+            })?;
+        let reader = StagedBatchReader {
+            batches,
+            schema: self.create_schema(vector_dim),
+            runtime: Handle::current(),
+        };
+        let table = self.open_search_table().await?;
+        table
+            .add(reader)
+            .mode(AddDataMode::Overwrite)
+            .execute()
+            .await
+            .map_err(|e| {
+                DocsError::Database(format!("Failed to replace embeddings table: {}", e))
+            })?;
+
+        self.vector_dimension = vector_dim;
+        self.invalidate_search_table();
+        self.connection
+            .drop_table(self.staging_table_name())
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to drop staging table: {}", e)))?;
+
+        info!(
+            "Replaced embeddings table with {} staged embeddings of {} dimensions",
+            rows, vector_dim
+        );
+        Ok(rows as u64)
+    }
+
+    async fn table_version(&self) -> Result<u64, DocsError> {
+        self.open_search_table()
+            .await?
+            .version()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to read table version: {}", e)))
+    }
+
+    /// Open the staging table, creating it for `vector_dim` dimensions if needed
+    async fn staging_table(&self, vector_dim: usize) -> Result<Table, DocsError> {
+        let staging_name = self.staging_table_name();
+        if !self.table_exists(&staging_name).await? {
+            self.connection
+                .create_empty_table(&staging_name, self.create_schema(vector_dim))
+                .execute()
+                .await
+                .map_err(|e| {
+                    DocsError::Database(format!("Failed to create staging table: {}", e))
+                })?;
+        }
+
+        self.connection
+            .open_table(&staging_name)
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to open staging table: {}", e)))
+    }
+
+    async fn table_exists(&self, name: &str) -> Result<bool, DocsError> {
+        let table_names = self
+            .connection
+            .table_names()
+            .execute()
+            .await
+            .map_err(|e| DocsError::Database(format!("Failed to list tables: {}", e)))?;
+        Ok(table_names.iter().any(|table_name| table_name == name))
+    }
+}
+
+/// Reads the staged embeddings one batch at a time for the table overwrite
+///
+/// LanceDB pulls batches from the reader on a blocking thread, so each batch
+/// can wait on the asynchronous query stream there.
+struct StagedBatchReader {
+    batches: SendableRecordBatchStream,
+    schema: SchemaRef,
+    runtime: Handle,
+}
+
+impl Iterator for StagedBatchReader {
+    type Item = Result<RecordBatch, ArrowError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.runtime
+            .block_on(self.batches.try_next())
+            .map_err(|e| ArrowError::ExternalError(Box::new(e)))
+            .transpose()
+    }
+}
+
+impl RecordBatchReader for StagedBatchReader {
+    fn schema(&self) -> SchemaRef {
+        Arc::clone(&self.schema)
+    }
+}
@@ -12,6 +12,8 @@ fn create_test_config() -> (Config, TempDir) {
             ..OllamaConfig::default()
         },
         chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: Default::default(),
     };
     (config, temp_dir)
 }
@@ -168,7 +170,7 @@ async fn search_with_site_filter() {
     // Search with site filter
     let query_vector = vec![0.1, 0.2, 0.3, 0.4, 0.5];
     let results = store
-        .search_similar(&query_vector, 10, Some("site_1"))
+        .search_similar(&query_vector, 10, Some(&["site_1".to_string()]))
         .await
         .expect("search should succeed");
 
@@ -180,6 +182,137 @@ async fn search_with_site_filter() {
     }
 }
 
+#[tokio::test]
+async fn search_with_multiple_site_filter() {
+    let (config, _temp_dir) = create_test_config();
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    let records = vec![
+        create_test_embedding_record("test_1", "site_1"),
+        create_test_embedding_record("test_2", "site_2"),
+        create_test_embedding_record("test_3", "site_3"),
+    ];
+
+    store
+        .store_embeddings_batch(records)
+        .await
+        .expect("should store embeddings successfully");
+
+    let query_vector = vec![0.1, 0.2, 0.3, 0.4, 0.5];
+    let site_ids = vec!["site_1".to_string(), "site_3".to_string()];
+    let results = store
+        .search_similar(&query_vector, 10, Some(&site_ids))
+        .await
+        .expect("search should succeed");
+
+    assert_eq!(results.len(), 2);
+    for result in &results {
+        assert!(site_ids.contains(&result.chunk_metadata.site_id));
+    }
+
+    let no_results = store
+        .search_similar(&query_vector, 10, Some(&[]))
+        .await
+        .expect("search should succeed");
+    assert!(no_results.is_empty());
+}
+
+#[tokio::test]
+async fn search_table_refreshes_after_external_writes() {
+    let (config, _temp_dir) = create_test_config();
+    let mut writer = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+    writer
+        .store_embeddings_batch(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    // A separate store stands in for a server process reading the same table
+    let reader = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+    let query = vec![0.1, 0.2, 0.3, 0.4, 0.5];
+    let search = || reader.search_similar(&query, 10, None);
+    assert_eq!(search().await.expect("search should succeed").len(), 1);
+
+    writer
+        .store_embeddings_batch(vec![create_test_embedding_record("2", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    // The reader keeps its table handle until it is refreshed
+    assert_eq!(search().await.expect("search should succeed").len(), 1);
+    assert!(
+        reader
+            .refresh_search_table()
+            .await
+            .expect("refresh should succeed")
+    );
+    assert_eq!(search().await.expect("search should succeed").len(), 2);
+    assert!(
+        !reader
+            .refresh_search_table()
+            .await
+            .expect("refresh should succeed")
+    );
+}
+
+#[tokio::test]
+async fn search_table_refreshes_after_the_table_is_recreated() {
+    let (config, _temp_dir) = create_test_config();
+    let mut writer = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+    writer
+        .store_embeddings_batch(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    let reader = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+    let results = reader
+        .search_similar(&[0.1, 0.2, 0.3, 0.4, 0.5], 10, None)
+        .await
+        .expect("search should succeed");
+    assert_eq!(results.len(), 1);
+
+    // Emptying the table lets a new dimension recreate it, which brings it
+    // back to the version the reader holds
+    writer
+        .delete_site_embeddings("site_1")
+        .await
+        .expect("should delete embeddings");
+    let mut record = create_test_embedding_record("2", "site_2");
+    record.vector.truncate(3);
+    writer
+        .store_embeddings_batch(vec![record])
+        .await
+        .expect("should store embedding");
+
+    assert!(
+        reader
+            .refresh_search_table()
+            .await
+            .expect("refresh should succeed")
+    );
+    let results = reader
+        .search_similar(&[0.1, 0.2, 0.3], 10, None)
+        .await
+        .expect("search should succeed");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].chunk_metadata.site_id, "site_2");
+}
+
+#[test]
+fn site_filter_predicate_escapes_quotes() {
+    let predicate = super::site_filter_predicate(&["1".to_string(), "it's".to_string()]);
+    assert_eq!(predicate, "site_id IN ('1', 'it''s')");
+}
+
 #[tokio::test]
 async fn delete_site_embeddings() {
     let (config, _temp_dir) = create_test_config();
@@ -265,3 +398,289 @@ async fn optimize_database() {
         result.err()
     );
 }
+
+#[tokio::test]
+async fn similarity_scores_agree_across_distance_metrics() {
+    for metric in [
+        DistanceMetric::Cosine,
+        DistanceMetric::Dot,
+        DistanceMetric::L2,
+    ] {
+        let (mut config, _temp_dir) = create_test_config();
+        config.search.distance_metric = metric;
+        let mut store = VectorStore::new(&config)
+            .await
+            .expect("should create vector store");
+
+        // Unit vectors with cosine similarities 1, 0.6 and 0 to the query
+        let records = [
+            ("same", vec![1.0, 0.0, 0.0, 0.0, 0.0]),
+            ("close", vec![0.6, 0.8, 0.0, 0.0, 0.0]),
+            ("orthogonal", vec![0.0, 1.0, 0.0, 0.0, 0.0]),
+        ]
+        .into_iter()
+        .map(|(id, vector)| EmbeddingRecord {
+            vector,
+            ..create_test_embedding_record(id, "site_1")
+        })
+        .collect();
+        store
+            .store_embeddings_batch(records)
+            .await
+            .expect("should store embeddings successfully");
+
+        let results = store
+            .search_similar(&[1.0, 0.0, 0.0, 0.0, 0.0], 10, None)
+            .await
+            .expect("search should succeed");
+
+        let scores: Vec<f32> = results.iter().map(|r| r.similarity_score).collect();
+        assert_eq!(scores.len(), 3, "{:?}", metric);
+        for (score, expected) in scores.iter().zip([1.0, 0.6, 0.0]) {
+            assert!(
+                (score - expected).abs() < 1e-4,
+                "{:?} scored {:?}",
+                metric,
+                scores
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn vector_index_is_built_past_threshold() {
+    let (mut config, _temp_dir) = create_test_config();
+    config.search.index_threshold = 300;
+    config.search.refine_factor = Some(5);
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    let records = |range: std::ops::Range<u32>| -> Vec<EmbeddingRecord> {
+        range
+            .map(|i| {
+                let angle = i as f32 * 0.05;
+                EmbeddingRecord {
+                    vector: vec![angle.cos(), angle.sin(), 0.1, 0.2, 0.3],
+                    ..create_test_embedding_record(&i.to_string(), "site_1")
+                }
+            })
+            .collect()
+    };
+
+    store
+        .store_embeddings_batch(records(0..299))
+        .await
+        .expect("should store embeddings");
+    assert!(
+        !store
+            .update_vector_index()
+            .await
+            .expect("should check index")
+    );
+    assert_eq!(store.vector_index_status().await.expect("status"), None);
+
+    store
+        .store_embeddings_batch(records(299..320))
+        .await
+        .expect("should store embeddings");
+    assert!(
+        store
+            .update_vector_index()
+            .await
+            .expect("should build index")
+    );
+    let status = store
+        .vector_index_status()
+        .await
+        .expect("status")
+        .expect("index should exist");
+    assert_eq!(status.distance_metric, Some(DistanceMetric::L2));
+    assert_eq!(status.indexed_rows, 320);
+    assert_eq!(status.unindexed_rows, 0);
+
+    // An up to date index is left alone
+    assert!(
+        !store
+            .update_vector_index()
+            .await
+            .expect("should check index")
+    );
+
+    // Searches go through the index
+    let results = store
+        .search_similar(&[1.0, 0.0, 0.1, 0.2, 0.3], 5, None)
+        .await
+        .expect("search should succeed");
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[0].chunk_metadata.chunk_id, "chunk_0");
+
+    // Changing the metric rebuilds the index
+    config.search.distance_metric = DistanceMetric::Cosine;
+    let store = VectorStore::new(&config)
+        .await
+        .expect("should reopen vector store");
+    assert!(
+        store
+            .update_vector_index()
+            .await
+            .expect("should rebuild index")
+    );
+    let status = store
+        .vector_index_status()
+        .await
+        .expect("status")
+        .expect("index should exist");
+    assert_eq!(status.distance_metric, Some(DistanceMetric::Cosine));
+}
+
+#[tokio::test]
+async fn dimension_change_is_refused_for_stored_embeddings() {
+    let (config, _temp_dir) = create_test_config();
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    store
+        .store_embeddings_batch(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    let record = EmbeddingRecord {
+        vector: vec![0.1, 0.2, 0.3],
+        ..create_test_embedding_record("2", "site_1")
+    };
+    let error = store
+        .store_embeddings_batch(vec![record])
+        .await
+        .expect_err("mismatched dimension should be refused");
+    assert!(error.to_string().contains("reembed"), "{}", error);
+
+    // The existing embeddings are untouched
+    assert_eq!(store.count_embeddings().await.expect("count"), 1);
+    assert_eq!(store.vector_dimension(), 5);
+}
+
+#[tokio::test]
+async fn staged_embeddings_replace_the_table() {
+    let (config, _temp_dir) = create_test_config();
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    store
+        .store_embeddings_batch(vec![
+            create_test_embedding_record("1", "site_1"),
+            create_test_embedding_record("2", "site_2"),
+        ])
+        .await
+        .expect("should store embeddings");
+
+    let base_version = store.begin_staging().await.expect("should begin staging");
+    let restaged = EmbeddingRecord {
+        vector: vec![0.9, 0.1, 0.2, 0.3, 0.4],
+        ..create_test_embedding_record("1", "site_1")
+    };
+    store
+        .store_staged_embeddings(vec![restaged])
+        .await
+        .expect("should stage embeddings");
+    let kept = store
+        .stage_existing_embeddings(&["site_1".to_string()])
+        .await
+        .expect("should copy other sites");
+    assert_eq!(kept, 1);
+
+    // Searches see the old embeddings until the swap
+    let before = store
+        .search_similar(&[0.9, 0.1, 0.2, 0.3, 0.4], 5, Some(&["site_1".to_string()]))
+        .await
+        .expect("search should succeed");
+    assert!(before[0].similarity_score < 0.99);
+
+    let rows = store
+        .swap_in_staged_table(base_version)
+        .await
+        .expect("should swap in staged table");
+    assert_eq!(rows, 2);
+    assert_eq!(store.count_embeddings().await.expect("count"), 2);
+    let after = store
+        .search_similar(&[0.9, 0.1, 0.2, 0.3, 0.4], 5, Some(&["site_1".to_string()]))
+        .await
+        .expect("search should succeed");
+    assert!(after[0].similarity_score > 0.99);
+}
+
+#[tokio::test]
+async fn staged_embeddings_may_change_dimension() {
+    let (config, _temp_dir) = create_test_config();
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    store
+        .store_embeddings_batch(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    // A leftover staging table from an interrupted run is discarded
+    store
+        .store_staged_embeddings(vec![create_test_embedding_record("9", "site_9")])
+        .await
+        .expect("should stage embeddings");
+    let base_version = store.begin_staging().await.expect("should begin staging");
+
+    let record = EmbeddingRecord {
+        vector: vec![1.0, 0.0, 0.0],
+        ..create_test_embedding_record("1", "site_1")
+    };
+    store
+        .store_staged_embeddings(vec![record])
+        .await
+        .expect("should stage embeddings");
+    store
+        .swap_in_staged_table(base_version)
+        .await
+        .expect("should swap in staged table");
+
+    assert_eq!(store.vector_dimension(), 3);
+    assert_eq!(store.count_embeddings().await.expect("count"), 1);
+    let results = store
+        .search_similar(&[1.0, 0.0, 0.0], 5, None)
+        .await
+        .expect("search should succeed");
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].chunk_metadata.site_id, "site_1");
+}
+
+#[tokio::test]
+async fn staged_embeddings_are_not_swapped_over_newer_embeddings() {
+    let (config, _temp_dir) = create_test_config();
+    let mut store = VectorStore::new(&config)
+        .await
+        .expect("should create vector store");
+
+    store
+        .store_embeddings_batch(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should store embedding");
+
+    let base_version = store.begin_staging().await.expect("should begin staging");
+    store
+        .store_staged_embeddings(vec![create_test_embedding_record("1", "site_1")])
+        .await
+        .expect("should stage embeddings");
+
+    // Another site is indexed while re-embedding
+    store
+        .store_embeddings_batch(vec![create_test_embedding_record("2", "site_2")])
+        .await
+        .expect("should store embedding");
+
+    let error = store
+        .swap_in_staged_table(base_version)
+        .await
+        .expect_err("swap should be refused");
+    assert!(error.to_string().contains("changed"), "{}", error);
+    assert_eq!(store.count_embeddings().await.expect("count"), 2);
+}
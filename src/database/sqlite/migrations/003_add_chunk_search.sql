@@ -0,0 +1,34 @@
+-- Full-text index over indexed chunks for keyword search
+-- Underscores are kept inside tokens so identifiers like SQLITE_BUSY match whole
+CREATE VIRTUAL TABLE IF NOT EXISTS indexed_chunks_fts USING fts5 (
+    page_title,
+    heading_path,
+    chunk_content,
+    content = 'indexed_chunks',
+    content_rowid = 'id',
+    tokenize = "unicode61 tokenchars '_'"
+);
+
+-- Keep the index in sync with indexed_chunks, including cascading deletes of sites
+CREATE TRIGGER IF NOT EXISTS indexed_chunks_fts_insert AFTER INSERT ON indexed_chunks
+BEGIN
+    INSERT INTO indexed_chunks_fts (rowid, page_title, heading_path, chunk_content)
+    VALUES (new.id, new.page_title, new.heading_path, new.chunk_content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS indexed_chunks_fts_delete AFTER DELETE ON indexed_chunks
+BEGIN
+    INSERT INTO indexed_chunks_fts (indexed_chunks_fts, rowid, page_title, heading_path, chunk_content)
+    VALUES ('delete', old.id, old.page_title, old.heading_path, old.chunk_content);
+END;
+
+CREATE TRIGGER IF NOT EXISTS indexed_chunks_fts_update AFTER UPDATE ON indexed_chunks
+BEGIN
+    INSERT INTO indexed_chunks_fts (indexed_chunks_fts, rowid, page_title, heading_path, chunk_content)
+    VALUES ('delete', old.id, old.page_title, old.heading_path, old.chunk_content);
+    INSERT INTO indexed_chunks_fts (rowid, page_title, heading_path, chunk_content)
+    VALUES (new.id, new.page_title, new.heading_path, new.chunk_content);
+END;
+
+-- Index chunks stored before this migration
+INSERT INTO indexed_chunks_fts (indexed_chunks_fts) VALUES ('rebuild');
@@ -0,0 +1,8 @@
+-- Record the embedding model and vector dimension each site was indexed with,
+-- so that incompatible embeddings are never mixed in the vector table.
+-- Sites indexed before this migration have NULL for both.
+ALTER TABLE sites
+ADD COLUMN embedding_model TEXT;
+
+ALTER TABLE sites
+ADD COLUMN embedding_dimension INTEGER CHECK (embedding_dimension > 0);
@@ -0,0 +1,9 @@
+-- Embeddings keyed by a hash of the model and the text embedded, so that
+-- re-indexing a site, or indexing another version of it, reuses the vectors
+-- of chunks which haven't changed instead of embedding them again.
+CREATE TABLE IF NOT EXISTS embedding_cache (
+    content_hash BLOB PRIMARY KEY NOT NULL,
+    -- Little-endian f32 values
+    embedding BLOB NOT NULL,
+    created_date DATETIME NOT NULL DEFAULT CURRENT_TIMESTAMP
+) WITHOUT ROWID;
@@ -0,0 +1,22 @@
+-- Record the prefix each site's chunks were embedded with, since models
+-- trained with instruction prefixes embed the same text differently with
+-- another one. Query prefixes only apply when searching, so they aren't
+-- recorded.
+ALTER TABLE sites
+ADD COLUMN embedding_document_prefix TEXT;
+
+-- Chunks indexed before this migration were embedded without any prefix, so
+-- sites holding them are recorded as such and flagged when the configured
+-- model now expects one. Sites without chunks are left NULL.
+UPDATE sites
+SET
+    embedding_document_prefix = ''
+WHERE
+    EXISTS (
+        SELECT
+            1
+        FROM
+            indexed_chunks
+        WHERE
+            indexed_chunks.site_id = sites.id
+    );
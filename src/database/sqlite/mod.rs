@@ -5,9 +5,12 @@ use std::path::Path;
 use tracing::{debug, info};
 
 use crate::database::sqlite::models::{
-    CrawlQueueItem, IndexedChunk, NewIndexedChunk, Site, SiteStatus, SiteUpdate,
+    CachedEmbedding, ChunkMatch, CrawlQueueItem, IndexedChunk, IndexedPage, NewIndexedChunk, Site,
+    SiteStatus, SiteUpdate,
+};
+use crate::database::sqlite::queries::{
+    CrawlQueueQueries, EmbeddingCacheQueries, IndexedChunkQueries, SiteQueries,
 };
-use crate::database::sqlite::queries::{CrawlQueueQueries, IndexedChunkQueries, SiteQueries};
 
 #[cfg(test)]
 mod tests;
@@ -103,6 +106,56 @@ impl Database {
         IndexedChunkQueries::list_by_site(&self.pool, site_id).await
     }
 
+    pub async fn count_chunks_for_site(&self, site_id: i64) -> Result<i64> {
+        IndexedChunkQueries::count_by_site(&self.pool, site_id).await
+    }
+
+    /// Get all chunks for a page, optionally restricted to a single site
+    pub async fn get_chunks_for_page(
+        &self,
+        site_id: Option<i64>,
+        url: &str,
+    ) -> Result<Vec<IndexedChunk>> {
+        match site_id {
+            Some(site_id) => {
+                IndexedChunkQueries::list_by_site_and_url(&self.pool, site_id, url).await
+            }
+            None => IndexedChunkQueries::list_by_url(&self.pool, url).await,
+        }
+    }
+
+    /// Get a chunk along with up to `before` and `after` neighbouring chunks on the same page
+    pub async fn get_chunk_window(
+        &self,
+        chunk: &IndexedChunk,
+        before: i64,
+        after: i64,
+    ) -> Result<Vec<IndexedChunk>> {
+        IndexedChunkQueries::list_by_site_and_url_range(
+            &self.pool,
+            chunk.site_id,
+            &chunk.url,
+            chunk.chunk_index.saturating_sub(before),
+            chunk.chunk_index.saturating_add(after),
+        )
+        .await
+    }
+
+    /// List pages of completed sites, for exposing them as MCP resources
+    pub async fn list_completed_pages(&self, limit: i64, offset: i64) -> Result<Vec<IndexedPage>> {
+        IndexedChunkQueries::list_completed_pages(&self.pool, limit, offset).await
+    }
+
+    /// Keyword search over indexed chunks, optionally restricted to some sites
+    pub async fn search_chunks(
+        &self,
+        query: &str,
+        limit: i64,
+        site_ids: Option<&[i64]>,
+    ) -> Result<Vec<ChunkMatch>> {
+        IndexedChunkQueries::search(&self.pool, query, limit, site_ids).await
+    }
+
     pub async fn insert_indexed_chunk_with_transaction(
         &self,
         chunk: &NewIndexedChunk,
@@ -115,6 +168,30 @@ impl Database {
         IndexedChunkQueries::get_by_vector_id(&self.pool, vector_id).await
     }
 
+    // Embedding cache operations
+    pub async fn get_cached_embeddings(
+        &self,
+        content_hashes: &[Vec<u8>],
+    ) -> Result<Vec<CachedEmbedding>> {
+        EmbeddingCacheQueries::get_many(&self.pool, content_hashes).await
+    }
+
+    pub async fn cache_embeddings(&self, embeddings: &[CachedEmbedding]) -> Result<()> {
+        EmbeddingCacheQueries::insert_many(&self.pool, embeddings).await
+    }
+
+    pub async fn cached_embedding_hashes(&self) -> Result<Vec<Vec<u8>>> {
+        EmbeddingCacheQueries::list_hashes(&self.pool).await
+    }
+
+    pub async fn delete_cached_embeddings(&self, content_hashes: &[Vec<u8>]) -> Result<u64> {
+        EmbeddingCacheQueries::delete_many(&self.pool, content_hashes).await
+    }
+
+    pub async fn clear_embedding_cache(&self) -> Result<u64> {
+        EmbeddingCacheQueries::clear(&self.pool).await
+    }
+
     /// Optimize database performance by running VACUUM and ANALYZE
     pub async fn optimize(&self) -> Result<()> {
         info!("Optimizing database performance");
@@ -20,6 +20,13 @@ pub struct Site {
     pub error_message: Option<String>,
     pub created_date: NaiveDateTime,
     pub last_heartbeat: Option<NaiveDateTime>,
+    /// Model the site's embeddings were generated with, unknown for sites
+    /// indexed before it was recorded
+    pub embedding_model: Option<String>,
+    pub embedding_dimension: Option<i64>,
+    /// Prefix put before the site's chunks when embedding them, unknown for
+    /// sites indexed before it was recorded
+    pub embedding_document_prefix: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Type)]
@@ -59,6 +66,9 @@ pub struct SiteUpdate {
     pub error_message: Option<String>,
     pub last_heartbeat: Option<NaiveDateTime>,
     pub indexed_date: Option<NaiveDateTime>,
+    pub embedding_model: Option<String>,
+    pub embedding_dimension: Option<i64>,
+    pub embedding_document_prefix: Option<String>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
@@ -118,6 +128,23 @@ pub struct IndexedChunk {
     pub indexed_date: NaiveDateTime,
 }
 
+/// An embedding cached under a hash of its model and text
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct CachedEmbedding {
+    pub content_hash: Vec<u8>,
+    /// Little-endian f32 values
+    pub embedding: Vec<u8>,
+}
+
+/// An indexed chunk matching a full-text search
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, FromRow)]
+pub struct ChunkMatch {
+    #[sqlx(flatten)]
+    pub chunk: IndexedChunk,
+    /// BM25 relevance of the match, higher is better
+    pub score: f64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct NewIndexedChunk {
     pub site_id: i64,
@@ -129,6 +156,17 @@ pub struct NewIndexedChunk {
     pub vector_id: String,
 }
 
+/// A distinct page of a completed site, summarized from its indexed chunks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, FromRow)]
+pub struct IndexedPage {
+    pub site_id: i64,
+    pub site_name: String,
+    pub site_version: String,
+    pub url: String,
+    pub page_title: Option<String>,
+    pub chunk_count: i64,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SiteStatistics {
     pub site: Site,
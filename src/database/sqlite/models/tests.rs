@@ -34,6 +34,9 @@ fn site_progress_calculation() {
         error_message: None,
         created_date: Utc::now().naive_utc(),
         last_heartbeat: None,
+        embedding_model: None,
+        embedding_dimension: None,
+        embedding_document_prefix: None,
     };
 
     assert!(site.is_indexing());
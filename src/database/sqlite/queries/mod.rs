@@ -4,7 +4,7 @@ mod tests;
 use super::models::*;
 use anyhow::{Context, Result};
 use chrono::Utc;
-use sqlx::{SqliteConnection, SqlitePool};
+use sqlx::{QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
 
 pub struct SiteQueries;
 
@@ -45,7 +45,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites WHERE id = ?
             "#,
             id
@@ -78,7 +81,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites WHERE name = ? AND version = ?
             "#,
             name,
@@ -107,7 +113,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites WHERE index_url = ?
             "#,
             index_url
@@ -135,7 +144,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites ORDER BY created_date DESC
             "#
         )
@@ -163,7 +175,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites WHERE status = 'completed' ORDER BY indexed_date DESC
             "#
         )
@@ -219,6 +234,21 @@ impl SiteQueries {
             query_values.push(indexed_date.to_string());
         }
 
+        if let Some(model) = update.embedding_model {
+            query_parts.push("embedding_model = ?");
+            query_values.push(model);
+        }
+
+        if let Some(dimension) = update.embedding_dimension {
+            query_parts.push("embedding_dimension = ?");
+            query_values.push(dimension.to_string());
+        }
+
+        if let Some(prefix) = update.embedding_document_prefix {
+            query_parts.push("embedding_document_prefix = ?");
+            query_values.push(prefix);
+        }
+
         if query_parts.is_empty() {
             return Self::get_by_id(pool, id).await;
         }
@@ -305,7 +335,10 @@ impl SiteQueries {
                    indexed_pages,
                    error_message,
                    created_date,
-                   last_heartbeat
+                   last_heartbeat,
+                   embedding_model,
+                   embedding_dimension,
+                   embedding_document_prefix
             FROM sites WHERE status = ? ORDER BY created_date ASC
             "#,
             status_str
@@ -645,7 +678,184 @@ impl IndexedChunkQueries {
         Ok(chunks)
     }
 
-    #[cfg(test)]
+    pub async fn list_by_url(pool: &SqlitePool, url: &str) -> Result<Vec<IndexedChunk>> {
+        let chunks = sqlx::query_as!(
+            IndexedChunk,
+            r#"
+            SELECT id,
+                   site_id,
+                   url,
+                   page_title,
+                   heading_path,
+                   chunk_content,
+                   chunk_index,
+                   vector_id,
+                   indexed_date
+            FROM indexed_chunks WHERE url = ? ORDER BY site_id, chunk_index
+            "#,
+            url
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to list indexed chunks by url")?;
+
+        Ok(chunks)
+    }
+
+    pub async fn list_by_site_and_url(
+        pool: &SqlitePool,
+        site_id: i64,
+        url: &str,
+    ) -> Result<Vec<IndexedChunk>> {
+        let chunks = sqlx::query_as!(
+            IndexedChunk,
+            r#"
+            SELECT id,
+                   site_id,
+                   url,
+                   page_title,
+                   heading_path,
+                   chunk_content,
+                   chunk_index,
+                   vector_id,
+                   indexed_date
+            FROM indexed_chunks WHERE site_id = ? AND url = ? ORDER BY chunk_index
+            "#,
+            site_id,
+            url
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to list indexed chunks by site and url")?;
+
+        Ok(chunks)
+    }
+
+    pub async fn list_by_site_and_url_range(
+        pool: &SqlitePool,
+        site_id: i64,
+        url: &str,
+        first_index: i64,
+        last_index: i64,
+    ) -> Result<Vec<IndexedChunk>> {
+        let chunks = sqlx::query_as!(
+            IndexedChunk,
+            r#"
+            SELECT id,
+                   site_id,
+                   url,
+                   page_title,
+                   heading_path,
+                   chunk_content,
+                   chunk_index,
+                   vector_id,
+                   indexed_date
+            FROM indexed_chunks
+            WHERE site_id = ? AND url = ? AND chunk_index BETWEEN ? AND ?
+            ORDER BY chunk_index
+            "#,
+            site_id,
+            url,
+            first_index,
+            last_index
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to list indexed chunks by index range")?;
+
+        Ok(chunks)
+    }
+
+    /// List the distinct pages of completed sites, ordered by site and URL
+    pub async fn list_completed_pages(
+        pool: &SqlitePool,
+        limit: i64,
+        offset: i64,
+    ) -> Result<Vec<IndexedPage>> {
+        let pages = sqlx::query_as!(
+            IndexedPage,
+            r#"
+            SELECT c.site_id AS "site_id!",
+                   s.name AS "site_name!",
+                   s.version AS "site_version!",
+                   c.url AS "url!",
+                   MAX(c.page_title) AS "page_title?: String",
+                   COUNT(*) AS "chunk_count!: i64"
+            FROM indexed_chunks c
+            JOIN sites s ON s.id = c.site_id
+            WHERE s.status = 'completed'
+            GROUP BY c.site_id, c.url
+            ORDER BY c.site_id, c.url
+            LIMIT ? OFFSET ?
+            "#,
+            limit,
+            offset
+        )
+        .fetch_all(pool)
+        .await
+        .context("Failed to list indexed pages")?;
+
+        Ok(pages)
+    }
+
+    /// Full-text search of chunk titles, headings and content ranked by BM25
+    ///
+    /// The query is treated as plain text rather than FTS5 syntax, and a chunk
+    /// matches if it contains any of the query's terms. Punctuated terms such as
+    /// `tokio::select!` match as phrases.
+    pub async fn search(
+        pool: &SqlitePool,
+        query: &str,
+        limit: i64,
+        site_ids: Option<&[i64]>,
+    ) -> Result<Vec<ChunkMatch>> {
+        let Some(match_expression) = fts_match_expression(query) else {
+            return Ok(Vec::new());
+        };
+
+        // Matches in titles and headings count for more than in the content
+        let mut builder = QueryBuilder::<Sqlite>::new(
+            r#"
+            SELECT c.id,
+                   c.site_id,
+                   c.url,
+                   c.page_title,
+                   c.heading_path,
+                   c.chunk_content,
+                   c.chunk_index,
+                   c.vector_id,
+                   c.indexed_date,
+                   -bm25(indexed_chunks_fts, 2.0, 2.0, 1.0) AS score
+            FROM indexed_chunks_fts
+            JOIN indexed_chunks c ON c.id = indexed_chunks_fts.rowid
+            WHERE indexed_chunks_fts MATCH "#,
+        );
+        builder.push_bind(match_expression);
+
+        if let Some(site_ids) = site_ids {
+            if site_ids.is_empty() {
+                return Ok(Vec::new());
+            }
+            builder.push(" AND c.site_id IN (");
+            let mut separated = builder.separated(", ");
+            for site_id in site_ids {
+                separated.push_bind(*site_id);
+            }
+            separated.push_unseparated(")");
+        }
+
+        builder.push(" ORDER BY score DESC LIMIT ");
+        builder.push_bind(limit);
+
+        let matches = builder
+            .build_query_as::<ChunkMatch>()
+            .fetch_all(pool)
+            .await
+            .context("Failed to search indexed chunks")?;
+
+        Ok(matches)
+    }
+
     pub async fn count_by_site(pool: &SqlitePool, site_id: i64) -> Result<i64> {
         let count = sqlx::query_scalar!(
             "SELECT COUNT(*) FROM indexed_chunks WHERE site_id = ?",
@@ -658,3 +868,134 @@ impl IndexedChunkQueries {
         Ok(count)
     }
 }
+
+/// Rows read or written by a single cache query, well within SQLite's limit
+/// on bound parameters
+const CACHE_ROWS_PER_QUERY: usize = 500;
+
+pub struct EmbeddingCacheQueries;
+
+impl EmbeddingCacheQueries {
+    /// Get the cached embeddings of those `content_hashes` which are cached
+    pub async fn get_many(
+        pool: &SqlitePool,
+        content_hashes: &[Vec<u8>],
+    ) -> Result<Vec<CachedEmbedding>> {
+        let mut cached = Vec::new();
+        for content_hashes in content_hashes.chunks(CACHE_ROWS_PER_QUERY) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                "SELECT content_hash, embedding FROM embedding_cache WHERE content_hash IN (",
+            );
+            let mut separated = builder.separated(", ");
+            for content_hash in content_hashes {
+                separated.push_bind(content_hash.clone());
+            }
+            separated.push_unseparated(")");
+
+            cached.extend(
+                builder
+                    .build_query_as::<CachedEmbedding>()
+                    .fetch_all(pool)
+                    .await
+                    .context("Failed to get cached embeddings")?,
+            );
+        }
+
+        Ok(cached)
+    }
+
+    /// Cache embeddings, keeping those already cached under the same hash
+    pub async fn insert_many(pool: &SqlitePool, embeddings: &[CachedEmbedding]) -> Result<()> {
+        for embeddings in embeddings.chunks(CACHE_ROWS_PER_QUERY) {
+            let mut builder = QueryBuilder::<Sqlite>::new(
+                "INSERT OR IGNORE INTO embedding_cache (content_hash, embedding) ",
+            );
+            builder.push_values(embeddings, |mut row, cached| {
+                row.push_bind(cached.content_hash.clone())
+                    .push_bind(cached.embedding.clone());
+            });
+
+            builder
+                .build()
+                .execute(pool)
+                .await
+                .context("Failed to cache embeddings")?;
+        }
+
+        Ok(())
+    }
+
+    /// Hashes of every cached embedding
+    pub async fn list_hashes(pool: &SqlitePool) -> Result<Vec<Vec<u8>>> {
+        let content_hashes = sqlx::query_scalar!("SELECT content_hash FROM embedding_cache")
+            .fetch_all(pool)
+            .await
+            .context("Failed to list cached embeddings")?;
+
+        Ok(content_hashes)
+    }
+
+    /// Delete the cached embeddings of `content_hashes`, returning how many
+    /// were cached
+    pub async fn delete_many(pool: &SqlitePool, content_hashes: &[Vec<u8>]) -> Result<u64> {
+        let mut deleted = 0;
+        for content_hashes in content_hashes.chunks(CACHE_ROWS_PER_QUERY) {
+            let mut builder =
+                QueryBuilder::<Sqlite>::new("DELETE FROM embedding_cache WHERE content_hash IN (");
+            let mut separated = builder.separated(", ");
+            for content_hash in content_hashes {
+                separated.push_bind(content_hash.clone());
+            }
+            separated.push_unseparated(")");
+
+            deleted += builder
+                .build()
+                .execute(pool)
+                .await
+                .context("Failed to delete cached embeddings")?
+                .rows_affected();
+        }
+
+        Ok(deleted)
+    }
+
+    /// Delete every cached embedding, returning how many there were
+    pub async fn clear(pool: &SqlitePool) -> Result<u64> {
+        let result = sqlx::query!("DELETE FROM embedding_cache")
+            .execute(pool)
+            .await
+            .context("Failed to clear the embedding cache")?;
+
+        Ok(result.rows_affected())
+    }
+
+    pub async fn count(pool: &SqlitePool) -> Result<i64> {
+        let count = sqlx::query_scalar!("SELECT COUNT(*) FROM embedding_cache")
+            .fetch_one(pool)
+            .await
+            .context("Failed to count cached embeddings")?;
+
+        Ok(count)
+    }
+}
+
+/// Build an FTS5 query matching any term of a plain-text query
+///
+/// Words are split into tokens the same way as the full-text index, so a word
+/// with punctuation like `tokio::select!` becomes the phrase `"tokio select"`.
+/// Returns `None` if the query has no searchable terms.
+fn fts_match_expression(query: &str) -> Option<String> {
+    let terms: Vec<String> = query
+        .split_whitespace()
+        .map(|word| {
+            word.split(|c: char| !c.is_alphanumeric() && c != '_')
+                .filter(|token| !token.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ")
+        })
+        .filter(|phrase| !phrase.is_empty())
+        .map(|phrase| format!("\"{}\"", phrase))
+        .collect();
+
+    (!terms.is_empty()).then(|| terms.join(" OR "))
+}
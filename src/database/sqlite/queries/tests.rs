@@ -2,7 +2,21 @@ use super::*;
 use sqlx::sqlite::SqlitePoolOptions;
 use tempfile::TempDir;
 
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations/001_initial_schema.sql"),
+    include_str!("../migrations/002_add_index_url.sql"),
+    include_str!("../migrations/003_add_chunk_search.sql"),
+    include_str!("../migrations/004_add_site_embedding_model.sql"),
+    include_str!("../migrations/005_add_embedding_cache.sql"),
+    include_str!("../migrations/006_add_site_embedding_prefix.sql"),
+];
+
 async fn create_test_pool() -> (TempDir, SqlitePool) {
+    create_test_pool_with(MIGRATIONS).await
+}
+
+/// A pool whose database has only `migrations` applied
+async fn create_test_pool_with(migrations: &[&str]) -> (TempDir, SqlitePool) {
     let temp_dir = TempDir::new().expect("Failed to create temp directory");
     let db_path = temp_dir.path().join("test.db");
 
@@ -17,14 +31,12 @@ async fn create_test_pool() -> (TempDir, SqlitePool) {
         .await
         .expect("Failed to create test pool");
 
-    sqlx::query(include_str!("../migrations/001_initial_schema.sql"))
-        .execute(&pool)
-        .await
-        .expect("Failed to run migrations");
-    sqlx::query(include_str!("../migrations/002_add_index_url.sql"))
-        .execute(&pool)
-        .await
-        .expect("Failed to run migrations");
+    for migration in migrations {
+        sqlx::query(migration)
+            .execute(&pool)
+            .await
+            .expect("Failed to run migrations");
+    }
 
     (temp_dir, pool)
 }
@@ -46,6 +58,7 @@ async fn site_crud_operations() {
 
     assert_eq!(created_site.name, "Test Site");
     assert_eq!(created_site.status, SiteStatus::Pending);
+    assert_eq!(created_site.embedding_model, None);
 
     let retrieved_site = SiteQueries::get_by_id(&pool, created_site.id)
         .await
@@ -63,6 +76,9 @@ async fn site_crud_operations() {
         error_message: None,
         last_heartbeat: Some(Utc::now().naive_utc()),
         indexed_date: None,
+        embedding_model: Some("nomic-embed-text:latest".to_string()),
+        embedding_dimension: Some(768),
+        embedding_document_prefix: Some("search_document: ".to_string()),
     };
 
     let updated_site = SiteQueries::update(&pool, created_site.id, update)
@@ -72,6 +88,15 @@ async fn site_crud_operations() {
 
     assert_eq!(updated_site.status, SiteStatus::Indexing);
     assert_eq!(updated_site.progress_percent, 50);
+    assert_eq!(
+        updated_site.embedding_model.as_deref(),
+        Some("nomic-embed-text:latest")
+    );
+    assert_eq!(updated_site.embedding_dimension, Some(768));
+    assert_eq!(
+        updated_site.embedding_document_prefix.as_deref(),
+        Some("search_document: ")
+    );
 
     let deleted = SiteQueries::delete(&pool, created_site.id)
         .await
@@ -183,4 +208,373 @@ async fn indexed_chunk_operations() {
         .expect("Failed to count chunks");
 
     assert_eq!(count, 1);
+
+    let by_url = IndexedChunkQueries::list_by_url(&pool, "https://example.com/page1")
+        .await
+        .expect("Failed to list chunks by url");
+    assert_eq!(by_url.len(), 1);
+
+    let by_site_and_url =
+        IndexedChunkQueries::list_by_site_and_url(&pool, site.id, "https://example.com/page1")
+            .await
+            .expect("Failed to list chunks by site and url");
+    assert_eq!(by_site_and_url.len(), 1);
+
+    let missing =
+        IndexedChunkQueries::list_by_site_and_url(&pool, site.id + 1, "https://example.com/page1")
+            .await
+            .expect("Failed to list chunks by site and url");
+    assert!(missing.is_empty());
+}
+
+#[tokio::test]
+async fn indexed_chunk_range_queries() {
+    let (_temp_dir, pool) = create_test_pool().await;
+
+    let site = SiteQueries::create(
+        &pool,
+        NewSite {
+            base_url: "https://example.com".to_string(),
+            index_url: "https://example.com".to_string(),
+            name: "Test Site".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await
+    .expect("Failed to create site");
+
+    for chunk_index in 0..5 {
+        let new_chunk = NewIndexedChunk {
+            site_id: site.id,
+            url: "https://example.com/page1".to_string(),
+            page_title: Some("Test Page".to_string()),
+            heading_path: Some("Page Title".to_string()),
+            chunk_content: format!("Chunk {}", chunk_index),
+            chunk_index,
+            vector_id: format!("vector-{}", chunk_index),
+        };
+        IndexedChunkQueries::create(&mut pool.acquire().await.expect("got conn"), new_chunk)
+            .await
+            .expect("Failed to create chunk");
+    }
+
+    let window = IndexedChunkQueries::list_by_site_and_url_range(
+        &pool,
+        site.id,
+        "https://example.com/page1",
+        1,
+        3,
+    )
+    .await
+    .expect("Failed to list chunk range");
+
+    let indexes: Vec<i64> = window.iter().map(|c| c.chunk_index).collect();
+    assert_eq!(indexes, vec![1, 2, 3]);
+
+    let clipped = IndexedChunkQueries::list_by_site_and_url_range(
+        &pool,
+        site.id,
+        "https://example.com/page1",
+        -2,
+        1,
+    )
+    .await
+    .expect("Failed to list chunk range");
+
+    assert_eq!(clipped.len(), 2);
+    assert_eq!(clipped[0].vector_id, "vector-0");
+}
+
+#[tokio::test]
+async fn list_completed_pages_groups_chunks() {
+    let (_temp_dir, pool) = create_test_pool().await;
+
+    let mut site_ids = Vec::new();
+    for (name, status) in [
+        ("Completed", SiteStatus::Completed),
+        ("Pending", SiteStatus::Pending),
+    ] {
+        let site = SiteQueries::create(
+            &pool,
+            NewSite {
+                base_url: format!("https://{}.example.com", name),
+                index_url: format!("https://{}.example.com", name),
+                name: name.to_string(),
+                version: "1.0".to_string(),
+            },
+        )
+        .await
+        .expect("Failed to create site");
+        SiteQueries::update(
+            &pool,
+            site.id,
+            SiteUpdate {
+                status: Some(status),
+                ..SiteUpdate::default()
+            },
+        )
+        .await
+        .expect("Failed to update site");
+        site_ids.push(site.id);
+    }
+
+    for &site_id in &site_ids {
+        for (url, chunk_index) in [("page1", 0), ("page1", 1), ("page2", 0)] {
+            let new_chunk = NewIndexedChunk {
+                site_id,
+                url: format!("https://example.com/{}", url),
+                page_title: (url == "page1").then(|| "Page One".to_string()),
+                heading_path: None,
+                chunk_content: "Content".to_string(),
+                chunk_index,
+                vector_id: format!("vector-{}-{}-{}", site_id, url, chunk_index),
+            };
+            IndexedChunkQueries::create(&mut pool.acquire().await.expect("got conn"), new_chunk)
+                .await
+                .expect("Failed to create chunk");
+        }
+    }
+
+    let pages = IndexedChunkQueries::list_completed_pages(&pool, 10, 0)
+        .await
+        .expect("Failed to list pages");
+
+    assert_eq!(pages.len(), 2);
+    assert!(pages.iter().all(|p| p.site_id == site_ids[0]));
+    assert_eq!(pages[0].url, "https://example.com/page1");
+    assert_eq!(pages[0].page_title.as_deref(), Some("Page One"));
+    assert_eq!(pages[0].chunk_count, 2);
+    assert_eq!(pages[1].url, "https://example.com/page2");
+    assert_eq!(pages[1].page_title, None);
+
+    let offset = IndexedChunkQueries::list_completed_pages(&pool, 10, 1)
+        .await
+        .expect("Failed to list pages");
+    assert_eq!(offset.len(), 1);
+}
+
+#[tokio::test]
+async fn chunk_search_ranks_keyword_matches() {
+    let (_temp_dir, pool) = create_test_pool().await;
+
+    let mut site_ids = Vec::new();
+    for name in ["tokio", "rusqlite"] {
+        let site = SiteQueries::create(
+            &pool,
+            NewSite {
+                base_url: format!("https://{}.example.com", name),
+                index_url: format!("https://{}.example.com", name),
+                name: name.to_string(),
+                version: "1.0".to_string(),
+            },
+        )
+        .await
+        .expect("Failed to create site");
+        site_ids.push(site.id);
+    }
+
+    let chunks = [
+        (
+            site_ids[0],
+            "Waits on multiple branches with tokio::select! until one completes.",
+        ),
+        (site_ids[0], "Spawning tasks on the runtime."),
+        (
+            site_ids[1],
+            "Returns SQLITE_BUSY when the database is locked.",
+        ),
+        (
+            site_ids[1],
+            "The database can be busy with another select statement.",
+        ),
+    ];
+    for (chunk_index, (site_id, content)) in chunks.into_iter().enumerate() {
+        let new_chunk = NewIndexedChunk {
+            site_id,
+            url: "https://example.com/page".to_string(),
+            page_title: Some("Page".to_string()),
+            heading_path: None,
+            chunk_content: content.to_string(),
+            chunk_index: chunk_index as i64,
+            vector_id: format!("vector-{}", chunk_index),
+        };
+        IndexedChunkQueries::create(&mut pool.acquire().await.expect("got conn"), new_chunk)
+            .await
+            .expect("Failed to create chunk");
+    }
+
+    let search = |query: &'static str, site_ids: Option<Vec<i64>>| {
+        let pool = pool.clone();
+        async move {
+            IndexedChunkQueries::search(&pool, query, 10, site_ids.as_deref())
+                .await
+                .expect("Failed to search chunks")
+                .into_iter()
+                .map(|m| m.chunk.vector_id)
+                .collect::<Vec<_>>()
+        }
+    };
+
+    // Identifiers match whole, not on their parts
+    assert_eq!(search("SQLITE_BUSY", None).await, vec!["vector-2"]);
+    assert_eq!(search("tokio::select!", None).await, vec!["vector-0"]);
+    assert_eq!(
+        search("select busy", None)
+            .await
+            .first()
+            .map(String::as_str),
+        Some("vector-3")
+    );
+    assert_eq!(
+        search("database", Some(vec![site_ids[0]])).await,
+        Vec::<String>::new()
+    );
+    assert_eq!(
+        search("database", Some(Vec::new())).await,
+        Vec::<String>::new()
+    );
+    assert_eq!(search("\"*:()", None).await, Vec::<String>::new());
+
+    // Deleting a site removes its chunks from the index
+    SiteQueries::delete(&pool, site_ids[1])
+        .await
+        .expect("Failed to delete site");
+    assert_eq!(search("database", None).await, Vec::<String>::new());
+}
+
+#[tokio::test]
+async fn embedding_cache_keeps_first_embedding() {
+    let (_temp_dir, pool) = create_test_pool().await;
+    let cached = |hash: u8, value: u8| CachedEmbedding {
+        content_hash: vec![hash; 32],
+        embedding: vec![value; 8],
+    };
+
+    EmbeddingCacheQueries::insert_many(&pool, &[cached(1, 10), cached(2, 20)])
+        .await
+        .expect("Failed to cache embeddings");
+    // A hash already cached keeps its embedding
+    EmbeddingCacheQueries::insert_many(&pool, &[cached(2, 99), cached(3, 30)])
+        .await
+        .expect("Failed to cache embeddings");
+
+    let mut found =
+        EmbeddingCacheQueries::get_many(&pool, &[vec![2; 32], vec![3; 32], vec![4; 32]])
+            .await
+            .expect("Failed to get cached embeddings");
+    found.sort_by(|a, b| a.content_hash.cmp(&b.content_hash));
+    assert_eq!(found, [cached(2, 20), cached(3, 30)]);
+    assert_eq!(
+        EmbeddingCacheQueries::count(&pool)
+            .await
+            .expect("Failed to count cached embeddings"),
+        3
+    );
+    assert!(
+        EmbeddingCacheQueries::get_many(&pool, &[])
+            .await
+            .expect("Failed to get cached embeddings")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn embedding_cache_deletes_and_clears() {
+    let (_temp_dir, pool) = create_test_pool().await;
+    let cached = |hash: u8| CachedEmbedding {
+        content_hash: vec![hash; 32],
+        embedding: vec![hash; 8],
+    };
+
+    EmbeddingCacheQueries::insert_many(&pool, &[cached(1), cached(2), cached(3)])
+        .await
+        .expect("Failed to cache embeddings");
+
+    // Hashes which aren't cached aren't counted as deleted
+    let deleted = EmbeddingCacheQueries::delete_many(&pool, &[vec![1; 32], vec![4; 32]])
+        .await
+        .expect("Failed to delete cached embeddings");
+    assert_eq!(deleted, 1);
+    let mut remaining = EmbeddingCacheQueries::list_hashes(&pool)
+        .await
+        .expect("Failed to list cached embeddings");
+    remaining.sort();
+    assert_eq!(remaining, [vec![2; 32], vec![3; 32]]);
+
+    let cleared = EmbeddingCacheQueries::clear(&pool)
+        .await
+        .expect("Failed to clear the embedding cache");
+    assert_eq!(cleared, 2);
+    assert_eq!(
+        EmbeddingCacheQueries::count(&pool)
+            .await
+            .expect("Failed to count cached embeddings"),
+        0
+    );
+}
+
+#[test]
+fn fts_match_expression_quotes_terms() {
+    assert_eq!(
+        fts_match_expression("tokio::select! usage").as_deref(),
+        Some(r#""tokio select" OR "usage""#)
+    );
+    assert_eq!(
+        fts_match_expression(r#"SQLITE_BUSY "NEAR(a b)""#).as_deref(),
+        Some(r#""SQLITE_BUSY" OR "NEAR a" OR "b""#)
+    );
+    assert_eq!(fts_match_expression(" -- "), None);
+}
+
+#[tokio::test]
+async fn sites_indexed_before_prefixes_are_recorded_unprefixed() {
+    use crate::config::settings::OllamaConfig;
+    use crate::indexer::embedded_differently;
+
+    let (applied, pending) = MIGRATIONS.split_at(MIGRATIONS.len() - 1);
+    let (_temp_dir, pool) = create_test_pool_with(applied).await;
+
+    // One site with chunks embedded from raw text, and one not indexed yet
+    for (id, name) in [(1, "Indexed"), (2, "Pending")] {
+        sqlx::query(
+            "INSERT INTO sites (id, index_url, base_url, name, version, status) VALUES (?, ?, ?, ?, '1.0', 'completed')",
+        )
+        .bind(id)
+        .bind(format!("https://{}.example.com", id))
+        .bind(format!("https://{}.example.com", id))
+        .bind(name)
+        .execute(&pool)
+        .await
+        .expect("Failed to insert site");
+    }
+    sqlx::query(
+        "INSERT INTO indexed_chunks (site_id, url, chunk_content, chunk_index, vector_id) VALUES (1, 'https://1.example.com', 'Content', 0, 'vector-1')",
+    )
+    .execute(&pool)
+    .await
+    .expect("Failed to insert chunk");
+
+    for migration in pending {
+        sqlx::query(migration)
+            .execute(&pool)
+            .await
+            .expect("Failed to run migrations");
+    }
+
+    let indexed = SiteQueries::get_by_id(&pool, 1)
+        .await
+        .expect("Failed to get site")
+        .expect("Site should exist");
+    let not_indexed = SiteQueries::get_by_id(&pool, 2)
+        .await
+        .expect("Failed to get site")
+        .expect("Site should exist");
+    assert_eq!(indexed.embedding_document_prefix.as_deref(), Some(""));
+    assert_eq!(not_indexed.embedding_document_prefix, None);
+
+    // The default nomic-embed-text prefixes documents, so the indexed site
+    // needs re-embedding
+    let config = OllamaConfig::default();
+    assert!(embedded_differently(&indexed, &config));
+    assert!(!embedded_differently(&not_indexed, &config));
 }
@@ -31,6 +31,13 @@ async fn integration_schema_migration() -> Result<()> {
         "crawl_queue",
         "indexed_chunks",
         "indexer_heartbeat",
+        // Full-text index of indexed_chunks and its shadow tables
+        "indexed_chunks_fts",
+        "indexed_chunks_fts_config",
+        "indexed_chunks_fts_data",
+        "indexed_chunks_fts_docsize",
+        "indexed_chunks_fts_idx",
+        "embedding_cache",
     ]
     .into_iter()
     .collect();
@@ -127,6 +134,9 @@ async fn integration_site_workflow() -> Result<()> {
         last_heartbeat: Some(Utc::now().naive_utc()),
         error_message: None,
         indexed_date: None,
+        embedding_model: None,
+        embedding_dimension: None,
+        embedding_document_prefix: None,
     };
 
     let updated_site = SiteQueries::update(database.pool(), site.id, update)
@@ -176,6 +186,9 @@ async fn integration_site_workflow() -> Result<()> {
             error_message: None,
             last_heartbeat: Some(Utc::now().naive_utc()),
             indexed_date: None,
+            embedding_model: None,
+            embedding_dimension: None,
+            embedding_document_prefix: None,
         };
         SiteQueries::update(database.pool(), site.id, site_progress_update).await?;
     }
@@ -188,6 +201,9 @@ async fn integration_site_workflow() -> Result<()> {
         indexed_pages: None,
         error_message: None,
         last_heartbeat: None,
+        embedding_model: None,
+        embedding_dimension: None,
+        embedding_document_prefix: None,
     };
 
     let final_site = SiteQueries::update(database.pool(), site.id, completion_update)
@@ -239,6 +255,9 @@ async fn integration_error_handling() -> Result<()> {
         indexed_pages: None,
         last_heartbeat: None,
         indexed_date: None,
+        embedding_model: None,
+        embedding_dimension: None,
+        embedding_document_prefix: None,
     };
 
     let failed_site = SiteQueries::update(database.pool(), site.id, error_update)
@@ -1,11 +1,17 @@
 #[cfg(test)]
 mod tests;
 
+use std::sync::Arc;
+
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
 use crate::crawler::extractor::{ContentSection, ExtractedContent};
+use crate::embeddings::tokenizer::WordPieceTokenizer;
+
+/// Tokens a model adds around every text, such as BERT's `[CLS]` and `[SEP]`
+const SPECIAL_TOKENS: usize = 2;
 
 /// Represents a chunk of content ready for embedding
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -16,7 +22,7 @@ pub struct ContentChunk {
     pub heading_path: String,
     /// The index of this chunk within the page
     pub chunk_index: usize,
-    /// Estimated token count
+    /// Token count, as counted by the [`ChunkSizer`]
     pub token_count: usize,
     /// Whether this chunk contains code blocks
     pub has_code_blocks: bool,
@@ -53,17 +59,84 @@ impl Default for ChunkingConfig {
     }
 }
 
-/// Chunk extracted content into embedding-ready pieces
+/// Measures chunks in the tokens of the embedding model
+///
+/// Without a tokenizer, tokens are estimated from the words of a text. Given
+/// the model's context length, no chunk is made longer than the model embeds
+/// without truncating it, whatever the [`ChunkingConfig`] allows.
+#[derive(Debug, Clone, Default)]
+pub struct ChunkSizer {
+    tokenizer: Option<Arc<WordPieceTokenizer>>,
+    window: Option<usize>,
+}
+
+impl ChunkSizer {
+    /// Sizer for a model embedding up to `context_length` tokens, with
+    /// `document_prefix` put before every chunk
+    pub fn new(
+        tokenizer: Option<Arc<WordPieceTokenizer>>,
+        context_length: Option<usize>,
+        document_prefix: &str,
+    ) -> Self {
+        let mut sizer = Self {
+            tokenizer,
+            window: None,
+        };
+        let reserved = sizer.count_tokens(document_prefix) + SPECIAL_TOKENS;
+        sizer.window = context_length.map(|length| length.saturating_sub(reserved).max(1));
+        sizer
+    }
+
+    /// Number of tokens in `text`, counted by the model's tokenizer if there
+    /// is one, or else estimated
+    pub fn count_tokens(&self, text: &str) -> usize {
+        self.tokenizer.as_ref().map_or_else(
+            || estimate_token_count(text),
+            |tokenizer| tokenizer.count_tokens(text),
+        )
+    }
+
+    /// Most tokens a chunk may have to fit in the model's context, next to
+    /// the document prefix, if the context length is known
+    pub fn window(&self) -> Option<usize> {
+        self.window
+    }
+
+    /// Whether a tokenizer counts the tokens, rather than an estimate
+    pub fn has_tokenizer(&self) -> bool {
+        self.tokenizer.is_some()
+    }
+
+    /// `config` with its sizes lowered to fit in the window
+    fn fit(&self, config: &ChunkingConfig) -> ChunkingConfig {
+        let Some(window) = self.window else {
+            return *config;
+        };
+        let max_chunk_size = config.max_chunk_size.min(window);
+        let target_chunk_size = config.target_chunk_size.min(max_chunk_size);
+        ChunkingConfig {
+            target_chunk_size,
+            max_chunk_size,
+            min_chunk_size: config.min_chunk_size.min(target_chunk_size),
+            ..*config
+        }
+    }
+}
+
+/// Chunk extracted content into embedding-ready pieces, measured and bounded
+/// by `sizer`
 pub fn chunk_content(
     content: &ExtractedContent,
     config: &ChunkingConfig,
+    sizer: &ChunkSizer,
 ) -> Result<Vec<ContentChunk>> {
+    let config = &sizer.fit(config);
     let mut chunks = Vec::new();
     let mut chunk_index = 0;
 
     // Process each section individually
     for section in &content.sections {
-        let section_chunks = chunk_section(section, config, &mut chunk_index)?;
+        let section_chunks = chunk_section(section, config, sizer, &mut chunk_index)?;
         chunks.extend(section_chunks);
     }
 
@@ -75,11 +148,11 @@ pub fn chunk_content(
             heading_level: None,
             has_code_blocks: false,
         };
-        chunks = chunk_section(&fallback_section, config, &mut chunk_index)?;
+        chunks = chunk_section(&fallback_section, config, sizer, &mut chunk_index)?;
     }
 
     // Post-process chunks: merge small chunks and add overlap
-    let processed_chunks = post_process_chunks(chunks, config)?;
+    let processed_chunks = post_process_chunks(chunks, config, sizer)?;
 
     debug!(
         "Chunked content '{}' into {} chunks (avg {} tokens)",
@@ -99,6 +172,7 @@ pub fn chunk_content(
 fn chunk_section(
     section: &ContentSection,
     config: &ChunkingConfig,
+    sizer: &ChunkSizer,
     chunk_index: &mut usize,
 ) -> Result<Vec<ContentChunk>> {
     let mut chunks = Vec::new();
@@ -108,7 +182,7 @@ fn chunk_section(
         return Ok(chunks);
     }
 
-    let token_count = estimate_token_count(content);
+    let token_count = sizer.count_tokens(content);
 
     // If content is small enough, return as single chunk
     if token_count <= config.target_chunk_size {
@@ -124,19 +198,28 @@ fn chunk_section(
     }
 
     // Split content using semantic chunking strategy
-    let splits = if section.has_code_blocks && config.preserve_code_blocks {
-        split_with_code_preservation(content, config)?
+    let mut splits = if section.has_code_blocks && config.preserve_code_blocks {
+        split_with_code_preservation(content, config, sizer)?
     } else {
-        split_by_semantics(content, config)?
+        split_by_semantics(content, config, sizer)?
     };
 
+    // Code blocks and long sentences are kept whole above, but the model
+    // would silently truncate any which don't fit in its context
+    if let Some(window) = sizer.window {
+        splits = splits
+            .iter()
+            .flat_map(|split| split_to_window(split, window, sizer))
+            .collect();
+    }
+
     // Create chunks from splits
     for split in splits {
         if split.trim().is_empty() {
             continue;
         }
 
-        let chunk_token_count = estimate_token_count(&split);
+        let chunk_token_count = sizer.count_tokens(&split);
         let has_code_blocks = section.has_code_blocks && contains_code_block(&split);
         chunks.push(ContentChunk {
             content: split,
@@ -152,7 +235,11 @@ fn chunk_section(
 }
 
 /// Split content while preserving code blocks
-fn split_with_code_preservation(content: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+fn split_with_code_preservation(
+    content: &str,
+    config: &ChunkingConfig,
+    sizer: &ChunkSizer,
+) -> Result<Vec<String>> {
     let mut splits = Vec::new();
     let mut current_split = String::new();
     let mut in_code_block = false;
@@ -160,7 +247,7 @@ fn split_with_code_preservation(content: &str, config: &ChunkingConfig) -> Resul
 
     for line in content.lines() {
         let line_with_newline = format!("{}\n", line);
-        let line_tokens = estimate_token_count(&line_with_newline);
+        let line_tokens = sizer.count_tokens(&line_with_newline);
 
         // Detect code block boundaries
         if line.trim().starts_with("```") {
@@ -190,7 +277,11 @@ fn split_with_code_preservation(content: &str, config: &ChunkingConfig) -> Resul
 }
 
 /// Split content using semantic boundaries
-fn split_by_semantics(content: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+fn split_by_semantics(
+    content: &str,
+    config: &ChunkingConfig,
+    sizer: &ChunkSizer,
+) -> Result<Vec<String>> {
     let mut splits = Vec::new();
     let mut current_split = String::new();
     let mut current_token_count = 0;
@@ -203,15 +294,15 @@ fn split_by_semantics(content: &str, config: &ChunkingConfig) -> Result<Vec<Stri
             continue;
         }
 
-        let paragraph_tokens = estimate_token_count(paragraph);
+        let paragraph_tokens = sizer.count_tokens(paragraph);
 
         // If this paragraph alone exceeds max size, split it further
         if paragraph_tokens > config.max_chunk_size {
             // Split by sentences if enabled
             if config.sentence_boundary_splitting {
-                let sentence_splits = split_by_sentences(paragraph, config)?;
+                let sentence_splits = split_by_sentences(paragraph, config, sizer)?;
                 for sentence_split in sentence_splits {
-                    if current_token_count + estimate_token_count(&sentence_split)
+                    if current_token_count + sizer.count_tokens(&sentence_split)
                         > config.target_chunk_size
                         && !current_split.trim().is_empty()
                     {
@@ -221,13 +312,13 @@ fn split_by_semantics(content: &str, config: &ChunkingConfig) -> Result<Vec<Stri
                     }
                     current_split.push_str(&sentence_split);
                     current_split.push_str("\n\n");
-                    current_token_count += estimate_token_count(&sentence_split);
+                    current_token_count += sizer.count_tokens(&sentence_split);
                 }
             } else {
                 // Fallback to word-based splitting
-                let word_splits = split_by_words(paragraph, config)?;
+                let word_splits = split_by_words(paragraph, config, sizer)?;
                 for word_split in word_splits {
-                    if current_token_count + estimate_token_count(&word_split)
+                    if current_token_count + sizer.count_tokens(&word_split)
                         > config.target_chunk_size
                         && !current_split.trim().is_empty()
                     {
@@ -237,7 +328,7 @@ fn split_by_semantics(content: &str, config: &ChunkingConfig) -> Result<Vec<Stri
                     }
                     current_split.push_str(&word_split);
                     current_split.push_str("\n\n");
-                    current_token_count += estimate_token_count(&word_split);
+                    current_token_count += sizer.count_tokens(&word_split);
                 }
             }
         } else {
@@ -265,7 +356,11 @@ fn split_by_semantics(content: &str, config: &ChunkingConfig) -> Result<Vec<Stri
 }
 
 /// Split text by sentences
-fn split_by_sentences(text: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+fn split_by_sentences(
+    text: &str,
+    config: &ChunkingConfig,
+    sizer: &ChunkSizer,
+) -> Result<Vec<String>> {
     let mut splits = Vec::new();
     let mut current_split = String::new();
     let mut current_token_count = 0;
@@ -284,7 +379,7 @@ fn split_by_sentences(text: &str, config: &ChunkingConfig) -> Result<Vec<String>
             (*sentence).to_string()
         };
 
-        let sentence_tokens = estimate_token_count(&sentence_with_punct);
+        let sentence_tokens = sizer.count_tokens(&sentence_with_punct);
 
         if current_token_count + sentence_tokens > config.target_chunk_size
             && !current_split.trim().is_empty()
@@ -306,7 +401,7 @@ fn split_by_sentences(text: &str, config: &ChunkingConfig) -> Result<Vec<String>
 }
 
 /// Split text by words as a last resort
-fn split_by_words(text: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
+fn split_by_words(text: &str, config: &ChunkingConfig, sizer: &ChunkSizer) -> Result<Vec<String>> {
     let mut splits = Vec::new();
     let mut current_split = String::new();
     let mut current_token_count = 0;
@@ -315,7 +410,7 @@ fn split_by_words(text: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
 
     for word in words {
         let word_with_space = format!("{} ", word);
-        let word_tokens = estimate_token_count(&word_with_space);
+        let word_tokens = sizer.count_tokens(&word_with_space);
 
         if current_token_count + word_tokens > config.target_chunk_size
             && !current_split.trim().is_empty()
@@ -336,10 +431,72 @@ fn split_by_words(text: &str, config: &ChunkingConfig) -> Result<Vec<String>> {
     Ok(splits)
 }
 
+/// Split text which doesn't fit in the model's context into pieces which do,
+/// breaking between words where possible
+fn split_to_window(text: &str, window: usize, sizer: &ChunkSizer) -> Vec<String> {
+    let mut splits = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        if sizer.count_tokens(rest) <= window {
+            splits.push(rest.to_string());
+            break;
+        }
+
+        // Every word is at least a token, so the window ends within the
+        // first `window + 1` words
+        let word_ends: Vec<usize> = rest
+            .split_inclusive(char::is_whitespace)
+            .scan(0, |end, piece| {
+                *end += piece.len();
+                Some((*end, piece))
+            })
+            .filter(|(_, piece)| !piece.trim().is_empty())
+            .take(window + 1)
+            .map(|(end, _)| end)
+            .collect();
+        let end = longest_fitting_prefix(rest, &word_ends, window, sizer)
+            .or_else(|| {
+                // The first word alone is too long, so split it between
+                // characters
+                let char_ends: Vec<usize> = rest
+                    .char_indices()
+                    .take_while(|(_, c)| !c.is_whitespace())
+                    .map(|(start, c)| start + c.len_utf8())
+                    .collect();
+                longest_fitting_prefix(rest, &char_ends, window, sizer)
+            })
+            .unwrap_or_else(|| rest.chars().next().map_or(rest.len(), char::len_utf8));
+
+        let (split, tail) = rest.split_at(end);
+        splits.push(split.trim_end().to_string());
+        rest = tail.trim_start();
+    }
+
+    splits
+}
+
+/// The largest of the increasing byte offsets `ends` up to which `text` fits
+/// in the window
+fn longest_fitting_prefix(
+    text: &str,
+    ends: &[usize],
+    window: usize,
+    sizer: &ChunkSizer,
+) -> Option<usize> {
+    let fitting = ends.partition_point(|&end| {
+        text.get(..end)
+            .is_some_and(|prefix| sizer.count_tokens(prefix) <= window)
+    });
+    fitting
+        .checked_sub(1)
+        .and_then(|index| ends.get(index).copied())
+}
+
 /// Post-process chunks to merge small ones and add overlap
 fn post_process_chunks(
     chunks: Vec<ContentChunk>,
     config: &ChunkingConfig,
+    sizer: &ChunkSizer,
 ) -> Result<Vec<ContentChunk>> {
     if chunks.is_empty() {
         return Ok(chunks);
@@ -355,17 +512,20 @@ fn post_process_chunks(
                 && pending.token_count + chunk.token_count <= config.max_chunk_size
                 && pending.heading_path == chunk.heading_path
             {
-                // Merge chunks
-                pending.content.push_str("\n\n");
-                pending.content.push_str(&chunk.content);
-                pending.token_count += chunk.token_count;
-                pending.has_code_blocks = pending.has_code_blocks || chunk.has_code_blocks;
-                pending_merge = Some(pending);
-                continue;
-            } else {
-                // Can't merge, add pending to processed
-                processed.push(pending);
+                // Merge chunks, recounting as estimates of the parts don't
+                // add up exactly
+                let merged = format!("{}\n\n{}", pending.content, chunk.content);
+                let merged_token_count = sizer.count_tokens(&merged);
+                if merged_token_count <= config.max_chunk_size {
+                    pending.content = merged;
+                    pending.token_count = merged_token_count;
+                    pending.has_code_blocks = pending.has_code_blocks || chunk.has_code_blocks;
+                    pending_merge = Some(pending);
+                    continue;
+                }
             }
+            // Can't merge, add pending to processed
+            processed.push(pending);
         }
 
         // If current chunk is too small, mark for potential merging
@@ -383,7 +543,7 @@ fn post_process_chunks(
 
     // Add overlap between adjacent chunks if configured
     if config.overlap_size > 0 {
-        processed = add_overlap(processed, config)?;
+        processed = add_overlap(processed, config, sizer)?;
     }
 
     // Re-index chunks
@@ -398,6 +558,7 @@ fn post_process_chunks(
 fn add_overlap(
     mut chunks: Vec<ContentChunk>,
     config: &ChunkingConfig,
+    sizer: &ChunkSizer,
 ) -> Result<Vec<ContentChunk>> {
     let mut i = 1;
     while i < chunks.len() {
@@ -409,8 +570,14 @@ fn add_overlap(
         if prev_chunk.heading_path == curr_chunk.heading_path {
             let overlap_text = extract_overlap_text(&prev_chunk.content, config.overlap_size);
             if !overlap_text.is_empty() {
-                curr_chunk.content = format!("{}\n\n{}", overlap_text, curr_chunk.content);
-                curr_chunk.token_count += estimate_token_count(&overlap_text);
+                let content = format!("{}\n\n{}", overlap_text, curr_chunk.content);
+                let token_count = sizer.count_tokens(&content);
+                // Overlap is left out where it would push a chunk past the
+                // model's context
+                if sizer.window.is_none_or(|window| token_count <= window) {
+                    curr_chunk.content = content;
+                    curr_chunk.token_count = token_count;
+                }
             }
         }
         i += 1;
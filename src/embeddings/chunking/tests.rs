@@ -2,6 +2,7 @@ use super::estimate_token_count as estimate_token_count_impl;
 use super::split_with_code_preservation as split_with_code_preservation_impl;
 use super::*;
 use crate::crawler::extractor::ContentSection;
+use crate::embeddings::tests::write_tokenizer;
 
 fn create_test_content() -> ExtractedContent {
     ExtractedContent {
@@ -42,7 +43,8 @@ fn chunk_small_content() {
     let content = create_test_content();
     let config = ChunkingConfig::default();
 
-    let chunks = chunk_content(&content, &config).expect("chunk_content should succeed");
+    let chunks = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
 
     assert!(!chunks.is_empty());
 
@@ -66,7 +68,8 @@ fn chunk_large_content() {
         ..ChunkingConfig::default()
     };
 
-    let chunks = chunk_content(&content, &config).expect("chunk_content should succeed");
+    let chunks = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
 
     // Large section should be split into multiple chunks
 
@@ -84,7 +87,8 @@ fn preserve_code_blocks() {
     let content = create_test_content();
     let config = ChunkingConfig::default();
 
-    let chunks = chunk_content(&content, &config).expect("chunk_content should succeed");
+    let chunks = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
 
     // Find chunk with code blocks
     let code_chunk = chunks
@@ -101,7 +105,8 @@ fn heading_path_preservation() {
     let content = create_test_content();
     let config = ChunkingConfig::default();
 
-    let chunks = chunk_content(&content, &config).expect("chunk_content should succeed");
+    let chunks = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
 
     // All chunks should have meaningful heading paths
     for chunk in &chunks {
@@ -116,7 +121,7 @@ fn split_with_code_preservation() {
         "Some text\n\n```rust\nfn main() {\n    println!(\"Hello\");\n}\n```\n\nMore text";
     let config = ChunkingConfig::default();
 
-    let splits = split_with_code_preservation_impl(content, &config)
+    let splits = split_with_code_preservation_impl(content, &config, &ChunkSizer::default())
         .expect("split_with_code_preservation should succeed");
 
     // Should preserve code block as a unit
@@ -137,6 +142,81 @@ fn empty_content() {
     };
     let config = ChunkingConfig::default();
 
-    let chunks = chunk_content(&content, &config).expect("chunk_content should succeed");
+    let chunks = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
     assert!(chunks.is_empty());
 }
+
+#[test]
+fn chunks_fit_in_the_model_window() {
+    let code = (0..300)
+        .map(|line| format!("let value_{} = compute({});", line, line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let content = ExtractedContent {
+        title: "Code".to_string(),
+        sections: vec![ContentSection {
+            heading_path: "Examples".to_string(),
+            content: format!("An example:\n\n```rust\n{}\n```", code),
+            heading_level: Some(1),
+            has_code_blocks: true,
+        }],
+        raw_text: String::new(),
+    };
+    let config = ChunkingConfig::default();
+    let sizer = ChunkSizer::new(None, Some(100), "search_document: ");
+    assert_eq!(sizer.window(), Some(97));
+
+    // The code block is kept whole without a window, far beyond it
+    let unbounded = chunk_content(&content, &config, &ChunkSizer::default())
+        .expect("chunk_content should succeed");
+    assert!(unbounded.iter().any(|chunk| chunk.token_count > 97));
+
+    let chunks = chunk_content(&content, &config, &sizer).expect("chunk_content should succeed");
+    assert!(chunks.len() > 1);
+    for chunk in &chunks {
+        assert!(chunk.token_count <= 97, "{} tokens", chunk.token_count);
+        assert_eq!(chunk.token_count, sizer.count_tokens(&chunk.content));
+    }
+    assert!(
+        chunks
+            .iter()
+            .any(|chunk| chunk.content.contains("value_299"))
+    );
+}
+
+#[test]
+fn long_words_are_split_to_fit() {
+    let word = "-".repeat(1000);
+    let sizer = ChunkSizer::new(None, Some(22), "");
+
+    let splits = split_to_window(&format!("before {} after", word), 20, &sizer);
+
+    assert!(splits.len() > 2);
+    for split in &splits {
+        assert!(sizer.count_tokens(split) <= 20, "{:?}", split);
+    }
+    assert_eq!(
+        splits.concat().replace(' ', ""),
+        format!("before{}after", word)
+    );
+}
+
+#[test]
+fn tokenizer_counts_chunk_tokens() {
+    let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+    write_tokenizer(
+        dir.path(),
+        &[
+            "[PAD]", "[UNK]", "[CLS]", "[SEP]", "search", "document", ":",
+        ],
+    );
+    let tokenizer = WordPieceTokenizer::load(&dir.path().join("tokenizer.json"))
+        .expect("Failed to load tokenizer");
+    let sizer = ChunkSizer::new(Some(Arc::new(tokenizer)), Some(16), "search_document: ");
+
+    // The prefix is "search", "_", "document" and ":", the underscore unknown
+    assert!(sizer.has_tokenizer());
+    assert_eq!(sizer.window(), Some(10));
+    assert_eq!(sizer.count_tokens("Search: documents, search!"), 6);
+}
@@ -0,0 +1,157 @@
+//! Hashing Embeddings
+//!
+//! Embeds text without any model by feature hashing: each word, and each
+//! three-character piece of a word, adds a signed weight to one dimension
+//! chosen by its hash. Texts sharing words end up close together, which is
+//! rough lexical recall rather than semantic search, but it needs no
+//! embedding server and gives the same embeddings on every machine.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+
+use anyhow::{Result, bail};
+use async_trait::async_trait;
+use tracing::debug;
+
+use crate::config::settings::{OllamaConfig, PromptTemplate};
+use crate::embeddings::chunking::estimate_token_count;
+use crate::embeddings::{EmbeddingProvider, EmbeddingResult};
+
+/// Model name recorded with sites embedded by the hashing embedder
+pub const HASHING_MODEL_NAME: &str = "feature-hashing";
+
+/// Weight of a whole word
+const WORD_WEIGHT: f32 = 1.0;
+/// Weight of each trigram of a word, which lets inflections of a word
+/// such as "index" and "indexing" partially match
+const TRIGRAM_WEIGHT: f32 = 0.5;
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0100_0000_01b3;
+
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimension: usize,
+    prompt: PromptTemplate,
+}
+
+impl HashingEmbedder {
+    pub fn new(config: &OllamaConfig) -> Self {
+        Self {
+            dimension: config.embedding_dimension as usize,
+            prompt: config.prompt_template(),
+        }
+    }
+
+    /// Hash the words of `text` into a normalized embedding
+    pub fn embed(&self, text: &str) -> Vec<f32> {
+        let text = text.to_lowercase();
+        // Weight and number of occurrences of each feature, by hash
+        let mut features: HashMap<u64, (f32, u32)> = HashMap::new();
+
+        for word in text.split(|c: char| !c.is_alphanumeric()) {
+            if word.is_empty() {
+                continue;
+            }
+            add_feature(&mut features, fnv1a(b'w', word.as_bytes()), WORD_WEIGHT);
+
+            let padded: Vec<char> = format!("<{}>", word).chars().collect();
+            for trigram in padded.windows(3) {
+                let trigram: String = trigram.iter().collect();
+                add_feature(
+                    &mut features,
+                    fnv1a(b't', trigram.as_bytes()),
+                    TRIGRAM_WEIGHT,
+                );
+            }
+        }
+        if features.is_empty() {
+            // Symbols and whitespace alone still get an embedding of their own
+            add_feature(
+                &mut features,
+                fnv1a(b's', text.trim().as_bytes()),
+                WORD_WEIGHT,
+            );
+        }
+
+        let mut embedding = vec![0.0; self.dimension];
+        for (hash, (weight, count)) in features {
+            // Repeating a word matters less than using it at all
+            let weight = weight * (1.0 + (count as f32).ln());
+            let index = (hash % self.dimension as u64) as usize;
+            if hash >> 63 == 0 {
+                embedding[index] += weight;
+            } else {
+                embedding[index] -= weight;
+            }
+        }
+
+        let norm = embedding
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|value| *value /= norm);
+        }
+        embedding
+    }
+}
+
+fn add_feature(features: &mut HashMap<u64, (f32, u32)>, hash: u64, weight: f32) {
+    features.entry(hash).or_insert((weight, 0)).1 += 1;
+}
+
+/// 64-bit FNV-1a hash of a feature, which unlike the standard library's
+/// hasher is stable across Rust versions and platforms
+fn fnv1a(kind: u8, bytes: &[u8]) -> u64 {
+    std::iter::once(kind)
+        .chain(bytes.iter().copied())
+        .fold(FNV_OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(FNV_PRIME)
+        })
+}
+
+#[async_trait]
+impl EmbeddingProvider for HashingEmbedder {
+    fn model(&self) -> &str {
+        HASHING_MODEL_NAME
+    }
+
+    fn prompt(&self) -> &PromptTemplate {
+        &self.prompt
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult> {
+        debug!("Hashing embedding for text (length: {})", text.len());
+
+        Ok(EmbeddingResult {
+            text: text.to_string(),
+            embedding: self.embed(text),
+            token_count: estimate_token_count(text),
+            chunk_index: None,
+            heading_path: None,
+        })
+    }
+
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
+        let mut results = Vec::with_capacity(texts.len());
+        for text in texts {
+            results.push(self.generate_embedding(text).await?);
+        }
+        Ok(results)
+    }
+
+    async fn rerank(&self, model: &str, _query: &str, _documents: &[String]) -> Result<Vec<f32>> {
+        bail!(
+            "Reranker model {} is not available with the hashing embedder",
+            model
+        )
+    }
+}
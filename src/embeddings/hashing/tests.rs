@@ -0,0 +1,93 @@
+use super::*;
+use crate::config::settings::EmbeddingBackend;
+use crate::embeddings::create_provider;
+
+fn embedder() -> HashingEmbedder {
+    HashingEmbedder::new(&OllamaConfig {
+        provider: EmbeddingBackend::Hashing,
+        embedding_dimension: 256,
+        ..OllamaConfig::default()
+    })
+}
+
+fn similarity(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[test]
+fn embeddings_are_normalized_to_the_configured_dimension() {
+    let embedding = embedder().embed("Configure the crawler's rate limit");
+
+    assert_eq!(embedding.len(), 256);
+    let norm = similarity(&embedding, &embedding).sqrt();
+    assert!((norm - 1.0).abs() < 1e-5, "norm was {}", norm);
+}
+
+#[test]
+fn embeddings_are_deterministic_and_ignore_case() {
+    let embedder = embedder();
+
+    assert_eq!(
+        embedder.embed("Rust documentation"),
+        embedder.embed("rust   DOCUMENTATION")
+    );
+    assert_ne!(
+        embedder.embed("Rust documentation"),
+        embedder.embed("Python documentation")
+    );
+}
+
+#[test]
+fn shared_words_are_closer_than_unrelated_text() {
+    let embedder = embedder();
+    let query = embedder.embed("how do I configure the crawler");
+    let related = embedder.embed("Crawler configuration: set the delay between requests");
+    let unrelated = embedder.embed("Vector indexes are trained with IVF-PQ partitions");
+
+    assert!(
+        similarity(&query, &related) > similarity(&query, &unrelated),
+        "related {} vs unrelated {}",
+        similarity(&query, &related),
+        similarity(&query, &unrelated)
+    );
+}
+
+#[test]
+fn inflections_partially_match() {
+    let embedder = embedder();
+    let index = embedder.embed("index");
+
+    assert!(similarity(&index, &embedder.embed("indexing")) > 0.3);
+    assert!(similarity(&index, &embedder.embed("banana")) < 0.3);
+}
+
+#[test]
+fn text_without_words_still_embeds() {
+    let embedding = embedder().embed("{} -> ()");
+
+    assert!((similarity(&embedding, &embedding) - 1.0).abs() < 1e-5);
+}
+
+#[tokio::test]
+async fn provider_needs_no_server() {
+    let provider = create_provider(&OllamaConfig {
+        provider: EmbeddingBackend::Hashing,
+        // Nothing listens here, and nothing needs to
+        port: 1,
+        ..OllamaConfig::default()
+    })
+    .expect("should create provider");
+
+    assert_eq!(provider.model(), HASHING_MODEL_NAME);
+    provider
+        .health_check()
+        .await
+        .expect("health check should pass");
+    let results = provider
+        .generate_embeddings_batch(&["first".to_string(), "second".to_string()])
+        .await
+        .expect("embedding should succeed");
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].embedding.len(), 768);
+    assert!(provider.rerank("bge-reranker", "query", &[]).await.is_err());
+}
@@ -0,0 +1,205 @@
+//! Embedding Server Requests
+//!
+//! Ollama and OpenAI-compatible servers are called over an async HTTP client,
+//! so a request waiting on the server holds no thread. Indexing keeps many
+//! requests in flight cheaply, and a search that times out or is cancelled
+//! drops its request along with the retries still to come.
+
+#[cfg(test)]
+mod tests;
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{Result, anyhow};
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use hyper::header::{self, HeaderValue};
+use hyper::{Method, Request};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use thiserror::Error;
+use tracing::{debug, error, warn};
+use url::Url;
+
+const EXPONENTIAL_BACKOFF_BASE: u64 = 2;
+
+/// Why a request to the embedding server failed
+#[derive(Debug, Error)]
+pub(crate) enum HttpError {
+    /// The server answered with a status other than success
+    #[error("HTTP status {0}")]
+    Status(u16),
+    /// The connection failed or was lost
+    #[error("{0}")]
+    Transport(String),
+    /// The server didn't answer within the client's timeout
+    #[error("Request timed out after {0:?}")]
+    Timeout(Duration),
+    /// The request couldn't be built or the response couldn't be read
+    #[error("{0}")]
+    Invalid(String),
+}
+
+/// HTTP client for an embedding server
+///
+/// Clones share the connection pool.
+#[derive(Debug, Clone)]
+pub(crate) struct HttpClient {
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+    /// Longest a request may take, including reading the response
+    timeout: Option<Duration>,
+}
+
+impl HttpClient {
+    /// Create a client for HTTP and HTTPS servers, without a timeout
+    pub(crate) fn new() -> Self {
+        let connector = HttpsConnectorBuilder::new()
+            .with_provider_and_webpki_roots(Arc::new(rustls::crypto::ring::default_provider()))
+            .expect("ring supports the default TLS versions")
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Self {
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            timeout: None,
+        }
+    }
+
+    /// Give up on requests which take longer than `timeout`
+    #[cfg(test)]
+    pub(crate) fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// GET `url`, returning the response body
+    pub(crate) async fn get(&self, url: &Url, bearer: Option<&str>) -> Result<String, HttpError> {
+        self.send(Method::GET, url, None, bearer).await
+    }
+
+    /// POST a JSON body to `url`, returning the response body
+    pub(crate) async fn post(
+        &self,
+        url: &Url,
+        json: &str,
+        bearer: Option<&str>,
+    ) -> Result<String, HttpError> {
+        self.send(Method::POST, url, Some(json), bearer).await
+    }
+
+    async fn send(
+        &self,
+        method: Method,
+        url: &Url,
+        json: Option<&str>,
+        bearer: Option<&str>,
+    ) -> Result<String, HttpError> {
+        let mut builder = Request::builder().method(method).uri(url.as_str());
+        if json.is_some() {
+            builder = builder.header(header::CONTENT_TYPE, "application/json");
+        }
+        if let Some(token) = bearer {
+            let value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .map_err(|e| HttpError::Invalid(format!("Invalid API key: {}", e)))?;
+            builder = builder.header(header::AUTHORIZATION, value);
+        }
+        let body = Full::new(Bytes::from(json.unwrap_or_default().to_string()));
+        let request = builder
+            .body(body)
+            .map_err(|e| HttpError::Invalid(format!("Invalid request: {}", e)))?;
+
+        let response = async {
+            let response = self
+                .client
+                .request(request)
+                .await
+                .map_err(|e| HttpError::Transport(format!("{:#}", anyhow!(e))))?;
+            let status = response.status();
+            let body = response
+                .into_body()
+                .collect()
+                .await
+                .map_err(|e| HttpError::Transport(format!("Failed to read response: {}", e)))?
+                .to_bytes();
+            if !status.is_success() {
+                return Err(HttpError::Status(status.as_u16()));
+            }
+            String::from_utf8(body.to_vec())
+                .map_err(|e| HttpError::Invalid(format!("Response is not UTF-8: {}", e)))
+        };
+
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, response)
+                .await
+                .map_err(|_| HttpError::Timeout(timeout))?,
+            None => response.await,
+        }
+    }
+}
+
+/// Run an HTTP request, retrying server and transport errors with
+/// exponential backoff
+pub(crate) async fn request_with_retry<F, Fut>(
+    base_url: &Url,
+    retry_attempts: u32,
+    mut request_fn: F,
+) -> Result<String>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<String, HttpError>>,
+{
+    let mut last_error = None;
+
+    for attempt in 1..=retry_attempts {
+        debug!("HTTP request attempt {}/{}", attempt, retry_attempts);
+
+        match request_fn().await {
+            Ok(response_text) => {
+                debug!("Request succeeded on attempt {}", attempt);
+                return Ok(response_text);
+            }
+            Err(error) => {
+                match &error {
+                    HttpError::Status(status) if *status >= 500 => {
+                        warn!(
+                            "Server error (status {}), attempt {}/{}",
+                            status, attempt, retry_attempts
+                        );
+                    }
+                    HttpError::Status(status) => {
+                        warn!("Client error (status {}), not retrying", status);
+                        return Err(anyhow!("Client error: HTTP {}", status));
+                    }
+                    HttpError::Transport(_) | HttpError::Timeout(_) => {
+                        warn!(
+                            "Transport error: {}, attempt {}/{}",
+                            error, attempt, retry_attempts
+                        );
+                    }
+                    HttpError::Invalid(_) => {
+                        warn!("Non-retryable error: {}", error);
+                        return Err(anyhow!("Non-retryable error: {}", error));
+                    }
+                }
+
+                last_error = Some(anyhow!("Request error: {}", error));
+
+                // Wait before retry (exponential backoff)
+                if attempt < retry_attempts {
+                    let delay_ms = EXPONENTIAL_BACKOFF_BASE.pow(attempt - 1) * 1000;
+                    let delay = Duration::from_millis(delay_ms);
+                    debug!("Waiting {:?} before retry", delay);
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+
+    error!("All retry attempts failed for request to {}", base_url);
+
+    Err(last_error.unwrap_or_else(|| anyhow!("Request failed after retries")))
+}
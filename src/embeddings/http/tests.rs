@@ -0,0 +1,159 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::*;
+
+/// Read one HTTP request from `stream`, returning its text
+async fn read_request(stream: &mut TcpStream) -> String {
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    loop {
+        let read = stream.read(&mut buffer).await.expect("should read request");
+        request.extend_from_slice(&buffer[..read]);
+        let text = String::from_utf8_lossy(&request).to_string();
+        if let Some((head, body)) = text.split_once("\r\n\r\n") {
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_ascii_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|length| length.trim().parse::<usize>().expect("valid length"))
+                })
+                .unwrap_or(0);
+            if body.len() >= content_length {
+                return text;
+            }
+        }
+        if read == 0 {
+            return text;
+        }
+    }
+}
+
+/// Answer requests with `responses` in turn, returning the server's URL and
+/// how many requests it received
+async fn serve(responses: Vec<(u16, &'static str)>) -> (Url, Arc<AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+    let url = Url::parse(&format!(
+        "http://{}/",
+        listener.local_addr().expect("has address")
+    ))
+    .expect("valid URL");
+    let requests = Arc::new(AtomicUsize::new(0));
+    let received = Arc::clone(&requests);
+    tokio::spawn(async move {
+        for (status, body) in responses {
+            let (mut stream, _) = listener.accept().await.expect("should accept");
+            read_request(&mut stream).await;
+            received.fetch_add(1, Ordering::SeqCst);
+            let response = format!(
+                "HTTP/1.1 {} Status\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            stream
+                .write_all(response.as_bytes())
+                .await
+                .expect("should write response");
+        }
+    });
+    (url, requests)
+}
+
+#[tokio::test]
+async fn server_errors_are_retried() {
+    let (url, requests) = serve(vec![(503, "busy"), (200, "ready")]).await;
+    let client = HttpClient::new();
+
+    let response = request_with_retry(&url, 3, || client.get(&url, None))
+        .await
+        .expect("second attempt should succeed");
+
+    assert_eq!(response, "ready");
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn client_errors_are_not_retried() {
+    let (url, requests) = serve(vec![(404, "missing"), (200, "found")]).await;
+    let client = HttpClient::new();
+
+    let error = request_with_retry(&url, 3, || client.get(&url, None))
+        .await
+        .expect_err("a client error should fail");
+
+    assert!(error.to_string().contains("404"), "{:#}", error);
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn slow_requests_time_out() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+    let url = Url::parse(&format!(
+        "http://{}/",
+        listener.local_addr().expect("has address")
+    ))
+    .expect("valid URL");
+    // Accept the connection but never answer
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("should accept");
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        drop(stream);
+    });
+    let client = HttpClient::new().with_timeout(Duration::from_millis(100));
+
+    let error = client
+        .post(&url, "{}", None)
+        .await
+        .expect_err("the request should time out");
+
+    assert!(matches!(error, HttpError::Timeout(_)), "{}", error);
+    server.abort();
+}
+
+#[tokio::test]
+async fn posts_json_with_the_api_key() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("should bind");
+    let url = Url::parse(&format!(
+        "http://{}/v1/embeddings",
+        listener.local_addr().expect("has address")
+    ))
+    .expect("valid URL");
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.expect("should accept");
+        let request = read_request(&mut stream).await;
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\n{}")
+            .await
+            .expect("should write response");
+        request
+    });
+
+    let response = HttpClient::new()
+        .post(&url, r#"{"input":["text"]}"#, Some("sk-test"))
+        .await
+        .expect("request should succeed");
+
+    assert_eq!(response, "{}");
+    let request = server
+        .await
+        .expect("server should finish")
+        .to_ascii_lowercase();
+    assert!(request.starts_with("post /v1/embeddings "), "{}", request);
+    assert!(
+        request.contains("authorization: bearer sk-test"),
+        "{}",
+        request
+    );
+    assert!(
+        request.contains("content-type: application/json"),
+        "{}",
+        request
+    );
+    assert!(request.ends_with(r#"{"input":["text"]}"#), "{}", request);
+}
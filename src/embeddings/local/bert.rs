@@ -0,0 +1,424 @@
+//! BERT Encoder
+//!
+//! The forward pass of a BERT encoder, which is all that sentence embedding
+//! models such as bge-small and all-MiniLM need. Each text is encoded on its
+//! own, so no attention masks or padding are involved, and matrix products
+//! are spread over threads with rayon.
+
+use std::collections::HashMap;
+
+use anyhow::{Result, anyhow, bail};
+use rayon::prelude::*;
+use serde::Deserialize;
+
+use super::safetensors::Tensor;
+
+/// Architecture settings from a model's `config.json`
+#[derive(Debug, Clone, Deserialize)]
+pub(super) struct BertConfig {
+    pub model_type: Option<String>,
+    pub hidden_size: usize,
+    pub num_hidden_layers: usize,
+    pub num_attention_heads: usize,
+    pub intermediate_size: usize,
+    pub max_position_embeddings: usize,
+    #[serde(default = "default_hidden_act")]
+    pub hidden_act: String,
+    #[serde(default = "default_layer_norm_eps")]
+    pub layer_norm_eps: f32,
+}
+
+fn default_hidden_act() -> String {
+    "gelu".to_string()
+}
+
+fn default_layer_norm_eps() -> f32 {
+    1e-12
+}
+
+#[derive(Debug)]
+pub(super) struct BertModel {
+    config: BertConfig,
+    word_embeddings: Tensor,
+    position_embeddings: Tensor,
+    token_type_embeddings: Tensor,
+    embeddings_norm: LayerNorm,
+    layers: Vec<EncoderLayer>,
+}
+
+#[derive(Debug)]
+struct EncoderLayer {
+    query: Linear,
+    key: Linear,
+    value: Linear,
+    attention_output: Linear,
+    attention_norm: LayerNorm,
+    intermediate: Linear,
+    output: Linear,
+    output_norm: LayerNorm,
+}
+
+/// A dense layer with weights stored as `out_dim` rows of `in_dim` values
+#[derive(Debug)]
+struct Linear {
+    weight: Vec<f32>,
+    bias: Vec<f32>,
+    in_dim: usize,
+    out_dim: usize,
+}
+
+#[derive(Debug)]
+struct LayerNorm {
+    weight: Vec<f32>,
+    bias: Vec<f32>,
+    eps: f32,
+}
+
+/// Takes the model's tensors by name, accepting checkpoints saved with or
+/// without a `bert.` prefix
+struct Weights(HashMap<String, Tensor>);
+
+impl Weights {
+    fn get(&self, name: &str) -> Option<&Tensor> {
+        self.0
+            .get(name)
+            .or_else(|| self.0.get(&format!("bert.{}", name)))
+    }
+
+    /// Number of rows of a table whose size depends on the vocabulary
+    fn rows(&self, name: &str) -> Result<usize> {
+        self.get(name)
+            .and_then(|tensor| tensor.shape.first().copied())
+            .ok_or_else(|| anyhow!("Model weights have no tensor {}", name))
+    }
+
+    fn take(&mut self, name: &str, shape: &[usize]) -> Result<Tensor> {
+        let tensor = self
+            .0
+            .remove(name)
+            .or_else(|| self.0.remove(&format!("bert.{}", name)))
+            .ok_or_else(|| anyhow!("Model weights have no tensor {}", name))?;
+        if tensor.shape != shape {
+            bail!(
+                "Tensor {} has shape {:?}, expected {:?}",
+                name,
+                tensor.shape,
+                shape
+            );
+        }
+        Ok(tensor)
+    }
+
+    fn linear(&mut self, name: &str, in_dim: usize, out_dim: usize) -> Result<Linear> {
+        Ok(Linear {
+            weight: self
+                .take(&format!("{}.weight", name), &[out_dim, in_dim])?
+                .data,
+            bias: self.take(&format!("{}.bias", name), &[out_dim])?.data,
+            in_dim,
+            out_dim,
+        })
+    }
+
+    fn layer_norm(&mut self, name: &str, dim: usize, eps: f32) -> Result<LayerNorm> {
+        // Older checkpoints name the parameters gamma and beta
+        let weight = self
+            .take(&format!("{}.weight", name), &[dim])
+            .or_else(|_| self.take(&format!("{}.gamma", name), &[dim]))?;
+        let bias = self
+            .take(&format!("{}.bias", name), &[dim])
+            .or_else(|_| self.take(&format!("{}.beta", name), &[dim]))?;
+        Ok(LayerNorm {
+            weight: weight.data,
+            bias: bias.data,
+            eps,
+        })
+    }
+}
+
+impl BertModel {
+    pub(super) fn load(config: BertConfig, tensors: HashMap<String, Tensor>) -> Result<Self> {
+        if config.hidden_act != "gelu" {
+            bail!(
+                "Unsupported activation {}: only gelu models are supported",
+                config.hidden_act
+            );
+        }
+        if config.num_attention_heads == 0 || config.hidden_size % config.num_attention_heads != 0 {
+            bail!(
+                "Hidden size {} is not divisible into {} attention heads",
+                config.hidden_size,
+                config.num_attention_heads
+            );
+        }
+
+        let hidden = config.hidden_size;
+        let eps = config.layer_norm_eps;
+        let mut weights = Weights(tensors);
+
+        let vocab_size = weights.rows("embeddings.word_embeddings.weight")?;
+        let word_embeddings =
+            weights.take("embeddings.word_embeddings.weight", &[vocab_size, hidden])?;
+        let position_embeddings = weights.take(
+            "embeddings.position_embeddings.weight",
+            &[config.max_position_embeddings, hidden],
+        )?;
+        let type_vocab_size = weights.rows("embeddings.token_type_embeddings.weight")?;
+        let token_type_embeddings = weights.take(
+            "embeddings.token_type_embeddings.weight",
+            &[type_vocab_size, hidden],
+        )?;
+        let embeddings_norm = weights.layer_norm("embeddings.LayerNorm", hidden, eps)?;
+
+        let layers = (0..config.num_hidden_layers)
+            .map(|i| {
+                let prefix = format!("encoder.layer.{}", i);
+                let intermediate = config.intermediate_size;
+                Ok(EncoderLayer {
+                    query: weights.linear(
+                        &format!("{}.attention.self.query", prefix),
+                        hidden,
+                        hidden,
+                    )?,
+                    key: weights.linear(
+                        &format!("{}.attention.self.key", prefix),
+                        hidden,
+                        hidden,
+                    )?,
+                    value: weights.linear(
+                        &format!("{}.attention.self.value", prefix),
+                        hidden,
+                        hidden,
+                    )?,
+                    attention_output: weights.linear(
+                        &format!("{}.attention.output.dense", prefix),
+                        hidden,
+                        hidden,
+                    )?,
+                    attention_norm: weights.layer_norm(
+                        &format!("{}.attention.output.LayerNorm", prefix),
+                        hidden,
+                        eps,
+                    )?,
+                    intermediate: weights.linear(
+                        &format!("{}.intermediate.dense", prefix),
+                        hidden,
+                        intermediate,
+                    )?,
+                    output: weights.linear(
+                        &format!("{}.output.dense", prefix),
+                        intermediate,
+                        hidden,
+                    )?,
+                    output_norm: weights.layer_norm(
+                        &format!("{}.output.LayerNorm", prefix),
+                        hidden,
+                        eps,
+                    )?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            config,
+            word_embeddings,
+            position_embeddings,
+            token_type_embeddings,
+            embeddings_norm,
+            layers,
+        })
+    }
+
+    /// Size of the hidden states, and so of the embeddings
+    pub(super) fn hidden_size(&self) -> usize {
+        self.config.hidden_size
+    }
+
+    /// Longest sequence of tokens the model accepts
+    pub(super) fn max_tokens(&self) -> usize {
+        self.config.max_position_embeddings
+    }
+
+    /// Hidden states of the last layer: a row of `hidden_size` values for each
+    /// token
+    pub(super) fn forward(&self, token_ids: &[u32]) -> Result<Vec<f32>> {
+        let hidden = self.config.hidden_size;
+        let vocab_size = self.word_embeddings.shape[0];
+        if token_ids.len() > self.max_tokens() {
+            bail!(
+                "{} tokens exceed the model's limit of {}",
+                token_ids.len(),
+                self.max_tokens()
+            );
+        }
+
+        let mut states = vec![0.0; token_ids.len() * hidden];
+        for (position, (&id, row)) in token_ids
+            .iter()
+            .zip(states.chunks_exact_mut(hidden))
+            .enumerate()
+        {
+            let id = id as usize;
+            if id >= vocab_size {
+                bail!("Token {} is outside the vocabulary", id);
+            }
+            let word = &self.word_embeddings.data[id * hidden..(id + 1) * hidden];
+            let position =
+                &self.position_embeddings.data[position * hidden..(position + 1) * hidden];
+            // Every token belongs to the first segment
+            let token_type = &self.token_type_embeddings.data[..hidden];
+            for (((value, w), p), t) in row.iter_mut().zip(word).zip(position).zip(token_type) {
+                *value = w + p + t;
+            }
+        }
+        self.embeddings_norm.apply(&mut states);
+
+        for layer in &self.layers {
+            states = layer.forward(&states, self.config.num_attention_heads);
+        }
+        Ok(states)
+    }
+}
+
+impl EncoderLayer {
+    fn forward(&self, input: &[f32], heads: usize) -> Vec<f32> {
+        let query = self.query.forward(input);
+        let key = self.key.forward(input);
+        let value = self.value.forward(input);
+        let context = attention(&query, &key, &value, self.query.out_dim, heads);
+
+        let mut attended = self.attention_output.forward(&context);
+        add(&mut attended, input);
+        self.attention_norm.apply(&mut attended);
+
+        let mut intermediate = self.intermediate.forward(&attended);
+        intermediate.par_iter_mut().for_each(|x| *x = gelu(*x));
+
+        let mut output = self.output.forward(&intermediate);
+        add(&mut output, &attended);
+        self.output_norm.apply(&mut output);
+        output
+    }
+}
+
+impl Linear {
+    fn forward(&self, input: &[f32]) -> Vec<f32> {
+        let rows = input.len() / self.in_dim;
+        let mut output = vec![0.0; rows * self.out_dim];
+        output
+            .par_chunks_mut(self.out_dim)
+            .zip(input.par_chunks(self.in_dim))
+            .for_each(|(output_row, input_row)| {
+                for ((out, weights), bias) in output_row
+                    .iter_mut()
+                    .zip(self.weight.chunks_exact(self.in_dim))
+                    .zip(&self.bias)
+                {
+                    *out = dot(weights, input_row) + bias;
+                }
+            });
+        output
+    }
+}
+
+impl LayerNorm {
+    fn apply(&self, states: &mut [f32]) {
+        let dim = self.weight.len();
+        states.par_chunks_mut(dim).for_each(|row| {
+            let mean = row.iter().sum::<f32>() / dim as f32;
+            let variance = row.iter().map(|x| (x - mean).powi(2)).sum::<f32>() / dim as f32;
+            let scale = 1.0 / (variance + self.eps).sqrt();
+            for ((x, weight), bias) in row.iter_mut().zip(&self.weight).zip(&self.bias) {
+                *x = ((*x - mean) * scale).mul_add(*weight, *bias);
+            }
+        });
+    }
+}
+
+/// Multi-head self-attention over every token
+fn attention(query: &[f32], key: &[f32], value: &[f32], hidden: usize, heads: usize) -> Vec<f32> {
+    let tokens = query.len() / hidden;
+    let head_dim = hidden / heads;
+    let scale = 1.0 / (head_dim as f32).sqrt();
+
+    let mut context = vec![0.0; query.len()];
+    context
+        .par_chunks_mut(hidden)
+        .zip(query.par_chunks(hidden))
+        .for_each(|(context_row, query_row)| {
+            let mut scores = vec![0.0; tokens];
+            for head in 0..heads {
+                let range = head * head_dim..(head + 1) * head_dim;
+                for (score, key_row) in scores.iter_mut().zip(key.chunks_exact(hidden)) {
+                    *score = dot(&query_row[range.clone()], &key_row[range.clone()]) * scale;
+                }
+                softmax(&mut scores);
+
+                let head_context = &mut context_row[range.clone()];
+                for (&score, value_row) in scores.iter().zip(value.chunks_exact(hidden)) {
+                    for (out, v) in head_context.iter_mut().zip(&value_row[range.clone()]) {
+                        *out = score.mul_add(*v, *out);
+                    }
+                }
+            }
+        });
+    context
+}
+
+/// Dot product, accumulated in eight lanes so that it vectorizes
+// `mul_add` would be a libm call on targets built without FMA, which is far
+// slower than separate multiplies and adds in this loop
+#[allow(clippy::suboptimal_flops)]
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    let mut lanes = [0.0f32; 8];
+    let a_chunks = a.chunks_exact(8);
+    let b_chunks = b.chunks_exact(8);
+    let remainder: f32 = a_chunks
+        .remainder()
+        .iter()
+        .zip(b_chunks.remainder())
+        .map(|(x, y)| x * y)
+        .sum();
+    for (a_chunk, b_chunk) in a_chunks.zip(b_chunks) {
+        for ((lane, x), y) in lanes.iter_mut().zip(a_chunk).zip(b_chunk) {
+            *lane += x * y;
+        }
+    }
+    lanes.iter().sum::<f32>() + remainder
+}
+
+fn softmax(values: &mut [f32]) {
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+    for value in values.iter_mut() {
+        *value = (*value - max).exp();
+        sum += *value;
+    }
+    for value in values.iter_mut() {
+        *value /= sum;
+    }
+}
+
+fn add(values: &mut [f32], other: &[f32]) {
+    for (value, other) in values.iter_mut().zip(other) {
+        *value += other;
+    }
+}
+
+/// GELU activation with the exact error function, as BERT's `gelu`
+fn gelu(x: f32) -> f32 {
+    0.5 * x * (1.0 + erf(x / std::f32::consts::SQRT_2))
+}
+
+/// Error function, accurate to about 1e-7 (Abramowitz and Stegun 7.1.26)
+fn erf(x: f32) -> f32 {
+    let t = 1.0 / 0.327_591_1_f32.mul_add(x.abs(), 1.0);
+    let polynomial = 1.061_405_4_f32
+        .mul_add(t, -1.453_152_1)
+        .mul_add(t, 1.421_413_8)
+        .mul_add(t, -0.284_496_74)
+        .mul_add(t, 0.254_829_6)
+        * t;
+    let y = 1.0 - polynomial * (-x * x).exp();
+    y.copysign(x)
+}
@@ -0,0 +1,238 @@
+//! In-process Embeddings
+//!
+//! Runs a BERT sentence embedding model, such as bge-small-en-v1.5 or
+//! all-MiniLM-L6-v2, on the CPU, so that no embedding server is needed. The
+//! model is read from a directory in the layout of a HuggingFace download:
+//! `config.json`, `model.safetensors`, `tokenizer.json` and, for
+//! sentence-transformers models, `1_Pooling/config.json`.
+
+mod bert;
+mod safetensors;
+#[cfg(test)]
+mod tests;
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow, bail};
+use async_trait::async_trait;
+use rayon::prelude::*;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+use crate::config::settings::{OllamaConfig, PromptTemplate};
+use crate::embeddings::tokenizer::WordPieceTokenizer;
+use crate::embeddings::{EmbeddingProvider, EmbeddingResult};
+use bert::{BertConfig, BertModel};
+
+/// How the hidden states of a text's tokens become its embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pooling {
+    /// The state of the leading `[CLS]` token, as used by the BGE models
+    Cls,
+    /// The mean over all tokens, as used by most sentence-transformers models
+    Mean,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PoolingConfig {
+    #[serde(default)]
+    pooling_mode_cls_token: bool,
+}
+
+#[derive(Debug)]
+pub struct LocalEmbedder {
+    model_name: String,
+    embedding_dimension: usize,
+    /// Shared with the blocking threads running inference
+    model: Arc<SentenceModel>,
+    prompt: PromptTemplate,
+}
+
+/// A loaded model with the tokenizer and pooling that turn texts into
+/// embeddings
+#[derive(Debug)]
+struct SentenceModel {
+    bert: BertModel,
+    tokenizer: WordPieceTokenizer,
+    pooling: Pooling,
+}
+
+impl LocalEmbedder {
+    /// Load the model in the configured `model_path`
+    pub fn new(config: &OllamaConfig) -> Result<Self> {
+        let model_path = config
+            .model_path
+            .as_deref()
+            .ok_or_else(|| anyhow!("The local embedding provider needs a model_path"))?;
+
+        info!("Loading embedding model from {}", model_path.display());
+
+        let config_path = model_path.join("config.json");
+        let content = fs::read_to_string(&config_path)
+            .with_context(|| format!("Failed to read model config: {}", config_path.display()))?;
+        let bert_config: BertConfig = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse model config: {}", config_path.display()))?;
+        if let Some(model_type) = &bert_config.model_type
+            && model_type != "bert"
+        {
+            bail!(
+                "Unsupported model type {}: only BERT models can run locally",
+                model_type
+            );
+        }
+
+        let tensors = safetensors::load(&model_path.join("model.safetensors"))?;
+        let bert = BertModel::load(bert_config, tensors).context("Failed to load model weights")?;
+        let tokenizer = WordPieceTokenizer::load(&model_path.join("tokenizer.json"))?;
+        let pooling = load_pooling(model_path)?;
+
+        debug!(
+            "Loaded model with hidden size {} and {:?} pooling",
+            bert.hidden_size(),
+            pooling
+        );
+
+        Ok(Self {
+            model_name: config.model.clone(),
+            embedding_dimension: config.embedding_dimension as usize,
+            model: Arc::new(SentenceModel {
+                bert,
+                tokenizer,
+                pooling,
+            }),
+            prompt: config.prompt_template(),
+        })
+    }
+
+    /// Embed texts on a blocking thread, so inference doesn't stall the
+    /// runtime
+    async fn embed_texts(&self, texts: Vec<String>) -> Result<Vec<EmbeddingResult>> {
+        let model = Arc::clone(&self.model);
+        tokio::task::spawn_blocking(move || {
+            texts
+                .into_par_iter()
+                .map(|text| {
+                    let (embedding, token_count) = model.embed(&text)?;
+                    Ok(EmbeddingResult {
+                        text,
+                        embedding,
+                        token_count,
+                        chunk_index: None,
+                        heading_path: None,
+                    })
+                })
+                .collect()
+        })
+        .await
+        .context("Embedding task failed")?
+    }
+}
+
+impl SentenceModel {
+    /// Embed a text, returning the normalized embedding and its token count
+    fn embed(&self, text: &str) -> Result<(Vec<f32>, usize)> {
+        let token_ids = self.tokenizer.encode(text, self.bert.max_tokens());
+        let states = self.bert.forward(&token_ids)?;
+        let hidden = self.bert.hidden_size();
+
+        let mut embedding = match self.pooling {
+            Pooling::Cls => states[..hidden].to_vec(),
+            Pooling::Mean => {
+                let mut sum = vec![0.0; hidden];
+                for row in states.chunks_exact(hidden) {
+                    for (total, value) in sum.iter_mut().zip(row) {
+                        *total += value;
+                    }
+                }
+                let count = token_ids.len() as f32;
+                sum.iter_mut().for_each(|value| *value /= count);
+                sum
+            }
+        };
+
+        let norm = embedding
+            .iter()
+            .map(|value| value * value)
+            .sum::<f32>()
+            .sqrt();
+        if norm > 0.0 {
+            embedding.iter_mut().for_each(|value| *value /= norm);
+        }
+
+        Ok((embedding, token_ids.len()))
+    }
+}
+
+/// Read the pooling of a sentence-transformers model, defaulting to mean
+/// pooling for models without a pooling config
+fn load_pooling(model_path: &Path) -> Result<Pooling> {
+    let path = model_path.join("1_Pooling").join("config.json");
+    if !path.exists() {
+        return Ok(Pooling::Mean);
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read pooling config: {}", path.display()))?;
+    let config: PoolingConfig = serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse pooling config: {}", path.display()))?;
+
+    Ok(if config.pooling_mode_cls_token {
+        Pooling::Cls
+    } else {
+        Pooling::Mean
+    })
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbedder {
+    fn model(&self) -> &str {
+        &self.model_name
+    }
+
+    fn prompt(&self) -> &PromptTemplate {
+        &self.prompt
+    }
+
+    /// The model's position embeddings, which texts are truncated to
+    async fn context_length(&self) -> Result<Option<usize>> {
+        Ok(Some(self.model.bert.max_tokens()))
+    }
+
+    /// Check that the model's embeddings fit the configured dimension
+    async fn health_check(&self) -> Result<()> {
+        if self.model.bert.hidden_size() != self.embedding_dimension {
+            bail!(
+                "Model produces {}-dimensional embeddings, but embedding_dimension is {}",
+                self.model.bert.hidden_size(),
+                self.embedding_dimension
+            );
+        }
+
+        info!("Health check passed for local model {}", self.model_name);
+        Ok(())
+    }
+
+    async fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult> {
+        debug!("Generating embedding for text (length: {})", text.len());
+
+        self.embed_texts(vec![text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("No embedding generated"))
+    }
+
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
+        debug!("Generating embeddings for {} texts", texts.len());
+
+        self.embed_texts(texts.to_vec()).await
+    }
+
+    async fn rerank(&self, model: &str, _query: &str, _documents: &[String]) -> Result<Vec<f32>> {
+        bail!(
+            "Reranker model {} is not available with the local embedding provider",
+            model
+        )
+    }
+}
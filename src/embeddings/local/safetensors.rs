@@ -0,0 +1,99 @@
+//! Safetensors Loading
+//!
+//! A `.safetensors` file is a little-endian header length, a JSON header
+//! describing each tensor, and the raw tensor data. Tensors are read as `f32`,
+//! converting half precision weights.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow, bail};
+use half::{bf16, f16};
+use serde::Deserialize;
+
+/// A dense row-major tensor
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct Tensor {
+    pub shape: Vec<usize>,
+    pub data: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TensorInfo {
+    dtype: String,
+    shape: Vec<usize>,
+    data_offsets: [usize; 2],
+}
+
+/// Read every tensor of a safetensors file
+pub(super) fn load(path: &Path) -> Result<HashMap<String, Tensor>> {
+    let bytes = fs::read(path)
+        .with_context(|| format!("Failed to read model weights: {}", path.display()))?;
+    parse(&bytes).with_context(|| format!("Invalid safetensors file: {}", path.display()))
+}
+
+fn parse(bytes: &[u8]) -> Result<HashMap<String, Tensor>> {
+    let header_len = bytes
+        .first_chunk::<8>()
+        .map(|len| u64::from_le_bytes(*len))
+        .ok_or_else(|| anyhow!("File is too short"))?;
+    let header_end = usize::try_from(header_len)
+        .ok()
+        .and_then(|len| len.checked_add(8))
+        .filter(|&end| end <= bytes.len())
+        .ok_or_else(|| anyhow!("Header length {} exceeds the file", header_len))?;
+
+    let header: HashMap<String, serde_json::Value> =
+        serde_json::from_slice(&bytes[8..header_end]).context("Failed to parse header")?;
+    let data = &bytes[header_end..];
+
+    let mut tensors = HashMap::with_capacity(header.len());
+    for (name, info) in header {
+        if name == "__metadata__" {
+            continue;
+        }
+        let info: TensorInfo = serde_json::from_value(info)
+            .with_context(|| format!("Invalid description of tensor {}", name))?;
+        let [begin, end] = info.data_offsets;
+        let raw = data
+            .get(begin..end)
+            .ok_or_else(|| anyhow!("Data of tensor {} is out of bounds", name))?;
+
+        let values: Vec<f32> = match info.dtype.as_str() {
+            "F32" => raw
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+                .collect(),
+            "F16" => raw
+                .chunks_exact(2)
+                .map(|b| f16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            "BF16" => raw
+                .chunks_exact(2)
+                .map(|b| bf16::from_le_bytes([b[0], b[1]]).to_f32())
+                .collect(),
+            dtype => bail!("Tensor {} has unsupported type {}", name, dtype),
+        };
+
+        let elements: usize = info.shape.iter().product();
+        if values.len() != elements {
+            bail!(
+                "Tensor {} has {} values for shape {:?}",
+                name,
+                values.len(),
+                info.shape
+            );
+        }
+
+        tensors.insert(
+            name,
+            Tensor {
+                shape: info.shape,
+                data: values,
+            },
+        );
+    }
+
+    Ok(tensors)
+}
@@ -0,0 +1,283 @@
+use std::path::Path;
+
+use serde_json::json;
+use tempfile::TempDir;
+
+use super::*;
+use crate::config::settings::EmbeddingBackend;
+use crate::embeddings::create_provider;
+use crate::embeddings::tests::write_tokenizer;
+
+const HIDDEN: usize = 8;
+const INTERMEDIATE: usize = 16;
+const MAX_POSITIONS: usize = 16;
+const VOCAB: &[&str] = &[
+    "[PAD]", "[UNK]", "[CLS]", "[SEP]", "rust", "docs", "search", "##ing", "index", "the", ",",
+    "!", "cafe", "lance", "##db",
+];
+
+/// Write tensors as a safetensors file, in half precision if `f16` is set
+fn write_safetensors(path: &Path, tensors: &[(String, Vec<usize>, Vec<f32>)], f16: bool) {
+    let mut header = serde_json::Map::new();
+    let mut data = Vec::new();
+    for (name, shape, values) in tensors {
+        let begin = data.len();
+        for value in values {
+            if f16 {
+                data.extend_from_slice(&half::f16::from_f32(*value).to_le_bytes());
+            } else {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        header.insert(
+            name.clone(),
+            json!({
+                "dtype": if f16 { "F16" } else { "F32" },
+                "shape": shape,
+                "data_offsets": [begin, data.len()],
+            }),
+        );
+    }
+    header.insert("__metadata__".to_string(), json!({"format": "pt"}));
+
+    let header = serde_json::to_vec(&header).expect("Failed to serialize header");
+    let mut bytes = (header.len() as u64).to_le_bytes().to_vec();
+    bytes.extend_from_slice(&header);
+    bytes.extend_from_slice(&data);
+    fs::write(path, bytes).expect("Failed to write weights");
+}
+
+/// Deterministic, varied weights for a tensor of `count` values
+fn weights(seed: usize, count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|i| ((seed * 31 + i * 7) as f32 * 0.37).sin() * 0.5)
+        .collect()
+}
+
+/// Write a one-layer BERT model small enough to run instantly
+fn write_model(dir: &Path, f16: bool) {
+    let mut tensors = Vec::new();
+    let mut add = |name: &str, shape: Vec<usize>| {
+        let count = shape.iter().product();
+        let values = if name.ends_with("LayerNorm.weight") {
+            vec![1.0; count]
+        } else {
+            weights(tensors.len(), count)
+        };
+        tensors.push((name.to_string(), shape, values));
+    };
+
+    add(
+        "embeddings.word_embeddings.weight",
+        vec![VOCAB.len(), HIDDEN],
+    );
+    add(
+        "embeddings.position_embeddings.weight",
+        vec![MAX_POSITIONS, HIDDEN],
+    );
+    add("embeddings.token_type_embeddings.weight", vec![2, HIDDEN]);
+    add("embeddings.LayerNorm.weight", vec![HIDDEN]);
+    add("embeddings.LayerNorm.bias", vec![HIDDEN]);
+    for (name, in_dim, out_dim) in [
+        ("attention.self.query", HIDDEN, HIDDEN),
+        ("attention.self.key", HIDDEN, HIDDEN),
+        ("attention.self.value", HIDDEN, HIDDEN),
+        ("attention.output.dense", HIDDEN, HIDDEN),
+        ("intermediate.dense", HIDDEN, INTERMEDIATE),
+        ("output.dense", INTERMEDIATE, HIDDEN),
+    ] {
+        add(
+            &format!("encoder.layer.0.{}.weight", name),
+            vec![out_dim, in_dim],
+        );
+        add(&format!("encoder.layer.0.{}.bias", name), vec![out_dim]);
+    }
+    for name in ["attention.output.LayerNorm", "output.LayerNorm"] {
+        add(&format!("encoder.layer.0.{}.weight", name), vec![HIDDEN]);
+        add(&format!("encoder.layer.0.{}.bias", name), vec![HIDDEN]);
+    }
+    write_safetensors(&dir.join("model.safetensors"), &tensors, f16);
+
+    let config = json!({
+        "model_type": "bert",
+        "hidden_size": HIDDEN,
+        "num_hidden_layers": 1,
+        "num_attention_heads": 2,
+        "intermediate_size": INTERMEDIATE,
+        "max_position_embeddings": MAX_POSITIONS,
+        "hidden_act": "gelu",
+        "layer_norm_eps": 1e-12,
+    });
+    fs::write(dir.join("config.json"), config.to_string()).expect("Failed to write config");
+
+    write_tokenizer(dir, VOCAB);
+}
+
+fn model_dir(f16: bool) -> TempDir {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    write_model(dir.path(), f16);
+    dir
+}
+
+fn local_config(model_path: &Path) -> OllamaConfig {
+    OllamaConfig {
+        provider: EmbeddingBackend::Local,
+        model: "tiny-bert".to_string(),
+        embedding_dimension: HIDDEN as u32,
+        model_path: Some(model_path.to_path_buf()),
+        ..OllamaConfig::default()
+    }
+}
+
+fn embedder(model_path: &Path) -> LocalEmbedder {
+    LocalEmbedder::new(&local_config(model_path)).expect("Failed to load model")
+}
+
+#[tokio::test]
+async fn embeddings_are_normalized_and_deterministic() {
+    let dir = model_dir(false);
+    let embedder = embedder(dir.path());
+
+    let first = embedder
+        .generate_embedding("searching rust docs")
+        .await
+        .expect("embedding should succeed");
+    let second = embedder
+        .generate_embedding("searching rust docs")
+        .await
+        .expect("embedding should succeed");
+
+    assert_eq!(first.embedding.len(), HIDDEN);
+    assert_eq!(first.embedding, second.embedding);
+    assert_eq!(first.token_count, 6);
+    let norm = first
+        .embedding
+        .iter()
+        .map(|value| value * value)
+        .sum::<f32>()
+        .sqrt();
+    assert!((norm - 1.0).abs() < 1e-5, "norm was {}", norm);
+    assert!(first.embedding.iter().all(|value| value.is_finite()));
+}
+
+#[tokio::test]
+async fn batches_match_single_embeddings() {
+    let dir = model_dir(false);
+    let embedder = embedder(dir.path());
+    let texts = vec!["rust docs".to_string(), "lancedb index".to_string()];
+
+    let batch = embedder
+        .generate_embeddings_batch(&texts)
+        .await
+        .expect("embedding should succeed");
+
+    assert_eq!(batch.len(), 2);
+    for (result, text) in batch.iter().zip(&texts) {
+        let single = embedder
+            .generate_embedding(text)
+            .await
+            .expect("embedding should succeed");
+        assert_eq!(&result.text, text);
+        assert_eq!(result.embedding, single.embedding);
+    }
+    assert_ne!(batch[0].embedding, batch[1].embedding);
+}
+
+#[tokio::test]
+async fn half_precision_weights_give_close_embeddings() {
+    let full = model_dir(false);
+    let half = model_dir(true);
+
+    let full = embedder(full.path())
+        .generate_embedding("rust docs")
+        .await
+        .expect("embedding should succeed");
+    let half = embedder(half.path())
+        .generate_embedding("rust docs")
+        .await
+        .expect("embedding should succeed");
+
+    let similarity: f32 = full
+        .embedding
+        .iter()
+        .zip(&half.embedding)
+        .map(|(a, b)| a * b)
+        .sum();
+    assert!(similarity > 0.99, "similarity was {}", similarity);
+}
+
+#[tokio::test]
+async fn pooling_config_selects_cls_pooling() {
+    let dir = model_dir(false);
+    let mean = embedder(dir.path())
+        .generate_embedding("rust docs")
+        .await
+        .expect("embedding should succeed");
+
+    fs::create_dir_all(dir.path().join("1_Pooling")).expect("Failed to create pooling dir");
+    fs::write(
+        dir.path().join("1_Pooling").join("config.json"),
+        r#"{"word_embedding_dimension": 8, "pooling_mode_cls_token": true, "pooling_mode_mean_tokens": false}"#,
+    )
+    .expect("Failed to write pooling config");
+    let embedder = embedder(dir.path());
+    let cls = embedder
+        .generate_embedding("rust docs")
+        .await
+        .expect("embedding should succeed");
+
+    assert_eq!(embedder.model.pooling, Pooling::Cls);
+    assert_ne!(cls.embedding, mean.embedding);
+}
+
+#[test]
+fn other_architectures_are_rejected() {
+    let dir = model_dir(false);
+    fs::write(
+        dir.path().join("config.json"),
+        json!({
+            "model_type": "nomic_bert",
+            "hidden_size": HIDDEN,
+            "num_hidden_layers": 1,
+            "num_attention_heads": 2,
+            "intermediate_size": INTERMEDIATE,
+            "max_position_embeddings": MAX_POSITIONS,
+        })
+        .to_string(),
+    )
+    .expect("Failed to write config");
+
+    let error = LocalEmbedder::new(&local_config(dir.path())).expect_err("model should not load");
+    assert!(error.to_string().contains("nomic_bert"), "{:#}", error);
+}
+
+#[tokio::test]
+async fn health_check_compares_dimensions() {
+    let dir = model_dir(false);
+    let mut config = local_config(dir.path());
+    config.embedding_dimension = 384;
+
+    let provider = create_provider(&config).expect("Failed to create provider");
+    assert_eq!(provider.model(), "tiny-bert");
+    let error = provider
+        .health_check()
+        .await
+        .expect_err("dimension mismatch should fail");
+    assert!(error.to_string().contains("384"), "{:#}", error);
+
+    embedder(dir.path())
+        .health_check()
+        .await
+        .expect("health check should pass");
+}
+
+#[tokio::test]
+async fn context_length_is_the_position_limit() {
+    let dir = model_dir(false);
+
+    let context_length = embedder(dir.path())
+        .context_length()
+        .await
+        .expect("context length should be known");
+    assert_eq!(context_length, Some(MAX_POSITIONS));
+}
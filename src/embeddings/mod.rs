@@ -1,5 +1,198 @@
 // Embeddings module
-// This module will handle Ollama integration and content chunking
+// This module handles embedding backends and content chunking
 
 pub mod chunking;
+pub mod hashing;
+pub(crate) mod http;
+#[cfg(feature = "local-embeddings")]
+pub mod local;
 pub mod ollama;
+pub mod openai;
+#[cfg(test)]
+pub(crate) mod tests;
+pub mod tokenizer;
+
+use std::fmt;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::config::settings::{EmbeddingBackend, OllamaConfig, PromptTemplate};
+use crate::embeddings::chunking::ContentChunk;
+use crate::embeddings::hashing::HashingEmbedder;
+use crate::embeddings::ollama::OllamaClient;
+use crate::embeddings::openai::OpenAiClient;
+
+pub(crate) const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmbeddingResult {
+    pub text: String,
+    pub embedding: Vec<f32>,
+    pub token_count: usize,
+    pub chunk_index: Option<usize>,
+    pub heading_path: Option<String>,
+}
+
+/// A backend which turns text into embeddings
+///
+/// The indexer embeds chunks and the MCP server embeds queries through this
+/// trait, so neither depends on the API of a particular embedding server.
+/// Requests are awaited, so dropping one, such as when a search is cancelled,
+/// stops it.
+#[async_trait]
+pub trait EmbeddingProvider: fmt::Debug + Send + Sync {
+    /// Name of the embedding model
+    fn model(&self) -> &str;
+
+    /// Prefixes the model expects before queries and documents
+    fn prompt(&self) -> &PromptTemplate;
+
+    /// Check that the backend is reachable and serves the model
+    async fn health_check(&self) -> Result<()>;
+
+    /// Generate the embedding of a single text
+    async fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult>;
+
+    /// Generate embeddings for several texts, in the same order
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>>;
+
+    /// Score how relevant each document is to `query` with a reranker model,
+    /// in the order of `documents`
+    async fn rerank(&self, model: &str, query: &str, documents: &[String]) -> Result<Vec<f32>>;
+
+    /// Most tokens of a text the model embeds before truncating it, if the
+    /// backend can tell
+    async fn context_length(&self) -> Result<Option<usize>> {
+        Ok(None)
+    }
+
+    /// Generate the embedding of a search query, prefixed as the model expects
+    async fn generate_query_embedding(&self, query: &str) -> Result<EmbeddingResult> {
+        let mut result = self
+            .generate_embedding(&self.prompt().format_query(query))
+            .await?;
+        result.text = query.to_string();
+        Ok(result)
+    }
+
+    /// Generate embeddings for documents, prefixed as the model expects, in
+    /// the same order
+    async fn generate_document_embeddings(
+        &self,
+        documents: &[String],
+    ) -> Result<Vec<EmbeddingResult>> {
+        let texts: Vec<String> = documents
+            .iter()
+            .map(|document| self.prompt().format_document(document))
+            .collect();
+        let mut results = self.generate_embeddings_batch(&texts).await?;
+        for (result, document) in results.iter_mut().zip(documents) {
+            result.text.clone_from(document);
+        }
+        Ok(results)
+    }
+
+    /// Generate embeddings for chunks, carrying over their metadata
+    async fn generate_chunk_embeddings(
+        &self,
+        chunks: &[ContentChunk],
+    ) -> Result<Vec<EmbeddingResult>> {
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!("Generating embeddings for {} content chunks", chunks.len());
+
+        let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
+        let mut results = self.generate_document_embeddings(&texts).await?;
+
+        // Add chunk-specific metadata to results
+        for (result, chunk) in results.iter_mut().zip(chunks.iter()) {
+            result.chunk_index = Some(chunk.chunk_index);
+            result.heading_path = Some(chunk.heading_path.clone());
+            result.token_count = chunk.token_count;
+        }
+
+        debug!("Enhanced embeddings with chunk metadata");
+        Ok(results)
+    }
+}
+
+/// Create the embedding provider selected by the configuration
+pub fn create_provider(config: &OllamaConfig) -> Result<Box<dyn EmbeddingProvider>> {
+    Ok(match config.provider {
+        EmbeddingBackend::Ollama => Box::new(OllamaClient::new(config.clone())?),
+        EmbeddingBackend::OpenAi => Box::new(OpenAiClient::new(config.clone())?),
+        EmbeddingBackend::Local => local_provider(config)?,
+        EmbeddingBackend::Hashing => Box::new(HashingEmbedder::new(config)),
+    })
+}
+
+#[cfg(feature = "local-embeddings")]
+fn local_provider(config: &OllamaConfig) -> Result<Box<dyn EmbeddingProvider>> {
+    Ok(Box::new(local::LocalEmbedder::new(config)?))
+}
+
+#[cfg(not(feature = "local-embeddings"))]
+fn local_provider(_config: &OllamaConfig) -> Result<Box<dyn EmbeddingProvider>> {
+    Err(anyhow!(
+        "The local embedding provider is not available in this build. Reinstall docs-mcp with `--features local-embeddings` to use it"
+    ))
+}
+
+#[derive(Debug, Serialize)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankedDocument>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RerankedDocument {
+    index: usize,
+    relevance_score: f32,
+}
+
+/// Body of a Jina/Cohere-style `/v1/rerank` request
+fn rerank_request_json(model: &str, query: &str, documents: &[String]) -> Result<String> {
+    let request = RerankRequest {
+        model,
+        query,
+        documents,
+    };
+    serde_json::to_string(&request).context("Failed to serialize rerank request")
+}
+
+/// Scores of a `/v1/rerank` response, in the order of the request's documents
+fn rerank_scores(response_text: &str, document_count: usize) -> Result<Vec<f32>> {
+    let rerank_response: RerankResponse =
+        serde_json::from_str(response_text).context("Failed to parse rerank response")?;
+
+    let mut scores: Vec<Option<f32>> = vec![None; document_count];
+    for result in rerank_response.results {
+        let score = scores.get_mut(result.index).ok_or_else(|| {
+            anyhow!(
+                "Reranker returned index {} for {} documents",
+                result.index,
+                document_count
+            )
+        })?;
+        *score = Some(result.relevance_score);
+    }
+
+    scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, score)| {
+            score.ok_or_else(|| anyhow!("Reranker did not score document {}", index))
+        })
+        .collect()
+}
@@ -1,26 +1,34 @@
 #[cfg(test)]
 mod tests;
 
+use std::collections::HashMap;
+use std::future::Future;
+
 use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+#[cfg(test)]
 use std::time::Duration;
-use tracing::{debug, error, info, warn};
+use tracing::{debug, info, warn};
 use url::Url;
 
-use crate::{config::settings::OllamaConfig, embeddings::chunking::ContentChunk};
+use crate::config::settings::{OllamaConfig, PromptTemplate};
+use crate::embeddings::http::{HttpClient, HttpError, request_with_retry};
+use crate::embeddings::{
+    DEFAULT_RETRY_ATTEMPTS, EmbeddingProvider, EmbeddingResult, rerank_request_json, rerank_scores,
+};
 
 pub const DEFAULT_EMBEDDING_DIMENSION: u32 = 768; // Standard embedding dimension
-const DEFAULT_RETRY_ATTEMPTS: u32 = 3;
-const EXPONENTIAL_BACKOFF_BASE: u64 = 2;
 
 #[derive(Debug, Clone)]
 pub struct OllamaClient {
     base_url: Url,
     model: String,
     batch_size: u32,
-    agent: ureq::Agent,
+    http: HttpClient,
     retry_attempts: u32,
     embedding_dimension: u32,
+    prompt: PromptTemplate,
 }
 
 #[derive(Debug, Serialize)]
@@ -48,6 +56,21 @@ struct BatchEmbedResponse {
     embeddings: Vec<Vec<f32>>,
 }
 
+#[derive(Debug, Serialize)]
+struct ShowRequest<'a> {
+    model: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct ShowResponse {
+    /// Modelfile parameters, one `name value` pair per line
+    #[serde(default)]
+    parameters: String,
+    /// Architecture details, such as `bert.context_length`
+    #[serde(default)]
+    model_info: HashMap<String, serde_json::Value>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct ModelInfo {
     pub name: String,
@@ -58,31 +81,18 @@ struct ModelsResponse {
     models: Vec<ModelInfo>,
 }
 
-#[derive(Debug, Clone, PartialEq)]
-pub struct EmbeddingResult {
-    pub text: String,
-    pub embedding: Vec<f32>,
-    pub token_count: usize,
-    pub chunk_index: Option<usize>,
-    pub heading_path: Option<String>,
-}
-
 impl OllamaClient {
     pub fn new(config: OllamaConfig) -> Result<Self> {
         let base_url = config
             .ollama_url()
             .context("Failed to generate Ollama URL from config")?;
 
-        let agent = ureq::Agent::config_builder()
-            .timeout_global(None)
-            .build()
-            .into();
-
         Ok(Self {
             base_url,
+            prompt: config.prompt_template(),
             batch_size: config.batch_size,
             model: config.model,
-            agent,
+            http: HttpClient::new(),
             retry_attempts: DEFAULT_RETRY_ATTEMPTS,
             embedding_dimension: config.embedding_dimension,
         })
@@ -90,10 +100,7 @@ impl OllamaClient {
 
     #[cfg(test)]
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
-        self.agent = ureq::Agent::config_builder()
-            .timeout_global(Some(timeout))
-            .build()
-            .into();
+        self.http = self.http.with_timeout(timeout);
         self
     }
 
@@ -103,25 +110,8 @@ impl OllamaClient {
         self
     }
 
-    /// Test connection to Ollama server and verify model availability
-    pub fn health_check(&self) -> Result<()> {
-        debug!("Performing health check for Ollama at {}", self.base_url);
-
-        // First check if server is reachable
-        self.ping().context("Server ping failed")?;
-
-        // Then check if model is available
-        self.validate_model().context("Model validation failed")?;
-
-        info!(
-            "Health check passed for Ollama server at {} with model {}",
-            self.base_url, self.model
-        );
-        Ok(())
-    }
-
     /// Ping the Ollama server to check if it's responsive
-    pub fn ping(&self) -> Result<()> {
+    pub async fn ping(&self) -> Result<()> {
         let url = self
             .base_url
             .join("/api/tags")
@@ -129,23 +119,19 @@ impl OllamaClient {
 
         debug!("Pinging Ollama server at {}", url);
 
-        self.make_request_with_retry(|| {
-            self.agent
-                .get(url.as_str())
-                .call()
-                .and_then(|mut resp| resp.body_mut().read_to_string())
-        })
-        .context("Failed to ping Ollama server")?;
+        self.make_request_with_retry(|| self.http.get(&url, None))
+            .await
+            .context("Failed to ping Ollama server")?;
 
         debug!("Server ping successful");
         Ok(())
     }
 
     /// Validate that the configured model is available
-    pub fn validate_model(&self) -> Result<()> {
+    pub async fn validate_model(&self) -> Result<()> {
         debug!("Validating model: {}", self.model);
 
-        let models = self.list_models().context("Failed to list models")?;
+        let models = self.list_models().await.context("Failed to list models")?;
 
         if models.iter().any(|m| m.name == self.model) {
             debug!("Model {} is available", self.model);
@@ -165,7 +151,7 @@ impl OllamaClient {
     }
 
     /// List all available models
-    pub fn list_models(&self) -> Result<Vec<ModelInfo>> {
+    pub async fn list_models(&self) -> Result<Vec<ModelInfo>> {
         let url = self
             .base_url
             .join("/api/tags")
@@ -174,12 +160,8 @@ impl OllamaClient {
         debug!("Fetching available models from {}", url);
 
         let response_text = self
-            .make_request_with_retry(|| {
-                self.agent
-                    .get(url.as_str())
-                    .call()
-                    .and_then(|mut resp| resp.body_mut().read_to_string())
-            })
+            .make_request_with_retry(|| self.http.get(&url, None))
+            .await
             .context("Failed to fetch models")?;
 
         let models_response: ModelsResponse =
@@ -189,8 +171,126 @@ impl OllamaClient {
         Ok(models_response.models)
     }
 
+    /// Fetch the details of the configured model from `/api/show`
+    async fn show_model(&self) -> Result<String> {
+        let url = self
+            .base_url
+            .join("/api/show")
+            .context("Failed to build model details URL")?;
+
+        let request_json = serde_json::to_string(&ShowRequest { model: &self.model })
+            .context("Failed to serialize model details request")?;
+
+        self.make_request_with_retry(|| self.http.post(&url, &request_json, None))
+            .await
+            .with_context(|| format!("Failed to fetch details of model {}", self.model))
+    }
+
+    async fn generate_embeddings_single_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<EmbeddingResult>> {
+        if texts.len() == 1 {
+            // Use single embedding API for single text
+            let result = self.generate_embedding(&texts[0]).await?;
+            return Ok(vec![result]);
+        }
+
+        // Use batch API for multiple texts
+        let request = BatchEmbedRequest {
+            model: self.model.clone(),
+            inputs: texts.to_vec(),
+            options: Some(EmbedOptions {
+                num_predict: Some(self.embedding_dimension),
+            }),
+        };
+
+        let url = self
+            .base_url
+            .join("/api/embed")
+            .context("Failed to build batch embedding URL")?;
+
+        let request_json = serde_json::to_string(&request)
+            .context("Failed to serialize batch embedding request")?;
+
+        let response_text = self
+            .make_request_with_retry(|| self.http.post(&url, &request_json, None))
+            .await
+            .context("Failed to generate batch embeddings")?;
+
+        let batch_response: BatchEmbedResponse = serde_json::from_str(&response_text)
+            .context("Failed to parse batch embedding response")?;
+
+        if batch_response.embeddings.len() != texts.len() {
+            return Err(anyhow::anyhow!(
+                "Mismatch between request and response counts: {} vs {}",
+                texts.len(),
+                batch_response.embeddings.len()
+            ));
+        }
+
+        let results = texts
+            .iter()
+            .zip(batch_response.embeddings.iter())
+            .map(|(text, embedding)| EmbeddingResult {
+                text: text.clone(),
+                embedding: embedding.clone(),
+                token_count: crate::embeddings::chunking::estimate_token_count(text),
+                chunk_index: None,
+                heading_path: None,
+            })
+            .collect();
+
+        Ok(results)
+    }
+
+    async fn make_request_with_retry<F, Fut>(&self, request_fn: F) -> Result<String>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<String, HttpError>>,
+    {
+        request_with_retry(&self.base_url, self.retry_attempts, request_fn).await
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaClient {
+    /// Name of the embedding model
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self) -> &PromptTemplate {
+        &self.prompt
+    }
+
+    /// The model's context length from `/api/show`, lowered to its `num_ctx`
+    /// parameter if that is smaller, as Ollama truncates input to it
+    async fn context_length(&self) -> Result<Option<usize>> {
+        parse_context_length(&self.show_model().await?)
+    }
+
+    /// Test connection to Ollama server and verify model availability
+    async fn health_check(&self) -> Result<()> {
+        debug!("Performing health check for Ollama at {}", self.base_url);
+
+        // First check if server is reachable
+        self.ping().await.context("Server ping failed")?;
+
+        // Then check if model is available
+        self.validate_model()
+            .await
+            .context("Model validation failed")?;
+
+        info!(
+            "Health check passed for Ollama server at {} with model {}",
+            self.base_url, self.model
+        );
+        Ok(())
+    }
+
     /// Generate embeddings for a single text input
-    pub fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult> {
+    async fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult> {
         debug!("Generating embedding for text (length: {})", text.len());
 
         let request = EmbedRequest {
@@ -210,13 +310,8 @@ impl OllamaClient {
             serde_json::to_string(&request).context("Failed to serialize embedding request")?;
 
         let response_text = self
-            .make_request_with_retry(|| {
-                self.agent
-                    .post(url.as_str())
-                    .header("Content-Type", "application/json")
-                    .send(&request_json)
-                    .and_then(|mut resp| resp.body_mut().read_to_string())
-            })
+            .make_request_with_retry(|| self.http.post(&url, &request_json, None))
+            .await
             .context("Failed to generate embedding")?;
 
         let embed_response: BatchEmbedResponse =
@@ -242,8 +337,38 @@ impl OllamaClient {
         Ok(result)
     }
 
+    /// Score how relevant each document is to `query` with a reranker model
+    ///
+    /// Uses the Jina/Cohere-style `/v1/rerank` endpoint, which the embedding
+    /// server must provide. Scores are returned in the order of `documents`.
+    async fn rerank(&self, model: &str, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!(
+            "Reranking {} documents with model {}",
+            documents.len(),
+            model
+        );
+
+        let url = self
+            .base_url
+            .join("/v1/rerank")
+            .context("Failed to build rerank URL")?;
+
+        let request_json = rerank_request_json(model, query, documents)?;
+
+        let response_text = self
+            .make_request_with_retry(|| self.http.post(&url, &request_json, None))
+            .await
+            .context("Failed to rerank documents")?;
+
+        rerank_scores(&response_text, documents.len())
+    }
+
     /// Generate embeddings for multiple text inputs using batch processing
-    pub fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
         if texts.is_empty() {
             return Ok(Vec::new());
         }
@@ -256,6 +381,7 @@ impl OllamaClient {
         for chunk in texts.chunks(self.batch_size as usize) {
             let batch_results = self
                 .generate_embeddings_single_batch(chunk)
+                .await
                 .with_context(|| format!("Failed to process batch of {} texts", chunk.len()))?;
 
             results.extend(batch_results);
@@ -264,155 +390,28 @@ impl OllamaClient {
         debug!("Generated {} embeddings total", results.len());
         Ok(results)
     }
+}
 
-    /// Generate embeddings for ContentChunk objects
-    pub fn generate_chunk_embeddings(
-        &self,
-        chunks: &[ContentChunk],
-    ) -> Result<Vec<EmbeddingResult>> {
-        if chunks.is_empty() {
-            return Ok(Vec::new());
-        }
-
-        debug!("Generating embeddings for {} content chunks", chunks.len());
-
-        let texts: Vec<String> = chunks.iter().map(|c| c.content.clone()).collect();
-        let mut results = self.generate_embeddings_batch(&texts)?;
-
-        // Add chunk-specific metadata to results
-        for (result, chunk) in results.iter_mut().zip(chunks.iter()) {
-            result.chunk_index = Some(chunk.chunk_index);
-            result.heading_path = Some(chunk.heading_path.clone());
-            result.token_count = chunk.token_count;
-        }
-
-        debug!("Enhanced embeddings with chunk metadata");
-        Ok(results)
-    }
-
-    fn generate_embeddings_single_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
-        if texts.len() == 1 {
-            // Use single embedding API for single text
-            let result = self.generate_embedding(&texts[0])?;
-            return Ok(vec![result]);
-        }
-
-        // Use batch API for multiple texts
-        let request = BatchEmbedRequest {
-            model: self.model.clone(),
-            inputs: texts.to_vec(),
-            options: Some(EmbedOptions {
-                num_predict: Some(self.embedding_dimension),
-            }),
-        };
-
-        let url = self
-            .base_url
-            .join("/api/embed")
-            .context("Failed to build batch embedding URL")?;
-
-        let request_json = serde_json::to_string(&request)
-            .context("Failed to serialize batch embedding request")?;
-
-        let response_text = self
-            .make_request_with_retry(|| {
-                self.agent
-                    .post(url.as_str())
-                    .header("Content-Type", "application/json")
-                    .send(&request_json)
-                    .and_then(|mut resp| resp.body_mut().read_to_string())
-            })
-            .context("Failed to generate batch embeddings")?;
-
-        let batch_response: BatchEmbedResponse = serde_json::from_str(&response_text)
-            .context("Failed to parse batch embedding response")?;
-
-        if batch_response.embeddings.len() != texts.len() {
-            return Err(anyhow::anyhow!(
-                "Mismatch between request and response counts: {} vs {}",
-                texts.len(),
-                batch_response.embeddings.len()
-            ));
-        }
-
-        let results = texts
-            .iter()
-            .zip(batch_response.embeddings.iter())
-            .map(|(text, embedding)| EmbeddingResult {
-                text: text.clone(),
-                embedding: embedding.clone(),
-                token_count: crate::embeddings::chunking::estimate_token_count(text),
-                chunk_index: None,
-                heading_path: None,
-            })
-            .collect();
-
-        Ok(results)
-    }
-
-    fn make_request_with_retry<F>(&self, mut request_fn: F) -> Result<String>
-    where
-        F: FnMut() -> Result<String, ureq::Error>,
-    {
-        let mut last_error = None;
-
-        for attempt in 1..=self.retry_attempts {
-            debug!("HTTP request attempt {}/{}", attempt, self.retry_attempts);
-
-            match request_fn() {
-                Ok(response_text) => {
-                    debug!("Request succeeded on attempt {}", attempt);
-                    return Ok(response_text);
-                }
-                Err(error) => {
-                    let should_retry = match &error {
-                        ureq::Error::StatusCode(status) => {
-                            if *status >= 500 {
-                                warn!(
-                                    "Server error (status {}), attempt {}/{}",
-                                    status, attempt, self.retry_attempts
-                                );
-                                true // Retry server errors
-                            } else {
-                                warn!("Client error (status {}), not retrying", status);
-                                return Err(anyhow::anyhow!("Client error: HTTP {}", status));
-                            }
-                        }
-                        ureq::Error::ConnectionFailed
-                        | ureq::Error::HostNotFound
-                        | ureq::Error::Timeout(_)
-                        | ureq::Error::Io(_) => {
-                            warn!(
-                                "Transport error: {}, attempt {}/{}",
-                                error, attempt, self.retry_attempts
-                            );
-                            true // Retry transport errors
-                        }
-                        _ => {
-                            warn!("Non-retryable error: {}", error);
-                            false // Don't retry other errors
-                        }
-                    };
-
-                    if !should_retry {
-                        return Err(anyhow::anyhow!("Non-retryable error: {}", error));
-                    }
-
-                    last_error = Some(anyhow::anyhow!("Request error: {}", error));
-
-                    // Wait before retry (exponential backoff)
-                    if attempt < self.retry_attempts {
-                        let delay_ms = EXPONENTIAL_BACKOFF_BASE.pow(attempt - 1) * 1000;
-                        let delay = Duration::from_millis(delay_ms);
-                        debug!("Waiting {:?} before retry", delay);
-                        std::thread::sleep(delay);
-                    }
-                }
-            }
-        }
-
-        error!("All retry attempts failed for request to {}", self.base_url);
-
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Request failed after retries")))
-    }
+/// Context length of an `/api/show` response
+fn parse_context_length(response_text: &str) -> Result<Option<usize>> {
+    let show_response: ShowResponse =
+        serde_json::from_str(response_text).context("Failed to parse model details")?;
+
+    let context_length = show_response
+        .model_info
+        .iter()
+        .find(|(key, _)| key.ends_with(".context_length"))
+        .and_then(|(_, value)| value.as_u64())
+        .and_then(|length| usize::try_from(length).ok());
+    let num_ctx = show_response.parameters.lines().find_map(|line| {
+        let mut parts = line.split_whitespace();
+        (parts.next() == Some("num_ctx"))
+            .then(|| parts.next()?.parse::<usize>().ok())
+            .flatten()
+    });
+
+    Ok(match (context_length, num_ctx) {
+        (Some(context_length), Some(num_ctx)) => Some(context_length.min(num_ctx)),
+        (context_length, num_ctx) => context_length.or(num_ctx),
+    })
 }
@@ -1,4 +1,5 @@
 use super::*;
+use crate::embeddings::tests::serve_once;
 
 #[test]
 fn client_configuration() {
@@ -9,6 +10,7 @@ fn client_configuration() {
         model: "test-model".to_string(),
         batch_size: 128,
         embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+        ..Default::default()
     };
     let client = OllamaClient::new(config).expect("Failed to create client");
 
@@ -37,10 +39,139 @@ fn embedding_result_structure() {
     assert_eq!(result.heading_path, Some("Test Section".to_string()));
 }
 
+fn local_client(port: u16) -> OllamaClient {
+    OllamaClient::new(OllamaConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        ..OllamaConfig::default()
+    })
+    .expect("Failed to create client")
+    .with_retry_attempts(1)
+}
+
+#[tokio::test]
+async fn rerank_scores_documents_in_request_order() {
+    let (port, server) = serve_once(
+        r#"{"results":[{"index":1,"relevance_score":0.9},{"index":0,"relevance_score":0.2}]}"#,
+    );
+    let documents = vec!["first".to_string(), "second".to_string()];
+
+    let scores = local_client(port)
+        .rerank("bge-reranker", "which one", &documents)
+        .await
+        .expect("rerank should succeed");
+
+    assert_eq!(scores, vec![0.2, 0.9]);
+    let request = server.join().expect("server thread");
+    assert!(request.starts_with("POST /v1/rerank "));
+    assert!(request.contains(r#""model":"bge-reranker""#));
+    assert!(request.contains(r#""documents":["first","second"]"#));
+}
+
+#[tokio::test]
+async fn queries_and_documents_get_the_model_prefixes() {
+    let (port, server) = serve_once(r#"{"embeddings":[[0.5, 0.5]]}"#);
+    let result = local_client(port)
+        .generate_query_embedding("how to configure")
+        .await
+        .expect("embedding should succeed");
+    assert_eq!(result.text, "how to configure");
+    let request = server.join().expect("server thread");
+    assert!(
+        request.contains(r#""input":"search_query: how to configure""#),
+        "{}",
+        request
+    );
+
+    let (port, server) = serve_once(r#"{"embeddings":[[0.5, 0.5],[0.5, 0.5]]}"#);
+    let documents = vec!["first".to_string(), "second".to_string()];
+    let results = local_client(port)
+        .generate_document_embeddings(&documents)
+        .await
+        .expect("embedding should succeed");
+    assert_eq!(results[1].text, "second");
+    let request = server.join().expect("server thread");
+    assert!(
+        request.contains(r#""input":["search_document: first","search_document: second"]"#),
+        "{}",
+        request
+    );
+}
+
+#[tokio::test]
+async fn configured_prompt_replaces_built_in_prefixes() {
+    let (port, server) = serve_once(r#"{"embeddings":[[0.5, 0.5]]}"#);
+    let client = OllamaClient::new(OllamaConfig {
+        host: "127.0.0.1".to_string(),
+        port,
+        prompt: Some(PromptTemplate {
+            query: "Q: ".to_string(),
+            document: String::new(),
+        }),
+        ..OllamaConfig::default()
+    })
+    .expect("Failed to create client")
+    .with_retry_attempts(1);
+
+    client
+        .generate_query_embedding("rate limits")
+        .await
+        .expect("embedding should succeed");
+    let request = server.join().expect("server thread");
+    assert!(
+        request.contains(r#""input":"Q: rate limits""#),
+        "{}",
+        request
+    );
+}
+
+#[tokio::test]
+async fn context_length_comes_from_model_details() {
+    let (port, server) = serve_once(
+        r#"{"parameters":"num_ctx                        8192","model_info":{"general.architecture":"nomic-bert","nomic-bert.context_length":2048}}"#,
+    );
+
+    let context_length = local_client(port)
+        .context_length()
+        .await
+        .expect("model details should be fetched");
+
+    // The smaller of the architecture's limit and num_ctx applies
+    assert_eq!(context_length, Some(2048));
+    let request = server.join().expect("server thread");
+    assert!(request.starts_with("POST /api/show "));
+    assert!(request.contains(r#""model":"nomic-embed-text:latest""#));
+
+    assert_eq!(
+        parse_context_length(r#"{"parameters":"num_ctx 512\nstop \"<eos>\"","model_info":{"bert.context_length":8192}}"#)
+            .expect("should parse"),
+        Some(512)
+    );
+    assert_eq!(
+        parse_context_length(r#"{"model_info":{"general.architecture":"bert"}}"#)
+            .expect("should parse"),
+        None
+    );
+}
+
+#[tokio::test]
+async fn rerank_rejects_incomplete_responses() {
+    let (port, server) = serve_once(r#"{"results":[{"index":0,"relevance_score":0.5}]}"#);
+    let documents = vec!["first".to_string(), "second".to_string()];
+
+    let error = local_client(port)
+        .rerank("bge-reranker", "which one", &documents)
+        .await
+        .expect_err("a missing score is an error");
+
+    assert!(error.to_string().contains("did not score document 1"));
+    server.join().expect("server thread");
+}
+
 mod integration_tests {
     use std::env;
 
-    use crate::embeddings::chunking::estimate_token_count;
+    use crate::embeddings::chunking::{ContentChunk, estimate_token_count};
 
     use super::*;
 
@@ -63,6 +194,7 @@ mod integration_tests {
             model,
             batch_size: 5, // Smaller batch size for testing
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         };
 
         OllamaClient::new(config)
@@ -79,14 +211,14 @@ mod integration_tests {
             .ok(); // Ignore error if already initialized
     }
 
-    #[test]
-    fn real_ollama_health_check() {
+    #[tokio::test]
+    async fn real_ollama_health_check() {
         init_test_tracing();
 
         let client = create_integration_test_client();
 
         info!("Testing health check against real Ollama instance");
-        let result = client.health_check();
+        let result = client.health_check().await;
 
         assert!(
             result.is_ok(),
@@ -97,14 +229,14 @@ mod integration_tests {
         info!("Health check passed successfully");
     }
 
-    #[test]
-    fn real_ollama_list_models() {
+    #[tokio::test]
+    async fn real_ollama_list_models() {
         init_test_tracing();
 
         let client = create_integration_test_client();
 
         info!("Testing model listing against real Ollama instance");
-        let result = client.list_models();
+        let result = client.list_models().await;
 
         assert!(result.is_ok(), "Model listing should succeed: {:?}", result);
 
@@ -130,8 +262,8 @@ mod integration_tests {
         }
     }
 
-    #[test]
-    fn real_ollama_single_embedding() {
+    #[tokio::test]
+    async fn real_ollama_single_embedding() {
         init_test_tracing();
 
         let client = create_integration_test_client();
@@ -140,7 +272,7 @@ mod integration_tests {
             "This is a test document about machine learning and artificial intelligence.";
 
         info!("Generating embedding for single text");
-        let result = client.generate_embedding(test_text);
+        let result = client.generate_embedding(test_text).await;
 
         assert!(
             result.is_ok(),
@@ -173,8 +305,8 @@ mod integration_tests {
         );
     }
 
-    #[test]
-    fn real_ollama_batch_embeddings() {
+    #[tokio::test]
+    async fn real_ollama_batch_embeddings() {
         init_test_tracing();
 
         let client = create_integration_test_client();
@@ -190,7 +322,7 @@ mod integration_tests {
             "Generating embeddings for batch of {} texts",
             test_texts.len()
         );
-        let result = client.generate_embeddings_batch(&test_texts);
+        let result = client.generate_embeddings_batch(&test_texts).await;
 
         assert!(
             result.is_ok(),
@@ -244,8 +376,8 @@ mod integration_tests {
         );
     }
 
-    #[test]
-    fn real_ollama_chunk_embeddings() {
+    #[tokio::test]
+    async fn real_ollama_chunk_embeddings() {
         init_test_tracing();
 
         let client = create_integration_test_client();
@@ -278,7 +410,7 @@ mod integration_tests {
             "Generating embeddings for {} content chunks",
             test_chunks.len()
         );
-        let result = client.generate_chunk_embeddings(&test_chunks);
+        let result = client.generate_chunk_embeddings(&test_chunks).await;
 
         assert!(
             result.is_ok(),
@@ -327,8 +459,8 @@ mod integration_tests {
         );
     }
 
-    #[test]
-    fn real_ollama_large_batch() {
+    #[tokio::test]
+    async fn real_ollama_large_batch() {
         init_test_tracing();
 
         let client = create_integration_test_client();
@@ -347,7 +479,7 @@ mod integration_tests {
             "Generating embeddings for large batch of {} texts",
             test_texts.len()
         );
-        let result = client.generate_embeddings_batch(&test_texts);
+        let result = client.generate_embeddings_batch(&test_texts).await;
 
         assert!(
             result.is_ok(),
@@ -399,14 +531,14 @@ mod integration_tests {
         info!("Large batch processing completed successfully");
     }
 
-    #[test]
-    fn real_ollama_empty_input() {
+    #[tokio::test]
+    async fn real_ollama_empty_input() {
         init_test_tracing();
 
         let client = create_integration_test_client();
 
         // Test empty batch
-        let result = client.generate_embeddings_batch(&[]);
+        let result = client.generate_embeddings_batch(&[]).await;
         assert!(result.is_ok(), "Empty batch should be handled gracefully");
         assert!(
             result.expect("embedding result succeeded").is_empty(),
@@ -414,7 +546,7 @@ mod integration_tests {
         );
 
         // Test empty chunks
-        let result = client.generate_chunk_embeddings(&[]);
+        let result = client.generate_chunk_embeddings(&[]).await;
         assert!(result.is_ok(), "Empty chunks should be handled gracefully");
         assert!(
             result.expect("embedding result succeeded").is_empty(),
@@ -424,8 +556,8 @@ mod integration_tests {
         info!("Empty input handling works correctly");
     }
 
-    #[test]
-    fn real_ollama_error_recovery() {
+    #[tokio::test]
+    async fn real_ollama_error_recovery() {
         init_test_tracing();
 
         // Create client with invalid model to test error handling
@@ -436,6 +568,7 @@ mod integration_tests {
             model: "non-existent-model-12345".to_string(),
             batch_size: 5,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         };
 
         let client = OllamaClient::new(config)
@@ -446,14 +579,14 @@ mod integration_tests {
         info!("Testing error recovery with invalid model");
 
         // Health check should fail due to invalid model
-        let result = client.health_check();
+        let result = client.health_check().await;
         assert!(
             result.is_err(),
             "Health check should fail with invalid model"
         );
 
         // Embedding generation should also fail
-        let result = client.generate_embedding("test text");
+        let result = client.generate_embedding("test text").await;
         assert!(
             result.is_err(),
             "Embedding generation should fail with invalid model"
@@ -0,0 +1,275 @@
+//! OpenAI-compatible Embeddings
+//!
+//! llama.cpp server, vLLM, LM Studio and LocalAI all serve the `/v1/embeddings`
+//! endpoint of the OpenAI API, so a single client covers them, as well as
+//! hosted APIs which take a bearer token.
+
+#[cfg(test)]
+mod tests;
+
+use std::fmt;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tracing::{debug, info, warn};
+use url::Url;
+
+use crate::config::settings::{OllamaConfig, PromptTemplate};
+use crate::embeddings::chunking::estimate_token_count;
+use crate::embeddings::http::{HttpClient, request_with_retry};
+use crate::embeddings::{
+    DEFAULT_RETRY_ATTEMPTS, EmbeddingProvider, EmbeddingResult, rerank_request_json, rerank_scores,
+};
+
+#[derive(Clone)]
+pub struct OpenAiClient {
+    base_url: Url,
+    model: String,
+    batch_size: u32,
+    api_key: Option<String>,
+    http: HttpClient,
+    retry_attempts: u32,
+    prompt: PromptTemplate,
+}
+
+#[derive(Debug, Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [String],
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelData {
+    id: String,
+}
+
+impl OpenAiClient {
+    pub fn new(config: OllamaConfig) -> Result<Self> {
+        let base_url = config
+            .ollama_url()
+            .context("Failed to generate embedding server URL from config")?;
+
+        Ok(Self {
+            base_url,
+            prompt: config.prompt_template(),
+            model: config.model,
+            batch_size: config.batch_size,
+            api_key: config.api_key,
+            http: HttpClient::new(),
+            retry_attempts: DEFAULT_RETRY_ATTEMPTS,
+        })
+    }
+
+    #[cfg(test)]
+    pub fn with_retry_attempts(mut self, attempts: u32) -> Self {
+        self.retry_attempts = attempts;
+        self
+    }
+
+    /// List the IDs of the models served
+    pub async fn list_models(&self) -> Result<Vec<String>> {
+        let url = self
+            .base_url
+            .join("/v1/models")
+            .context("Failed to build models URL")?;
+
+        debug!("Fetching available models from {}", url);
+
+        let response_text = request_with_retry(&self.base_url, self.retry_attempts, || {
+            self.http.get(&url, self.api_key.as_deref())
+        })
+        .await
+        .context("Failed to fetch models")?;
+
+        let models_response: ModelsResponse =
+            serde_json::from_str(&response_text).context("Failed to parse models response")?;
+
+        Ok(models_response
+            .data
+            .into_iter()
+            .map(|model| model.id)
+            .collect())
+    }
+
+    /// POST a JSON body to `path`, returning the response body
+    async fn post(&self, path: &str, body: &str) -> Result<String> {
+        let url = self
+            .base_url
+            .join(path)
+            .with_context(|| format!("Failed to build {} URL", path))?;
+
+        request_with_retry(&self.base_url, self.retry_attempts, || {
+            self.http.post(&url, body, self.api_key.as_deref())
+        })
+        .await
+    }
+
+    async fn generate_embeddings_single_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<EmbeddingResult>> {
+        let request = EmbeddingsRequest {
+            model: &self.model,
+            input: texts,
+        };
+        let request_json =
+            serde_json::to_string(&request).context("Failed to serialize embedding request")?;
+
+        let response_text = self
+            .post("/v1/embeddings", &request_json)
+            .await
+            .context("Failed to generate embeddings")?;
+
+        let mut response: EmbeddingsResponse =
+            serde_json::from_str(&response_text).context("Failed to parse embedding response")?;
+
+        if response.data.len() != texts.len() {
+            return Err(anyhow!(
+                "Mismatch between request and response counts: {} vs {}",
+                texts.len(),
+                response.data.len()
+            ));
+        }
+
+        // The API doesn't promise to return embeddings in request order
+        response.data.sort_by_key(|data| data.index);
+        if response
+            .data
+            .iter()
+            .enumerate()
+            .any(|(position, data)| data.index != position)
+        {
+            return Err(anyhow!(
+                "Embedding response has missing or repeated indices"
+            ));
+        }
+
+        Ok(texts
+            .iter()
+            .zip(response.data)
+            .map(|(text, data)| EmbeddingResult {
+                text: text.clone(),
+                embedding: data.embedding,
+                token_count: estimate_token_count(text),
+                chunk_index: None,
+                heading_path: None,
+            })
+            .collect())
+    }
+}
+
+impl fmt::Debug for OpenAiClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Keep the API key out of logs
+        f.debug_struct("OpenAiClient")
+            .field("base_url", &self.base_url)
+            .field("model", &self.model)
+            .field("batch_size", &self.batch_size)
+            .field("api_key", &self.api_key.as_ref().map(|_| "<redacted>"))
+            .field("retry_attempts", &self.retry_attempts)
+            .field("prompt", &self.prompt)
+            .finish_non_exhaustive()
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OpenAiClient {
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn prompt(&self) -> &PromptTemplate {
+        &self.prompt
+    }
+
+    /// Check that the server answers, and that it lists the model
+    ///
+    /// Servers which host a single model, like llama.cpp, ignore the model
+    /// named in requests, so a model missing from the list is only a warning.
+    async fn health_check(&self) -> Result<()> {
+        debug!(
+            "Performing health check for embedding server at {}",
+            self.base_url
+        );
+
+        let models = self.list_models().await.context("Server ping failed")?;
+        if !models.contains(&self.model) {
+            warn!(
+                "Model {} is not listed by the embedding server. Available models: {:?}",
+                self.model, models
+            );
+        }
+
+        info!(
+            "Health check passed for embedding server at {} with model {}",
+            self.base_url, self.model
+        );
+        Ok(())
+    }
+
+    async fn generate_embedding(&self, text: &str) -> Result<EmbeddingResult> {
+        debug!("Generating embedding for text (length: {})", text.len());
+
+        self.generate_embeddings_single_batch(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("Embedding server did not return any embeddings"))
+    }
+
+    async fn generate_embeddings_batch(&self, texts: &[String]) -> Result<Vec<EmbeddingResult>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!("Generating embeddings for {} texts", texts.len());
+
+        let mut results = Vec::with_capacity(texts.len());
+        for chunk in texts.chunks(self.batch_size as usize) {
+            let batch_results = self
+                .generate_embeddings_single_batch(chunk)
+                .await
+                .with_context(|| format!("Failed to process batch of {} texts", chunk.len()))?;
+            results.extend(batch_results);
+        }
+
+        debug!("Generated {} embeddings total", results.len());
+        Ok(results)
+    }
+
+    async fn rerank(&self, model: &str, query: &str, documents: &[String]) -> Result<Vec<f32>> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        debug!(
+            "Reranking {} documents with model {}",
+            documents.len(),
+            model
+        );
+
+        let request_json = rerank_request_json(model, query, documents)?;
+        let response_text = self
+            .post("/v1/rerank", &request_json)
+            .await
+            .context("Failed to rerank documents")?;
+
+        rerank_scores(&response_text, documents.len())
+    }
+}
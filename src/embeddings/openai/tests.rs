@@ -0,0 +1,93 @@
+use super::*;
+use crate::config::settings::EmbeddingBackend;
+use crate::embeddings::tests::serve_once;
+
+fn local_client(port: u16, api_key: Option<&str>) -> OpenAiClient {
+    OpenAiClient::new(OllamaConfig {
+        provider: EmbeddingBackend::OpenAi,
+        host: "127.0.0.1".to_string(),
+        port,
+        model: "bge-small-en-v1.5".to_string(),
+        api_key: api_key.map(str::to_string),
+        ..OllamaConfig::default()
+    })
+    .expect("Failed to create client")
+    .with_retry_attempts(1)
+}
+
+#[tokio::test]
+async fn embeddings_are_returned_in_request_order() {
+    let (port, server) = serve_once(
+        r#"{"object":"list","data":[{"object":"embedding","index":1,"embedding":[0.0,1.0]},{"object":"embedding","index":0,"embedding":[1.0,0.0]}],"model":"bge-small-en-v1.5"}"#,
+    );
+    let texts = vec!["first".to_string(), "second".to_string()];
+
+    let results = local_client(port, None)
+        .generate_embeddings_batch(&texts)
+        .await
+        .expect("embedding should succeed");
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].text, "first");
+    assert_eq!(results[0].embedding, vec![1.0, 0.0]);
+    assert_eq!(results[1].embedding, vec![0.0, 1.0]);
+
+    let request = server.join().expect("server thread");
+    assert!(request.starts_with("POST /v1/embeddings "));
+    assert!(request.contains(r#""model":"bge-small-en-v1.5""#));
+    assert!(request.contains(r#""input":["first","second"]"#));
+    assert!(!request.to_ascii_lowercase().contains("authorization"));
+}
+
+#[tokio::test]
+async fn api_key_is_sent_as_bearer_token() {
+    let (port, server) =
+        serve_once(r#"{"data":[{"index":0,"embedding":[0.5,0.5]}],"model":"bge-small-en-v1.5"}"#);
+
+    let result = local_client(port, Some("secret"))
+        .generate_embedding("query")
+        .await
+        .expect("embedding should succeed");
+
+    assert_eq!(result.embedding, vec![0.5, 0.5]);
+    let request = server.join().expect("server thread");
+    assert!(
+        request
+            .to_ascii_lowercase()
+            .contains("authorization: bearer secret")
+    );
+}
+
+#[tokio::test]
+async fn incomplete_responses_are_rejected() {
+    let (port, server) = serve_once(r#"{"data":[{"index":0,"embedding":[0.5,0.5]}]}"#);
+    let texts = vec!["first".to_string(), "second".to_string()];
+
+    let error = local_client(port, None)
+        .generate_embeddings_batch(&texts)
+        .await
+        .expect_err("a missing embedding is an error");
+
+    assert!(format!("{:#}", error).contains("Mismatch"), "{:#}", error);
+    server.join().expect("server thread");
+}
+
+#[tokio::test]
+async fn health_check_lists_models() {
+    let (port, server) = serve_once(r#"{"object":"list","data":[{"id":"other-model"}]}"#);
+
+    // Single-model servers ignore the requested model, so this isn't fatal
+    local_client(port, None)
+        .health_check()
+        .await
+        .expect("health check should pass");
+
+    let request = server.join().expect("server thread");
+    assert!(request.starts_with("GET /v1/models "));
+}
+
+#[test]
+fn api_key_is_not_logged() {
+    let client = local_client(1, Some("secret"));
+    assert!(!format!("{:?}", client).contains("secret"));
+}
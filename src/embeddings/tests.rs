@@ -0,0 +1,91 @@
+use std::path::Path;
+
+use super::*;
+
+/// Write a lowercasing WordPiece `tokenizer.json` with `vocab`, in order of
+/// token ID, to `dir`
+pub(crate) fn write_tokenizer(dir: &Path, vocab: &[&str]) {
+    let vocab: serde_json::Map<String, serde_json::Value> = vocab
+        .iter()
+        .enumerate()
+        .map(|(id, token)| (token.to_string(), serde_json::json!(id)))
+        .collect();
+    let tokenizer = serde_json::json!({
+        "version": "1.0",
+        "normalizer": {
+            "type": "BertNormalizer",
+            "clean_text": true,
+            "handle_chinese_chars": true,
+            "strip_accents": null,
+            "lowercase": true,
+        },
+        "model": {
+            "type": "WordPiece",
+            "unk_token": "[UNK]",
+            "continuing_subword_prefix": "##",
+            "max_input_chars_per_word": 100,
+            "vocab": vocab,
+        },
+    });
+    std::fs::write(dir.join("tokenizer.json"), tokenizer.to_string())
+        .expect("Failed to write tokenizer");
+}
+
+/// Answer a single HTTP request with `body`, returning the request text
+pub(super) fn serve_once(body: &'static str) -> (u16, std::thread::JoinHandle<String>) {
+    use std::io::{BufRead, BufReader, Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").expect("should bind");
+    let port = listener.local_addr().expect("has address").port();
+    let handle = std::thread::spawn(move || {
+        let (stream, _) = listener.accept().expect("should accept");
+        let mut reader = BufReader::new(stream);
+        let mut request = String::new();
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).expect("should read header");
+            if let Some(length) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                content_length = length.trim().parse().expect("valid length");
+            }
+            request.push_str(&line);
+            if line == "\r\n" {
+                break;
+            }
+        }
+        let mut request_body = vec![0; content_length];
+        reader
+            .read_exact(&mut request_body)
+            .expect("should read body");
+        request.push_str(&String::from_utf8_lossy(&request_body));
+
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        reader
+            .get_mut()
+            .write_all(response.as_bytes())
+            .expect("should write response");
+        request
+    });
+    (port, handle)
+}
+
+#[test]
+fn provider_follows_configuration() {
+    let ollama = create_provider(&OllamaConfig::default()).expect("should create provider");
+    assert_eq!(ollama.model(), "nomic-embed-text:latest");
+    assert!(format!("{:?}", ollama).starts_with("OllamaClient"));
+
+    let openai = create_provider(&OllamaConfig {
+        provider: EmbeddingBackend::OpenAi,
+        port: 8080,
+        model: "bge-small-en-v1.5".to_string(),
+        ..OllamaConfig::default()
+    })
+    .expect("should create provider");
+    assert_eq!(openai.model(), "bge-small-en-v1.5");
+    assert!(format!("{:?}", openai).starts_with("OpenAiClient"));
+}
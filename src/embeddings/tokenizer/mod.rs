@@ -0,0 +1,261 @@
+//! WordPiece Tokenization
+//!
+//! BERT models split text into the word pieces of their vocabulary. The
+//! vocabulary and normalization settings are read from the HuggingFace
+//! `tokenizer.json` shipped with the model; pre-tokenization follows BERT's,
+//! splitting on whitespace and punctuation. Besides feeding the local
+//! embedder, the tokenizer measures chunks in the tokens the model sees.
+
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use anyhow::{Context, Result, anyhow};
+use serde::Deserialize;
+use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
+use unicode_normalization::char::is_combining_mark;
+
+#[derive(Debug, Clone)]
+pub(crate) struct WordPieceTokenizer {
+    vocab: HashMap<String, u32>,
+    unk_id: u32,
+    cls_id: u32,
+    sep_id: u32,
+    continuing_subword_prefix: String,
+    max_input_chars_per_word: usize,
+    lowercase: bool,
+    strip_accents: bool,
+}
+
+/// A `tokenizer.json` whose model isn't WordPiece, such as the BPE and Unigram
+/// tokenizers of GPT and T5 style models
+#[derive(Debug, Error)]
+#[error("Unsupported tokenizer model {0}: only WordPiece tokenizers are supported")]
+pub(crate) struct UnsupportedTokenizer(pub(crate) String);
+
+#[derive(Debug, Deserialize)]
+struct TokenizerFile {
+    model: serde_json::Value,
+    normalizer: Option<Normalizer>,
+}
+
+/// The model type, read before the rest of the model as each type has its
+/// own fields
+#[derive(Debug, Deserialize)]
+struct ModelKind {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct WordPieceModel {
+    vocab: HashMap<String, u32>,
+    unk_token: String,
+    #[serde(default = "default_subword_prefix")]
+    continuing_subword_prefix: String,
+    #[serde(default = "default_max_input_chars_per_word")]
+    max_input_chars_per_word: usize,
+}
+
+#[derive(Debug, Deserialize)]
+struct Normalizer {
+    #[serde(default)]
+    lowercase: bool,
+    strip_accents: Option<bool>,
+}
+
+fn default_subword_prefix() -> String {
+    "##".to_string()
+}
+
+fn default_max_input_chars_per_word() -> usize {
+    100
+}
+
+impl WordPieceTokenizer {
+    /// Load the WordPiece model of a `tokenizer.json`
+    ///
+    /// Other models fail with [`UnsupportedTokenizer`].
+    pub(crate) fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read tokenizer: {}", path.display()))?;
+        let file: TokenizerFile = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse tokenizer: {}", path.display()))?;
+        Self::from_file(file)
+            .with_context(|| format!("Failed to load tokenizer: {}", path.display()))
+    }
+
+    fn from_file(file: TokenizerFile) -> Result<Self> {
+        let ModelKind { kind } = ModelKind::deserialize(&file.model)?;
+        if kind != "WordPiece" {
+            return Err(UnsupportedTokenizer(kind).into());
+        }
+        let model = WordPieceModel::deserialize(file.model)?;
+
+        let token_id = |token: &str| {
+            model
+                .vocab
+                .get(token)
+                .copied()
+                .ok_or_else(|| anyhow!("Tokenizer vocabulary has no {} token", token))
+        };
+        let unk_id = token_id(&model.unk_token)?;
+        let cls_id = token_id("[CLS]")?;
+        let sep_id = token_id("[SEP]")?;
+
+        let lowercase = file.normalizer.as_ref().is_some_and(|n| n.lowercase);
+        // BERT strips accents whenever it lowercases, unless told otherwise
+        let strip_accents = file
+            .normalizer
+            .as_ref()
+            .and_then(|n| n.strip_accents)
+            .unwrap_or(lowercase);
+
+        Ok(Self {
+            vocab: model.vocab,
+            unk_id,
+            cls_id,
+            sep_id,
+            continuing_subword_prefix: model.continuing_subword_prefix,
+            max_input_chars_per_word: model.max_input_chars_per_word,
+            lowercase,
+            strip_accents,
+        })
+    }
+
+    /// Token IDs of `text` between `[CLS]` and `[SEP]`, truncated to
+    /// `max_tokens` in total
+    pub(crate) fn encode(&self, text: &str, max_tokens: usize) -> Vec<u32> {
+        let limit = max_tokens.saturating_sub(2);
+        let mut pieces = Vec::new();
+        for word in self.words(text) {
+            if pieces.len() >= limit {
+                break;
+            }
+            self.push_word_pieces(&word, &mut pieces);
+        }
+        pieces.truncate(limit);
+
+        let mut ids = Vec::with_capacity(pieces.len() + 2);
+        ids.push(self.cls_id);
+        ids.extend(pieces);
+        ids.push(self.sep_id);
+        ids
+    }
+
+    /// Number of tokens in `text`, not counting `[CLS]` and `[SEP]`
+    pub(crate) fn count_tokens(&self, text: &str) -> usize {
+        self.encode(text, usize::MAX).len() - 2
+    }
+
+    /// Normalize `text` and split it into words and punctuation
+    fn words(&self, text: &str) -> Vec<String> {
+        let mut cleaned = String::with_capacity(text.len());
+        for c in text.chars() {
+            if c == '\0' || c == '\u{fffd}' || (c.is_control() && !c.is_whitespace()) {
+                continue;
+            }
+            if is_cjk(c) {
+                // Every CJK character is a word of its own
+                cleaned.push(' ');
+                cleaned.push(c);
+                cleaned.push(' ');
+            } else if c.is_whitespace() {
+                cleaned.push(' ');
+            } else {
+                cleaned.push(c);
+            }
+        }
+
+        let mut normalized = if self.lowercase {
+            cleaned.to_lowercase()
+        } else {
+            cleaned
+        };
+        if self.strip_accents {
+            normalized = normalized
+                .nfd()
+                .filter(|&c| !is_combining_mark(c))
+                .collect();
+        }
+
+        let mut words = Vec::new();
+        for token in normalized.split_whitespace() {
+            let mut word = String::new();
+            for c in token.chars() {
+                if is_punctuation(c) {
+                    if !word.is_empty() {
+                        words.push(std::mem::take(&mut word));
+                    }
+                    words.push(c.to_string());
+                } else {
+                    word.push(c);
+                }
+            }
+            if !word.is_empty() {
+                words.push(word);
+            }
+        }
+        words
+    }
+
+    /// Split a word into the longest pieces found in the vocabulary, or
+    /// `[UNK]` if it can't be split
+    fn push_word_pieces(&self, word: &str, ids: &mut Vec<u32>) {
+        let boundaries: Vec<usize> = word
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(word.len()))
+            .collect();
+        if boundaries.len() - 1 > self.max_input_chars_per_word {
+            ids.push(self.unk_id);
+            return;
+        }
+
+        let mut pieces = Vec::new();
+        let mut start = 0;
+        while start < boundaries.len() - 1 {
+            let piece = (start + 1..boundaries.len()).rev().find_map(|end| {
+                let substring = word.get(boundaries[start]..boundaries[end])?;
+                let id = if start == 0 {
+                    self.vocab.get(substring)
+                } else {
+                    self.vocab
+                        .get(&format!("{}{}", self.continuing_subword_prefix, substring))
+                };
+                id.map(|&id| (id, end))
+            });
+            let Some((id, end)) = piece else {
+                ids.push(self.unk_id);
+                return;
+            };
+            pieces.push(id);
+            start = end;
+        }
+        ids.extend(pieces);
+    }
+}
+
+/// Punctuation as BERT sees it: any ASCII symbol, and other non-alphanumeric
+/// characters
+fn is_punctuation(c: char) -> bool {
+    c.is_ascii_punctuation() || !(c.is_alphanumeric() || c.is_whitespace() || c.is_control())
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(
+        u32::from(c),
+        0x4E00..=0x9FFF
+            | 0x3400..=0x4DBF
+            | 0x20000..=0x2A6DF
+            | 0x2A700..=0x2B73F
+            | 0x2B740..=0x2B81F
+            | 0x2B820..=0x2CEAF
+            | 0xF900..=0xFAFF
+            | 0x2F800..=0x2FA1F
+    )
+}
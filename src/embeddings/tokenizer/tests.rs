@@ -0,0 +1,81 @@
+use tempfile::TempDir;
+
+use super::*;
+use crate::embeddings::tests::write_tokenizer;
+
+const VOCAB: &[&str] = &[
+    "[PAD]", "[UNK]", "[CLS]", "[SEP]", "rust", "docs", "search", "##ing", "index", "the", ",",
+    "!", "cafe", "lance", "##db",
+];
+
+fn tokenizer() -> WordPieceTokenizer {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    write_tokenizer(dir.path(), VOCAB);
+    WordPieceTokenizer::load(&dir.path().join("tokenizer.json")).expect("Failed to load tokenizer")
+}
+
+fn token_id(token: &str) -> u32 {
+    VOCAB
+        .iter()
+        .position(|&t| t == token)
+        .expect("token in vocabulary") as u32
+}
+
+#[test]
+fn tokenizer_splits_words_and_punctuation() {
+    let ids = tokenizer().encode("Rust, DOCS!", 16);
+
+    let expected: Vec<u32> = ["[CLS]", "rust", ",", "docs", "!", "[SEP]"]
+        .iter()
+        .map(|token| token_id(token))
+        .collect();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn tokenizer_uses_word_pieces_and_unknown_token() {
+    let ids = tokenizer().encode("Indexing LanceDB café zebra", 16);
+
+    let expected: Vec<u32> = [
+        "[CLS]", "index", "##ing", "lance", "##db", "cafe", "[UNK]", "[SEP]",
+    ]
+    .iter()
+    .map(|token| token_id(token))
+    .collect();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn tokenizer_truncates_to_the_limit() {
+    let ids = tokenizer().encode("the the the the the the", 5);
+
+    assert_eq!(ids.len(), 5);
+    assert_eq!(ids.first(), Some(&token_id("[CLS]")));
+    assert_eq!(ids.last(), Some(&token_id("[SEP]")));
+}
+
+#[test]
+fn token_counts_exclude_special_tokens() {
+    let tokenizer = tokenizer();
+
+    assert_eq!(tokenizer.count_tokens(""), 0);
+    assert_eq!(tokenizer.count_tokens("Rust, DOCS!"), 4);
+    assert_eq!(tokenizer.count_tokens("Indexing LanceDB café zebra"), 6);
+}
+
+#[test]
+fn non_wordpiece_tokenizers_are_rejected() {
+    let dir = TempDir::new().expect("Failed to create temp dir");
+    let path = dir.path().join("tokenizer.json");
+    fs::write(
+        &path,
+        r#"{"model": {"type": "BPE", "vocab": {}, "merges": [], "unk_token": null}}"#,
+    )
+    .expect("Failed to write tokenizer");
+
+    let error = WordPieceTokenizer::load(&path).expect_err("BPE should be rejected");
+    let unsupported = error
+        .downcast_ref::<UnsupportedTokenizer>()
+        .expect("BPE should be reported as unsupported");
+    assert_eq!(unsupported.0, "BPE");
+}
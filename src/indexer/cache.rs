@@ -0,0 +1,191 @@
+//! Embedding Cache
+//!
+//! Embeddings are cached in SQLite under a SHA-256 hash of the model, the
+//! document prefix and the text embedded. Re-indexing a site, or indexing
+//! another version of it, then only embeds the chunks which have changed.
+//! A model's embeddings have the size the model gives them, whatever
+//! `embedding_dimension` says, so the configured dimension is only part of
+//! the key for the hashing embedder, whose output it sizes.
+//! The cache is best effort: failing to read or write it is logged and the
+//! chunks are embedded as if they weren't cached.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use anyhow::Result;
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::config::settings::{EmbeddingBackend, OllamaConfig};
+use crate::database::sqlite::Database;
+use crate::database::sqlite::models::CachedEmbedding;
+
+/// Embeddings of previously embedded texts, with how often they were found
+#[derive(Debug)]
+pub(super) struct EmbeddingCache {
+    database: Database,
+    model: String,
+    /// Size of the embeddings, when the configuration decides it
+    dimension: Option<usize>,
+    document_prefix: String,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+/// How many lookups an [`EmbeddingCache`] answered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(super) struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups found in the cache, from 0 to 1
+    pub(super) fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 0.0,
+            lookups => self.hits as f64 / lookups as f64,
+        }
+    }
+}
+
+impl fmt::Display for CacheStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} of {} chunks reused from the embedding cache ({:.1}%)",
+            self.hits,
+            self.hits + self.misses,
+            self.hit_rate() * 100.0
+        )
+    }
+}
+
+impl EmbeddingCache {
+    /// Cache for the embedding model of `config`
+    pub(super) fn new(database: Database, config: &OllamaConfig) -> Self {
+        Self {
+            database,
+            model: config.model_name().to_string(),
+            dimension: (config.provider == EmbeddingBackend::Hashing)
+                .then_some(config.embedding_dimension as usize),
+            document_prefix: config.prompt_template().document,
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Look up the embeddings of `texts`, in the same order, with `None` for
+    /// texts which aren't cached
+    pub(super) async fn get(&self, texts: &[&str]) -> Vec<Option<Vec<f32>>> {
+        if texts.is_empty() {
+            return Vec::new();
+        }
+
+        let content_hashes: Vec<Vec<u8>> = texts.iter().map(|text| self.key(text)).collect();
+        let cached: HashMap<Vec<u8>, Vec<u8>> =
+            match self.database.get_cached_embeddings(&content_hashes).await {
+                Ok(cached) => cached
+                    .into_iter()
+                    .map(|cached| (cached.content_hash, cached.embedding))
+                    .collect(),
+                Err(e) => {
+                    warn!("Failed to read the embedding cache: {:#}", e);
+                    HashMap::new()
+                }
+            };
+
+        let embeddings: Vec<Option<Vec<f32>>> = content_hashes
+            .iter()
+            .map(|content_hash| {
+                cached
+                    .get(content_hash)
+                    .and_then(|embedding| self.decode(embedding))
+            })
+            .collect();
+
+        let hits = embeddings
+            .iter()
+            .filter(|embedding| embedding.is_some())
+            .count();
+        self.hits.fetch_add(hits, Ordering::Relaxed);
+        self.misses
+            .fetch_add(embeddings.len() - hits, Ordering::Relaxed);
+        embeddings
+    }
+
+    /// Cache the embeddings of texts
+    pub(super) async fn put(&self, embedded: &[(&str, &[f32])]) {
+        let embeddings: Vec<CachedEmbedding> = embedded
+            .iter()
+            .map(|(text, embedding)| CachedEmbedding {
+                content_hash: self.key(text),
+                embedding: embedding
+                    .iter()
+                    .flat_map(|value| value.to_le_bytes())
+                    .collect(),
+            })
+            .collect();
+
+        if let Err(e) = self.database.cache_embeddings(&embeddings).await {
+            warn!("Failed to write the embedding cache: {:#}", e);
+        }
+    }
+
+    /// Delete cached embeddings which no stored chunk is looked up by, such
+    /// as those of deleted sites, changed pages or other models, returning
+    /// how many were deleted
+    pub(super) async fn prune(&self) -> Result<u64> {
+        let mut used = HashSet::new();
+        for site in self.database.list_sites().await? {
+            for chunk in self.database.get_chunks_for_site(site.id).await? {
+                used.insert(self.key(&chunk.chunk_content));
+            }
+        }
+
+        let unused: Vec<Vec<u8>> = self
+            .database
+            .cached_embedding_hashes()
+            .await?
+            .into_iter()
+            .filter(|content_hash| !used.contains(content_hash))
+            .collect();
+        self.database.delete_cached_embeddings(&unused).await
+    }
+
+    pub(super) fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    fn key(&self, text: &str) -> Vec<u8> {
+        let mut hasher = Sha256::new();
+        hasher.update(self.model.as_bytes());
+        hasher.update([0]);
+        hasher.update((self.dimension.unwrap_or(0) as u64).to_le_bytes());
+        hasher.update(self.document_prefix.as_bytes());
+        hasher.update([0]);
+        hasher.update(text.as_bytes());
+        hasher.finalize().to_vec()
+    }
+
+    /// Decode a cached embedding, ignoring a malformed one or one of the wrong
+    /// dimension
+    fn decode(&self, bytes: &[u8]) -> Option<Vec<f32>> {
+        if bytes.is_empty()
+            || bytes.len() % size_of::<f32>() != 0
+            || self
+                .dimension
+                .is_some_and(|dimension| bytes.len() != dimension * size_of::<f32>())
+        {
+            return None;
+        }
+        bytes
+            .chunks_exact(size_of::<f32>())
+            .map(|value| value.try_into().ok().map(f32::from_le_bytes))
+            .collect()
+    }
+}
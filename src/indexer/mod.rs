@@ -1,42 +1,54 @@
 // Indexer module
 // This module handles background process coordination and queue management
 
+mod cache;
 pub mod consistency;
+mod pipeline;
+mod reembed;
+mod writer;
 
 #[cfg(test)]
 mod tests;
 
-use std::fs::{self, File};
+use std::fs;
+use std::sync::Arc;
 
-use anyhow::{Context, Result};
+use anyhow::{Context, Result, bail};
 use chrono::Utc;
 use indicatif::{ProgressBar, ProgressStyle};
-use tracing::{debug, error, info, warn};
-use uuid::Uuid;
+use tracing::{error, info, warn};
 
 use crate::config::Config;
-use crate::crawler::extractor::ExtractedContent;
+use crate::config::settings::OllamaConfig;
 use crate::database::lancedb::vector_store::VectorStore;
-use crate::database::lancedb::{ChunkMetadata, EmbeddingRecord};
 use crate::database::sqlite::Database;
-use crate::database::sqlite::models::{
-    CrawlQueueItem, NewIndexedChunk, Site, SiteStatus, SiteUpdate,
-};
-use crate::embeddings::chunking::{ChunkingConfig, ContentChunk, chunk_content};
-use crate::embeddings::ollama::OllamaClient;
+use crate::database::sqlite::models::{CrawlQueueItem, Site, SiteStatus, SiteUpdate};
+use crate::embeddings::chunking::{ChunkSizer, ChunkingConfig};
+use crate::embeddings::tokenizer::{UnsupportedTokenizer, WordPieceTokenizer};
+use crate::embeddings::{EmbeddingProvider, create_provider};
+use crate::indexer::cache::EmbeddingCache;
 use crate::indexer::consistency::{ConsistencyReport, ConsistencyValidator};
+use crate::indexer::pipeline::PageEmbedder;
+use crate::indexer::writer::{FLUSH_INTERVAL, FLUSH_ROWS, PageWriter};
 
 /// Indexer that processes crawled content into searchable embeddings
 pub struct Indexer {
     database: Database,
     vector_store: VectorStore,
-    ollama_client: OllamaClient,
+    embedder: Arc<dyn EmbeddingProvider>,
     chunking_config: ChunkingConfig,
     app_config: Config,
-    batch_size: usize,
     verbose: bool,
 }
 
+/// Pages and chunks stored while indexing a site
+#[derive(Debug, Default)]
+struct SiteProgress {
+    pages: u64,
+    chunks: usize,
+    model_recorded: bool,
+}
+
 impl Indexer {
     /// Create a new indexer
     #[inline]
@@ -49,16 +61,16 @@ impl Indexer {
             .await
             .context("Failed to initialize LanceDB vector store")?;
 
-        let ollama_client = OllamaClient::new(config.ollama.clone())
-            .context("Failed to initialize Ollama client")?;
+        let embedder = Arc::from(
+            create_provider(&config.ollama).context("Failed to initialize embedding provider")?,
+        );
 
         Ok(Self {
             database,
             vector_store,
-            ollama_client,
+            embedder,
             chunking_config: config.chunking,
             app_config: config,
-            batch_size: 64,
             verbose,
         })
     }
@@ -70,6 +82,17 @@ impl Indexer {
 
         info!("Processing embeddings for site: {}", site.name);
 
+        // Embeddings from different models can't be compared, so refuse to add
+        // to a vector table holding another model's embeddings
+        let conflicting = self.sites_with_other_models(None).await?;
+        if !conflicting.is_empty() {
+            bail!(
+                "{}. Run `docs-mcp reembed` to re-embed them with {} before indexing more pages",
+                describe_model_conflict(&conflicting, &self.app_config.ollama),
+                self.app_config.ollama.model_name()
+            );
+        }
+
         // Get all completed crawl items that don't have indexed chunks yet
         let crawl_items = self
             .database
@@ -93,8 +116,7 @@ impl Indexer {
 
         eprintln!("Processing {} pages for embeddings", items_to_process.len());
 
-        let mut total_chunks_created = 0;
-        let mut pages_processed = 0;
+        let mut progress = SiteProgress::default();
 
         let bar = if console::user_attended_stderr() {
             ProgressBar::new_spinner().with_style(
@@ -109,27 +131,67 @@ impl Indexer {
         bar.set_position(0);
         bar.set_length(items_to_process.len() as u64);
 
-        for crawl_item in items_to_process {
-            bar.set_message(crawl_item.url.clone());
-            match self.process_single_page(&crawl_item, site.id, &bar).await {
-                Ok(chunks_created) => {
-                    total_chunks_created += chunks_created;
-                    pages_processed += 1;
-                    bar.set_position(pages_processed);
-
-                    // Update site progress
-                    let progress_update = SiteUpdate {
-                        indexed_pages: Some(site.indexed_pages + pages_processed as i64),
-                        ..Default::default()
-                    };
-                    self.database.update_site(site.id, &progress_update).await?;
+        let sizer = chunk_sizer(&self.app_config.ollama, self.embedder.as_ref()).await?;
+        if let Some(warning) = describe_oversized_chunks(
+            &self.chunking_config,
+            &sizer,
+            self.app_config.ollama.model_name(),
+        ) {
+            warn!("{}", warning);
+        }
+
+        let cache = Arc::new(EmbeddingCache::new(
+            self.database.clone(),
+            &self.app_config.ollama,
+        ));
+        let page_embedder = PageEmbedder {
+            embedder: Arc::clone(&self.embedder),
+            cache: Arc::clone(&cache),
+            chunking_config: self.chunking_config,
+            sizer,
+            pages_dir: self.app_config.cache_dir_path()?.join("pages"),
+            batch_size: self.app_config.ollama.batch_size as usize,
+            concurrency: self.app_config.ollama.concurrency as usize,
+        };
+        let mut embedded_pages = page_embedder.spawn(items_to_process);
+        let mut writer = PageWriter::new(site.id, FLUSH_ROWS, FLUSH_INTERVAL);
+
+        loop {
+            // Pages left in the buffer are written once they have waited long enough
+            let received = match writer.deadline() {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, embedded_pages.recv()).await {
+                        Ok(received) => received,
+                        Err(_) => {
+                            self.flush_pages(&mut writer, site, &mut progress, &bar)
+                                .await?;
+                            continue;
+                        }
+                    }
+                }
+                None => embedded_pages.recv().await,
+            };
+            let Some((crawl_item, page)) = received else {
+                break;
+            };
+
+            match page {
+                Ok(page) => {
+                    bar.set_message(crawl_item.url.clone());
+                    writer.push(crawl_item, page);
+                    if writer.is_full() {
+                        self.flush_pages(&mut writer, site, &mut progress, &bar)
+                            .await?;
+                    }
                 }
                 Err(e) => {
-                    error!("Failed to process page {}: {}", crawl_item.url, e);
+                    error!("Failed to process page {}: {:#}", crawl_item.url, e);
                     // Continue processing other pages
                 }
             }
         }
+        self.flush_pages(&mut writer, site, &mut progress, &bar)
+            .await?;
 
         // Check if all pages are now processed
         let remaining_items = self
@@ -146,138 +208,61 @@ impl Indexer {
 
         eprintln!(
             "Processed {} pages, created {} chunks for site: {}",
-            pages_processed, total_chunks_created, site.name
+            progress.pages, progress.chunks, site.name
         );
+        let cache_stats = cache.stats();
+        if cache_stats.hits + cache_stats.misses > 0 {
+            eprintln!("{}", cache_stats);
+        }
 
         Ok(())
     }
 
-    /// Process a single page for embedding generation
-    async fn process_single_page(
+    /// Write the buffered pages, and record the site's progress
+    ///
+    /// Pages which fail to be written are left to be processed again.
+    async fn flush_pages(
         &mut self,
-        crawl_item: &CrawlQueueItem,
-        site_id: i64,
+        writer: &mut PageWriter,
+        site: &Site,
+        progress: &mut SiteProgress,
         bar: &ProgressBar,
-    ) -> Result<usize> {
-        debug!("Processing page for embeddings: {}", crawl_item.url);
-
-        // Get the extracted content for this URL
-        let extracted_content = self.get_extracted_content_for_page(crawl_item.id)?;
-
-        // Chunk the content
+    ) -> Result<()> {
         if self.verbose {
-            bar.set_message(format!("{} (Chunking content)", crawl_item.url));
+            bar.set_message("(Saving embeddings)");
         }
-        let chunks = chunk_content(&extracted_content, &self.chunking_config)
-            .context("Failed to chunk content")?;
-
-        if chunks.is_empty() {
-            debug!("No chunks generated for URL: {}", crawl_item.url);
-            return Ok(0);
+        let (pages, written) = writer.flush(&self.database, &mut self.vector_store).await;
+        if pages.is_empty() {
+            return Ok(());
         }
-
-        // Generate embeddings in batches
-        let chunk_batches: Vec<Vec<ContentChunk>> = chunks
-            .chunks(self.batch_size)
-            .map(|batch| batch.to_vec())
-            .collect();
-
-        let mut processed_chunks = Vec::with_capacity(chunks.len());
-
-        // Begin sqlite transaction
-        let mut transaction = self
-            .database
-            .pool()
-            .begin()
-            .await
-            .context("Failed to begin SQLite transaction")?;
-
-        for batch in chunk_batches {
-            let batch_size = batch.len();
-
-            // Generate embeddings for this batch
-            if self.verbose {
-                bar.set_message(format!(
-                    "{} (Generating embeddings {}-{} of {})",
-                    crawl_item.url,
-                    processed_chunks.len() + 1,
-                    processed_chunks.len() + batch.len(),
-                    chunks.len()
-                ));
+        if let Err(e) = written {
+            for (crawl_item, _) in &pages {
+                error!("Failed to store page {}: {:#}", crawl_item.url, e);
             }
-            let embedding_results = self
-                .ollama_client
-                .generate_chunk_embeddings(&batch)
-                .context("Failed to generate embeddings")?;
-
-            // Store embeddings and create indexed chunk records
-            for (chunk, embedding_result) in batch.into_iter().zip(embedding_results.into_iter()) {
-                let vector_id = Uuid::new_v4().to_string();
-
-                // Create embedding record for LanceDB
-                let embedding_record = EmbeddingRecord {
-                    id: vector_id.clone(),
-                    vector: embedding_result.embedding,
-                    metadata: ChunkMetadata {
-                        chunk_id: vector_id.clone(),
-                        site_id: site_id.to_string(),
-                        page_title: extracted_content.title.clone(),
-                        page_url: crawl_item.url.clone(),
-                        heading_path: Some(chunk.heading_path.clone()),
-                        content: chunk.content.clone(),
-                        token_count: chunk.token_count as u32,
-                        chunk_index: chunk.chunk_index as u32,
-                        created_at: Utc::now().to_rfc3339(),
-                    },
-                };
-                processed_chunks.push(embedding_record);
-
-                // Create indexed chunk record for SQLite (part of transaction)
-                let indexed_chunk = NewIndexedChunk {
-                    site_id,
-                    url: crawl_item.url.clone(),
-                    page_title: Some(extracted_content.title.clone()),
-                    heading_path: Some(chunk.heading_path),
-                    chunk_content: chunk.content,
-                    chunk_index: chunk.chunk_index as i64,
-                    vector_id,
-                };
-
-                self.database
-                    .insert_indexed_chunk_with_transaction(&indexed_chunk, &mut transaction)
-                    .await
-                    .context("Failed to store indexed chunk in SQLite")?;
-            }
-
-            debug!(
-                "Processed batch of {} chunks for URL: {}",
-                batch_size, crawl_item.url
-            );
+            return Ok(());
         }
 
-        // Store in LanceDB
-        // We HAVE to do this as a batch, single insertions are extremely bad for LanceDB performance
-        if self.verbose {
-            bar.set_message(format!("{} (Saving embeddings)", crawl_item.url));
+        for (crawl_item, chunks) in &pages {
+            progress.pages += 1;
+            progress.chunks += chunks;
+            if *chunks > 0 {
+                self.remove_cached_page(crawl_item.id)?;
+            }
         }
-        let processed_chunks_count = processed_chunks.len();
-        self.vector_store
-            .store_embeddings_batch(processed_chunks)
-            .await
-            .context("Failed to store embedding in LanceDB")?;
+        bar.set_position(progress.pages);
 
-        if self.verbose {
-            bar.set_message(format!("{} (Finalizing)", crawl_item.url));
+        if !progress.model_recorded && progress.chunks > 0 {
+            self.record_embedding_model(site.id).await?;
+            progress.model_recorded = true;
         }
-        // Commit sqlite transaction
-        transaction
-            .commit()
-            .await
-            .context("Failed to commit SQLite transaction")?;
 
-        self.remove_cached_page(crawl_item.id)?;
-
-        Ok(processed_chunks_count)
+        // Update site progress
+        let progress_update = SiteUpdate {
+            indexed_pages: Some(site.indexed_pages + progress.pages as i64),
+            ..Default::default()
+        };
+        self.database.update_site(site.id, &progress_update).await?;
+        Ok(())
     }
 
     /// Complete indexing for a site
@@ -301,30 +286,49 @@ impl Indexer {
             warn!("Failed to optimize vector database: {}", e);
         }
 
+        // Searches scan every embedding until the table is large enough to index
+        match self.vector_store.update_vector_index().await {
+            Ok(true) => info!("Built vector index"),
+            Ok(false) => {}
+            Err(e) => warn!("Failed to update vector index: {}", e),
+        }
+
         info!("Successfully completed indexing for site: {}", site.name);
         Ok(())
     }
 
-    /// Get extracted content for a page from the cached file
-    fn get_extracted_content_for_page(&self, page_id: i64) -> Result<ExtractedContent> {
-        let cached_file_path = self
-            .app_config
-            .cache_dir_path()?
-            .join("pages")
-            .join(format!("{page_id}.json"));
-        let extracted_content: ExtractedContent = serde_json::from_reader(
-            File::open(&cached_file_path).context("Failed to open cached page file")?,
-        )
-        .context("Failed to read cached page file")?;
-
-        debug!(
-            "Loaded extracted content for page {}: {} sections, {} chars",
-            page_id,
-            extracted_content.sections.len(),
-            extracted_content.raw_text.len()
-        );
+    /// Sites with indexed chunks whose embeddings came from a model or
+    /// document prefix other than the configured ones, ignoring
+    /// `except_site_id`
+    async fn sites_with_other_models(&self, except_site_id: Option<i64>) -> Result<Vec<Site>> {
+        let mut conflicting = Vec::new();
+        for site in self.database.list_sites().await? {
+            if Some(site.id) == except_site_id
+                || !embedded_differently(&site, &self.app_config.ollama)
+            {
+                continue;
+            }
+            if self.database.count_chunks_for_site(site.id).await? > 0 {
+                conflicting.push(site);
+            }
+        }
+        Ok(conflicting)
+    }
 
-        Ok(extracted_content)
+    /// Record that a site's embeddings come from the configured model and
+    /// document prefix
+    async fn record_embedding_model(&self, site_id: i64) -> Result<()> {
+        let update = SiteUpdate {
+            embedding_model: Some(self.app_config.ollama.model_name().to_string()),
+            embedding_dimension: Some(self.vector_store.vector_dimension() as i64),
+            embedding_document_prefix: Some(self.app_config.ollama.prompt_template().document),
+            ..Default::default()
+        };
+        self.database
+            .update_site(site_id, &update)
+            .await
+            .context("Failed to record the site's embedding model")?;
+        Ok(())
     }
 
     /// Cleanup extracted content for a page after we are finished
@@ -381,3 +385,120 @@ impl Indexer {
         Ok(())
     }
 }
+
+/// Measure chunks for the configured model: in the tokens of its tokenizer,
+/// if there is one, and within its context length, if the provider knows it
+///
+/// Tokens are estimated instead if the tokenizer isn't a WordPiece one.
+pub(crate) async fn chunk_sizer(
+    config: &OllamaConfig,
+    embedder: &dyn EmbeddingProvider,
+) -> Result<ChunkSizer> {
+    let tokenizer = match config.tokenizer_file() {
+        Some(path) => match tokio::task::spawn_blocking(move || WordPieceTokenizer::load(&path))
+            .await
+            .context("Tokenizer loading task panicked")?
+        {
+            Ok(tokenizer) => Some(Arc::new(tokenizer)),
+            Err(e) if e.downcast_ref::<UnsupportedTokenizer>().is_some() => {
+                warn!("Estimating token counts instead: {:#}", e);
+                None
+            }
+            Err(e) => return Err(e),
+        },
+        None => None,
+    };
+    let context_length = embedder.context_length().await.unwrap_or_else(|e| {
+        warn!(
+            "Failed to read the context length of {}, chunks may be truncated: {:#}",
+            embedder.model(),
+            e
+        );
+        None
+    });
+    Ok(ChunkSizer::new(
+        tokenizer,
+        context_length,
+        &config.prompt_template().document,
+    ))
+}
+
+/// Describe how chunks are limited if `max_chunk_size` doesn't fit in the
+/// context of `model`
+pub(crate) fn describe_oversized_chunks(
+    config: &ChunkingConfig,
+    sizer: &ChunkSizer,
+    model: &str,
+) -> Option<String> {
+    let window = sizer.window()?;
+    (config.max_chunk_size > window).then(|| {
+        format!(
+            "max_chunk_size of {} tokens exceeds what {} embeds without truncating, so chunks are limited to {} tokens{}",
+            config.max_chunk_size,
+            model,
+            window,
+            if sizer.has_tokenizer() {
+                ""
+            } else {
+                " (estimated; set tokenizer_path to count them exactly)"
+            }
+        )
+    })
+}
+
+/// Delete cached embeddings which re-indexing with `config` can't reuse,
+/// returning how many were deleted
+pub(crate) async fn prune_embedding_cache(
+    database: &Database,
+    config: &OllamaConfig,
+) -> Result<u64> {
+    EmbeddingCache::new(database.clone(), config).prune().await
+}
+
+/// Whether a site's chunks were embedded with another model or document
+/// prefix than `config` embeds with
+///
+/// Sites indexed before models were recorded are assumed to use the
+/// configured model. Those indexed before prefixes were recorded were
+/// embedded without one, which the migration recording prefixes notes.
+pub(crate) fn embedded_differently(site: &Site, config: &OllamaConfig) -> bool {
+    site.embedding_model
+        .as_ref()
+        .is_some_and(|model| model != config.model_name())
+        || site
+            .embedding_document_prefix
+            .as_ref()
+            .is_some_and(|prefix| *prefix != config.prompt_template().document)
+}
+
+/// Describe sites embedded differently than `config` embeds
+fn describe_model_conflict(sites: &[Site], config: &OllamaConfig) -> String {
+    let sites = sites
+        .iter()
+        .map(|site| {
+            let model = site.embedding_model.as_deref().unwrap_or("unknown model");
+            match &site.embedding_document_prefix {
+                Some(prefix) if !prefix.is_empty() => format!(
+                    "{} {} ({} with document prefix {:?})",
+                    site.name, site.version, model, prefix
+                ),
+                _ => format!("{} {} ({})", site.name, site.version, model),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    let prompt = config.prompt_template();
+    let configured = if prompt.document.is_empty() {
+        config.model_name().to_string()
+    } else {
+        format!(
+            "{} with document prefix {:?}",
+            config.model_name(),
+            prompt.document
+        )
+    };
+    format!(
+        "Sites were embedded differently than the configured {}: {}",
+        configured, sites
+    )
+}
@@ -0,0 +1,324 @@
+//! Indexing Pipeline
+//!
+//! Pages go through overlapping stages: loading and chunking, embedding, and
+//! storage. Pages are chunked on the blocking thread pool, and their chunks
+//! are gathered into embedding requests of `batch_size` chunks regardless of
+//! which page they come from, so small pages don't make for small requests.
+//! Chunks found in the embedding cache skip embedding altogether. Up to
+//! `concurrency` requests are in flight at once, and a page is handed on to
+//! storage once all of its chunks are embedded, while later pages are still
+//! being embedded.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use futures::stream::FuturesUnordered;
+use futures::{StreamExt, stream};
+use tokio::sync::mpsc;
+use tracing::debug;
+
+use super::cache::EmbeddingCache;
+use crate::crawler::extractor::ExtractedContent;
+use crate::database::sqlite::models::CrawlQueueItem;
+use crate::embeddings::chunking::{ChunkSizer, ChunkingConfig, ContentChunk, chunk_content};
+use crate::embeddings::{EmbeddingProvider, EmbeddingResult};
+
+/// The chunks of a crawled page with their embeddings, ready to be stored
+#[derive(Debug)]
+pub(super) struct EmbeddedPage {
+    pub title: String,
+    pub chunks: Vec<(ContentChunk, EmbeddingResult)>,
+}
+
+/// A loaded page's chunks, with the cached embeddings of some of them
+#[derive(Debug)]
+struct ChunkedPage {
+    title: String,
+    chunks: Vec<ContentChunk>,
+    /// Cached embedding of each chunk, if any
+    cached: Vec<Option<Vec<f32>>>,
+}
+
+/// A page being embedded, whose chunks may be spread over several requests
+#[derive(Debug)]
+struct PageInProgress {
+    crawl_item: CrawlQueueItem,
+    title: String,
+    chunk_count: usize,
+    embedded: Vec<(ContentChunk, EmbeddingResult)>,
+}
+
+/// Chunks of one or more pages embedded in a single request, each with the
+/// ID of its page
+type Batch = Vec<(i64, ContentChunk)>;
+
+/// Everything needed to embed pages away from the indexer
+#[derive(Debug, Clone)]
+pub(super) struct PageEmbedder {
+    pub embedder: Arc<dyn EmbeddingProvider>,
+    pub cache: Arc<EmbeddingCache>,
+    pub chunking_config: ChunkingConfig,
+    /// Measures chunks in the embedding model's tokens
+    pub sizer: ChunkSizer,
+    /// Directory of the extracted pages cached by the crawler
+    pub pages_dir: PathBuf,
+    /// Chunks sent to the embedder at once
+    pub batch_size: usize,
+    /// Embedding requests in flight at once
+    pub concurrency: usize,
+}
+
+impl PageEmbedder {
+    /// Embed `pages`, returning each page as soon as all of its chunks are
+    /// embedded
+    ///
+    /// Embedding runs in a task of its own, so it carries on while the
+    /// receiver stores earlier pages.
+    pub(super) fn spawn(
+        self,
+        pages: Vec<CrawlQueueItem>,
+    ) -> mpsc::Receiver<(CrawlQueueItem, Result<EmbeddedPage>)> {
+        let (sender, receiver) = mpsc::channel(self.concurrency);
+        tokio::spawn(async move { self.run(pages, sender).await });
+        receiver
+    }
+
+    async fn run(
+        self,
+        pages: Vec<CrawlQueueItem>,
+        sender: mpsc::Sender<(CrawlQueueItem, Result<EmbeddedPage>)>,
+    ) {
+        let batch_size = self.batch_size.max(1);
+        let concurrency = self.concurrency.max(1);
+
+        let mut chunked_pages = stream::iter(pages)
+            .map(|crawl_item| self.clone().chunk(crawl_item))
+            .buffer_unordered(concurrency);
+        let mut chunking_done = false;
+        let mut requests = FuturesUnordered::new();
+        let mut pending: Batch = Vec::new();
+        let mut in_progress: HashMap<i64, PageInProgress> = HashMap::new();
+
+        loop {
+            // Send full batches, and whatever is left once every page is chunked
+            while requests.len() < concurrency
+                && (pending.len() >= batch_size || (chunking_done && !pending.is_empty()))
+            {
+                let rest = pending.split_off(batch_size.min(pending.len()));
+                let batch = std::mem::replace(&mut pending, rest);
+                requests.push(self.clone().embed(batch));
+            }
+            if chunking_done && pending.is_empty() && requests.is_empty() {
+                break;
+            }
+
+            let finished = tokio::select! {
+                // Only chunk more pages while requests can be sent
+                page = chunked_pages.next(), if !chunking_done && pending.len() < batch_size => {
+                    match page {
+                        Some((crawl_item, Ok(page))) => {
+                            let chunk_count = page.chunks.len();
+                            let mut embedded = Vec::with_capacity(chunk_count);
+                            for (chunk, cached) in page.chunks.into_iter().zip(page.cached) {
+                                match cached {
+                                    Some(embedding) => {
+                                        let embedding = cached_embedding(&chunk, embedding);
+                                        embedded.push((chunk, embedding));
+                                    }
+                                    None => pending.push((crawl_item.id, chunk)),
+                                }
+                            }
+
+                            if embedded.len() == chunk_count {
+                                if chunk_count == 0 {
+                                    debug!("No chunks generated for URL: {}", crawl_item.url);
+                                }
+                                let page = EmbeddedPage { title: page.title, chunks: embedded };
+                                vec![(crawl_item, Ok(page))]
+                            } else {
+                                in_progress.insert(
+                                    crawl_item.id,
+                                    PageInProgress {
+                                        crawl_item,
+                                        title: page.title,
+                                        chunk_count,
+                                        embedded,
+                                    },
+                                );
+                                Vec::new()
+                            }
+                        }
+                        Some((crawl_item, Err(e))) => vec![(crawl_item, Err(e))],
+                        None => {
+                            chunking_done = true;
+                            Vec::new()
+                        }
+                    }
+                }
+                Some((page_ids, embedded)) = requests.next(), if !requests.is_empty() => {
+                    collect_embeddings(&mut in_progress, &page_ids, embedded)
+                }
+            };
+
+            for page in finished {
+                if sender.send(page).await.is_err() {
+                    // Storage has stopped, so there is no one to embed for
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Load and chunk a page on the blocking thread pool, and look up its
+    /// chunks in the embedding cache
+    async fn chunk(self, crawl_item: CrawlQueueItem) -> (CrawlQueueItem, Result<ChunkedPage>) {
+        let page_id = crawl_item.id;
+        let cache = Arc::clone(&self.cache);
+        let chunked = tokio::task::spawn_blocking(move || self.chunk_blocking(page_id))
+            .await
+            .context("Chunking task panicked")
+            .and_then(|chunked| chunked);
+
+        let page = match chunked {
+            Ok((title, chunks)) => {
+                let texts: Vec<&str> = chunks.iter().map(|chunk| chunk.content.as_str()).collect();
+                let cached = cache.get(&texts).await;
+                Ok(ChunkedPage {
+                    title,
+                    chunks,
+                    cached,
+                })
+            }
+            Err(e) => Err(e),
+        };
+        (crawl_item, page)
+    }
+
+    fn chunk_blocking(&self, page_id: i64) -> Result<(String, Vec<ContentChunk>)> {
+        let cached_file_path = self.pages_dir.join(format!("{}.json", page_id));
+        let extracted_content: ExtractedContent = serde_json::from_reader(
+            File::open(&cached_file_path).context("Failed to open cached page file")?,
+        )
+        .context("Failed to read cached page file")?;
+
+        debug!(
+            "Loaded extracted content for page {}: {} sections, {} chars",
+            page_id,
+            extracted_content.sections.len(),
+            extracted_content.raw_text.len()
+        );
+
+        let chunks = chunk_content(&extracted_content, &self.chunking_config, &self.sizer)
+            .context("Failed to chunk content")?;
+        Ok((extracted_content.title, chunks))
+    }
+
+    /// Embed a batch, returning the IDs of the pages in it with the embedded
+    /// chunks
+    async fn embed(
+        self,
+        batch: Batch,
+    ) -> (Vec<i64>, Result<Vec<(i64, ContentChunk, EmbeddingResult)>>) {
+        let page_ids: Vec<i64> = batch.iter().map(|(page_id, _)| *page_id).collect();
+        debug!("Embedding batch of {} chunks", batch.len());
+
+        let embedded: Result<Vec<_>> = async {
+            let chunks: Vec<ContentChunk> = batch.iter().map(|(_, chunk)| chunk.clone()).collect();
+            let embeddings = self
+                .embedder
+                .generate_chunk_embeddings(&chunks)
+                .await
+                .context("Failed to generate embeddings")?;
+            if embeddings.len() != batch.len() {
+                return Err(anyhow!(
+                    "Embedder returned {} embeddings for {} chunks",
+                    embeddings.len(),
+                    batch.len()
+                ));
+            }
+            Ok(batch
+                .into_iter()
+                .zip(embeddings)
+                .map(|((page_id, chunk), embedding)| (page_id, chunk, embedding))
+                .collect())
+        }
+        .await;
+
+        if let Ok(embedded) = &embedded {
+            let texts: Vec<(&str, &[f32])> = embedded
+                .iter()
+                .map(|(_, chunk, embedding)| {
+                    (chunk.content.as_str(), embedding.embedding.as_slice())
+                })
+                .collect();
+            self.cache.put(&texts).await;
+        }
+
+        (page_ids, embedded)
+    }
+}
+
+/// Embedding of a chunk found in the cache, as the embedder would have
+/// returned it
+fn cached_embedding(chunk: &ContentChunk, embedding: Vec<f32>) -> EmbeddingResult {
+    EmbeddingResult {
+        text: chunk.content.clone(),
+        embedding,
+        token_count: chunk.token_count,
+        chunk_index: Some(chunk.chunk_index),
+        heading_path: Some(chunk.heading_path.clone()),
+    }
+}
+
+/// Add embedded chunks to their pages, returning the pages this completes
+///
+/// When a request fails, every page with chunks in it fails, and any of their
+/// chunks embedded by other requests are dropped.
+fn collect_embeddings(
+    in_progress: &mut HashMap<i64, PageInProgress>,
+    page_ids: &[i64],
+    embedded: Result<Vec<(i64, ContentChunk, EmbeddingResult)>>,
+) -> Vec<(CrawlQueueItem, Result<EmbeddedPage>)> {
+    let embedded = match embedded {
+        Ok(embedded) => embedded,
+        Err(e) => {
+            let failed: HashSet<i64> = page_ids.iter().copied().collect();
+            return failed
+                .into_iter()
+                .filter_map(|page_id| in_progress.remove(&page_id))
+                .map(|page| (page.crawl_item, Err(anyhow!("{:#}", e))))
+                .collect();
+        }
+    };
+
+    let mut touched = HashSet::new();
+    for (page_id, chunk, embedding) in embedded {
+        if let Some(page) = in_progress.get_mut(&page_id) {
+            page.embedded.push((chunk, embedding));
+            touched.insert(page_id);
+        }
+    }
+
+    let mut finished = Vec::new();
+    for page_id in touched {
+        if in_progress
+            .get(&page_id)
+            .is_some_and(|page| page.embedded.len() == page.chunk_count)
+            && let Some(mut page) = in_progress.remove(&page_id)
+        {
+            // Requests may finish out of order
+            page.embedded.sort_by_key(|(chunk, _)| chunk.chunk_index);
+            finished.push((
+                page.crawl_item,
+                Ok(EmbeddedPage {
+                    title: page.title,
+                    chunks: page.embedded,
+                }),
+            ));
+        }
+    }
+    finished
+}
@@ -0,0 +1,226 @@
+//! Re-embedding
+//!
+//! Changing the embedding model makes every stored vector incomparable with
+//! new queries. Re-embedding rebuilds the vectors from the chunk text kept in
+//! SQLite, so nothing has to be crawled again, and only replaces the vector
+//! table once all of them have been generated.
+
+use anyhow::{Context, Result, bail};
+use futures::{StreamExt, stream};
+use indicatif::{ProgressBar, ProgressStyle};
+use tracing::{info, warn};
+
+use super::cache::EmbeddingCache;
+use super::{Indexer, describe_model_conflict};
+use crate::database::lancedb::{ChunkMetadata, EmbeddingRecord};
+use crate::database::sqlite::models::{IndexedChunk, Site, SiteStatus};
+use crate::embeddings::chunking::estimate_token_count;
+
+/// Outcome of [`Indexer::reembed`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReembedSummary {
+    /// Sites whose chunks were re-embedded
+    pub sites: usize,
+    /// Chunks re-embedded with the configured model
+    pub chunks: usize,
+    /// Embeddings of other sites carried over unchanged
+    pub kept: u64,
+    /// Re-embedded chunks whose embeddings were found in the embedding cache
+    pub cached: usize,
+}
+
+impl Indexer {
+    /// Re-embed the chunks of `site`, or of every site, with the configured
+    /// model
+    ///
+    /// Searches keep using the existing embeddings until all new ones have been
+    /// generated. When only one site is re-embedded, the other sites'
+    /// embeddings are kept, so they must already come from the configured model.
+    ///
+    /// Re-embedding is refused while any site is being indexed, since the
+    /// embeddings it stores meanwhile would be replaced along with the rest.
+    #[inline]
+    pub async fn reembed(&mut self, site: Option<&Site>) -> Result<ReembedSummary> {
+        let model = self.app_config.ollama.model_name().to_string();
+
+        let indexing: Vec<String> = self
+            .database
+            .get_sites_by_status(SiteStatus::Indexing)
+            .await?
+            .into_iter()
+            .map(|site| format!("{} {}", site.name, site.version))
+            .collect();
+        if !indexing.is_empty() {
+            bail!(
+                "{} still being indexed. Wait for indexing to finish before re-embedding, as embeddings stored meanwhile would be lost",
+                match indexing.as_slice() {
+                    [site] => format!("{} is", site),
+                    sites => format!("{} are", sites.join(", ")),
+                }
+            );
+        }
+
+        let targets = match site {
+            Some(site) => {
+                let conflicting = self.sites_with_other_models(Some(site.id)).await?;
+                if !conflicting.is_empty() {
+                    bail!(
+                        "{}. Their embeddings would be mixed with {}'s, so run `docs-mcp reembed` without --site to re-embed every site",
+                        describe_model_conflict(&conflicting, &self.app_config.ollama),
+                        site.name
+                    );
+                }
+                vec![site.clone()]
+            }
+            None => self.database.list_sites().await?,
+        };
+
+        let base_version = self.vector_store.begin_staging().await?;
+        let cache = EmbeddingCache::new(self.database.clone(), &self.app_config.ollama);
+
+        let mut summary = ReembedSummary::default();
+        let mut reembedded: Vec<&Site> = Vec::new();
+        for site in &targets {
+            let chunks = self.database.get_chunks_for_site(site.id).await?;
+            if chunks.is_empty() {
+                continue;
+            }
+
+            eprintln!(
+                "Re-embedding {} chunks of {} {} with {}",
+                chunks.len(),
+                site.name,
+                site.version,
+                model
+            );
+            self.stage_chunks(&chunks, &cache)
+                .await
+                .with_context(|| format!("Failed to re-embed {} {}", site.name, site.version))?;
+            summary.sites += 1;
+            summary.chunks += chunks.len();
+            reembedded.push(site);
+        }
+
+        summary.cached = cache.stats().hits;
+
+        if site.is_some() {
+            let site_ids: Vec<String> = targets.iter().map(|site| site.id.to_string()).collect();
+            summary.kept = self
+                .vector_store
+                .stage_existing_embeddings(&site_ids)
+                .await
+                .context("Failed to copy the embeddings of other sites")?;
+        }
+
+        self.vector_store
+            .swap_in_staged_table(base_version)
+            .await
+            .context("Failed to replace the vector table; run `docs-mcp reembed` again, which reuses the cached embeddings")?;
+
+        for site in reembedded {
+            self.record_embedding_model(site.id).await?;
+        }
+
+        // The replaced table has no index, and its old version is no longer needed
+        if let Err(e) = self.vector_store.optimize().await {
+            warn!("Failed to optimize vector database: {}", e);
+        }
+        if let Err(e) = self.vector_store.update_vector_index().await {
+            warn!("Failed to update vector index: {}", e);
+        }
+
+        info!(
+            "Re-embedded {} chunks of {} sites with {}",
+            summary.chunks, summary.sites, model
+        );
+        Ok(summary)
+    }
+
+    /// Embed chunks with the configured model into the staging table, reusing
+    /// cached embeddings
+    async fn stage_chunks(&self, chunks: &[IndexedChunk], cache: &EmbeddingCache) -> Result<()> {
+        let bar = if console::user_attended_stderr() {
+            ProgressBar::new(chunks.len() as u64).with_style(
+                ProgressStyle::with_template("{spinner} [{pos}/{len}] Re-embedding chunks")
+                    .expect("style template is valid"),
+            )
+        } else {
+            ProgressBar::hidden()
+        };
+
+        // Batches are embedded `concurrency` at a time, while earlier ones are
+        // stored in order
+        let mut batches = stream::iter(chunks.chunks(self.app_config.ollama.batch_size as usize))
+            .map(|batch| async move {
+                let texts: Vec<&str> = batch
+                    .iter()
+                    .map(|chunk| chunk.chunk_content.as_str())
+                    .collect();
+                let cached = cache.get(&texts).await;
+                let missing: Vec<String> = texts
+                    .iter()
+                    .zip(&cached)
+                    .filter(|(_, cached)| cached.is_none())
+                    .map(|(text, _)| (*text).to_string())
+                    .collect();
+
+                let mut embeddings = Vec::new();
+                if !missing.is_empty() {
+                    embeddings = self
+                        .embedder
+                        .generate_document_embeddings(&missing)
+                        .await
+                        .context("Failed to generate embeddings")?;
+
+                    let new: Vec<(&str, &[f32])> = missing
+                        .iter()
+                        .zip(&embeddings)
+                        .map(|(text, embedding)| (text.as_str(), embedding.embedding.as_slice()))
+                        .collect();
+                    cache.put(&new).await;
+                }
+
+                let mut embeddings = embeddings.into_iter().map(|result| result.embedding);
+                let vectors = cached
+                    .into_iter()
+                    .map(|cached| cached.or_else(|| embeddings.next()))
+                    .collect::<Option<Vec<_>>>()
+                    .context("Embedder returned fewer embeddings than chunks")?;
+                Ok::<_, anyhow::Error>((batch, vectors))
+            })
+            .buffered(self.app_config.ollama.concurrency as usize);
+
+        while let Some(embedded) = batches.next().await {
+            let (batch, vectors) = embedded?;
+            let records = batch
+                .iter()
+                .zip(vectors)
+                .map(|(chunk, vector)| chunk_record(chunk, vector))
+                .collect();
+            self.vector_store.store_staged_embeddings(records).await?;
+            bar.inc(batch.len() as u64);
+        }
+
+        bar.finish_and_clear();
+        Ok(())
+    }
+}
+
+/// Embedding record for a stored chunk, keeping its vector ID
+fn chunk_record(chunk: &IndexedChunk, vector: Vec<f32>) -> EmbeddingRecord {
+    EmbeddingRecord {
+        id: chunk.vector_id.clone(),
+        vector,
+        metadata: ChunkMetadata {
+            chunk_id: chunk.vector_id.clone(),
+            site_id: chunk.site_id.to_string(),
+            page_title: chunk.page_title.clone().unwrap_or_default(),
+            page_url: chunk.url.clone(),
+            heading_path: chunk.heading_path.clone(),
+            content: chunk.chunk_content.clone(),
+            token_count: estimate_token_count(&chunk.chunk_content) as u32,
+            chunk_index: u32::try_from(chunk.chunk_index).unwrap_or_default(),
+            created_at: chunk.indexed_date.and_utc().to_rfc3339(),
+        },
+    }
+}
@@ -1,7 +1,9 @@
 use super::*;
-use crate::config::settings::OllamaConfig;
+use crate::config::settings::{OllamaConfig, PromptTemplate};
 use crate::embeddings::chunking::ChunkingConfig;
 use crate::embeddings::ollama::DEFAULT_EMBEDDING_DIMENSION;
+use crate::indexer::cache::CacheStats;
+use crate::indexer::pipeline::EmbeddedPage;
 use tempfile::TempDir;
 
 async fn create_test_indexer() -> Result<(Indexer, TempDir)> {
@@ -14,8 +16,11 @@ async fn create_test_indexer() -> Result<(Indexer, TempDir)> {
             model: "nomic-embed-text:latest".to_string(),
             batch_size: 32,
             embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+            ..Default::default()
         },
         chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: Default::default(),
         base_dir: temp_dir.path().to_path_buf(),
     };
 
@@ -29,11 +34,311 @@ async fn indexer_creation() {
     assert!(result.is_ok(), "Should create indexer successfully");
 }
 
+#[tokio::test]
+async fn sites_embedded_with_other_models_are_detected() -> Result<()> {
+    use crate::database::sqlite::models::{NewIndexedChunk, NewSite};
+    use crate::database::sqlite::queries::{IndexedChunkQueries, SiteQueries};
+
+    let (mut indexer, _temp_dir) = create_test_indexer().await?;
+    let pool = indexer.database.pool().clone();
+
+    let mut sites = Vec::new();
+    for (name, model, prefix) in [
+        ("Legacy", None, None),
+        (
+            "Current",
+            Some("nomic-embed-text:latest"),
+            Some("search_document: "),
+        ),
+        ("Other", Some("mxbai-embed-large"), Some("")),
+        ("Empty", Some("mxbai-embed-large"), Some("")),
+        // Embedded before prefixes were recorded
+        ("Unrecorded", Some("nomic-embed-text:latest"), None),
+        // Embedded by the same model without its prefix
+        ("Unprefixed", Some("nomic-embed-text:latest"), Some("")),
+    ] {
+        let site = SiteQueries::create(
+            &pool,
+            NewSite {
+                name: name.to_string(),
+                base_url: format!("https://{}.example.com", name.to_lowercase()),
+                index_url: format!("https://{}.example.com", name.to_lowercase()),
+                version: "1.0".to_string(),
+            },
+        )
+        .await?;
+        let update = SiteUpdate {
+            embedding_model: model.map(str::to_string),
+            embedding_document_prefix: prefix.map(str::to_string),
+            ..Default::default()
+        };
+        indexer.database.update_site(site.id, &update).await?;
+        if name != "Empty" {
+            let new_chunk = NewIndexedChunk {
+                site_id: site.id,
+                url: format!("https://{}.example.com/page", name.to_lowercase()),
+                page_title: None,
+                heading_path: None,
+                chunk_content: "Some content".to_string(),
+                chunk_index: 0,
+                vector_id: format!("{}_0", name.to_lowercase()),
+            };
+            IndexedChunkQueries::create(&mut *pool.acquire().await?, new_chunk).await?;
+        }
+        sites.push(site);
+    }
+
+    // Sites without a recorded model and sites without chunks don't conflict
+    let conflicting = indexer.sites_with_other_models(None).await?;
+    let mut names: Vec<&str> = conflicting.iter().map(|site| site.name.as_str()).collect();
+    names.sort_unstable();
+    assert_eq!(names, ["Other", "Unprefixed"]);
+    let conflicting = indexer.sites_with_other_models(Some(sites[2].id)).await?;
+    let names: Vec<&str> = conflicting.iter().map(|site| site.name.as_str()).collect();
+    assert_eq!(names, ["Unprefixed"]);
+    let message = describe_model_conflict(&conflicting, &indexer.app_config.ollama);
+    assert!(
+        message.contains(r#"nomic-embed-text:latest with document prefix "search_document: ""#),
+        "{}",
+        message
+    );
+
+    // Re-embedding one site would mix its embeddings with another model's
+    let error = indexer
+        .reembed(Some(&sites[1]))
+        .await
+        .expect_err("re-embedding one site should be refused");
+    assert!(error.to_string().contains("mxbai-embed-large"), "{}", error);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn reembedding_is_refused_while_sites_are_indexing() -> Result<()> {
+    use crate::database::sqlite::models::{NewSite, SiteStatus};
+    use crate::database::sqlite::queries::SiteQueries;
+
+    let (mut indexer, _temp_dir) = create_test_indexer().await?;
+    let site = SiteQueries::create(
+        indexer.database.pool(),
+        NewSite {
+            name: "Busy".to_string(),
+            base_url: "https://busy.example.com".to_string(),
+            index_url: "https://busy.example.com".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await?;
+    let update = SiteUpdate {
+        status: Some(SiteStatus::Indexing),
+        ..Default::default()
+    };
+    indexer.database.update_site(site.id, &update).await?;
+
+    let error = indexer
+        .reembed(None)
+        .await
+        .expect_err("re-embedding should wait for indexing");
+    assert!(
+        error
+            .to_string()
+            .contains("Busy 1.0 is still being indexed"),
+        "{}",
+        error
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn chunks_larger_than_the_model_context_are_reported() -> Result<()> {
+    use crate::config::settings::EmbeddingBackend;
+    use crate::embeddings::hashing::HashingEmbedder;
+
+    let config = ChunkingConfig::default();
+    let sizer = ChunkSizer::new(None, Some(512), "search_document: ");
+    let warning = describe_oversized_chunks(&config, &sizer, "nomic-embed-text:latest")
+        .expect("1024 tokens don't fit in 512");
+    assert!(
+        warning.contains("max_chunk_size of 1024 tokens exceeds what nomic-embed-text:latest"),
+        "{}",
+        warning
+    );
+    assert!(warning.contains("limited to 509 tokens"), "{}", warning);
+    assert!(warning.contains("set tokenizer_path"), "{}", warning);
+
+    let small = ChunkingConfig {
+        target_chunk_size: 256,
+        max_chunk_size: 400,
+        ..ChunkingConfig::default()
+    };
+    assert_eq!(describe_oversized_chunks(&small, &sizer, "nomic"), None);
+
+    // Without a known context length, nothing limits the chunks
+    let ollama = OllamaConfig {
+        provider: EmbeddingBackend::Hashing,
+        ..OllamaConfig::default()
+    };
+    let sizer = chunk_sizer(&ollama, &HashingEmbedder::new(&ollama)).await?;
+    assert_eq!(sizer.window(), None);
+    assert!(!sizer.has_tokenizer());
+    assert_eq!(describe_oversized_chunks(&config, &sizer, "hashing"), None);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsupported_tokenizers_fall_back_to_estimates() -> Result<()> {
+    use crate::config::settings::EmbeddingBackend;
+    use crate::embeddings::hashing::HashingEmbedder;
+
+    let temp_dir = TempDir::new()?;
+    let tokenizer_path = temp_dir.path().join("tokenizer.json");
+    fs::write(
+        &tokenizer_path,
+        r#"{"model": {"type": "BPE", "vocab": {}, "merges": [], "unk_token": null}}"#,
+    )?;
+    let ollama = OllamaConfig {
+        provider: EmbeddingBackend::Hashing,
+        tokenizer_path: Some(tokenizer_path.clone()),
+        ..OllamaConfig::default()
+    };
+
+    let sizer = chunk_sizer(&ollama, &HashingEmbedder::new(&ollama)).await?;
+    assert!(!sizer.has_tokenizer());
+
+    // A tokenizer that can't be read is still an error
+    fs::write(&tokenizer_path, "not json")?;
+    assert!(
+        chunk_sizer(&ollama, &HashingEmbedder::new(&ollama))
+            .await
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn hashing_embedder_indexes_without_a_server() -> Result<()> {
+    use crate::config::settings::EmbeddingBackend;
+    use crate::crawler::extractor::{ContentSection, ExtractedContent};
+    use crate::database::sqlite::models::{
+        CrawlQueueUpdate, CrawlStatus, NewCrawlQueueItem, NewSite,
+    };
+    use crate::database::sqlite::queries::{CrawlQueueQueries, SiteQueries};
+    use crate::embeddings::hashing::HASHING_MODEL_NAME;
+
+    let temp_dir = TempDir::new()?;
+    let config = Config {
+        ollama: OllamaConfig {
+            provider: EmbeddingBackend::Hashing,
+            // Nothing listens here, and nothing needs to
+            port: 1,
+            ..Default::default()
+        },
+        chunking: ChunkingConfig::default(),
+        search: Default::default(),
+        reranking: Default::default(),
+        base_dir: temp_dir.path().to_path_buf(),
+    };
+    let mut indexer = Indexer::new(config.clone(), false).await?;
+    let pool = indexer.database.pool().clone();
+
+    let site = SiteQueries::create(
+        &pool,
+        NewSite {
+            name: "Smoke Test".to_string(),
+            base_url: "https://smoke.example.com".to_string(),
+            index_url: "https://smoke.example.com".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await?;
+    let pages_dir = config.cache_dir_path()?.join("pages");
+    fs::create_dir_all(&pages_dir)?;
+    for (path, title, content) in [
+        (
+            "crawler",
+            "Crawler",
+            "The crawler follows links within the base URL and waits between requests to respect rate limits.",
+        ),
+        (
+            "index",
+            "Vector Index",
+            "Once enough embeddings are stored, an IVF-PQ vector index is trained to keep searches fast.",
+        ),
+    ] {
+        let item = CrawlQueueQueries::create(
+            &pool,
+            NewCrawlQueueItem {
+                site_id: site.id,
+                url: format!("https://smoke.example.com/{}", path),
+            },
+        )
+        .await?;
+        CrawlQueueQueries::update(
+            &pool,
+            item.id,
+            CrawlQueueUpdate {
+                status: Some(CrawlStatus::Completed),
+                retry_count: None,
+                error_message: None,
+            },
+        )
+        .await?;
+        let page = ExtractedContent {
+            title: title.to_string(),
+            sections: vec![ContentSection {
+                heading_path: title.to_string(),
+                content: content.to_string(),
+                heading_level: Some(1),
+                has_code_blocks: false,
+            }],
+            raw_text: content.to_string(),
+        };
+        fs::write(
+            pages_dir.join(format!("{}.json", item.id)),
+            serde_json::to_string(&page)?,
+        )?;
+    }
+
+    indexer.process_site_embeddings(&site).await?;
+
+    let site = indexer
+        .database
+        .get_site_by_id(site.id)
+        .await?
+        .expect("site exists");
+    assert_eq!(site.embedding_model.as_deref(), Some(HASHING_MODEL_NAME));
+    assert_eq!(
+        indexer.database.get_chunks_for_site(site.id).await?.len(),
+        2
+    );
+
+    // Both search paths work on what was indexed
+    let query = indexer
+        .embedder
+        .generate_embedding("crawler rate limits")
+        .await?;
+    let results = indexer
+        .vector_store
+        .search_similar(&query.embedding, 2, None)
+        .await?;
+    assert_eq!(
+        results[0].chunk_metadata.page_url,
+        "https://smoke.example.com/crawler"
+    );
+    let matches = indexer.database.search_chunks("crawler", 2, None).await?;
+    assert_eq!(matches.len(), 1);
+
+    Ok(())
+}
+
 mod integration_tests {
     use std::env;
 
     use crate::database::sqlite::{
-        models::{CrawlQueueUpdate, CrawlStatus, NewCrawlQueueItem, NewSite},
+        models::{CrawlQueueUpdate, CrawlStatus, NewCrawlQueueItem, NewIndexedChunk, NewSite},
         queries::{CrawlQueueQueries, IndexedChunkQueries, SiteQueries},
     };
 
@@ -63,8 +368,11 @@ mod integration_tests {
                 model,
                 batch_size: 5, // Smaller batch size for testing
                 embedding_dimension: DEFAULT_EMBEDDING_DIMENSION,
+                ..Default::default()
             },
             chunking: ChunkingConfig::default(),
+            search: Default::default(),
+            reranking: Default::default(),
         };
 
         (config, temp_dir)
@@ -409,3 +717,515 @@ mod integration_tests {
         Ok(())
     }
 }
+
+/// Embedder which counts its requests, and those in flight at once
+#[derive(Debug, Default)]
+struct SlowEmbedder {
+    requests: std::sync::atomic::AtomicUsize,
+    in_flight: std::sync::atomic::AtomicUsize,
+    max_in_flight: std::sync::atomic::AtomicUsize,
+    prompt: PromptTemplate,
+}
+
+#[async_trait::async_trait]
+impl EmbeddingProvider for SlowEmbedder {
+    fn model(&self) -> &str {
+        "slow"
+    }
+
+    fn prompt(&self) -> &PromptTemplate {
+        &self.prompt
+    }
+
+    async fn health_check(&self) -> Result<()> {
+        Ok(())
+    }
+
+    async fn generate_embedding(&self, text: &str) -> Result<crate::embeddings::EmbeddingResult> {
+        use std::sync::atomic::Ordering;
+
+        let in_flight = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        self.max_in_flight.fetch_max(in_flight, Ordering::SeqCst);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+
+        Ok(crate::embeddings::EmbeddingResult {
+            text: text.to_string(),
+            embedding: vec![1.0; 4],
+            token_count: 1,
+            chunk_index: None,
+            heading_path: None,
+        })
+    }
+
+    async fn generate_embeddings_batch(
+        &self,
+        texts: &[String],
+    ) -> Result<Vec<crate::embeddings::EmbeddingResult>> {
+        self.requests
+            .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        let mut results = Vec::with_capacity(texts.len());
+        for text in texts {
+            results.push(self.generate_embedding(text).await?);
+        }
+        Ok(results)
+    }
+
+    async fn rerank(&self, _model: &str, _query: &str, _documents: &[String]) -> Result<Vec<f32>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Cache `count` one-chunk pages in `pages_dir`, returning their queue items
+fn cache_test_pages(pages_dir: &std::path::Path, count: i64) -> Result<Vec<CrawlQueueItem>> {
+    use crate::crawler::extractor::{ContentSection, ExtractedContent};
+    use crate::database::sqlite::models::CrawlStatus;
+
+    let mut pages = Vec::new();
+    for id in 1..=count {
+        let page = ExtractedContent {
+            title: format!("Page {}", id),
+            sections: vec![ContentSection {
+                heading_path: format!("Page {}", id),
+                content: format!("Content of page number {} about configuration.", id),
+                heading_level: Some(1),
+                has_code_blocks: false,
+            }],
+            raw_text: String::new(),
+        };
+        fs::write(
+            pages_dir.join(format!("{}.json", id)),
+            serde_json::to_string(&page)?,
+        )?;
+        pages.push(CrawlQueueItem {
+            id,
+            site_id: 1,
+            url: format!("https://example.com/{}", id),
+            status: CrawlStatus::Completed,
+            retry_count: 0,
+            error_message: None,
+            created_date: Utc::now().naive_utc(),
+        });
+    }
+    Ok(pages)
+}
+
+/// Embedding cache for the test embedders, in a database of its own
+async fn create_test_cache(temp_dir: &TempDir) -> Result<Arc<EmbeddingCache>> {
+    let database = Database::new(temp_dir.path().join("cache.db")).await?;
+    Ok(Arc::new(EmbeddingCache::new(
+        database,
+        &OllamaConfig {
+            model: "slow".to_string(),
+            embedding_dimension: 4,
+            ..Default::default()
+        },
+    )))
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn pages_are_embedded_concurrently() -> Result<()> {
+    use crate::indexer::pipeline::PageEmbedder;
+
+    let temp_dir = TempDir::new()?;
+    let mut pages = cache_test_pages(temp_dir.path(), 6)?;
+    // A page whose cached content is gone fails on its own
+    pages.push(CrawlQueueItem {
+        id: 7,
+        url: "https://example.com/missing".to_string(),
+        ..pages[0].clone()
+    });
+
+    let embedder = Arc::new(SlowEmbedder::default());
+    let page_embedder = PageEmbedder {
+        embedder: Arc::clone(&embedder) as Arc<dyn EmbeddingProvider>,
+        cache: create_test_cache(&temp_dir).await?,
+        chunking_config: ChunkingConfig::default(),
+        sizer: ChunkSizer::default(),
+        pages_dir: temp_dir.path().to_path_buf(),
+        // One chunk per request, so that pages need requests of their own
+        batch_size: 1,
+        concurrency: 3,
+    };
+
+    let mut embedded_pages = page_embedder.spawn(pages);
+    let mut embedded = 0;
+    let mut failed = Vec::new();
+    while let Some((crawl_item, page)) = embedded_pages.recv().await {
+        match page {
+            Ok(page) => {
+                assert_eq!(page.title, format!("Page {}", crawl_item.id));
+                assert_eq!(page.chunks.len(), 1);
+                embedded += 1;
+            }
+            Err(_) => failed.push(crawl_item.url),
+        }
+    }
+
+    assert_eq!(embedded, 6);
+    assert_eq!(failed, ["https://example.com/missing"]);
+    let max_in_flight = embedder
+        .max_in_flight
+        .load(std::sync::atomic::Ordering::SeqCst);
+    assert!(
+        (2..=3).contains(&max_in_flight),
+        "{} requests were in flight",
+        max_in_flight
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn small_pages_share_embedding_requests() -> Result<()> {
+    use crate::indexer::pipeline::PageEmbedder;
+
+    let temp_dir = TempDir::new()?;
+    let pages = cache_test_pages(temp_dir.path(), 6)?;
+
+    let embedder = Arc::new(SlowEmbedder::default());
+    let page_embedder = PageEmbedder {
+        embedder: Arc::clone(&embedder) as Arc<dyn EmbeddingProvider>,
+        cache: create_test_cache(&temp_dir).await?,
+        chunking_config: ChunkingConfig::default(),
+        sizer: ChunkSizer::default(),
+        pages_dir: temp_dir.path().to_path_buf(),
+        batch_size: 4,
+        concurrency: 2,
+    };
+
+    let mut embedded_pages = page_embedder.spawn(pages);
+    let mut embedded = Vec::new();
+    while let Some((crawl_item, page)) = embedded_pages.recv().await {
+        let page = page?;
+        assert_eq!(page.chunks.len(), 1);
+        assert_eq!(page.title, format!("Page {}", crawl_item.id));
+        embedded.push(crawl_item.id);
+    }
+
+    embedded.sort_unstable();
+    assert_eq!(embedded, [1, 2, 3, 4, 5, 6]);
+    // Six one-chunk pages fill a batch of four and then a batch of two
+    assert_eq!(
+        embedder.requests.load(std::sync::atomic::Ordering::SeqCst),
+        2
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unchanged_chunks_are_embedded_once() -> Result<()> {
+    use crate::indexer::pipeline::PageEmbedder;
+
+    let temp_dir = TempDir::new()?;
+    let pages = cache_test_pages(temp_dir.path(), 4)?;
+    let embedder = Arc::new(SlowEmbedder::default());
+    let cache = create_test_cache(&temp_dir).await?;
+
+    let embed_pages = |pages: Vec<CrawlQueueItem>, cache: Arc<EmbeddingCache>| {
+        let page_embedder = PageEmbedder {
+            embedder: Arc::clone(&embedder) as Arc<dyn EmbeddingProvider>,
+            cache,
+            chunking_config: ChunkingConfig::default(),
+            sizer: ChunkSizer::default(),
+            pages_dir: temp_dir.path().to_path_buf(),
+            batch_size: 2,
+            concurrency: 2,
+        };
+        async move {
+            let mut embedded = Vec::new();
+            let mut receiver = page_embedder.spawn(pages);
+            while let Some((_, page)) = receiver.recv().await {
+                embedded.push(page?);
+            }
+            Ok::<_, anyhow::Error>(embedded)
+        }
+    };
+
+    let first = embed_pages(pages[..2].to_vec(), Arc::clone(&cache)).await?;
+    assert_eq!(first.len(), 2);
+    assert_eq!(cache.stats(), CacheStats { hits: 0, misses: 2 });
+    let requests = embedder.requests.load(std::sync::atomic::Ordering::SeqCst);
+
+    // Indexing again reuses the first pages' embeddings, and only embeds the new ones
+    let cache = create_test_cache(&temp_dir).await?;
+    let second = embed_pages(pages, Arc::clone(&cache)).await?;
+    assert_eq!(second.len(), 4);
+    assert!(second.iter().all(|page| page.chunks.len() == 1));
+    assert_eq!(cache.stats(), CacheStats { hits: 2, misses: 2 });
+    assert!((cache.stats().hit_rate() - 0.5).abs() < f64::EPSILON);
+    assert_eq!(
+        embedder.requests.load(std::sync::atomic::Ordering::SeqCst),
+        requests + 1
+    );
+
+    let embedding = |page: &EmbeddedPage| page.chunks[0].1.embedding.clone();
+    let first_title = &first[0].title;
+    let reused = second
+        .iter()
+        .find(|page| &page.title == first_title)
+        .expect("first page should be embedded again");
+    assert_eq!(embedding(reused), embedding(&first[0]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn embedding_cache_is_keyed_by_model_and_dimension() -> Result<()> {
+    use crate::config::settings::EmbeddingBackend;
+
+    let temp_dir = TempDir::new()?;
+    let database = Database::new(temp_dir.path().join("cache.db")).await?;
+    let cache = |provider: EmbeddingBackend, model: &str, embedding_dimension: u32| {
+        EmbeddingCache::new(
+            database.clone(),
+            &OllamaConfig {
+                provider,
+                model: model.to_string(),
+                embedding_dimension,
+                ..Default::default()
+            },
+        )
+    };
+
+    // A model's embeddings are reused whatever size `embedding_dimension`
+    // claims they have
+    cache(EmbeddingBackend::Ollama, "first", 768)
+        .put(&[("shared text", [0.25, -1.5].as_slice())])
+        .await;
+    assert_eq!(
+        cache(EmbeddingBackend::Ollama, "first", 768)
+            .get(&["shared text", "other text"])
+            .await,
+        [Some(vec![0.25, -1.5]), None]
+    );
+    assert_eq!(
+        cache(EmbeddingBackend::OpenAi, "first", 384)
+            .get(&["shared text"])
+            .await,
+        [Some(vec![0.25, -1.5])]
+    );
+    assert_eq!(
+        cache(EmbeddingBackend::Ollama, "second", 768)
+            .get(&["shared text"])
+            .await,
+        [None]
+    );
+
+    // The hashing embedder's output is sized by the configuration
+    cache(EmbeddingBackend::Hashing, "", 2)
+        .put(&[("shared text", [0.5, 0.5].as_slice())])
+        .await;
+    assert_eq!(
+        cache(EmbeddingBackend::Hashing, "", 2)
+            .get(&["shared text"])
+            .await,
+        [Some(vec![0.5, 0.5])]
+    );
+    assert_eq!(
+        cache(EmbeddingBackend::Hashing, "", 3)
+            .get(&["shared text"])
+            .await,
+        [None]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn pruning_keeps_embeddings_of_stored_chunks() -> Result<()> {
+    use crate::database::sqlite::models::{NewIndexedChunk, NewSite};
+    use crate::database::sqlite::queries::{IndexedChunkQueries, SiteQueries};
+
+    let temp_dir = TempDir::new()?;
+    let database = Database::new(temp_dir.path().join("cache.db")).await?;
+    let site = SiteQueries::create(
+        database.pool(),
+        NewSite {
+            name: "Docs".to_string(),
+            base_url: "https://docs.example.com".to_string(),
+            index_url: "https://docs.example.com".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await?;
+    IndexedChunkQueries::create(
+        &mut *database.pool().acquire().await?,
+        NewIndexedChunk {
+            site_id: site.id,
+            url: "https://docs.example.com/page".to_string(),
+            page_title: None,
+            heading_path: None,
+            chunk_content: "Stored chunk".to_string(),
+            chunk_index: 0,
+            vector_id: "docs_0".to_string(),
+        },
+    )
+    .await?;
+
+    let config = OllamaConfig::default();
+    let other_model = OllamaConfig {
+        model: "all-minilm".to_string(),
+        ..OllamaConfig::default()
+    };
+    EmbeddingCache::new(database.clone(), &config)
+        .put(&[
+            ("Stored chunk", [1.0, 0.0].as_slice()),
+            ("Changed chunk", [0.0, 1.0].as_slice()),
+        ])
+        .await;
+    EmbeddingCache::new(database.clone(), &other_model)
+        .put(&[("Stored chunk", [0.5, 0.5].as_slice())])
+        .await;
+
+    // Only the configured model's embedding of the stored chunk is kept
+    assert_eq!(prune_embedding_cache(&database, &config).await?, 2);
+    let cache = EmbeddingCache::new(database.clone(), &config);
+    assert_eq!(
+        cache.get(&["Stored chunk", "Changed chunk"]).await,
+        [Some(vec![1.0, 0.0]), None]
+    );
+    assert_eq!(prune_embedding_cache(&database, &config).await?, 0);
+
+    Ok(())
+}
+
+#[test]
+fn cache_stats_report_hit_rate() {
+    let stats = CacheStats { hits: 3, misses: 1 };
+
+    assert!((stats.hit_rate() - 0.75).abs() < f64::EPSILON);
+    assert_eq!(
+        stats.to_string(),
+        "3 of 4 chunks reused from the embedding cache (75.0%)"
+    );
+    assert!(CacheStats::default().hit_rate().abs() < f64::EPSILON);
+}
+
+#[tokio::test]
+async fn page_writer_removes_embeddings_when_sqlite_fails() -> Result<()> {
+    use crate::embeddings::EmbeddingResult;
+    use crate::embeddings::chunking::ContentChunk;
+    use crate::indexer::writer::PageWriter;
+
+    let (mut indexer, temp_dir) = create_test_indexer().await?;
+    let crawl_item = cache_test_pages(temp_dir.path(), 1)?.remove(0);
+
+    // No such site, so SQLite refuses the chunk after LanceDB stored it
+    let mut writer = PageWriter::new(999, 4, std::time::Duration::from_secs(60));
+    let content = "Orphaned chunk".to_string();
+    let chunk = (
+        ContentChunk {
+            content: content.clone(),
+            heading_path: "Page".to_string(),
+            chunk_index: 0,
+            token_count: 2,
+            has_code_blocks: false,
+        },
+        EmbeddingResult {
+            text: content,
+            embedding: vec![0.5; DEFAULT_EMBEDDING_DIMENSION as usize],
+            token_count: 2,
+            chunk_index: Some(0),
+            heading_path: None,
+        },
+    );
+    writer.push(
+        crawl_item,
+        EmbeddedPage {
+            title: "Page".to_string(),
+            chunks: vec![chunk],
+        },
+    );
+
+    let (written, result) = writer
+        .flush(&indexer.database, &mut indexer.vector_store)
+        .await;
+    assert_eq!(written.len(), 1);
+    result.expect_err("the chunk's site doesn't exist");
+    assert_eq!(indexer.vector_store.count_embeddings().await?, 0);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn page_writer_flushes_pages_to_both_stores() -> Result<()> {
+    use crate::database::sqlite::models::NewSite;
+    use crate::database::sqlite::queries::SiteQueries;
+    use crate::embeddings::EmbeddingResult;
+    use crate::embeddings::chunking::ContentChunk;
+    use crate::indexer::writer::PageWriter;
+
+    let (mut indexer, temp_dir) = create_test_indexer().await?;
+    let site = SiteQueries::create(
+        indexer.database.pool(),
+        NewSite {
+            name: "Buffered".to_string(),
+            base_url: "https://example.com".to_string(),
+            index_url: "https://example.com".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await?;
+    let pages = cache_test_pages(temp_dir.path(), 3)?;
+
+    let mut writer = PageWriter::new(site.id, 4, std::time::Duration::from_secs(60));
+    assert!(writer.deadline().is_none());
+    for crawl_item in pages {
+        let chunks = (0..2)
+            .map(|chunk_index| {
+                let content = format!("Chunk {} of page {}", chunk_index, crawl_item.id);
+                (
+                    ContentChunk {
+                        content: content.clone(),
+                        heading_path: format!("Page {}", crawl_item.id),
+                        chunk_index,
+                        token_count: 4,
+                        has_code_blocks: false,
+                    },
+                    EmbeddingResult {
+                        text: content,
+                        embedding: vec![0.5; DEFAULT_EMBEDDING_DIMENSION as usize],
+                        token_count: 4,
+                        chunk_index: Some(chunk_index),
+                        heading_path: None,
+                    },
+                )
+            })
+            .collect();
+        let title = format!("Page {}", crawl_item.id);
+        writer.push(crawl_item, EmbeddedPage { title, chunks });
+        assert!(writer.deadline().is_some());
+
+        if writer.is_full() {
+            let (written, result) = writer
+                .flush(&indexer.database, &mut indexer.vector_store)
+                .await;
+            result?;
+            // Whole pages are written together
+            assert_eq!(written.len(), 2);
+            assert!(written.iter().all(|(_, chunks)| *chunks == 2));
+            assert!(writer.deadline().is_none());
+        }
+    }
+
+    assert_eq!(
+        indexer.database.get_chunks_for_site(site.id).await?.len(),
+        4
+    );
+    assert_eq!(indexer.vector_store.count_embeddings().await?, 4);
+
+    let (written, result) = writer
+        .flush(&indexer.database, &mut indexer.vector_store)
+        .await;
+    result?;
+    assert_eq!(written.len(), 1);
+    assert_eq!(
+        indexer.database.get_chunks_for_site(site.id).await?.len(),
+        6
+    );
+    assert_eq!(indexer.vector_store.count_embeddings().await?, 6);
+
+    Ok(())
+}
@@ -0,0 +1,178 @@
+//! Buffered Page Writer
+//!
+//! LanceDB stores every write as a new fragment, so writing each page on its
+//! own leaves thousands of small fragments behind. Embedded pages are buffered
+//! instead, and written together once enough rows have built up or the oldest
+//! page has waited long enough. Each flush writes its pages' chunks to SQLite
+//! in one transaction, committed only once LanceDB has their embeddings, so a
+//! page is either stored in both or in neither and processed again later.
+
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use tokio::time::Instant;
+use tracing::warn;
+use uuid::Uuid;
+
+use super::pipeline::EmbeddedPage;
+use crate::database::lancedb::vector_store::VectorStore;
+use crate::database::lancedb::{ChunkMetadata, EmbeddingRecord};
+use crate::database::sqlite::Database;
+use crate::database::sqlite::models::{CrawlQueueItem, NewIndexedChunk};
+
+/// Rows buffered before they are written
+pub(super) const FLUSH_ROWS: usize = 2048;
+/// Longest a page waits in the buffer before it is written
+pub(super) const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Embedded pages waiting to be written
+#[derive(Debug)]
+pub(super) struct PageWriter {
+    site_id: i64,
+    flush_rows: usize,
+    flush_interval: Duration,
+    embedding_records: Vec<EmbeddingRecord>,
+    indexed_chunks: Vec<NewIndexedChunk>,
+    /// Pages buffered, with how many chunks each has
+    pages: Vec<(CrawlQueueItem, usize)>,
+    /// When the oldest buffered page arrived
+    oldest: Option<Instant>,
+}
+
+impl PageWriter {
+    pub(super) fn new(site_id: i64, flush_rows: usize, flush_interval: Duration) -> Self {
+        Self {
+            site_id,
+            flush_rows,
+            flush_interval,
+            embedding_records: Vec::new(),
+            indexed_chunks: Vec::new(),
+            pages: Vec::new(),
+            oldest: None,
+        }
+    }
+
+    /// Buffer a page's chunks and embeddings
+    pub(super) fn push(&mut self, crawl_item: CrawlQueueItem, page: EmbeddedPage) {
+        let chunk_count = page.chunks.len();
+        for (chunk, embedding_result) in page.chunks {
+            let vector_id = Uuid::new_v4().to_string();
+
+            self.embedding_records.push(EmbeddingRecord {
+                id: vector_id.clone(),
+                vector: embedding_result.embedding,
+                metadata: ChunkMetadata {
+                    chunk_id: vector_id.clone(),
+                    site_id: self.site_id.to_string(),
+                    page_title: page.title.clone(),
+                    page_url: crawl_item.url.clone(),
+                    heading_path: Some(chunk.heading_path.clone()),
+                    content: chunk.content.clone(),
+                    token_count: chunk.token_count as u32,
+                    chunk_index: chunk.chunk_index as u32,
+                    created_at: Utc::now().to_rfc3339(),
+                },
+            });
+            self.indexed_chunks.push(NewIndexedChunk {
+                site_id: self.site_id,
+                url: crawl_item.url.clone(),
+                page_title: Some(page.title.clone()),
+                heading_path: Some(chunk.heading_path),
+                chunk_content: chunk.content,
+                chunk_index: chunk.chunk_index as i64,
+                vector_id,
+            });
+        }
+        self.pages.push((crawl_item, chunk_count));
+        self.oldest.get_or_insert_with(Instant::now);
+    }
+
+    /// Whether enough rows are buffered to be written
+    pub(super) fn is_full(&self) -> bool {
+        self.embedding_records.len() >= self.flush_rows
+    }
+
+    /// When the buffer must be written by, if anything is buffered
+    pub(super) fn deadline(&self) -> Option<Instant> {
+        self.oldest.map(|oldest| oldest + self.flush_interval)
+    }
+
+    /// Write every buffered page, returning the pages with their chunk counts
+    ///
+    /// If writing fails, the buffered pages are dropped and the error names
+    /// them, so that they can be processed again.
+    pub(super) async fn flush(
+        &mut self,
+        database: &Database,
+        vector_store: &mut VectorStore,
+    ) -> (Vec<(CrawlQueueItem, usize)>, Result<()>) {
+        let pages = std::mem::take(&mut self.pages);
+        let embedding_records = std::mem::take(&mut self.embedding_records);
+        let indexed_chunks = std::mem::take(&mut self.indexed_chunks);
+        self.oldest = None;
+
+        let written = write(database, vector_store, embedding_records, indexed_chunks).await;
+        (pages, written)
+    }
+}
+
+/// Write chunks to SQLite and their embeddings to LanceDB at once,
+/// committing SQLite only once LanceDB has succeeded
+///
+/// If SQLite fails after LanceDB has stored the embeddings, they are deleted
+/// again, so that no embedding is left without its chunk.
+async fn write(
+    database: &Database,
+    vector_store: &mut VectorStore,
+    embedding_records: Vec<EmbeddingRecord>,
+    indexed_chunks: Vec<NewIndexedChunk>,
+) -> Result<()> {
+    let sqlite_writes = async {
+        let mut transaction = database
+            .pool()
+            .begin()
+            .await
+            .context("Failed to begin SQLite transaction")?;
+        for indexed_chunk in &indexed_chunks {
+            database
+                .insert_indexed_chunk_with_transaction(indexed_chunk, &mut transaction)
+                .await
+                .context("Failed to store indexed chunk in SQLite")?;
+        }
+        Ok::<_, anyhow::Error>(transaction)
+    };
+    let lancedb_writes = async {
+        vector_store
+            .store_embeddings_batch(embedding_records)
+            .await
+            .context("Failed to store embeddings in LanceDB")
+    };
+    // Both writes run to completion, so LanceDB isn't interrupted part way
+    // through a write that then can't be undone
+    let (transaction, stored) = tokio::join!(sqlite_writes, lancedb_writes);
+    stored?;
+
+    let committed = match transaction {
+        Ok(transaction) => transaction
+            .commit()
+            .await
+            .context("Failed to commit SQLite transaction"),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = committed {
+        let vector_ids: Vec<String> = indexed_chunks
+            .into_iter()
+            .map(|chunk| chunk.vector_id)
+            .collect();
+        if let Err(delete_err) = vector_store.delete_embeddings(&vector_ids).await {
+            warn!(
+                "Failed to delete {} embeddings whose chunks weren't stored: {}",
+                vector_ids.len(),
+                delete_err
+            );
+        }
+        return Err(e);
+    }
+    Ok(())
+}
@@ -9,9 +9,14 @@ pub(crate) mod turndown;
 
 use thiserror::Error;
 
-pub use self::commands::{add_site, delete_site, list_sites, serve_mcp, show_status, update_site};
+pub use self::commands::{
+    add_site, clear_cache, delete_site, list_sites, prune_cache, rebuild_index, reembed, serve_mcp,
+    show_status, update_site,
+};
 pub use self::config::{Config, ConfigError, run_interactive_config, show_config};
 pub use self::indexer::Indexer;
+pub use self::mcp::dispatch::DEFAULT_TOOL_TIMEOUT;
+pub use self::mcp::http::{DEFAULT_SESSION_IDLE_TIMEOUT, HttpServerOptions};
 
 pub type Result<T> = std::result::Result<T, DocsError>;
 
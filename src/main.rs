@@ -1,10 +1,15 @@
+use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use clap::{Parser, Subcommand};
-use docs_mcp::Indexer;
 use docs_mcp::{Config, ConfigError, run_interactive_config, show_config};
+use docs_mcp::{DEFAULT_SESSION_IDLE_TIMEOUT, DEFAULT_TOOL_TIMEOUT, HttpServerOptions, Indexer};
 use docs_mcp::{DocsError, Result as DocsResult};
-use docs_mcp::{add_site, delete_site, list_sites, serve_mcp, show_status, update_site};
+use docs_mcp::{
+    add_site, clear_cache, delete_site, list_sites, prune_cache, rebuild_index, reembed, serve_mcp,
+    show_status, update_site,
+};
 
 #[derive(Parser)]
 #[command(name = "docs-mcp")]
@@ -59,10 +64,61 @@ enum Commands {
         #[arg(long, short)]
         verbose: bool,
     },
-    /// Start MCP server on stdio
-    Serve,
+    /// Re-embed indexed content with the configured embedding model
+    Reembed {
+        /// Site ID or name to re-embed, instead of every site
+        #[arg(long)]
+        site: Option<String>,
+        /// Output additional information during processing
+        #[arg(long, short)]
+        verbose: bool,
+    },
+    /// Start MCP server on stdio, or over HTTP with --http
+    Serve {
+        /// Serve streamable HTTP (with SSE fallback) on this address, e.g. 127.0.0.1:8090
+        #[arg(long, value_name = "ADDR")]
+        http: Option<SocketAddr>,
+        /// Bearer token required from HTTP clients
+        #[arg(
+            long,
+            env = "DOCS_MCP_TOKEN",
+            hide_env_values = true,
+            requires = "http"
+        )]
+        token: Option<String>,
+        /// Maximum time in seconds a single tool call may run
+        #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_TOOL_TIMEOUT.as_secs())]
+        tool_timeout: u64,
+        /// Seconds after which an idle HTTP session without an open event stream is closed
+        #[arg(long, value_name = "SECONDS", default_value_t = DEFAULT_SESSION_IDLE_TIMEOUT.as_secs())]
+        session_timeout: u64,
+    },
     /// Show detailed status of the indexing pipeline
     Status,
+    /// Manage the vector search index
+    Index {
+        #[command(subcommand)]
+        command: IndexCommands,
+    },
+    /// Manage the embedding cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+}
+
+#[derive(Subcommand)]
+enum IndexCommands {
+    /// Rebuild the vector index from all stored embeddings
+    Rebuild,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Remove cached embeddings not used by any indexed chunk with the configured model
+    Prune,
+    /// Remove every cached embedding
+    Clear,
 }
 
 #[tokio::main]
@@ -111,12 +167,38 @@ async fn main() -> DocsResult<()> {
                 .process_site_embeddings(&site)
                 .await?;
         }
-        Commands::Serve => {
-            serve_mcp(&config).await?;
+        Commands::Reembed { site, verbose } => {
+            reembed(site, &config, verbose).await?;
+        }
+        Commands::Serve {
+            http,
+            token,
+            tool_timeout,
+            session_timeout,
+        } => {
+            let http = http.map(|addr| HttpServerOptions {
+                addr,
+                bearer_token: token,
+                session_idle_timeout: Duration::from_secs(session_timeout),
+            });
+            serve_mcp(&config, http, Duration::from_secs(tool_timeout)).await?;
         }
         Commands::Status => {
             show_status(&config).await?;
         }
+        Commands::Index { command } => match command {
+            IndexCommands::Rebuild => {
+                rebuild_index(&config).await?;
+            }
+        },
+        Commands::Cache { command } => match command {
+            CacheCommands::Prune => {
+                prune_cache(&config).await?;
+            }
+            CacheCommands::Clear => {
+                clear_cache(&config).await?;
+            }
+        },
     }
 
     Ok(())
@@ -0,0 +1,126 @@
+//! Context Assembly
+//!
+//! Packs retrieved documentation sections into a single markdown document
+//! with numbered citations, staying within a token budget. The budget is
+//! measured with the same [`ChunkSizer`] as chunks are.
+
+use crate::embeddings::chunking::ChunkSizer;
+
+/// A retrieved documentation section that may be included in the context
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SectionCandidate {
+    pub url: String,
+    pub page_title: Option<String>,
+    pub heading_path: Option<String>,
+    pub site_name: String,
+    pub site_version: String,
+    /// Renderings of the section, most complete first. The first one that
+    /// fits in the remaining budget is used.
+    pub renderings: Vec<String>,
+}
+
+impl SectionCandidate {
+    fn title(&self) -> String {
+        let page_title = self
+            .page_title
+            .as_deref()
+            .filter(|t| !t.is_empty())
+            .unwrap_or(&self.url);
+        match self.heading_path.as_deref().filter(|h| !h.is_empty()) {
+            Some(heading_path) if heading_path != page_title => {
+                format!("{}: {}", page_title, heading_path)
+            }
+            _ => page_title.to_string(),
+        }
+    }
+
+    fn citation(&self) -> String {
+        format!(
+            "{} ({} {}): {}",
+            self.page_title.as_deref().unwrap_or(&self.url),
+            self.site_name,
+            self.site_version,
+            self.url
+        )
+    }
+}
+
+/// A markdown document assembled from section candidates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PackedContext {
+    pub markdown: String,
+    /// Token count of `markdown`, as measured by the sizer
+    pub token_count: usize,
+    /// Number of sections included
+    pub sections: usize,
+    /// Number of candidates which did not fit in the budget
+    pub omitted: usize,
+}
+
+/// Pack candidates, in order, into a document of at most `max_tokens` tokens
+/// as counted by `sizer`
+///
+/// Each candidate is added using its most complete rendering that still fits.
+/// Candidates which don't fit at all are skipped so that smaller sections
+/// further down the list can still use the remaining budget.
+pub fn pack_context(
+    candidates: &[SectionCandidate],
+    max_tokens: usize,
+    sizer: &ChunkSizer,
+) -> PackedContext {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut citations: Vec<String> = Vec::new();
+    let mut omitted = 0;
+
+    for candidate in candidates {
+        let number = blocks.len() + 1;
+        let citation = format!("[{}] {}", number, candidate.citation());
+
+        let fitting = candidate.renderings.iter().find_map(|rendering| {
+            let block = format!(
+                "## [{}] {}\n\n{}",
+                number,
+                candidate.title(),
+                rendering.trim()
+            );
+            let document = render_document(
+                blocks.iter().chain([&block]),
+                citations.iter().chain([&citation]),
+            );
+            (sizer.count_tokens(&document) <= max_tokens).then_some(block)
+        });
+
+        match fitting {
+            Some(block) => {
+                blocks.push(block);
+                citations.push(citation);
+            }
+            None => omitted += 1,
+        }
+    }
+
+    let markdown = render_document(blocks.iter(), citations.iter());
+    PackedContext {
+        token_count: sizer.count_tokens(&markdown),
+        markdown,
+        sections: blocks.len(),
+        omitted,
+    }
+}
+
+fn render_document<'a>(
+    blocks: impl Iterator<Item = &'a String>,
+    citations: impl Iterator<Item = &'a String>,
+) -> String {
+    let mut document = blocks.map(String::as_str).collect::<Vec<_>>().join("\n\n");
+    if document.is_empty() {
+        return document;
+    }
+
+    document.push_str("\n\n## Sources\n");
+    for citation in citations {
+        document.push('\n');
+        document.push_str(citation);
+    }
+    document
+}
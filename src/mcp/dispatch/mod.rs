@@ -0,0 +1,251 @@
+//! Tool Dispatch
+//!
+//! The server's built-in tool manager calls handlers synchronously, one message
+//! at a time, so a slow search holds up every other request. Tool calls are
+//! instead answered by [`ToolTransport`], which wraps the server's transport
+//! and runs each `tools/call` request as its own task. Calls are bounded by a
+//! timeout and aborted when the client sends `notifications/cancelled`.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use modelcontextprotocol_server::Transport;
+use modelcontextprotocol_server::mcp_protocol::JsonRpcMessage;
+use modelcontextprotocol_server::mcp_protocol::constants::{error_codes, methods};
+use modelcontextprotocol_server::mcp_protocol::tool::{Tool, ToolCallResult, ToolContent};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::{debug, warn};
+
+use crate::mcp::tools::{CallToolParams, ToolHandler};
+
+/// Notification sent by clients to cancel an in-flight request
+pub const CANCELLED_NOTIFICATION: &str = "notifications/cancelled";
+
+/// Default time limit for a single tool call
+pub const DEFAULT_TOOL_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Tools served by a [`ToolDispatcher`]
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    tools: Vec<(Tool, Arc<dyn ToolHandler>)>,
+}
+
+impl ToolRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a tool with the handler that answers its calls
+    pub fn register(&mut self, tool: Tool, handler: impl ToolHandler + 'static) {
+        self.tools.push((tool, Arc::new(handler)));
+    }
+
+    /// Definitions of all registered tools, in registration order
+    pub fn definitions(&self) -> impl Iterator<Item = &Tool> {
+        self.tools.iter().map(|(tool, _)| tool)
+    }
+
+    fn handler(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        self.tools
+            .iter()
+            .find(|(tool, _)| tool.name == name)
+            .map(|(_, handler)| Arc::clone(handler))
+    }
+}
+
+/// Runs tool calls concurrently and tracks them for cancellation
+pub struct ToolDispatcher {
+    registry: ToolRegistry,
+    timeout: Duration,
+    /// Calls in progress, keyed by JSON-encoded request ID
+    running: Mutex<HashMap<String, AbortHandle>>,
+}
+
+impl ToolDispatcher {
+    /// Create a dispatcher for the registered tools
+    pub fn new(registry: ToolRegistry, timeout: Duration) -> Self {
+        Self {
+            registry,
+            timeout,
+            running: Mutex::default(),
+        }
+    }
+
+    /// Answer a `tools/call` request, producing the response message
+    ///
+    /// A call which exceeds the timeout is answered with an error result.
+    pub async fn call(&self, id: Value, params: Option<Value>) -> JsonRpcMessage {
+        let params = match params.map(serde_json::from_value::<CallToolParams>) {
+            Some(Ok(params)) => params,
+            Some(Err(e)) => {
+                return JsonRpcMessage::error(
+                    id,
+                    error_codes::INVALID_PARAMS,
+                    &format!("Invalid tool call parameters: {}", e),
+                    None,
+                );
+            }
+            None => {
+                return JsonRpcMessage::error(
+                    id,
+                    error_codes::INVALID_PARAMS,
+                    "Missing tool call parameters",
+                    None,
+                );
+            }
+        };
+
+        let Some(handler) = self.registry.handler(&params.name) else {
+            return JsonRpcMessage::error(
+                id,
+                error_codes::INVALID_PARAMS,
+                &format!("Unknown tool: {}", params.name),
+                None,
+            );
+        };
+
+        let name = params.name.clone();
+        debug!("Calling tool '{}' for request {}", name, id);
+        let result = match tokio::time::timeout(self.timeout, handler.handle(params)).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(e)) => {
+                return JsonRpcMessage::error(
+                    id,
+                    error_codes::INTERNAL_ERROR,
+                    &format!("Tool execution error: {}", e),
+                    None,
+                );
+            }
+            Err(_) => {
+                warn!(
+                    "Tool '{}' timed out after {} seconds",
+                    name,
+                    self.timeout.as_secs_f64()
+                );
+                ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!(
+                            "Tool call timed out after {} seconds",
+                            self.timeout.as_secs_f64()
+                        ),
+                    }],
+                    is_error: Some(true),
+                }
+            }
+        };
+
+        JsonRpcMessage::response(id, json!(result))
+    }
+
+    /// Answer a `tools/call` request in a new task, sending the response
+    /// through `transport` once the call finishes
+    pub fn spawn<T: Transport>(self: &Arc<Self>, id: Value, params: Option<Value>, transport: T) {
+        let key = id.to_string();
+        let dispatcher = Arc::clone(self);
+
+        // Hold the lock while spawning so the task can't finish and deregister
+        // itself before it has been registered
+        let mut running = self.lock();
+        let task_key = key.clone();
+        let task = tokio::spawn(async move {
+            let response = dispatcher.call(id, params).await;
+            dispatcher.lock().remove(&task_key);
+            if let Err(e) = transport.send(response).await {
+                warn!("Failed to send tool response: {}", e);
+            }
+        });
+        running.insert(key, task.abort_handle());
+    }
+
+    /// Abort the call for a request, returning whether it was still running
+    ///
+    /// Cancelled calls are not answered. Work already handed off to blocking
+    /// threads, such as an Ollama request, still runs to completion.
+    pub fn cancel(&self, request_id: &Value) -> bool {
+        let Some(task) = self.lock().remove(&request_id.to_string()) else {
+            return false;
+        };
+        task.abort();
+        true
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, AbortHandle>> {
+        self.running.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Transport wrapper that runs tool calls concurrently
+///
+/// All other messages are passed through to the server unchanged.
+#[derive(Clone)]
+pub struct ToolTransport<T> {
+    inner: T,
+    dispatcher: Arc<ToolDispatcher>,
+}
+
+impl<T> ToolTransport<T> {
+    /// Wrap a transport so tool calls are answered by `dispatcher`
+    pub fn new(inner: T, dispatcher: Arc<ToolDispatcher>) -> Self {
+        Self { inner, dispatcher }
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone> Transport for ToolTransport<T> {
+    async fn start(&self, message_tx: mpsc::Sender<JsonRpcMessage>) -> Result<()> {
+        let (inner_tx, mut inner_rx) = mpsc::channel(100);
+        self.inner.start(inner_tx).await?;
+
+        let transport = self.inner.clone();
+        let dispatcher = Arc::clone(&self.dispatcher);
+        tokio::spawn(async move {
+            while let Some(message) = inner_rx.recv().await {
+                match message {
+                    JsonRpcMessage::Request {
+                        id, method, params, ..
+                    } if method == methods::TOOLS_CALL => {
+                        dispatcher.spawn(id, params, transport.clone());
+                    }
+                    JsonRpcMessage::Notification { method, params, .. }
+                        if method == CANCELLED_NOTIFICATION =>
+                    {
+                        let request_id = params
+                            .as_ref()
+                            .and_then(|params| params.get("requestId"))
+                            .unwrap_or(&Value::Null);
+                        if dispatcher.cancel(request_id) {
+                            debug!("Cancelled tool call for request {}", request_id);
+                        }
+                    }
+                    message => {
+                        if message_tx.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn send(&self, message: JsonRpcMessage) -> Result<()> {
+        self.inner.send(message).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    fn box_clone(&self) -> Box<dyn Transport> {
+        Box::new(self.clone())
+    }
+}
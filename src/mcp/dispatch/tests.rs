@@ -0,0 +1,313 @@
+use super::*;
+use anyhow::anyhow;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::Barrier;
+
+/// Transport whose incoming messages are fed by the test and whose sent
+/// messages are recorded
+#[derive(Clone)]
+struct TestTransport {
+    incoming: Arc<Mutex<Option<mpsc::Receiver<JsonRpcMessage>>>>,
+    sent: mpsc::UnboundedSender<JsonRpcMessage>,
+}
+
+impl TestTransport {
+    fn new() -> (
+        Self,
+        mpsc::Sender<JsonRpcMessage>,
+        mpsc::UnboundedReceiver<JsonRpcMessage>,
+    ) {
+        let (incoming_tx, incoming_rx) = mpsc::channel(16);
+        let (sent_tx, sent_rx) = mpsc::unbounded_channel();
+        let transport = Self {
+            incoming: Arc::new(Mutex::new(Some(incoming_rx))),
+            sent: sent_tx,
+        };
+        (transport, incoming_tx, sent_rx)
+    }
+}
+
+#[async_trait]
+impl Transport for TestTransport {
+    async fn start(&self, message_tx: mpsc::Sender<JsonRpcMessage>) -> Result<()> {
+        let mut incoming = self
+            .incoming
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .ok_or_else(|| anyhow!("Transport already started"))?;
+        tokio::spawn(async move {
+            while let Some(message) = incoming.recv().await {
+                if message_tx.send(message).await.is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    async fn send(&self, message: JsonRpcMessage) -> Result<()> {
+        self.sent
+            .send(message)
+            .map_err(|_| anyhow!("Transport closed"))
+    }
+
+    async fn close(&self) -> Result<()> {
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Transport> {
+        Box::new(self.clone())
+    }
+}
+
+/// Waits until `barrier` is reached by another call
+struct BarrierHandler {
+    barrier: Arc<Barrier>,
+}
+
+#[async_trait]
+impl ToolHandler for BarrierHandler {
+    async fn handle(&self, _params: CallToolParams) -> Result<ToolCallResult> {
+        self.barrier.wait().await;
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: "done".to_string(),
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Never finishes, counting how many calls were started
+struct HangingHandler {
+    started: Arc<AtomicUsize>,
+}
+
+#[async_trait]
+impl ToolHandler for HangingHandler {
+    async fn handle(&self, _params: CallToolParams) -> Result<ToolCallResult> {
+        self.started.fetch_add(1, Ordering::SeqCst);
+        std::future::pending().await
+    }
+}
+
+struct FailingHandler;
+
+#[async_trait]
+impl ToolHandler for FailingHandler {
+    async fn handle(&self, _params: CallToolParams) -> Result<ToolCallResult> {
+        Err(anyhow!("database is locked"))
+    }
+}
+
+fn tool(name: &str) -> Tool {
+    Tool {
+        name: name.to_string(),
+        description: None,
+        input_schema: json!({ "type": "object" }),
+        annotations: None,
+    }
+}
+
+fn tool_call(id: i64, name: &str) -> JsonRpcMessage {
+    JsonRpcMessage::Request {
+        jsonrpc: "2.0".to_string(),
+        id: json!(id),
+        method: methods::TOOLS_CALL.to_string(),
+        params: tool_call_params(name),
+    }
+}
+
+fn tool_call_params(name: &str) -> Option<Value> {
+    Some(json!({ "name": name, "arguments": {} }))
+}
+
+async fn start_transport(
+    registry: ToolRegistry,
+    timeout: Duration,
+) -> (
+    mpsc::Sender<JsonRpcMessage>,
+    mpsc::Receiver<JsonRpcMessage>,
+    mpsc::UnboundedReceiver<JsonRpcMessage>,
+) {
+    let (inner, client_tx, sent_rx) = TestTransport::new();
+    let dispatcher = Arc::new(ToolDispatcher::new(registry, timeout));
+    let transport = ToolTransport::new(inner, dispatcher);
+
+    let (server_tx, server_rx) = mpsc::channel(16);
+    transport
+        .start(server_tx)
+        .await
+        .expect("transport should start");
+    (client_tx, server_rx, sent_rx)
+}
+
+async fn next_sent(sent_rx: &mut mpsc::UnboundedReceiver<JsonRpcMessage>) -> JsonRpcMessage {
+    tokio::time::timeout(Duration::from_secs(5), sent_rx.recv())
+        .await
+        .expect("response should be sent in time")
+        .expect("transport is open")
+}
+
+#[tokio::test]
+async fn tool_calls_run_concurrently() {
+    let mut registry = ToolRegistry::new();
+    registry.register(
+        tool("wait"),
+        BarrierHandler {
+            barrier: Arc::new(Barrier::new(2)),
+        },
+    );
+    let (client_tx, _server_rx, mut sent_rx) =
+        start_transport(registry, DEFAULT_TOOL_TIMEOUT).await;
+
+    // Each call only finishes once the other one is running
+    client_tx.send(tool_call(1, "wait")).await.expect("send");
+    client_tx.send(tool_call(2, "wait")).await.expect("send");
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        match next_sent(&mut sent_rx).await {
+            JsonRpcMessage::Response { id, result, .. } => {
+                let result = result.expect("call should succeed");
+                assert_eq!(result["content"][0]["text"], "done");
+                ids.push(id);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+    ids.sort_by_key(|id| id.as_i64());
+    assert_eq!(ids, vec![json!(1), json!(2)]);
+}
+
+#[tokio::test]
+async fn tool_calls_time_out() {
+    let mut registry = ToolRegistry::new();
+    registry.register(
+        tool("hang"),
+        HangingHandler {
+            started: Arc::default(),
+        },
+    );
+    let (client_tx, _server_rx, mut sent_rx) =
+        start_transport(registry, Duration::from_millis(50)).await;
+
+    client_tx.send(tool_call(7, "hang")).await.expect("send");
+
+    match next_sent(&mut sent_rx).await {
+        JsonRpcMessage::Response { id, result, .. } => {
+            assert_eq!(id, json!(7));
+            let result = result.expect("timeouts are tool results");
+            assert_eq!(result["isError"], true);
+            assert!(
+                result["content"][0]["text"]
+                    .as_str()
+                    .is_some_and(|text| text.contains("timed out"))
+            );
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn cancelled_calls_are_aborted() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let mut registry = ToolRegistry::new();
+    registry.register(
+        tool("hang"),
+        HangingHandler {
+            started: Arc::clone(&started),
+        },
+    );
+    registry.register(tool("fail"), FailingHandler);
+    let dispatcher = Arc::new(ToolDispatcher::new(registry, DEFAULT_TOOL_TIMEOUT));
+    let (transport, _client_tx, mut sent_rx) = TestTransport::new();
+
+    dispatcher.spawn(json!("a"), tool_call_params("hang"), transport.clone());
+    while started.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    assert!(dispatcher.cancel(&json!("a")));
+    assert!(!dispatcher.cancel(&json!("a")));
+    assert!(!dispatcher.cancel(&json!("unknown")));
+
+    // The cancelled call is never answered, while later calls still are
+    dispatcher.spawn(json!("b"), tool_call_params("fail"), transport);
+    match next_sent(&mut sent_rx).await {
+        JsonRpcMessage::Response { id, error, .. } => {
+            assert_eq!(id, json!("b"));
+            let error = error.expect("handler errors are JSON-RPC errors");
+            assert_eq!(error.code, error_codes::INTERNAL_ERROR);
+            assert!(error.message.contains("database is locked"));
+        }
+        other => panic!("unexpected message: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn cancel_notifications_abort_calls() {
+    let started = Arc::new(AtomicUsize::new(0));
+    let mut registry = ToolRegistry::new();
+    registry.register(
+        tool("hang"),
+        HangingHandler {
+            started: Arc::clone(&started),
+        },
+    );
+    let (inner, client_tx, _sent_rx) = TestTransport::new();
+    let dispatcher = Arc::new(ToolDispatcher::new(registry, DEFAULT_TOOL_TIMEOUT));
+    let transport = ToolTransport::new(inner, Arc::clone(&dispatcher));
+    let (server_tx, mut server_rx) = mpsc::channel(16);
+    transport
+        .start(server_tx)
+        .await
+        .expect("transport should start");
+
+    client_tx.send(tool_call(3, "hang")).await.expect("send");
+    while started.load(Ordering::SeqCst) == 0 {
+        tokio::task::yield_now().await;
+    }
+
+    client_tx
+        .send(JsonRpcMessage::notification(
+            CANCELLED_NOTIFICATION,
+            Some(json!({ "requestId": 3, "reason": "user aborted" })),
+        ))
+        .await
+        .expect("send");
+    client_tx
+        .send(JsonRpcMessage::notification(methods::INITIALIZED, None))
+        .await
+        .expect("send");
+
+    // Neither the call nor the cancellation reaches the server
+    match server_rx.recv().await.expect("message is forwarded") {
+        JsonRpcMessage::Notification { method, .. } => assert_eq!(method, methods::INITIALIZED),
+        other => panic!("unexpected message: {:?}", other),
+    }
+    assert!(!dispatcher.cancel(&json!(3)));
+}
+
+#[tokio::test]
+async fn invalid_tool_calls_are_rejected() {
+    let dispatcher = ToolDispatcher::new(ToolRegistry::new(), DEFAULT_TOOL_TIMEOUT);
+
+    let unknown = dispatcher.call(json!(1), tool_call_params("missing")).await;
+    let missing_params = dispatcher.call(json!(2), None).await;
+
+    for (response, message) in [
+        (unknown, "Unknown tool: missing"),
+        (missing_params, "Missing tool call parameters"),
+    ] {
+        match response {
+            JsonRpcMessage::Response { error, .. } => {
+                let error = error.expect("error response");
+                assert_eq!(error.code, error_codes::INVALID_PARAMS);
+                assert_eq!(error.message, message);
+            }
+            other => panic!("unexpected message: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,839 @@
+//! HTTP Transport
+//!
+//! Serves MCP over HTTP so that a single long-running server can be shared by
+//! many clients. Both the streamable HTTP transport (`/mcp`) and the legacy
+//! HTTP+SSE transport (`/sse` + `/messages`) are supported.
+//!
+//! The MCP server processes a single stream of messages, so request IDs from
+//! each client are rewritten to unique IDs before being forwarded, and restored
+//! when the response is routed back to the client that sent the request.
+//! Cancellation notifications are rewritten to match.
+//!
+//! Legacy SSE sessions end when their event stream is closed. Streamable HTTP
+//! sessions end when the client deletes them, or once they have been idle
+//! without an open event stream for the session timeout.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result, anyhow};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::{StreamExt, stream};
+use http_body_util::{BodyExt, Full, StreamBody, combinators::BoxBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{self, HeaderValue};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use modelcontextprotocol_server::Transport;
+use modelcontextprotocol_server::mcp_protocol::JsonRpcMessage;
+use modelcontextprotocol_server::mcp_protocol::constants::{error_codes, methods};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, Weak};
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::{mpsc, oneshot, watch};
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+use crate::mcp::dispatch::CANCELLED_NOTIFICATION;
+
+/// Path of the streamable HTTP endpoint
+pub const MCP_PATH: &str = "/mcp";
+
+/// Path of the legacy SSE endpoint
+pub const SSE_PATH: &str = "/sse";
+
+/// Path that legacy SSE clients post messages to
+pub const MESSAGES_PATH: &str = "/messages";
+
+/// Header carrying the session ID for the streamable HTTP transport
+const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Interval between keep-alive comments on open event streams
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Default time after which idle streamable HTTP sessions are closed
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Longest interval between checks for idle sessions
+const SESSION_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+type ResponseBody = BoxBody<Bytes, Infallible>;
+
+/// Options for serving MCP over HTTP
+#[derive(Debug, Clone)]
+pub struct HttpServerOptions {
+    /// Address to listen on
+    pub addr: SocketAddr,
+    /// Bearer token that clients must present, if any
+    pub bearer_token: Option<String>,
+    /// Time without requests or an open event stream after which a streamable
+    /// HTTP session is closed
+    pub session_idle_timeout: Duration,
+}
+
+/// MCP transport serving streamable HTTP with a legacy SSE fallback
+#[derive(Clone)]
+pub struct HttpTransport {
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    options: HttpServerOptions,
+    message_tx: Mutex<Option<mpsc::Sender<JsonRpcMessage>>>,
+    local_addr: Mutex<Option<SocketAddr>>,
+    state: Mutex<TransportState>,
+    next_request_id: AtomicU64,
+    shutdown: watch::Sender<bool>,
+}
+
+#[derive(Default)]
+struct TransportState {
+    sessions: HashMap<String, Session>,
+    pending: HashMap<String, PendingRequest>,
+    /// Result of the first successful `initialize`, reused for later clients
+    initialize_result: Option<Value>,
+}
+
+struct Session {
+    /// Open event streams for messages sent outside of a request
+    streams: Vec<mpsc::UnboundedSender<JsonRpcMessage>>,
+    /// When the client last sent a request on this session
+    last_active: Instant,
+}
+
+impl Session {
+    fn new(streams: Vec<mpsc::UnboundedSender<JsonRpcMessage>>) -> Self {
+        Self {
+            streams,
+            last_active: Instant::now(),
+        }
+    }
+
+    /// Whether the session has been idle for `timeout` with no open stream
+    fn is_idle(&self, timeout: Duration) -> bool {
+        self.last_active.elapsed() >= timeout && self.streams.iter().all(|s| s.is_closed())
+    }
+
+    /// Send a message on the first open stream, returning whether it was delivered
+    fn deliver(&mut self, message: JsonRpcMessage) -> bool {
+        self.streams.retain(|stream| !stream.is_closed());
+        self.streams
+            .first()
+            .is_some_and(|stream| stream.send(message).is_ok())
+    }
+}
+
+struct PendingRequest {
+    session_id: String,
+    client_id: Value,
+    method: String,
+    reply: Reply,
+}
+
+enum Reply {
+    /// Respond on the HTTP request that carried the message
+    Direct(oneshot::Sender<JsonRpcMessage>),
+    /// Respond on the session's event stream
+    Stream(String),
+}
+
+/// Outcome of forwarding a single client message
+enum Dispatched {
+    /// The message does not expect a response
+    Accepted,
+    /// The response will arrive on this channel
+    Pending(oneshot::Receiver<JsonRpcMessage>),
+    /// The response is already available
+    Immediate(JsonRpcMessage),
+}
+
+impl HttpTransport {
+    /// Create a new HTTP transport
+    pub fn new(options: HttpServerOptions) -> Self {
+        let (shutdown, _) = watch::channel(false);
+        Self {
+            shared: Arc::new(Shared {
+                options,
+                message_tx: Mutex::new(None),
+                local_addr: Mutex::new(None),
+                state: Mutex::new(TransportState::default()),
+                next_request_id: AtomicU64::new(1),
+                shutdown,
+            }),
+        }
+    }
+
+    /// Address the transport is listening on, once started
+    #[cfg(test)]
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        *lock(&self.shared.local_addr)
+    }
+}
+
+#[async_trait]
+impl Transport for HttpTransport {
+    async fn start(&self, message_tx: mpsc::Sender<JsonRpcMessage>) -> Result<()> {
+        *lock(&self.shared.message_tx) = Some(message_tx);
+
+        let listener = TcpListener::bind(self.shared.options.addr)
+            .await
+            .with_context(|| format!("Failed to bind to {}", self.shared.options.addr))?;
+        let local_addr = listener.local_addr()?;
+        *lock(&self.shared.local_addr) = Some(local_addr);
+        info!("MCP HTTP transport listening on http://{}", local_addr);
+
+        let shared = Arc::clone(&self.shared);
+        let mut shutdown = self.shared.shutdown.subscribe();
+        let mut sweep = tokio::time::interval(
+            self.shared
+                .options
+                .session_idle_timeout
+                .clamp(Duration::from_secs(1), SESSION_SWEEP_INTERVAL),
+        );
+        sweep.reset();
+        tokio::spawn(async move {
+            loop {
+                tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, peer)) => {
+                            debug!("Accepted HTTP connection from {}", peer);
+                            let shared = Arc::clone(&shared);
+                            tokio::spawn(async move {
+                                let service = service_fn(move |request| {
+                                    let shared = Arc::clone(&shared);
+                                    async move { Ok::<_, Infallible>(shared.handle(request).await) }
+                                });
+                                if let Err(e) = http1::Builder::new()
+                                    .serve_connection(TokioIo::new(stream), service)
+                                    .await
+                                {
+                                    debug!("HTTP connection from {} closed with error: {}", peer, e);
+                                }
+                            });
+                        }
+                        Err(e) => warn!("Failed to accept HTTP connection: {}", e),
+                    },
+                    _ = sweep.tick() => shared.expire_idle_sessions(),
+                    _ = shutdown.changed() => break,
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn send(&self, message: JsonRpcMessage) -> Result<()> {
+        self.shared.route_outgoing(message);
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.shared.shutdown.send_replace(true);
+        let mut state = lock(&self.shared.state);
+        state.sessions.clear();
+        state.pending.clear();
+        Ok(())
+    }
+
+    fn box_clone(&self) -> Box<dyn Transport> {
+        Box::new(self.clone())
+    }
+}
+
+impl Shared {
+    /// Handle a single HTTP request
+    async fn handle(self: &Arc<Self>, request: Request<Incoming>) -> Response<ResponseBody> {
+        if let Some(origin) = request.headers().get(header::ORIGIN) {
+            // Without a token, only local pages may talk to the server (DNS rebinding protection)
+            if self.options.bearer_token.is_none() && !origin.to_str().is_ok_and(is_local_origin) {
+                return text_response(StatusCode::FORBIDDEN, "Origin not allowed");
+            }
+        }
+
+        if let Some(token) = &self.options.bearer_token {
+            let authorized = request
+                .headers()
+                .get(header::AUTHORIZATION)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.strip_prefix("Bearer "))
+                .is_some_and(|provided| tokens_match(provided.trim(), token));
+            if !authorized {
+                let mut response = text_response(StatusCode::UNAUTHORIZED, "Unauthorized");
+                response
+                    .headers_mut()
+                    .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+                return response;
+            }
+        }
+
+        let path = request.uri().path().to_string();
+        match (request.method().clone(), path.as_str()) {
+            (Method::POST, MCP_PATH) => self.handle_mcp_post(request).await,
+            (Method::GET, MCP_PATH) => self.handle_mcp_get(&request),
+            (Method::DELETE, MCP_PATH) => self.handle_mcp_delete(&request),
+            (Method::GET, SSE_PATH) => self.handle_sse_connect(),
+            (Method::POST, MESSAGES_PATH) => self.handle_sse_post(request).await,
+            (_, MCP_PATH | SSE_PATH | MESSAGES_PATH) => {
+                text_response(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed")
+            }
+            _ => text_response(StatusCode::NOT_FOUND, "Not found"),
+        }
+    }
+
+    /// Streamable HTTP: client messages posted to the MCP endpoint
+    async fn handle_mcp_post(&self, request: Request<Incoming>) -> Response<ResponseBody> {
+        let session_id = session_header(&request);
+        let (messages, is_batch) = match read_messages(request).await {
+            Ok(parsed) => parsed,
+            Err(response) => return response,
+        };
+
+        let is_initialize = messages.iter().any(|message| {
+            matches!(message, JsonRpcMessage::Request { method, .. } if method == methods::INITIALIZE)
+        });
+
+        let session_id = match session_id {
+            Some(id) if self.touch_session(&id) => id,
+            Some(_) => return text_response(StatusCode::NOT_FOUND, "Unknown session"),
+            None if is_initialize => {
+                let id = Uuid::new_v4().to_string();
+                lock(&self.state)
+                    .sessions
+                    .insert(id.clone(), Session::new(Vec::new()));
+                debug!("Created MCP session {}", id);
+                id
+            }
+            None => {
+                return text_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+            }
+        };
+
+        let mut dispatched = Vec::with_capacity(messages.len());
+        for message in messages {
+            match self.dispatch(message, &session_id, false).await {
+                Ok(outcome) => dispatched.push(outcome),
+                Err(e) => {
+                    return text_response(StatusCode::SERVICE_UNAVAILABLE, &e.to_string());
+                }
+            }
+        }
+
+        let mut responses = Vec::new();
+        for outcome in dispatched {
+            match outcome {
+                Dispatched::Accepted => {}
+                Dispatched::Immediate(response) => responses.push(response),
+                Dispatched::Pending(receiver) => match receiver.await {
+                    Ok(response) => responses.push(response),
+                    Err(_) => {
+                        return text_response(
+                            StatusCode::SERVICE_UNAVAILABLE,
+                            "Server closed before responding",
+                        );
+                    }
+                },
+            }
+        }
+
+        let mut response = if responses.is_empty() {
+            empty_response(StatusCode::ACCEPTED)
+        } else {
+            let body = if is_batch {
+                serde_json::to_vec(&responses)
+            } else {
+                serde_json::to_vec(&responses[0])
+            };
+            match body {
+                Ok(body) => json_response(StatusCode::OK, body),
+                Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+            }
+        };
+
+        if let Ok(value) = HeaderValue::from_str(&session_id) {
+            response.headers_mut().insert(SESSION_HEADER, value);
+        }
+        response
+    }
+
+    /// Streamable HTTP: open an event stream for server-initiated messages
+    fn handle_mcp_get(&self, request: &Request<Incoming>) -> Response<ResponseBody> {
+        let accepts_events = request
+            .headers()
+            .get(header::ACCEPT)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("text/event-stream"));
+        if !accepts_events {
+            return text_response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "GET requires Accept: text/event-stream",
+            );
+        }
+
+        let Some(session_id) = session_header(request) else {
+            return text_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+        };
+
+        let (stream_tx, stream_rx) = mpsc::unbounded_channel();
+        match lock(&self.state).sessions.get_mut(&session_id) {
+            Some(session) => {
+                session.streams.push(stream_tx);
+                session.last_active = Instant::now();
+            }
+            None => return text_response(StatusCode::NOT_FOUND, "Unknown session"),
+        }
+
+        event_stream_response(None, stream_rx, None)
+    }
+
+    /// Streamable HTTP: terminate a session
+    fn handle_mcp_delete(&self, request: &Request<Incoming>) -> Response<ResponseBody> {
+        let Some(session_id) = session_header(request) else {
+            return text_response(StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header");
+        };
+
+        if self.remove_session(&session_id) {
+            debug!("Closed MCP session {}", session_id);
+            empty_response(StatusCode::OK)
+        } else {
+            text_response(StatusCode::NOT_FOUND, "Unknown session")
+        }
+    }
+
+    /// Legacy SSE: open the event stream and announce the message endpoint
+    ///
+    /// The session lasts as long as the event stream.
+    fn handle_sse_connect(self: &Arc<Self>) -> Response<ResponseBody> {
+        let session_id = Uuid::new_v4().to_string();
+        let (stream_tx, stream_rx) = mpsc::unbounded_channel();
+        lock(&self.state)
+            .sessions
+            .insert(session_id.clone(), Session::new(vec![stream_tx]));
+        debug!("Created legacy SSE session {}", session_id);
+
+        let endpoint = format!("{}?sessionId={}", MESSAGES_PATH, session_id);
+        let guard = SessionGuard {
+            shared: Arc::downgrade(self),
+            session_id,
+        };
+        event_stream_response(
+            Some(sse_event("endpoint", &endpoint)),
+            stream_rx,
+            Some(guard),
+        )
+    }
+
+    /// Legacy SSE: client messages, answered on the session's event stream
+    async fn handle_sse_post(&self, request: Request<Incoming>) -> Response<ResponseBody> {
+        let session_id = request.uri().query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "sessionId")
+                .map(|(_, value)| value.into_owned())
+        });
+        let Some(session_id) = session_id else {
+            return text_response(StatusCode::BAD_REQUEST, "Missing sessionId");
+        };
+        if !self.touch_session(&session_id) {
+            return text_response(StatusCode::NOT_FOUND, "Unknown session");
+        }
+
+        let (messages, _) = match read_messages(request).await {
+            Ok(parsed) => parsed,
+            Err(response) => return response,
+        };
+
+        for message in messages {
+            match self.dispatch(message, &session_id, true).await {
+                Ok(Dispatched::Immediate(response)) => {
+                    if let Some(session) = lock(&self.state).sessions.get_mut(&session_id) {
+                        session.deliver(response);
+                    }
+                }
+                Ok(Dispatched::Accepted | Dispatched::Pending(_)) => {}
+                Err(e) => {
+                    return text_response(StatusCode::SERVICE_UNAVAILABLE, &e.to_string());
+                }
+            }
+        }
+
+        text_response(StatusCode::ACCEPTED, "Accepted")
+    }
+
+    /// Forward a client message from a session to the server
+    ///
+    /// Responses are returned directly unless `stream` is set, in which case
+    /// they are delivered on the session's event stream.
+    async fn dispatch(
+        &self,
+        message: JsonRpcMessage,
+        session_id: &str,
+        stream: bool,
+    ) -> Result<Dispatched> {
+        let (message, outcome) = match message {
+            JsonRpcMessage::Request {
+                jsonrpc,
+                id,
+                method,
+                params,
+            } => {
+                if method == methods::INITIALIZE {
+                    // The server tracks a single lifecycle, so later clients are
+                    // answered from the first successful initialization
+                    let cached = lock(&self.state).initialize_result.clone();
+                    if let Some(result) = cached {
+                        return Ok(Dispatched::Immediate(JsonRpcMessage::response(id, result)));
+                    }
+                }
+
+                let request_id = format!(
+                    "http-{}",
+                    self.next_request_id.fetch_add(1, Ordering::Relaxed)
+                );
+                let (reply, outcome) = if stream {
+                    (Reply::Stream(session_id.to_string()), Dispatched::Accepted)
+                } else {
+                    let (response_tx, response_rx) = oneshot::channel();
+                    (Reply::Direct(response_tx), Dispatched::Pending(response_rx))
+                };
+                lock(&self.state).pending.insert(
+                    request_id.clone(),
+                    PendingRequest {
+                        session_id: session_id.to_string(),
+                        client_id: id,
+                        method: method.clone(),
+                        reply,
+                    },
+                );
+
+                let message = JsonRpcMessage::Request {
+                    jsonrpc,
+                    id: Value::String(request_id),
+                    method,
+                    params,
+                };
+                (message, outcome)
+            }
+            JsonRpcMessage::Notification {
+                jsonrpc,
+                method,
+                params: Some(mut params),
+            } if method == CANCELLED_NOTIFICATION => {
+                // Point the cancellation at the forwarded request ID. The server
+                // doesn't answer cancelled requests, so stop waiting for one.
+                let client_id = params.get("requestId").cloned().unwrap_or(Value::Null);
+                let request_id = {
+                    let mut state = lock(&self.state);
+                    let request_id = state
+                        .pending
+                        .iter()
+                        .find(|(_, pending)| {
+                            pending.session_id == session_id && pending.client_id == client_id
+                        })
+                        .map(|(request_id, _)| request_id.clone());
+                    if let Some(ref request_id) = request_id {
+                        state.pending.remove(request_id);
+                    }
+                    request_id
+                };
+                let Some(request_id) = request_id else {
+                    debug!("Ignoring cancellation of unknown request {}", client_id);
+                    return Ok(Dispatched::Accepted);
+                };
+                if let Some(params) = params.as_object_mut() {
+                    params.insert("requestId".to_string(), Value::String(request_id));
+                }
+
+                let message = JsonRpcMessage::Notification {
+                    jsonrpc,
+                    method,
+                    params: Some(params),
+                };
+                (message, Dispatched::Accepted)
+            }
+            other => (other, Dispatched::Accepted),
+        };
+
+        let message_tx = lock(&self.message_tx)
+            .clone()
+            .ok_or_else(|| anyhow!("Transport has not been started"))?;
+        message_tx
+            .send(message)
+            .await
+            .map_err(|_| anyhow!("MCP server is shutting down"))?;
+
+        Ok(outcome)
+    }
+
+    /// Route a message from the server to the client it is meant for
+    fn route_outgoing(&self, message: JsonRpcMessage) {
+        let mut state = lock(&self.state);
+
+        match message {
+            JsonRpcMessage::Response {
+                jsonrpc,
+                id,
+                result,
+                error,
+            } => {
+                let Some(pending) = id.as_str().and_then(|id| state.pending.remove(id)) else {
+                    warn!("Dropping response for unknown request {}", id);
+                    return;
+                };
+
+                if pending.method == methods::INITIALIZE && state.initialize_result.is_none() {
+                    state.initialize_result.clone_from(&result);
+                }
+
+                let response = JsonRpcMessage::Response {
+                    jsonrpc,
+                    id: pending.client_id,
+                    result,
+                    error,
+                };
+                match pending.reply {
+                    Reply::Direct(response_tx) => {
+                        // The client may have disconnected while waiting
+                        let _ = response_tx.send(response);
+                    }
+                    Reply::Stream(session_id) => {
+                        let delivered = state
+                            .sessions
+                            .get_mut(&session_id)
+                            .is_some_and(|session| session.deliver(response));
+                        if !delivered {
+                            debug!("No open stream for session {}", session_id);
+                        }
+                    }
+                }
+            }
+            // Notifications and server requests go to every open stream
+            message => {
+                for session in state.sessions.values_mut() {
+                    session
+                        .streams
+                        .retain(|stream| stream.send(message.clone()).is_ok());
+                }
+            }
+        }
+    }
+
+    /// Record activity on a session, returning whether it exists
+    fn touch_session(&self, session_id: &str) -> bool {
+        lock(&self.state)
+            .sessions
+            .get_mut(session_id)
+            .map(|session| session.last_active = Instant::now())
+            .is_some()
+    }
+
+    /// Close sessions idle for longer than the session timeout
+    fn expire_idle_sessions(&self) {
+        let timeout = self.options.session_idle_timeout;
+        let idle: Vec<String> = lock(&self.state)
+            .sessions
+            .iter()
+            .filter(|(_, session)| session.is_idle(timeout))
+            .map(|(session_id, _)| session_id.clone())
+            .collect();
+        for session_id in idle {
+            if self.remove_session(&session_id) {
+                debug!("Expired idle MCP session {}", session_id);
+            }
+        }
+    }
+
+    fn remove_session(&self, session_id: &str) -> bool {
+        let mut state = lock(&self.state);
+        let removed = state.sessions.remove(session_id).is_some();
+        state
+            .pending
+            .retain(|_, pending| !matches!(&pending.reply, Reply::Stream(id) if id == session_id));
+        removed
+    }
+}
+
+/// Removes a session when the event stream holding it is dropped
+struct SessionGuard {
+    shared: Weak<Shared>,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let Some(shared) = self.shared.upgrade() else {
+            return;
+        };
+        if shared.remove_session(&self.session_id) {
+            debug!("Closed legacy SSE session {}", self.session_id);
+        }
+    }
+}
+
+/// Lock a mutex, recovering the data if a previous holder panicked
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+}
+
+fn session_header(request: &Request<Incoming>) -> Option<String> {
+    request
+        .headers()
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(ToString::to_string)
+}
+
+/// Read a JSON-RPC message or batch of messages from a request body
+async fn read_messages(
+    request: Request<Incoming>,
+) -> std::result::Result<(Vec<JsonRpcMessage>, bool), Response<ResponseBody>> {
+    let body = match request.into_body().collect().await {
+        Ok(collected) => collected.to_bytes(),
+        Err(e) => return Err(text_response(StatusCode::BAD_REQUEST, &e.to_string())),
+    };
+
+    let parsed = serde_json::from_slice::<Value>(&body).and_then(|value| match value {
+        Value::Array(values) => values
+            .into_iter()
+            .map(serde_json::from_value)
+            .collect::<serde_json::Result<Vec<_>>>()
+            .map(|messages| (messages, true)),
+        value => serde_json::from_value(value).map(|message| (vec![message], false)),
+    });
+
+    match parsed {
+        Ok((messages, _)) if messages.is_empty() => Err(parse_error_response("Empty batch")),
+        Ok(parsed) => Ok(parsed),
+        Err(e) => Err(parse_error_response(&e.to_string())),
+    }
+}
+
+/// Whether an `Origin` header refers to the local machine
+pub(crate) fn is_local_origin(origin: &str) -> bool {
+    let Ok(url) = url::Url::parse(origin) else {
+        return false;
+    };
+    match url.host() {
+        Some(url::Host::Domain(domain)) => domain == "localhost",
+        Some(url::Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(url::Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
+
+/// Compare tokens without exiting early on the first mismatch
+pub(crate) fn tokens_match(provided: &str, expected: &str) -> bool {
+    provided.len() == expected.len()
+        && provided
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+/// Format a server-sent event
+fn sse_event(event: &str, data: &str) -> Bytes {
+    let mut formatted = format!("event: {}\n", event);
+    for line in data.lines() {
+        formatted.push_str("data: ");
+        formatted.push_str(line);
+        formatted.push('\n');
+    }
+    formatted.push('\n');
+    Bytes::from(formatted)
+}
+
+/// Stream messages as server-sent events, holding `guard` until the stream
+/// is dropped
+fn event_stream_response(
+    first_event: Option<Bytes>,
+    messages: mpsc::UnboundedReceiver<JsonRpcMessage>,
+    guard: Option<SessionGuard>,
+) -> Response<ResponseBody> {
+    let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+    keep_alive.reset();
+
+    let events = stream::unfold(
+        (messages, keep_alive, guard),
+        |(mut messages, mut keep_alive, guard)| async move {
+            let event = tokio::select! {
+                message = messages.recv() => {
+                    let message = message?;
+                    match serde_json::to_string(&message) {
+                        Ok(json) => sse_event("message", &json),
+                        Err(e) => {
+                            warn!("Failed to serialize message for event stream: {}", e);
+                            Bytes::new()
+                        }
+                    }
+                }
+                _ = keep_alive.tick() => Bytes::from_static(b": keep-alive\n\n"),
+            };
+            Some((event, (messages, keep_alive, guard)))
+        },
+    );
+
+    let frames = stream::iter(first_event)
+        .chain(events)
+        .map(|event| Ok::<_, Infallible>(Frame::data(event)));
+
+    let mut response = Response::new(BodyExt::boxed(StreamBody::new(frames)));
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/event-stream"),
+    );
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+fn full_body(body: impl Into<Bytes>) -> ResponseBody {
+    Full::new(body.into()).boxed()
+}
+
+fn empty_response(status: StatusCode) -> Response<ResponseBody> {
+    let mut response = Response::new(full_body(Bytes::new()));
+    *response.status_mut() = status;
+    response
+}
+
+fn text_response(status: StatusCode, text: &str) -> Response<ResponseBody> {
+    let mut response = Response::new(full_body(text.to_string()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("text/plain; charset=utf-8"),
+    );
+    response
+}
+
+fn json_response(status: StatusCode, body: Vec<u8>) -> Response<ResponseBody> {
+    let mut response = Response::new(full_body(body));
+    *response.status_mut() = status;
+    response.headers_mut().insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    response
+}
+
+fn parse_error_response(message: &str) -> Response<ResponseBody> {
+    let error = JsonRpcMessage::error(
+        Value::Null,
+        error_codes::PARSE_ERROR,
+        &format!("Invalid JSON-RPC message: {}", message),
+        None,
+    );
+    serde_json::to_vec(&error).map_or_else(
+        |_| text_response(StatusCode::BAD_REQUEST, message),
+        |body| json_response(StatusCode::BAD_REQUEST, body),
+    )
+}
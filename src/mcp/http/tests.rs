@@ -0,0 +1,465 @@
+use super::*;
+use serde_json::json;
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::AtomicUsize;
+
+/// Start a transport on an ephemeral port, backed by a fake server that echoes
+/// each request's method and params. Returns the base URL and the number of
+/// `initialize` requests the fake server has seen.
+async fn start_transport(bearer_token: Option<&str>) -> (HttpTransport, String, Arc<AtomicUsize>) {
+    start_transport_with(HttpServerOptions {
+        addr: "127.0.0.1:0".parse().expect("valid address"),
+        bearer_token: bearer_token.map(ToString::to_string),
+        session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+    })
+    .await
+}
+
+async fn start_transport_with(
+    options: HttpServerOptions,
+) -> (HttpTransport, String, Arc<AtomicUsize>) {
+    let transport = HttpTransport::new(options);
+
+    let (message_tx, mut message_rx) = mpsc::channel(16);
+    transport
+        .start(message_tx)
+        .await
+        .expect("transport should start");
+
+    let initialize_count = Arc::new(AtomicUsize::new(0));
+    let server_transport = transport.clone();
+    let server_initialize_count = Arc::clone(&initialize_count);
+    tokio::spawn(async move {
+        while let Some(message) = message_rx.recv().await {
+            if let JsonRpcMessage::Request {
+                id, method, params, ..
+            } = message
+            {
+                if method == methods::INITIALIZE {
+                    server_initialize_count.fetch_add(1, Ordering::SeqCst);
+                }
+                let result = json!({ "method": method, "params": params });
+                server_transport
+                    .send(JsonRpcMessage::response(id, result))
+                    .await
+                    .expect("send should succeed");
+            }
+        }
+    });
+
+    let addr = transport.local_addr().expect("transport is listening");
+    (transport, format!("http://{}", addr), initialize_count)
+}
+
+struct HttpReply {
+    status: u16,
+    session_id: Option<String>,
+    body: String,
+}
+
+async fn post(url: String, body: Value, headers: Vec<(&'static str, String)>) -> HttpReply {
+    tokio::task::spawn_blocking(move || {
+        let agent: ureq::Agent = ureq::Agent::config_builder()
+            .http_status_as_error(false)
+            .build()
+            .into();
+        let mut request = agent
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .header("Accept", "application/json, text/event-stream");
+        for (name, value) in headers {
+            request = request.header(name, &value);
+        }
+        let mut response = request
+            .send(body.to_string().as_str())
+            .expect("request should complete");
+        HttpReply {
+            status: response.status().as_u16(),
+            session_id: response
+                .headers()
+                .get(SESSION_HEADER)
+                .and_then(|v| v.to_str().ok())
+                .map(ToString::to_string),
+            body: response
+                .body_mut()
+                .read_to_string()
+                .expect("body should be readable"),
+        }
+    })
+    .await
+    .expect("request task should complete")
+}
+
+fn initialize_request(id: i64) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "method": "initialize",
+        "params": {
+            "protocolVersion": "2025-06-18",
+            "capabilities": {},
+            "clientInfo": { "name": "test", "version": "1.0" }
+        }
+    })
+}
+
+#[tokio::test]
+async fn streamable_http_round_trip_preserves_request_ids() {
+    let (_transport, base_url, _) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let reply = post(url.clone(), initialize_request(1), vec![]).await;
+    assert_eq!(reply.status, 200);
+    let session_id = reply
+        .session_id
+        .expect("initialize should create a session");
+
+    let response: Value = serde_json::from_str(&reply.body).expect("valid JSON");
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["method"], "initialize");
+
+    let reply = post(
+        url.clone(),
+        json!({ "jsonrpc": "2.0", "id": "abc", "method": "tools/list" }),
+        vec![(SESSION_HEADER, session_id.clone())],
+    )
+    .await;
+    assert_eq!(reply.status, 200);
+    let response: Value = serde_json::from_str(&reply.body).expect("valid JSON");
+    assert_eq!(response["id"], "abc");
+    assert_eq!(response["result"]["method"], "tools/list");
+
+    let reply = post(
+        url,
+        json!({ "jsonrpc": "2.0", "method": "notifications/initialized" }),
+        vec![(SESSION_HEADER, session_id)],
+    )
+    .await;
+    assert_eq!(reply.status, 202);
+    assert!(reply.body.is_empty());
+}
+
+#[tokio::test]
+async fn streamable_http_batch_returns_array() {
+    let (_transport, base_url, _) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let reply = post(url.clone(), initialize_request(1), vec![]).await;
+    let session_id = reply
+        .session_id
+        .expect("initialize should create a session");
+
+    let reply = post(
+        url,
+        json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "tools/list" },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": 2, "method": "resources/list" }
+        ]),
+        vec![(SESSION_HEADER, session_id)],
+    )
+    .await;
+    assert_eq!(reply.status, 200);
+
+    let responses: Vec<Value> = serde_json::from_str(&reply.body).expect("valid JSON array");
+    assert_eq!(responses.len(), 2);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[1]["id"], 2);
+    assert_eq!(responses[1]["result"]["method"], "resources/list");
+}
+
+#[tokio::test]
+async fn later_clients_are_initialized_from_cache() {
+    let (_transport, base_url, initialize_count) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let first = post(url.clone(), initialize_request(1), vec![]).await;
+    let second = post(url, initialize_request(7), vec![]).await;
+
+    assert_eq!(first.status, 200);
+    assert_eq!(second.status, 200);
+    assert_ne!(first.session_id, second.session_id);
+
+    let response: Value = serde_json::from_str(&second.body).expect("valid JSON");
+    assert_eq!(response["id"], 7);
+    assert_eq!(response["result"]["method"], "initialize");
+    assert_eq!(initialize_count.load(Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn streamable_http_requires_known_session() {
+    let (_transport, base_url, _) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+    let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" });
+
+    let missing = post(url.clone(), request.clone(), vec![]).await;
+    assert_eq!(missing.status, 400);
+
+    let unknown = post(url, request, vec![(SESSION_HEADER, "nope".to_string())]).await;
+    assert_eq!(unknown.status, 404);
+}
+
+#[tokio::test]
+async fn invalid_json_returns_parse_error() {
+    let (_transport, base_url, _) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let reply = post(url, json!({ "not": "json-rpc" }), vec![]).await;
+    assert_eq!(reply.status, 400);
+
+    let response: Value = serde_json::from_str(&reply.body).expect("valid JSON");
+    assert_eq!(response["error"]["code"], error_codes::PARSE_ERROR);
+}
+
+#[tokio::test]
+async fn bearer_token_is_required_when_configured() {
+    let (_transport, base_url, _) = start_transport(Some("secret")).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let missing = post(url.clone(), initialize_request(1), vec![]).await;
+    assert_eq!(missing.status, 401);
+
+    let wrong = post(
+        url.clone(),
+        initialize_request(1),
+        vec![("Authorization", "Bearer wrong".to_string())],
+    )
+    .await;
+    assert_eq!(wrong.status, 401);
+
+    let authorized = post(
+        url,
+        initialize_request(1),
+        vec![("Authorization", "Bearer secret".to_string())],
+    )
+    .await;
+    assert_eq!(authorized.status, 200);
+}
+
+#[tokio::test]
+async fn remote_origins_are_rejected_without_token() {
+    let (_transport, base_url, _) = start_transport(None).await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let remote = post(
+        url.clone(),
+        initialize_request(1),
+        vec![("Origin", "https://evil.example".to_string())],
+    )
+    .await;
+    assert_eq!(remote.status, 403);
+
+    let local = post(
+        url,
+        initialize_request(1),
+        vec![("Origin", "http://localhost:3000".to_string())],
+    )
+    .await;
+    assert_eq!(local.status, 200);
+}
+
+#[tokio::test]
+async fn legacy_sse_delivers_responses_on_event_stream() {
+    let (_transport, base_url, _) = start_transport(None).await;
+
+    let sse_url = format!("{}{}", base_url, SSE_PATH);
+    let (endpoint_tx, endpoint_rx) = oneshot::channel::<String>();
+    let events = tokio::task::spawn_blocking(move || {
+        let mut response = ureq::get(&sse_url)
+            .header("Accept", "text/event-stream")
+            .call()
+            .expect("SSE connection should open");
+        let mut lines = BufReader::new(response.body_mut().as_reader()).lines();
+
+        let mut read_event = || {
+            let mut event = Vec::new();
+            for line in lines.by_ref() {
+                let line = line.expect("stream should be readable");
+                if line.is_empty() {
+                    break;
+                }
+                event.push(line);
+            }
+            event
+        };
+
+        let endpoint = read_event();
+        let data = endpoint
+            .iter()
+            .find_map(|line| line.strip_prefix("data: "))
+            .expect("endpoint event has data")
+            .to_string();
+        endpoint_tx.send(data).expect("endpoint should be received");
+
+        read_event()
+    });
+
+    let endpoint = endpoint_rx.await.expect("endpoint event should arrive");
+    assert!(endpoint.starts_with(MESSAGES_PATH));
+
+    let reply = post(
+        format!("{}{}", base_url, endpoint),
+        json!({ "jsonrpc": "2.0", "id": 42, "method": "tools/list" }),
+        vec![],
+    )
+    .await;
+    assert_eq!(reply.status, 202);
+
+    let event = events.await.expect("event task should complete");
+    assert_eq!(event[0], "event: message");
+    let data = event[1].strip_prefix("data: ").expect("event has data");
+    let response: Value = serde_json::from_str(data).expect("valid JSON");
+    assert_eq!(response["id"], 42);
+    assert_eq!(response["result"]["method"], "tools/list");
+}
+
+#[tokio::test]
+async fn legacy_sse_sessions_end_when_the_stream_closes() {
+    let (transport, base_url, _) = start_transport(None).await;
+
+    let sse_url = format!("{}{}", base_url, SSE_PATH);
+    let connected = transport.clone();
+    tokio::task::spawn_blocking(move || {
+        let mut response = ureq::get(&sse_url)
+            .header("Accept", "text/event-stream")
+            .call()
+            .expect("SSE connection should open");
+        let mut lines = BufReader::new(response.body_mut().as_reader()).lines();
+        let endpoint = lines
+            .next()
+            .expect("endpoint event should arrive")
+            .expect("stream should be readable");
+        assert_eq!(endpoint, "event: endpoint");
+        assert_eq!(lock(&connected.shared.state).sessions.len(), 1);
+    })
+    .await
+    .expect("SSE task should complete");
+
+    // The connection was dropped with the response
+    for _ in 0..50 {
+        if lock(&transport.shared.state).sessions.is_empty() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("SSE session should be removed after the client disconnects");
+}
+
+#[tokio::test]
+async fn idle_streamable_sessions_expire() {
+    let (_transport, base_url, _) = start_transport_with(HttpServerOptions {
+        addr: "127.0.0.1:0".parse().expect("valid address"),
+        bearer_token: None,
+        session_idle_timeout: Duration::from_secs(2),
+    })
+    .await;
+    let url = format!("{}{}", base_url, MCP_PATH);
+
+    let init = post(url.clone(), initialize_request(1), vec![]).await;
+    let session_id = init.session_id.expect("session ID should be returned");
+    let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+
+    // Requests keep the session alive
+    for _ in 0..3 {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        let reply = post(
+            url.clone(),
+            list.clone(),
+            vec![(SESSION_HEADER, session_id.clone())],
+        )
+        .await;
+        assert_eq!(reply.status, 200);
+    }
+
+    tokio::time::sleep(Duration::from_millis(4500)).await;
+    let reply = post(url, list, vec![(SESSION_HEADER, session_id)]).await;
+    assert_eq!(reply.status, 404);
+}
+
+#[tokio::test]
+async fn cancellations_are_rewritten_to_forwarded_ids() {
+    let transport = HttpTransport::new(HttpServerOptions {
+        addr: "127.0.0.1:0".parse().expect("valid address"),
+        bearer_token: None,
+        session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+    });
+    let (message_tx, mut message_rx) = mpsc::channel(16);
+    *lock(&transport.shared.message_tx) = Some(message_tx);
+
+    let request = JsonRpcMessage::Request {
+        jsonrpc: "2.0".to_string(),
+        id: json!(5),
+        method: methods::TOOLS_CALL.to_string(),
+        params: Some(json!({ "name": "search_docs" })),
+    };
+    let Ok(Dispatched::Pending(response_rx)) =
+        transport.shared.dispatch(request, "session-a", false).await
+    else {
+        panic!("request should wait for a response");
+    };
+    let Some(JsonRpcMessage::Request { id: forwarded, .. }) = message_rx.recv().await else {
+        panic!("request should be forwarded");
+    };
+
+    let cancel = |reason| {
+        JsonRpcMessage::notification(
+            CANCELLED_NOTIFICATION,
+            Some(json!({ "requestId": 5, "reason": reason })),
+        )
+    };
+
+    // Another session can't cancel the request
+    transport
+        .shared
+        .dispatch(cancel("other session"), "session-b", false)
+        .await
+        .expect("notification should be accepted");
+    assert!(message_rx.try_recv().is_err());
+
+    transport
+        .shared
+        .dispatch(cancel("user aborted"), "session-a", false)
+        .await
+        .expect("notification should be accepted");
+    let Some(JsonRpcMessage::Notification { method, params, .. }) = message_rx.recv().await else {
+        panic!("cancellation should be forwarded");
+    };
+    assert_eq!(method, CANCELLED_NOTIFICATION);
+    let params = params.expect("cancellation has params");
+    assert_eq!(params["requestId"], forwarded);
+    assert_eq!(params["reason"], "user aborted");
+
+    // The cancelled request is no longer waiting for a response
+    assert!(response_rx.await.is_err());
+    assert!(lock(&transport.shared.state).pending.is_empty());
+}
+
+#[test]
+fn local_origin_detection() {
+    assert!(is_local_origin("http://localhost:3000"));
+    assert!(is_local_origin("http://127.0.0.1:8090"));
+    assert!(is_local_origin("http://[::1]"));
+    assert!(!is_local_origin("https://example.com"));
+    assert!(!is_local_origin("http://localhost.example.com"));
+    assert!(!is_local_origin("null"));
+}
+
+#[test]
+fn token_comparison() {
+    assert!(tokens_match("secret", "secret"));
+    assert!(!tokens_match("secret", "secreT"));
+    assert!(!tokens_match("secret", "secret2"));
+    assert!(!tokens_match("", "secret"));
+}
+
+#[test]
+fn sse_event_formatting() {
+    assert_eq!(
+        sse_event("message", "{\"a\":1}"),
+        Bytes::from_static(b"event: message\ndata: {\"a\":1}\n\n")
+    );
+    assert_eq!(
+        sse_event("message", "a\nb"),
+        Bytes::from_static(b"event: message\ndata: a\ndata: b\n\n")
+    );
+}
@@ -0,0 +1,116 @@
+//! Hybrid Search
+//!
+//! Vector search misses exact identifiers such as `tokio::select!` or error
+//! codes, while keyword search misses paraphrases. Hybrid search runs both and
+//! merges the rankings with reciprocal rank fusion, which only looks at the
+//! position of each hit, so the incomparable BM25 and vector scores never have
+//! to be combined.
+
+use std::collections::HashMap;
+
+use crate::database::lancedb::ChunkMetadata;
+use crate::database::lancedb::vector_store::SearchResult;
+use crate::database::sqlite::models::ChunkMatch;
+use crate::embeddings::chunking::estimate_token_count;
+
+/// Rank constant of reciprocal rank fusion, damping the weight of top ranks
+pub const RRF_K: f32 = 60.0;
+
+/// How search_docs finds matching chunks
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchMode {
+    /// Vector similarity of the query embedding
+    Semantic,
+    /// BM25 full-text search
+    Keyword,
+    /// Both, merged with reciprocal rank fusion
+    #[default]
+    Hybrid,
+}
+
+impl SearchMode {
+    /// Names accepted by [`SearchMode::parse`]
+    pub const NAMES: [&str; 3] = ["semantic", "keyword", "hybrid"];
+
+    /// Parse a mode name, ignoring case
+    pub fn parse(mode: &str) -> Option<Self> {
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "semantic" => Some(Self::Semantic),
+            "keyword" => Some(Self::Keyword),
+            "hybrid" => Some(Self::Hybrid),
+            _ => None,
+        }
+    }
+
+    /// Whether the mode needs an embedding of the query
+    pub fn uses_embeddings(self) -> bool {
+        self != Self::Keyword
+    }
+}
+
+/// Convert a full-text match into a search result scored by BM25
+///
+/// BM25 scores are unbounded, so they are mapped to `score / (1 + score)` to
+/// share the 0 to 1 range of the other search modes.
+pub fn keyword_result(chunk_match: ChunkMatch) -> SearchResult {
+    let chunk = chunk_match.chunk;
+    SearchResult {
+        chunk_metadata: ChunkMetadata {
+            // Chunks are stored in LanceDB under their vector ID
+            chunk_id: chunk.vector_id,
+            site_id: chunk.site_id.to_string(),
+            page_title: chunk.page_title.unwrap_or_default(),
+            page_url: chunk.url,
+            heading_path: chunk.heading_path,
+            token_count: estimate_token_count(&chunk.chunk_content) as u32,
+            content: chunk.chunk_content,
+            chunk_index: u32::try_from(chunk.chunk_index).unwrap_or_default(),
+            created_at: chunk.indexed_date.and_utc().to_rfc3339(),
+        },
+        similarity_score: normalize_bm25(chunk_match.score as f32),
+        distance: 0.0,
+        score_breakdown: None,
+    }
+}
+
+/// Merge rankings of the same chunks with reciprocal rank fusion
+///
+/// Each chunk scores `1 / (RRF_K + rank)` summed over the rankings it appears
+/// in, with ranks starting at 1. The fused score is divided by the score of a
+/// chunk ranked first everywhere, so that it is between 0 and 1, and replaces
+/// `similarity_score`. The first ranking's copy of a chunk is kept. Ties keep
+/// the order in which chunks were first seen.
+pub fn reciprocal_rank_fusion(rankings: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let best_score = rankings.len() as f32 / (RRF_K + 1.0);
+    let mut fused: Vec<SearchResult> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+
+    for ranking in rankings {
+        for (rank, result) in ranking.into_iter().enumerate() {
+            let score = 1.0 / (RRF_K + rank as f32 + 1.0);
+            match positions.get(&result.chunk_metadata.chunk_id) {
+                Some(&position) => fused[position].similarity_score += score,
+                None => {
+                    positions.insert(result.chunk_metadata.chunk_id.clone(), fused.len());
+                    fused.push(SearchResult {
+                        similarity_score: score,
+                        ..result
+                    });
+                }
+            }
+        }
+    }
+
+    for result in &mut fused {
+        result.similarity_score /= best_score;
+    }
+    fused.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+    fused.truncate(limit);
+    fused
+}
+
+/// Map a BM25 score, where higher is better, to the range 0 to 1
+pub fn normalize_bm25(score: f32) -> f32 {
+    let score = score.max(0.0);
+    score / (1.0 + score)
+}
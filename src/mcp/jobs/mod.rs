@@ -0,0 +1,254 @@
+//! Background Indexing Jobs
+//!
+//! Runs `index_site` requests inside the MCP server. A job crawls the site and
+//! then generates its embeddings, the same as `docs-mcp add`, while clients
+//! poll `indexing_status` for progress.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result, anyhow};
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::runtime::Handle;
+use tracing::{error, info};
+use uuid::Uuid;
+
+use crate::commands::validation;
+use crate::commands::{default_site_name, normalize_index_url};
+use crate::config::Config;
+use crate::crawler::{CrawlerConfig, SiteCrawler, validate_url};
+use crate::database::sqlite::Database as SqliteDB;
+use crate::database::sqlite::models::{NewSite, Site};
+use crate::database::sqlite::queries::SiteQueries;
+use crate::indexer::Indexer;
+use crate::mcp::reload::Reloadable;
+
+/// A request to index a documentation site
+#[derive(Debug, Clone, Default)]
+pub struct IndexSiteRequest {
+    /// Index URL of the documentation site
+    pub url: String,
+    /// Name of the site, generated from the URL if not given
+    pub name: Option<String>,
+    /// Version of the site, "latest" if not given
+    pub version: Option<String>,
+    /// Base URL restricting which pages are crawled, the index URL if not given
+    pub base_url: Option<String>,
+}
+
+/// Stage of an indexing job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Crawling,
+    Embedding,
+    Completed,
+    Failed,
+}
+
+impl JobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed)
+    }
+}
+
+/// A background indexing job
+#[derive(Debug, Clone, Serialize)]
+pub struct IndexingJob {
+    pub id: String,
+    pub site_id: i64,
+    pub url: String,
+    pub state: JobState,
+    pub error: Option<String>,
+    pub started_at: NaiveDateTime,
+    pub finished_at: Option<NaiveDateTime>,
+}
+
+/// Registry of indexing jobs started by this server
+#[derive(Clone)]
+pub struct IndexingJobs {
+    config: Reloadable<Config>,
+    sqlite_db: Arc<SqliteDB>,
+    jobs: Arc<Mutex<HashMap<String, IndexingJob>>>,
+    /// Held while a site is looked up or created, so that concurrent requests
+    /// for a new URL share one site
+    sites: Arc<tokio::sync::Mutex<()>>,
+}
+
+impl IndexingJobs {
+    /// Create an empty job registry
+    ///
+    /// Each job uses the configuration current when it starts running.
+    pub fn new(config: Reloadable<Config>, sqlite_db: Arc<SqliteDB>) -> Self {
+        Self {
+            config,
+            sqlite_db,
+            jobs: Arc::default(),
+            sites: Arc::default(),
+        }
+    }
+
+    /// Start indexing a site in the background
+    ///
+    /// The site row is created (or found, if the URL was added before) right
+    /// away so the returned job already has its site ID. If a job for the site
+    /// is still running, that job is returned instead of starting another.
+    pub async fn start(&self, request: IndexSiteRequest) -> Result<IndexingJob> {
+        let index_url = normalize_index_url(&request.url)?.into_owned();
+        let parsed_url =
+            validation::validate_documentation_url(&index_url).context("Invalid URL provided")?;
+        let base_url = request.base_url.unwrap_or(request.url);
+        validation::validate_documentation_url(&base_url).context("Invalid base URL provided")?;
+        if let Some(ref name) = request.name {
+            validation::validate_site_name(name).context("Invalid site name provided")?;
+        }
+        if let Some(ref version) = request.version {
+            validation::validate_site_version(version).context("Invalid site version provided")?;
+        }
+
+        let new_site = NewSite {
+            index_url: index_url.clone(),
+            base_url,
+            name: request
+                .name
+                .unwrap_or_else(|| default_site_name(&parsed_url)),
+            version: request.version.unwrap_or_else(|| "latest".to_string()),
+        };
+        let (site, is_resuming) = self.find_or_create_site(new_site).await?;
+
+        let now = Utc::now().naive_utc();
+        let mut job = IndexingJob {
+            id: Uuid::new_v4().to_string(),
+            site_id: site.id,
+            url: index_url,
+            state: JobState::Crawling,
+            error: None,
+            started_at: now,
+            finished_at: None,
+        };
+
+        {
+            let mut jobs = self.lock();
+            if let Some(active) = jobs
+                .values()
+                .find(|j| j.site_id == site.id && !j.state.is_finished())
+            {
+                return Ok(active.clone());
+            }
+
+            if site.is_completed() {
+                job.state = JobState::Completed;
+                job.finished_at = Some(now);
+            } else if is_resuming && site.progress_percent == 100 {
+                // Crawling already finished, only embeddings remain
+                job.state = JobState::Embedding;
+            }
+            jobs.insert(job.id.clone(), job.clone());
+        }
+
+        if !job.state.is_finished() {
+            info!("Starting indexing job {} for site {}", job.id, site.id);
+            self.spawn(job.id.clone(), site, !is_resuming);
+        }
+
+        Ok(job)
+    }
+
+    /// Find the site with the index URL of `new_site`, or create it, returning
+    /// the site and whether it already existed
+    async fn find_or_create_site(&self, new_site: NewSite) -> Result<(Site, bool)> {
+        let _sites = self.sites.lock().await;
+        if let Some(site) =
+            SiteQueries::get_by_index_url(self.sqlite_db.pool(), &new_site.index_url).await?
+        {
+            return Ok((site, true));
+        }
+        let site = SiteQueries::create(self.sqlite_db.pool(), new_site)
+            .await
+            .context("Failed to create site entry")?;
+        Ok((site, false))
+    }
+
+    /// Get a job by ID
+    pub fn get(&self, job_id: &str) -> Option<IndexingJob> {
+        self.lock().get(job_id).cloned()
+    }
+
+    /// List all jobs, oldest first
+    pub fn list(&self) -> Vec<IndexingJob> {
+        let mut jobs: Vec<_> = self.lock().values().cloned().collect();
+        jobs.sort_by_key(|job| job.started_at);
+        jobs
+    }
+
+    fn spawn(&self, job_id: String, site: Site, init_queue: bool) {
+        let jobs = self.clone();
+        let handle = Handle::current();
+        // The crawler blocks on the browser, so keep jobs off the runtime's
+        // worker threads
+        tokio::task::spawn_blocking(move || {
+            let result = handle.block_on(jobs.run(&job_id, &site, init_queue));
+            if let Err(ref e) = result {
+                error!("Indexing job {} failed: {:#}", job_id, e);
+            }
+            jobs.finish(&job_id, result);
+        });
+    }
+
+    async fn run(&self, job_id: &str, site: &Site, init_queue: bool) -> Result<()> {
+        let config = Config::clone(&self.config.get());
+        if self.get(job_id).map(|job| job.state) == Some(JobState::Crawling) {
+            let mut crawler = SiteCrawler::new(
+                self.sqlite_db.pool().clone(),
+                CrawlerConfig::default(),
+                config.clone(),
+                false,
+            );
+            if init_queue {
+                let index_url = validate_url(&site.index_url)?;
+                crawler.init_crawl_queue(site.id, &index_url).await?;
+            }
+            crawler
+                .crawl_site(site.id, &site.index_url, &site.base_url)
+                .await?;
+            self.set_state(job_id, JobState::Embedding);
+        }
+
+        // Reload the site so the indexer continues from the crawl's progress
+        let site = self
+            .sqlite_db
+            .get_site_by_id(site.id)
+            .await?
+            .ok_or_else(|| anyhow!("Site {} was deleted during indexing", site.id))?;
+        Indexer::new(config, false)
+            .await?
+            .process_site_embeddings(&site)
+            .await
+    }
+
+    fn set_state(&self, job_id: &str, state: JobState) {
+        if let Some(job) = self.lock().get_mut(job_id) {
+            job.state = state;
+        }
+    }
+
+    fn finish(&self, job_id: &str, result: Result<()>) {
+        if let Some(job) = self.lock().get_mut(job_id) {
+            match result {
+                Ok(()) => job.state = JobState::Completed,
+                Err(e) => {
+                    job.state = JobState::Failed;
+                    job.error = Some(format!("{:#}", e));
+                }
+            }
+            job.finished_at = Some(Utc::now().naive_utc());
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, IndexingJob>> {
+        self.jobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
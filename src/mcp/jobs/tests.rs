@@ -0,0 +1,210 @@
+use super::*;
+use crate::database::sqlite::models::{SiteStatus, SiteUpdate};
+use tempfile::TempDir;
+
+async fn create_test_jobs() -> (TempDir, IndexingJobs) {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let database = SqliteDB::initialize_from_path(&temp_dir.path().join("docs.db"))
+        .await
+        .expect("Failed to create database");
+    let config = Config {
+        ollama: Default::default(),
+        chunking: Default::default(),
+        search: Default::default(),
+        reranking: Default::default(),
+        base_dir: temp_dir.path().to_path_buf(),
+    };
+    (
+        temp_dir,
+        IndexingJobs::new(Reloadable::new(config), Arc::new(database)),
+    )
+}
+
+async fn create_site(jobs: &IndexingJobs, index_url: &str, status: SiteStatus) -> Site {
+    let site = SiteQueries::create(
+        jobs.sqlite_db.pool(),
+        NewSite {
+            index_url: index_url.to_string(),
+            base_url: index_url.to_string(),
+            name: "Existing".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await
+    .expect("Failed to create site");
+
+    jobs.sqlite_db
+        .update_site(
+            site.id,
+            &SiteUpdate {
+                status: Some(status),
+                ..SiteUpdate::default()
+            },
+        )
+        .await
+        .expect("Failed to update site")
+        .expect("site exists")
+}
+
+#[tokio::test]
+async fn start_completes_immediately_for_indexed_site() {
+    let (_temp_dir, jobs) = create_test_jobs().await;
+    let site = create_site(&jobs, "https://docs.example.com/", SiteStatus::Completed).await;
+
+    let job = jobs
+        .start(IndexSiteRequest {
+            url: "https://docs.example.com/index.html".to_string(),
+            ..IndexSiteRequest::default()
+        })
+        .await
+        .expect("job should start");
+
+    assert_eq!(job.site_id, site.id);
+    assert_eq!(job.url, "https://docs.example.com/");
+    assert_eq!(job.state, JobState::Completed);
+    assert!(job.finished_at.is_some());
+
+    let again = jobs
+        .start(IndexSiteRequest {
+            url: "https://docs.example.com/".to_string(),
+            ..IndexSiteRequest::default()
+        })
+        .await
+        .expect("job should start");
+    assert_ne!(again.id, job.id);
+
+    let listed: Vec<String> = jobs.list().into_iter().map(|j| j.id).collect();
+    assert_eq!(listed.len(), 2);
+    assert!(listed.contains(&job.id));
+    assert_eq!(
+        jobs.get(&job.id).map(|j| j.state),
+        Some(JobState::Completed)
+    );
+}
+
+#[tokio::test]
+async fn start_returns_running_job_for_same_site() {
+    let (_temp_dir, jobs) = create_test_jobs().await;
+    let site = create_site(&jobs, "https://docs.example.com/", SiteStatus::Indexing).await;
+
+    let running = IndexingJob {
+        id: "running".to_string(),
+        site_id: site.id,
+        url: site.index_url.clone(),
+        state: JobState::Embedding,
+        error: None,
+        started_at: Utc::now().naive_utc(),
+        finished_at: None,
+    };
+    jobs.lock().insert(running.id.clone(), running);
+
+    let job = jobs
+        .start(IndexSiteRequest {
+            url: "https://docs.example.com/".to_string(),
+            ..IndexSiteRequest::default()
+        })
+        .await
+        .expect("job should start");
+
+    assert_eq!(job.id, "running");
+    assert_eq!(jobs.list().len(), 1);
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_requests_share_a_new_site() {
+    let (_temp_dir, jobs) = create_test_jobs().await;
+
+    let lookups = std::iter::repeat_with(|| {
+        let jobs = jobs.clone();
+        tokio::spawn(async move {
+            jobs.find_or_create_site(NewSite {
+                index_url: "https://docs.example.com/".to_string(),
+                base_url: "https://docs.example.com/".to_string(),
+                name: "Example".to_string(),
+                version: "latest".to_string(),
+            })
+            .await
+        })
+    })
+    .take(8);
+    let sites: Vec<(Site, bool)> = futures::future::try_join_all(lookups)
+        .await
+        .expect("lookups shouldn't panic")
+        .into_iter()
+        .collect::<Result<_>>()
+        .expect("site should be found or created");
+
+    assert!(sites.iter().all(|(site, _)| site.id == sites[0].0.id));
+    assert_eq!(sites.iter().filter(|(_, existed)| !existed).count(), 1);
+    assert_eq!(
+        jobs.sqlite_db
+            .list_sites()
+            .await
+            .expect("sites should list")
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn start_rejects_invalid_requests() {
+    let (_temp_dir, jobs) = create_test_jobs().await;
+
+    let invalid_url = jobs
+        .start(IndexSiteRequest {
+            url: "ftp://docs.example.com/".to_string(),
+            ..IndexSiteRequest::default()
+        })
+        .await;
+    assert!(invalid_url.is_err());
+
+    let invalid_name = jobs
+        .start(IndexSiteRequest {
+            url: "https://docs.example.com/".to_string(),
+            name: Some("bad\nname".to_string()),
+            ..IndexSiteRequest::default()
+        })
+        .await;
+    assert!(invalid_name.is_err());
+
+    assert!(jobs.list().is_empty());
+    assert!(
+        jobs.sqlite_db
+            .list_sites()
+            .await
+            .expect("sites should list")
+            .is_empty()
+    );
+}
+
+#[tokio::test]
+async fn finish_records_errors() {
+    let (_temp_dir, jobs) = create_test_jobs().await;
+
+    for id in ["ok", "failed"] {
+        jobs.lock().insert(
+            id.to_string(),
+            IndexingJob {
+                id: id.to_string(),
+                site_id: 1,
+                url: "https://docs.example.com/".to_string(),
+                state: JobState::Crawling,
+                error: None,
+                started_at: Utc::now().naive_utc(),
+                finished_at: None,
+            },
+        );
+    }
+
+    jobs.finish("ok", Ok(()));
+    jobs.finish("failed", Err(anyhow!("crawl failed")));
+
+    let ok = jobs.get("ok").expect("job exists");
+    assert_eq!(ok.state, JobState::Completed);
+    assert!(ok.error.is_none());
+    assert!(ok.finished_at.is_some());
+
+    let failed = jobs.get("failed").expect("job exists");
+    assert_eq!(failed.state, JobState::Failed);
+    assert_eq!(failed.error.as_deref(), Some("crawl failed"));
+}
@@ -6,4 +6,15 @@
 #[cfg(test)]
 mod tests;
 
+pub mod context;
+pub mod dispatch;
+pub mod http;
+pub mod hybrid;
+pub mod jobs;
+pub mod pages;
+pub mod query_cache;
+pub mod reload;
+pub mod rerank;
+pub mod resources;
 pub mod tools;
+pub mod versions;
@@ -0,0 +1,256 @@
+//! Page Reconstruction
+//!
+//! Reassembles full documentation pages from their stored chunks, removing
+//! the overlap text that the chunker prepends to adjacent chunks.
+
+use itertools::Itertools;
+use serde::Serialize;
+
+use crate::database::lancedb::vector_store::{ScoreBreakdown, SearchResult};
+use crate::database::sqlite::models::IndexedChunk;
+
+/// Separator used between segments of a chunk's heading path
+const HEADING_SEPARATOR: &str = " > ";
+
+/// Marks skipped text between non-adjacent hits in a grouped page result
+const GAP_MARKER: &str = "\n\n[...]\n\n";
+
+/// A documentation page rebuilt from its indexed chunks
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ReassembledPage {
+    pub site_id: i64,
+    pub url: String,
+    pub page_title: Option<String>,
+    /// Distinct heading paths in document order
+    pub outline: Vec<String>,
+    /// Markdown content of the page
+    pub content: String,
+    pub chunk_count: usize,
+}
+
+/// Rebuild a page from its chunks
+///
+/// Chunks are ordered by `chunk_index`, the overlap added during chunking is
+/// stripped, and a markdown heading is emitted whenever the heading path changes.
+pub fn reassemble_page(chunks: &[IndexedChunk]) -> Option<ReassembledPage> {
+    let first = chunks.first()?;
+
+    let mut ordered: Vec<&IndexedChunk> = chunks.iter().collect();
+    ordered.sort_by_key(|c| c.chunk_index);
+
+    let mut outline: Vec<String> = Vec::new();
+    let mut content = String::new();
+    let mut previous: Option<&IndexedChunk> = None;
+
+    for chunk in ordered {
+        let heading_path = chunk.heading_path.as_deref().unwrap_or_default();
+        let previous_heading_path = previous
+            .and_then(|p| p.heading_path.as_deref())
+            .unwrap_or_default();
+
+        let text = match previous {
+            Some(prev) if previous_heading_path == heading_path => {
+                strip_chunk_overlap(&prev.chunk_content, &chunk.chunk_content)
+            }
+            _ => chunk.chunk_content.as_str(),
+        };
+
+        if previous.is_none() || previous_heading_path != heading_path {
+            if !heading_path.is_empty() && !outline.iter().any(|h| h == heading_path) {
+                outline.push(heading_path.to_string());
+            }
+            let headings = changed_headings(previous_heading_path, heading_path);
+            for (depth, heading) in headings {
+                if !content.is_empty() {
+                    content.push_str("\n\n");
+                }
+                content.push_str(&"#".repeat(depth.min(6)));
+                content.push(' ');
+                content.push_str(heading);
+            }
+        }
+
+        if !content.is_empty() {
+            content.push_str("\n\n");
+        }
+        content.push_str(text.trim());
+
+        previous = Some(chunk);
+    }
+
+    Some(ReassembledPage {
+        site_id: first.site_id,
+        url: first.url.clone(),
+        page_title: first.page_title.clone(),
+        outline,
+        content,
+        chunk_count: chunks.len(),
+    })
+}
+
+/// Search hits from one page merged into a single result
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PageHit {
+    pub site_id: String,
+    pub url: String,
+    pub page_title: String,
+    /// Chunk ID of the best-scoring hit
+    pub chunk_id: String,
+    /// Chunk index of the best-scoring hit
+    pub chunk_index: u32,
+    /// Heading path of the best-scoring hit
+    pub heading_path: Option<String>,
+    /// Chunk IDs of all merged hits in page order
+    pub chunk_ids: Vec<String>,
+    /// Chunk indexes of all merged hits in page order
+    pub chunk_indexes: Vec<u32>,
+    /// Combined text of the hits, with chunk overlap removed
+    pub content: String,
+    /// Similarity score of the best-scoring hit
+    pub similarity_score: f32,
+    /// Score breakdown of the best-scoring hit, when it was reranked
+    pub score_breakdown: Option<ScoreBreakdown>,
+}
+
+/// Group search results by page, keeping at most `limit` distinct pages
+///
+/// Pages are ordered by their best-scoring hit. Hits from the same page are
+/// combined in chunk order: adjacent chunks are joined with their overlap
+/// stripped, and gaps between non-adjacent chunks are marked with `[...]`.
+pub fn group_results_by_page(results: Vec<SearchResult>, limit: usize) -> Vec<PageHit> {
+    // Group in order of first appearance so ties keep the search order
+    let mut pages: Vec<Vec<SearchResult>> = Vec::new();
+    for result in results {
+        let metadata = &result.chunk_metadata;
+        match pages.iter_mut().find(|page| {
+            page[0].chunk_metadata.site_id == metadata.site_id
+                && page[0].chunk_metadata.page_url == metadata.page_url
+        }) {
+            Some(page) => page.push(result),
+            None => pages.push(vec![result]),
+        }
+    }
+
+    pages
+        .into_iter()
+        .filter_map(merge_page_hits)
+        .sorted_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score))
+        .take(limit)
+        .collect()
+}
+
+/// Merge hits which all belong to the same page
+fn merge_page_hits(hits: Vec<SearchResult>) -> Option<PageHit> {
+    let best = hits
+        .iter()
+        .max_by(|a, b| a.similarity_score.total_cmp(&b.similarity_score))?
+        .clone();
+
+    let ordered: Vec<SearchResult> = hits
+        .into_iter()
+        .sorted_by_key(|hit| hit.chunk_metadata.chunk_index)
+        .dedup_by(|a, b| a.chunk_metadata.chunk_index == b.chunk_metadata.chunk_index)
+        .collect();
+
+    let content = join_chunks(ordered.iter().map(|hit| {
+        (
+            i64::from(hit.chunk_metadata.chunk_index),
+            hit.chunk_metadata.content.as_str(),
+        )
+    }));
+
+    Some(PageHit {
+        site_id: best.chunk_metadata.site_id,
+        url: best.chunk_metadata.page_url,
+        page_title: best.chunk_metadata.page_title,
+        chunk_id: best.chunk_metadata.chunk_id,
+        chunk_index: best.chunk_metadata.chunk_index,
+        heading_path: best.chunk_metadata.heading_path,
+        chunk_ids: ordered
+            .iter()
+            .map(|hit| hit.chunk_metadata.chunk_id.clone())
+            .collect(),
+        chunk_indexes: ordered
+            .iter()
+            .map(|hit| hit.chunk_metadata.chunk_index)
+            .collect(),
+        content,
+        similarity_score: best.similarity_score,
+        score_breakdown: best.score_breakdown,
+    })
+}
+
+/// Join chunk texts that are already in page order
+///
+/// Adjacent chunks are joined with their overlap stripped, and gaps between
+/// non-adjacent chunks are marked with `[...]`. Takes `(chunk_index, text)` pairs.
+pub fn join_chunks<'a>(chunks: impl IntoIterator<Item = (i64, &'a str)>) -> String {
+    let mut content = String::new();
+    let mut previous: Option<(i64, &str)> = None;
+    for (index, text) in chunks {
+        let text = match previous {
+            Some((prev_index, prev_text)) if index == prev_index + 1 => {
+                content.push_str("\n\n");
+                strip_chunk_overlap(prev_text, text)
+            }
+            Some(_) => {
+                content.push_str(GAP_MARKER);
+                text
+            }
+            None => text,
+        };
+        content.push_str(text.trim());
+        previous = Some((index, text));
+    }
+    content
+}
+
+/// Remove the overlap text the chunker prepends to a chunk
+///
+/// The chunker prefixes a chunk with the trailing words of the previous chunk
+/// in the same section, followed by a blank line. If the first paragraph of
+/// `current` matches the tail of `previous`, it is dropped.
+pub fn strip_chunk_overlap<'a>(previous: &str, current: &'a str) -> &'a str {
+    let Some((prefix, rest)) = current.split_once("\n\n") else {
+        return current;
+    };
+
+    let prefix_words: Vec<&str> = prefix.split_whitespace().collect();
+    if prefix_words.is_empty() {
+        return current;
+    }
+
+    let previous_words: Vec<&str> = previous.split_whitespace().collect();
+    if previous_words.ends_with(&prefix_words) {
+        rest
+    } else {
+        current
+    }
+}
+
+/// Headings that need to be emitted when moving from one heading path to another,
+/// along with their depth in the path (1-based)
+fn changed_headings<'a>(previous: &str, current: &'a str) -> Vec<(usize, &'a str)> {
+    if current.is_empty() {
+        return Vec::new();
+    }
+
+    let previous_segments: Vec<&str> = if previous.is_empty() {
+        Vec::new()
+    } else {
+        previous.split(HEADING_SEPARATOR).collect()
+    };
+
+    let shared = current
+        .split(HEADING_SEPARATOR)
+        .zip(previous_segments.iter())
+        .take_while(|(a, b)| a == *b)
+        .count();
+
+    current
+        .split(HEADING_SEPARATOR)
+        .enumerate()
+        .skip(shared)
+        .map(|(i, heading)| (i + 1, heading))
+        .collect()
+}
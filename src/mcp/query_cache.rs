@@ -0,0 +1,105 @@
+//! Query Embedding Cache
+//!
+//! Keeps the embeddings of recent search queries in memory so that repeated
+//! queries during a session don't have to wait on Ollama again.
+
+use anyhow::Result;
+use lru::LruCache;
+use serde::Serialize;
+use std::future::Future;
+use std::num::NonZeroUsize;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+/// Number of query embeddings kept by the MCP server
+pub const DEFAULT_QUERY_CACHE_CAPACITY: NonZeroUsize = NonZeroUsize::new(256).expect("non-zero");
+
+/// Hit and miss counts of a [`QueryEmbeddingCache`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct QueryCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub capacity: usize,
+}
+
+#[derive(Debug)]
+struct CacheState {
+    entries: LruCache<(String, String), Vec<f32>>,
+    hits: u64,
+    misses: u64,
+}
+
+/// Bounded LRU cache of query text to embedding, keyed by model name
+#[derive(Debug)]
+pub struct QueryEmbeddingCache {
+    state: Mutex<CacheState>,
+}
+
+impl QueryEmbeddingCache {
+    /// Create an empty cache holding at most `capacity` embeddings
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            state: Mutex::new(CacheState {
+                entries: LruCache::new(capacity),
+                hits: 0,
+                misses: 0,
+            }),
+        }
+    }
+
+    /// Get the embedding of `query` for `model`, awaiting `generate` only on
+    /// a miss
+    ///
+    /// Whitespace differences are ignored when matching queries. Failed
+    /// embeddings are not cached. The cache is not locked while generating, so
+    /// concurrent misses for the same query may both generate it.
+    pub async fn get_or_generate<F>(
+        &self,
+        model: &str,
+        query: &str,
+        generate: F,
+    ) -> Result<Vec<f32>>
+    where
+        F: Future<Output = Result<Vec<f32>>>,
+    {
+        let key = (model.to_string(), normalize_query(query));
+
+        {
+            let mut state = self.lock();
+            if let Some(embedding) = state.entries.get(&key).cloned() {
+                state.hits += 1;
+                return Ok(embedding);
+            }
+            state.misses += 1;
+        }
+
+        let embedding = generate.await?;
+        self.lock().entries.put(key, embedding.clone());
+        Ok(embedding)
+    }
+
+    /// Current hit/miss counts and size of the cache
+    pub fn stats(&self) -> QueryCacheStats {
+        let state = self.lock();
+        QueryCacheStats {
+            hits: state.hits,
+            misses: state.misses,
+            entries: state.entries.len(),
+            capacity: state.entries.cap().get(),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Default for QueryEmbeddingCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_QUERY_CACHE_CAPACITY)
+    }
+}
+
+fn normalize_query(query: &str) -> String {
+    query.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,294 @@
+//! Hot Reload
+//!
+//! Sites are indexed and the configuration is edited by other processes while
+//! a server is running. [`ServerReloader`] polls for those changes and applies
+//! them in place, so connected clients keep their session:
+//!
+//! - Edits to `config.toml` replace the embedding provider used for queries and
+//!   its token counter, the search settings and the configuration used by new
+//!   indexing jobs
+//! - Changes to the `sites` table or a new version of the LanceDB table reopen
+//!   the table used for searches
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use std::fmt;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::config::Config;
+use crate::database::lancedb::vector_store::VectorStore;
+use crate::database::sqlite::Database as SqliteDB;
+use crate::database::sqlite::models::SiteStatus;
+use crate::embeddings::chunking::ChunkSizer;
+use crate::embeddings::{EmbeddingProvider, create_provider};
+use crate::indexer::chunk_sizer;
+
+/// How often the server checks for configuration and index changes
+pub const RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A shared value that can be replaced while the server is running
+///
+/// Readers take a snapshot with [`Reloadable::get`], so a request in progress
+/// keeps using the value it started with.
+pub struct Reloadable<T> {
+    current: Arc<RwLock<Arc<T>>>,
+}
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(value))),
+        }
+    }
+
+    /// The current value
+    pub fn get(&self) -> Arc<T> {
+        Arc::clone(&self.current.read().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Replace the value for all future readers
+    pub fn replace(&self, value: T) {
+        *self.current.write().unwrap_or_else(PoisonError::into_inner) = Arc::new(value);
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self {
+            current: Arc::clone(&self.current),
+        }
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Reloadable").field(&self.get()).finish()
+    }
+}
+
+/// Changes applied by a single [`ServerReloader::check`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReloadChanges {
+    /// `config.toml` changed and was applied
+    pub config: bool,
+    /// A site was added, removed, re-indexed or changed status
+    pub sites: bool,
+    /// The search table was reopened after it was written or replaced
+    pub vector_table: bool,
+}
+
+/// The parts of a site which affect searches
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SiteSnapshot {
+    id: i64,
+    name: String,
+    version: String,
+    status: SiteStatus,
+    indexed_date: Option<NaiveDateTime>,
+}
+
+/// Applies configuration and index changes to a running server
+pub struct ServerReloader {
+    config: Reloadable<Config>,
+    embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+    sizer: Reloadable<ChunkSizer>,
+    sqlite_db: Arc<SqliteDB>,
+    vector_store: Arc<VectorStore>,
+    sites: Option<Vec<SiteSnapshot>>,
+    /// Last configuration error, so that it is only logged once
+    config_error: Option<String>,
+}
+
+impl ServerReloader {
+    /// Create a reloader for the server's shared state
+    pub fn new(
+        config: Reloadable<Config>,
+        embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+        sizer: Reloadable<ChunkSizer>,
+        sqlite_db: Arc<SqliteDB>,
+        vector_store: Arc<VectorStore>,
+    ) -> Self {
+        Self {
+            config,
+            embedder,
+            sizer,
+            sqlite_db,
+            vector_store,
+            sites: None,
+            config_error: None,
+        }
+    }
+
+    /// Check for changes every `interval` until the database is closed
+    pub async fn watch(mut self, interval: Duration) {
+        // Record the starting state so the first tick only reports changes
+        self.check().await;
+
+        let mut ticker = tokio::time::interval(interval);
+        ticker.tick().await;
+
+        while !self.sqlite_db.pool().is_closed() {
+            ticker.tick().await;
+            self.check().await;
+        }
+    }
+
+    /// Check for and apply any changes since the previous check
+    pub async fn check(&mut self) -> ReloadChanges {
+        let mut changes = ReloadChanges {
+            config: self.reload_config().await,
+            ..ReloadChanges::default()
+        };
+
+        // The search table is refreshed afterwards, so a site that just finished
+        // indexing is searchable as soon as it is reported
+        match self.reload_sites().await {
+            Ok(changed) => changes.sites = changed,
+            Err(e) => warn!("Failed to check sites for changes: {}", e),
+        }
+
+        match self.vector_store.refresh_search_table().await {
+            Ok(changed) => changes.vector_table = changed,
+            Err(e) => warn!("Failed to refresh vector table: {}", e),
+        }
+
+        if changes != ReloadChanges::default() {
+            debug!("Reloaded server state: {:?}", changes);
+        }
+        changes
+    }
+
+    /// Reload `config.toml`, returning whether the configuration changed
+    ///
+    /// Invalid configurations are logged and ignored, keeping the last valid one.
+    /// Reading the file and connecting to the new provider block, so they run
+    /// off the runtime's worker threads.
+    async fn reload_config(&mut self) -> bool {
+        let current = self.config.get();
+        let base_dir = current.get_base_dir().to_path_buf();
+        let loaded = tokio::task::spawn_blocking(move || Config::load(base_dir))
+            .await
+            .context("Configuration loading task panicked")
+            .and_then(|loaded| loaded);
+        let loaded = match loaded {
+            Ok(loaded) => {
+                self.config_error = None;
+                loaded
+            }
+            Err(e) => {
+                let message = format!("{:#}", e);
+                if self.config_error.as_ref() != Some(&message) {
+                    warn!("Ignoring invalid configuration: {}", message);
+                    self.config_error = Some(message);
+                }
+                return false;
+            }
+        };
+
+        if loaded == *current {
+            return false;
+        }
+
+        if loaded.ollama != current.ollama {
+            let ollama = loaded.ollama.clone();
+            // Loading a local model reads its weights, so keep it off the runtime
+            let provider = match tokio::task::spawn_blocking(move || create_provider(&ollama))
+                .await
+                .context("Embedding provider task panicked")
+                .and_then(|embedder| embedder)
+            {
+                Ok(embedder) => chunk_sizer(&loaded.ollama, embedder.as_ref())
+                    .await
+                    .map(|sizer| (embedder, sizer)),
+                Err(e) => Err(e),
+            };
+            match provider {
+                Ok((embedder, sizer)) => {
+                    self.embedder.replace(embedder);
+                    self.sizer.replace(sizer);
+                }
+                Err(e) => {
+                    warn!(
+                        "Ignoring configuration with invalid embedding settings: {:#}",
+                        e
+                    );
+                    return false;
+                }
+            }
+            info!(
+                "Using {} model {} at {}",
+                loaded.ollama.provider,
+                loaded.ollama.model_name(),
+                loaded.ollama.location()
+            );
+            if loaded.ollama.model_name() != current.ollama.model_name() {
+                warn!(
+                    "Embedding model changed from {} to {}, sites indexed with the old model need to be re-indexed",
+                    current.ollama.model_name(),
+                    loaded.ollama.model_name()
+                );
+            }
+        }
+
+        if loaded.search != current.search {
+            self.vector_store.set_search_config(loaded.search.clone());
+            info!(
+                "Searching with the {} distance metric, nprobes {}",
+                loaded.search.distance_metric, loaded.search.nprobes
+            );
+        }
+
+        info!("Reloaded configuration");
+        self.config.replace(loaded);
+        true
+    }
+
+    /// Reload the list of sites, returning whether any site changed
+    async fn reload_sites(&mut self) -> Result<bool> {
+        let sites: Vec<SiteSnapshot> = self
+            .sqlite_db
+            .list_sites()
+            .await?
+            .into_iter()
+            .map(|site| SiteSnapshot {
+                id: site.id,
+                name: site.name,
+                version: site.version,
+                status: site.status,
+                indexed_date: site.indexed_date,
+            })
+            .collect();
+
+        let Some(previous) = self.sites.replace(sites.clone()) else {
+            return Ok(false);
+        };
+        if previous == sites {
+            return Ok(false);
+        }
+
+        for site in &sites {
+            let before = previous.iter().find(|p| p.id == site.id);
+            if site.status == SiteStatus::Completed && before != Some(site) {
+                info!(
+                    "Site {} ({} {}) finished indexing and is now searchable",
+                    site.id, site.name, site.version
+                );
+            }
+        }
+        for site in previous
+            .iter()
+            .filter(|p| !sites.iter().any(|site| site.id == p.id))
+        {
+            info!(
+                "Site {} ({} {}) was removed",
+                site.id, site.name, site.version
+            );
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,186 @@
+use super::*;
+use crate::config::settings::{DistanceMetric, EmbeddingBackend, SearchConfig};
+use crate::database::sqlite::models::{NewSite, SiteUpdate};
+use crate::database::sqlite::queries::SiteQueries;
+use crate::embeddings::chunking::ChunkSizer;
+use tempfile::TempDir;
+
+struct TestServer {
+    _temp_dir: TempDir,
+    config: Reloadable<Config>,
+    embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+    sqlite_db: Arc<SqliteDB>,
+    vector_store: Arc<VectorStore>,
+    reloader: ServerReloader,
+}
+
+async fn create_test_server() -> TestServer {
+    let temp_dir = TempDir::new().expect("Failed to create temp directory");
+    let config = Config {
+        ollama: Default::default(),
+        chunking: Default::default(),
+        search: Default::default(),
+        reranking: Default::default(),
+        base_dir: temp_dir.path().to_path_buf(),
+    };
+    config.save().expect("Failed to save config");
+
+    let sqlite_db = Arc::new(
+        SqliteDB::initialize_from_path(&config.database_path().expect("database path"))
+            .await
+            .expect("Failed to create database"),
+    );
+    let vector_store = Arc::new(
+        VectorStore::new(&config)
+            .await
+            .expect("Failed to create vector store"),
+    );
+    let embedder = Reloadable::new(
+        create_provider(&config.ollama).expect("Failed to create embedding provider"),
+    );
+    let config = Reloadable::new(config);
+
+    let mut reloader = ServerReloader::new(
+        config.clone(),
+        embedder.clone(),
+        Reloadable::new(ChunkSizer::default()),
+        Arc::clone(&sqlite_db),
+        Arc::clone(&vector_store),
+    );
+    assert_eq!(reloader.check().await, ReloadChanges::default());
+
+    TestServer {
+        _temp_dir: temp_dir,
+        config,
+        embedder,
+        sqlite_db,
+        vector_store,
+        reloader,
+    }
+}
+
+#[test]
+fn reloadable_keeps_snapshots() {
+    let value = Reloadable::new("old".to_string());
+    let shared = value.clone();
+    let snapshot = value.get();
+
+    shared.replace("new".to_string());
+
+    assert_eq!(*snapshot, "old");
+    assert_eq!(*value.get(), "new");
+}
+
+#[tokio::test]
+async fn config_edits_replace_embedder() {
+    let mut server = create_test_server().await;
+    let old_client = server.embedder.get();
+
+    let mut edited = Config::clone(&server.config.get());
+    edited.ollama.model = "mxbai-embed-large".to_string();
+    edited.save().expect("Failed to save config");
+
+    let changes = server.reloader.check().await;
+    assert!(changes.config);
+    assert_eq!(server.config.get().ollama.model, "mxbai-embed-large");
+    assert_eq!(server.embedder.get().model(), "mxbai-embed-large");
+    assert_ne!(old_client.model(), "mxbai-embed-large");
+
+    assert!(!server.reloader.check().await.config);
+}
+
+#[tokio::test]
+async fn provider_edits_switch_backend() {
+    let mut server = create_test_server().await;
+    assert!(format!("{:?}", server.embedder.get()).starts_with("OllamaClient"));
+
+    let mut edited = Config::clone(&server.config.get());
+    edited.ollama.provider = EmbeddingBackend::OpenAi;
+    edited.ollama.port = 8080;
+    edited.save().expect("Failed to save config");
+
+    assert!(server.reloader.check().await.config);
+    assert!(format!("{:?}", server.embedder.get()).starts_with("OpenAiClient"));
+}
+
+#[tokio::test]
+async fn search_edits_apply_to_vector_store() {
+    let mut server = create_test_server().await;
+    assert_eq!(server.vector_store.search_config(), SearchConfig::default());
+
+    let mut edited = Config::clone(&server.config.get());
+    edited.search = SearchConfig {
+        distance_metric: DistanceMetric::Dot,
+        index_threshold: 50_000,
+        nprobes: 40,
+        refine_factor: Some(5),
+    };
+    edited.save().expect("Failed to save config");
+
+    assert!(server.reloader.check().await.config);
+    assert_eq!(server.vector_store.search_config(), edited.search);
+
+    // The embedder is kept when only the search settings change
+    assert!(format!("{:?}", server.embedder.get()).starts_with("OllamaClient"));
+}
+
+#[tokio::test]
+async fn invalid_config_is_ignored() {
+    let mut server = create_test_server().await;
+    let config_path = server.config.get().config_file_path().expect("config path");
+    std::fs::write(&config_path, "[ollama\nmodel = ").expect("Failed to write config");
+
+    assert!(!server.reloader.check().await.config);
+    assert!(server.reloader.config_error.is_some());
+    assert_eq!(
+        server.embedder.get().model(),
+        server.config.get().ollama.model
+    );
+
+    // Fixing the file clears the error without reporting a change
+    server.config.get().save().expect("Failed to save config");
+    assert!(!server.reloader.check().await.config);
+    assert!(server.reloader.config_error.is_none());
+}
+
+#[tokio::test]
+async fn site_changes_are_detected() {
+    let mut server = create_test_server().await;
+
+    let site = SiteQueries::create(
+        server.sqlite_db.pool(),
+        NewSite {
+            index_url: "https://docs.example.com/".to_string(),
+            base_url: "https://docs.example.com/".to_string(),
+            name: "Example".to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await
+    .expect("Failed to create site");
+    assert!(server.reloader.check().await.sites);
+    assert!(!server.reloader.check().await.sites);
+
+    // Progress updates alone don't affect searches
+    let progress = SiteUpdate {
+        progress_percent: Some(50),
+        ..SiteUpdate::default()
+    };
+    server
+        .sqlite_db
+        .update_site(site.id, &progress)
+        .await
+        .expect("Failed to update site");
+    assert!(!server.reloader.check().await.sites);
+
+    let completed = SiteUpdate {
+        status: Some(SiteStatus::Completed),
+        ..SiteUpdate::default()
+    };
+    server
+        .sqlite_db
+        .update_site(site.id, &completed)
+        .await
+        .expect("Failed to update site");
+    assert!(server.reloader.check().await.sites);
+}
@@ -0,0 +1,132 @@
+//! Search Reranking
+//!
+//! The first-stage search ranks chunks by a single signal, which often puts the
+//! best chunk a few places down. Searches therefore over-fetch candidates and
+//! rescore them: with a reranker model when one is configured, otherwise by how
+//! much of the query each candidate covers.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::config::settings::RerankingConfig;
+use crate::database::lancedb::vector_store::{ScoreBreakdown, SearchResult};
+
+/// Upper bound on candidates fetched for reranking, whatever the multiplier
+pub const MAX_RERANK_CANDIDATES: usize = 100;
+
+/// Name reported for the lexical overlap reranker
+pub const LEXICAL_RERANKER: &str = "lexical";
+
+/// Number of candidates to fetch for `limit` reranked results
+pub fn rerank_candidates(config: &RerankingConfig, limit: usize) -> usize {
+    if !config.enabled {
+        return limit;
+    }
+    limit
+        .saturating_mul(config.candidate_multiplier as usize)
+        .min(MAX_RERANK_CANDIDATES)
+        .max(limit)
+}
+
+/// Text of a candidate as seen by rerankers
+pub fn rerank_document(result: &SearchResult) -> String {
+    let metadata = &result.chunk_metadata;
+    [
+        metadata.page_title.as_str(),
+        metadata.heading_path.as_deref().unwrap_or_default(),
+        metadata.content.as_str(),
+    ]
+    .into_iter()
+    .filter(|part| !part.is_empty())
+    .collect::<Vec<_>>()
+    .join("\n")
+}
+
+/// Score candidates by the share of the query's terms they contain
+///
+/// Terms are weighted by their inverse document frequency among the
+/// candidates, so words which appear everywhere count for little. Scores are
+/// between 0 and 1.
+pub fn lexical_scores(query: &str, results: &[SearchResult]) -> Vec<f32> {
+    let query_terms: HashSet<String> = terms(query).collect();
+    let documents: Vec<HashSet<String>> = results
+        .iter()
+        .map(|result| terms(&rerank_document(result)).collect())
+        .collect();
+
+    let candidate_count = documents.len() as f32;
+    let weights: HashMap<&str, f32> = query_terms
+        .iter()
+        .map(|term| {
+            let frequency = documents.iter().filter(|doc| doc.contains(term)).count() as f32;
+            (term.as_str(), (candidate_count / (1.0 + frequency)).ln_1p())
+        })
+        .collect();
+    let total_weight: f32 = weights.values().sum();
+
+    documents
+        .iter()
+        .map(|document| {
+            if total_weight <= 0.0 {
+                return 0.0;
+            }
+            let matched: f32 = weights
+                .iter()
+                .filter(|(term, _)| document.contains(**term))
+                .map(|(_, weight)| weight)
+                .sum();
+            matched / total_weight
+        })
+        .collect()
+}
+
+/// Reorder candidates by reranker scores, keeping at most `limit`
+///
+/// A model's score replaces the retrieval score. Models which return logits
+/// rather than probabilities, noticed by a score outside 0 to 1, have their
+/// scores passed through a sigmoid. The lexical score is only a rough signal,
+/// so it is averaged with the retrieval score. Each result records the
+/// components in its `score_breakdown`.
+pub fn apply_rerank_scores(
+    results: Vec<SearchResult>,
+    scores: &[f32],
+    reranker: &str,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let logits =
+        reranker != LEXICAL_RERANKER && scores.iter().any(|score| !(0.0..=1.0).contains(score));
+
+    let mut reranked: Vec<SearchResult> = results
+        .into_iter()
+        .zip(scores)
+        .map(|(result, &rerank_score)| {
+            let retrieval_score = result.similarity_score;
+            let similarity_score = if reranker == LEXICAL_RERANKER {
+                f32::midpoint(retrieval_score, rerank_score)
+            } else if logits {
+                1.0 / (1.0 + (-rerank_score).exp())
+            } else {
+                rerank_score
+            };
+            SearchResult {
+                similarity_score,
+                score_breakdown: Some(ScoreBreakdown {
+                    retrieval_score,
+                    rerank_score,
+                    reranker: reranker.to_string(),
+                }),
+                ..result
+            }
+        })
+        .collect();
+
+    reranked.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+    reranked.truncate(limit);
+    reranked
+}
+
+/// Lowercase word tokens of a text, with underscores kept inside words
+fn terms(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric() && c != '_')
+        .filter(|term| !term.is_empty())
+        .map(str::to_lowercase)
+}
@@ -0,0 +1,368 @@
+//! MCP Resources
+//!
+//! Exposes indexed documentation pages as MCP resources, addressed as
+//! `docs://site/{site_id}/page?url=...` and reassembled from `indexed_chunks`.
+//!
+//! The server's built-in resource manager only serves resources registered up
+//! front, so resource requests are answered by [`ResourceTransport`], which
+//! wraps the server's transport and reads pages from SQLite on demand.
+
+#[cfg(test)]
+mod tests;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use modelcontextprotocol_server::Transport;
+use modelcontextprotocol_server::mcp_protocol::JsonRpcMessage;
+use modelcontextprotocol_server::mcp_protocol::constants::{error_codes, methods};
+use modelcontextprotocol_server::mcp_protocol::resource::{
+    Resource, ResourceContent, ResourceReadParams, ResourceReadResult, ResourceTemplate,
+    ResourceTemplatesListResult, ResourcesListParams, ResourcesListResult,
+};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::database::sqlite::Database as SqliteDB;
+use crate::database::sqlite::models::SiteStatus;
+use crate::mcp::pages::reassemble_page;
+
+/// URI template for documentation pages (RFC 6570)
+pub const PAGE_URI_TEMPLATE: &str = "docs://site/{site_id}/page{?url}";
+
+/// MIME type of page resources
+const PAGE_MIME_TYPE: &str = "text/markdown";
+
+/// Number of resources returned per `resources/list` page
+const RESOURCES_PAGE_SIZE: i64 = 100;
+
+/// How often to check SQLite for sites that finished indexing
+pub const COMPLETED_SITES_POLL_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Build the resource URI for a page
+pub fn page_uri(site_id: i64, url: &str) -> String {
+    let query = url::form_urlencoded::Serializer::new(String::new())
+        .append_pair("url", url)
+        .finish();
+    format!("docs://site/{}/page?{}", site_id, query)
+}
+
+/// Parse a page resource URI into its site ID and page URL
+pub fn parse_page_uri(uri: &str) -> Option<(i64, String)> {
+    let parsed = url::Url::parse(uri).ok()?;
+    if parsed.scheme() != "docs" || parsed.host_str() != Some("site") {
+        return None;
+    }
+
+    let mut segments = parsed.path_segments()?;
+    let site_id = segments.next()?.parse().ok()?;
+    if segments.next()? != "page" || segments.next().is_some() {
+        return None;
+    }
+
+    let url = parsed
+        .query_pairs()
+        .find(|(key, _)| key == "url")
+        .map(|(_, value)| value.into_owned())?;
+    Some((site_id, url))
+}
+
+/// Whether a method is answered by [`PageResources`]
+fn is_resource_method(method: &str) -> bool {
+    matches!(
+        method,
+        methods::RESOURCES_LIST | methods::RESOURCES_READ | methods::RESOURCES_TEMPLATES_LIST
+    )
+}
+
+/// Provides indexed pages as MCP resources
+pub struct PageResources {
+    sqlite_db: Arc<SqliteDB>,
+}
+
+impl PageResources {
+    /// Create a new page resource provider
+    pub fn new(sqlite_db: Arc<SqliteDB>) -> Self {
+        Self { sqlite_db }
+    }
+
+    /// Resource templates advertised to clients
+    pub fn templates() -> Vec<ResourceTemplate> {
+        vec![ResourceTemplate {
+            uri_template: PAGE_URI_TEMPLATE.to_string(),
+            name: "Documentation page".to_string(),
+            description: Some(
+                "A full indexed documentation page as markdown. `site_id` is the ID from `list_sites` and `url` is the page URL.".to_string(),
+            ),
+            mime_type: Some(PAGE_MIME_TYPE.to_string()),
+            annotations: None,
+        }]
+    }
+
+    /// List pages of completed sites, starting at the given cursor
+    pub async fn list(&self, cursor: Option<&str>) -> Result<ResourcesListResult, JsonRpcError> {
+        let offset = match cursor.filter(|c| !c.is_empty()) {
+            Some(cursor) => cursor.parse::<i64>().map_err(|_| {
+                JsonRpcError::new(
+                    error_codes::INVALID_PARAMS,
+                    format!("Invalid cursor: {}", cursor),
+                )
+            })?,
+            None => 0,
+        };
+
+        // Fetch one extra page to find out whether there is a next page
+        let mut pages = self
+            .sqlite_db
+            .list_completed_pages(RESOURCES_PAGE_SIZE + 1, offset)
+            .await
+            .map_err(|e| JsonRpcError::new(error_codes::INTERNAL_ERROR, e.to_string()))?;
+
+        let next_cursor = (pages.len() as i64 > RESOURCES_PAGE_SIZE)
+            .then(|| (offset + RESOURCES_PAGE_SIZE).to_string());
+        pages.truncate(RESOURCES_PAGE_SIZE as usize);
+
+        let resources = pages
+            .into_iter()
+            .map(|page| Resource {
+                uri: page_uri(page.site_id, &page.url),
+                name: page.page_title.unwrap_or_else(|| page.url.clone()),
+                description: Some(format!(
+                    "{} {}: {}",
+                    page.site_name, page.site_version, page.url
+                )),
+                mime_type: Some(PAGE_MIME_TYPE.to_string()),
+                size: None,
+                annotations: None,
+            })
+            .collect();
+
+        Ok(ResourcesListResult {
+            resources,
+            next_cursor,
+        })
+    }
+
+    /// Read a page resource
+    pub async fn read(&self, uri: &str) -> Result<ResourceReadResult, JsonRpcError> {
+        let not_found = || {
+            JsonRpcError::new(
+                error_codes::RESOURCE_NOT_FOUND,
+                format!("Resource not found: {}", uri),
+            )
+        };
+
+        let (site_id, url) = parse_page_uri(uri).ok_or_else(not_found)?;
+
+        let chunks = self
+            .sqlite_db
+            .get_chunks_for_page(Some(site_id), &url)
+            .await
+            .map_err(|e| JsonRpcError::new(error_codes::INTERNAL_ERROR, e.to_string()))?;
+        let page = reassemble_page(&chunks).ok_or_else(not_found)?;
+
+        Ok(ResourceReadResult {
+            contents: vec![ResourceContent {
+                uri: uri.to_string(),
+                mime_type: PAGE_MIME_TYPE.to_string(),
+                text: Some(page.content),
+                blob: None,
+            }],
+        })
+    }
+
+    /// Answer a resources request, producing the response message
+    pub async fn handle_request(
+        &self,
+        id: Value,
+        method: &str,
+        params: Option<Value>,
+    ) -> JsonRpcMessage {
+        let result = match method {
+            methods::RESOURCES_LIST => {
+                let cursor = params
+                    .and_then(|p| serde_json::from_value::<ResourcesListParams>(p).ok())
+                    .and_then(|p| p.cursor);
+                self.list(cursor.as_deref())
+                    .await
+                    .and_then(|result| JsonRpcError::to_value(&result))
+            }
+            methods::RESOURCES_READ => {
+                match params.map(serde_json::from_value::<ResourceReadParams>) {
+                    Some(Ok(params)) => self
+                        .read(&params.uri)
+                        .await
+                        .and_then(|result| JsonRpcError::to_value(&result)),
+                    Some(Err(e)) => Err(JsonRpcError::new(
+                        error_codes::INVALID_PARAMS,
+                        format!("Invalid resource read parameters: {}", e),
+                    )),
+                    None => Err(JsonRpcError::new(
+                        error_codes::INVALID_PARAMS,
+                        "Missing resource read parameters".to_string(),
+                    )),
+                }
+            }
+            methods::RESOURCES_TEMPLATES_LIST => {
+                JsonRpcError::to_value(&ResourceTemplatesListResult {
+                    resource_templates: Self::templates(),
+                    next_cursor: None,
+                })
+            }
+            _ => Err(JsonRpcError::new(
+                error_codes::METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            )),
+        };
+
+        match result {
+            Ok(result) => JsonRpcMessage::response(id, result),
+            Err(e) => {
+                debug!("Resource request '{}' failed: {}", method, e.message);
+                JsonRpcMessage::error(id, e.code, &e.message, None)
+            }
+        }
+    }
+}
+
+/// Error returned to the client for a failed resources request
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl JsonRpcError {
+    fn new(code: i32, message: String) -> Self {
+        Self { code, message }
+    }
+
+    fn to_value(result: &impl serde::Serialize) -> Result<Value, Self> {
+        serde_json::to_value(result)
+            .map_err(|e| Self::new(error_codes::INTERNAL_ERROR, e.to_string()))
+    }
+}
+
+/// Transport wrapper that answers resource requests from indexed pages
+///
+/// All other messages are passed through to the server unchanged.
+#[derive(Clone)]
+pub struct ResourceTransport<T> {
+    inner: T,
+    resources: Arc<PageResources>,
+}
+
+impl<T> ResourceTransport<T> {
+    /// Wrap a transport so resource requests are served from indexed pages
+    pub fn new(inner: T, resources: Arc<PageResources>) -> Self {
+        Self { inner, resources }
+    }
+}
+
+#[async_trait]
+impl<T: Transport + Clone> Transport for ResourceTransport<T> {
+    async fn start(&self, message_tx: mpsc::Sender<JsonRpcMessage>) -> Result<()> {
+        let (inner_tx, mut inner_rx) = mpsc::channel(100);
+        self.inner.start(inner_tx).await?;
+
+        let transport = self.inner.clone();
+        let resources = Arc::clone(&self.resources);
+        tokio::spawn(async move {
+            while let Some(message) = inner_rx.recv().await {
+                match message {
+                    JsonRpcMessage::Request {
+                        id, method, params, ..
+                    } if is_resource_method(&method) => {
+                        let transport = transport.clone();
+                        let resources = Arc::clone(&resources);
+                        tokio::spawn(async move {
+                            let response = resources.handle_request(id, &method, params).await;
+                            if let Err(e) = transport.send(response).await {
+                                warn!("Failed to send resource response: {}", e);
+                            }
+                        });
+                    }
+                    message => {
+                        if message_tx.send(message).await.is_err() {
+                            break;
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(())
+    }
+
+    async fn send(&self, message: JsonRpcMessage) -> Result<()> {
+        self.inner.send(message).await
+    }
+
+    async fn close(&self) -> Result<()> {
+        self.inner.close().await
+    }
+
+    fn box_clone(&self) -> Box<dyn Transport> {
+        Box::new(self.clone())
+    }
+}
+
+/// Notify clients whenever the set of completed sites changes
+///
+/// Indexing runs in a separate process, so SQLite is polled for sites that
+/// have moved to [`SiteStatus::Completed`] (or were re-indexed or removed).
+pub async fn watch_completed_sites(
+    sqlite_db: Arc<SqliteDB>,
+    transport: Box<dyn Transport>,
+    poll_interval: Duration,
+) {
+    let mut known = match completed_sites(&sqlite_db).await {
+        Ok(sites) => sites,
+        Err(e) => {
+            warn!("Failed to load completed sites: {}", e);
+            HashSet::new()
+        }
+    };
+
+    let mut ticker = tokio::time::interval(poll_interval);
+    ticker.tick().await;
+
+    loop {
+        ticker.tick().await;
+
+        let current = match completed_sites(&sqlite_db).await {
+            Ok(sites) => sites,
+            Err(e) => {
+                warn!("Failed to poll completed sites: {}", e);
+                continue;
+            }
+        };
+
+        if current != known {
+            for (site_id, _) in current.difference(&known) {
+                info!("Site {} completed indexing, resource list changed", site_id);
+            }
+
+            let notification = JsonRpcMessage::notification(methods::RESOURCES_LIST_CHANGED, None);
+            if let Err(e) = transport.send(notification).await {
+                // The transport only fails once it has been closed
+                debug!("Stopping completed site watcher: {}", e);
+                break;
+            }
+            known = current;
+        }
+    }
+}
+
+async fn completed_sites(sqlite_db: &SqliteDB) -> Result<HashSet<(i64, Option<NaiveDateTime>)>> {
+    Ok(sqlite_db
+        .get_sites_by_status(SiteStatus::Completed)
+        .await?
+        .into_iter()
+        .map(|site| (site.id, site.indexed_date))
+        .collect())
+}
@@ -0,0 +1,215 @@
+use super::*;
+use crate::database::sqlite::models::{NewIndexedChunk, NewSite, SiteUpdate};
+use crate::database::sqlite::queries::{IndexedChunkQueries, SiteQueries};
+use serde_json::json;
+use tempfile::{NamedTempFile, TempPath};
+
+async fn create_test_resources() -> (TempPath, Arc<SqliteDB>, PageResources) {
+    let temp_file = NamedTempFile::new()
+        .expect("Failed to create temp file")
+        .into_temp_path();
+    let database = Arc::new(
+        SqliteDB::initialize_from_path(&temp_file)
+            .await
+            .expect("Failed to create database"),
+    );
+    let resources = PageResources::new(Arc::clone(&database));
+    (temp_file, database, resources)
+}
+
+async fn create_site_with_pages(
+    database: &SqliteDB,
+    name: &str,
+    status: SiteStatus,
+    pages: &[&str],
+) -> i64 {
+    let site = SiteQueries::create(
+        database.pool(),
+        NewSite {
+            base_url: format!("https://{}.example.com", name),
+            index_url: format!("https://{}.example.com", name),
+            name: name.to_string(),
+            version: "1.0".to_string(),
+        },
+    )
+    .await
+    .expect("Failed to create site");
+
+    database
+        .update_site(
+            site.id,
+            &SiteUpdate {
+                status: Some(status),
+                ..SiteUpdate::default()
+            },
+        )
+        .await
+        .expect("Failed to update site");
+
+    let mut conn = database.pool().acquire().await.expect("got conn");
+    for url in pages {
+        for chunk_index in 0..2 {
+            IndexedChunkQueries::create(
+                &mut conn,
+                NewIndexedChunk {
+                    site_id: site.id,
+                    url: (*url).to_string(),
+                    page_title: Some(format!("Title of {}", url)),
+                    heading_path: Some("Intro".to_string()),
+                    chunk_content: format!("Chunk {} of {}", chunk_index, url),
+                    chunk_index,
+                    vector_id: format!("{}-{}-{}", site.id, url, chunk_index),
+                },
+            )
+            .await
+            .expect("Failed to create chunk");
+        }
+    }
+
+    site.id
+}
+
+#[test]
+fn page_uri_round_trip() {
+    let url = "https://docs.rs/tokio/latest/tokio/macro.select.html?search=a&b#frag";
+    let uri = page_uri(42, url);
+
+    assert!(uri.starts_with("docs://site/42/page?url="));
+    assert_eq!(parse_page_uri(&uri), Some((42, url.to_string())));
+}
+
+#[test]
+fn parse_page_uri_rejects_other_uris() {
+    assert_eq!(parse_page_uri("https://site/1/page?url=x"), None);
+    assert_eq!(parse_page_uri("docs://site/abc/page?url=x"), None);
+    assert_eq!(parse_page_uri("docs://site/1/other?url=x"), None);
+    assert_eq!(parse_page_uri("docs://site/1/page"), None);
+    assert_eq!(parse_page_uri("docs://other/1/page?url=x"), None);
+}
+
+#[tokio::test]
+async fn list_only_includes_completed_sites() {
+    let (_temp_file, database, resources) = create_test_resources().await;
+    let completed = create_site_with_pages(
+        &database,
+        "completed",
+        SiteStatus::Completed,
+        &[
+            "https://completed.example.com/a",
+            "https://completed.example.com/b",
+        ],
+    )
+    .await;
+    create_site_with_pages(
+        &database,
+        "indexing",
+        SiteStatus::Indexing,
+        &["https://indexing.example.com/a"],
+    )
+    .await;
+
+    let result = resources.list(None).await.expect("list should succeed");
+
+    assert_eq!(result.resources.len(), 2);
+    assert!(result.next_cursor.is_none());
+    assert_eq!(
+        result.resources[0].uri,
+        page_uri(completed, "https://completed.example.com/a")
+    );
+    assert_eq!(
+        result.resources[0].name,
+        "Title of https://completed.example.com/a"
+    );
+    assert_eq!(
+        result.resources[0].mime_type.as_deref(),
+        Some(PAGE_MIME_TYPE)
+    );
+}
+
+#[tokio::test]
+async fn list_paginates_with_cursor() {
+    let (_temp_file, database, resources) = create_test_resources().await;
+    let urls: Vec<String> = (0..(RESOURCES_PAGE_SIZE + 5))
+        .map(|i| format!("https://paged.example.com/{:04}", i))
+        .collect();
+    let url_refs: Vec<&str> = urls.iter().map(String::as_str).collect();
+    create_site_with_pages(&database, "paged", SiteStatus::Completed, &url_refs).await;
+
+    let first = resources.list(None).await.expect("list should succeed");
+    assert_eq!(first.resources.len() as i64, RESOURCES_PAGE_SIZE);
+    let cursor = first.next_cursor.expect("should have another page");
+
+    let second = resources
+        .list(Some(&cursor))
+        .await
+        .expect("list should succeed");
+    assert_eq!(second.resources.len(), 5);
+    assert!(second.next_cursor.is_none());
+
+    let invalid = resources.list(Some("not-a-cursor")).await;
+    assert_eq!(
+        invalid.expect_err("invalid cursor").code,
+        error_codes::INVALID_PARAMS
+    );
+}
+
+#[tokio::test]
+async fn read_reassembles_page() {
+    let (_temp_file, database, resources) = create_test_resources().await;
+    let site_id = create_site_with_pages(
+        &database,
+        "docs",
+        SiteStatus::Completed,
+        &["https://docs.example.com/page"],
+    )
+    .await;
+
+    let uri = page_uri(site_id, "https://docs.example.com/page");
+    let result = resources.read(&uri).await.expect("read should succeed");
+
+    assert_eq!(result.contents.len(), 1);
+    assert_eq!(result.contents[0].uri, uri);
+    assert_eq!(
+        result.contents[0].text.as_deref(),
+        Some(
+            "# Intro\n\nChunk 0 of https://docs.example.com/page\n\nChunk 1 of https://docs.example.com/page"
+        )
+    );
+
+    let missing = resources
+        .read(&page_uri(site_id, "https://docs.example.com/missing"))
+        .await;
+    assert_eq!(
+        missing.expect_err("missing page").code,
+        error_codes::RESOURCE_NOT_FOUND
+    );
+}
+
+#[tokio::test]
+async fn handle_request_builds_responses() {
+    let (_temp_file, _database, resources) = create_test_resources().await;
+
+    let templates = resources
+        .handle_request(json!(1), methods::RESOURCES_TEMPLATES_LIST, None)
+        .await;
+    let JsonRpcMessage::Response { id, result, .. } = templates else {
+        panic!("expected a response");
+    };
+    assert_eq!(id, json!(1));
+    let result = result.expect("templates list has a result");
+    assert_eq!(
+        result["resourceTemplates"][0]["uriTemplate"],
+        PAGE_URI_TEMPLATE
+    );
+
+    let invalid = resources
+        .handle_request(json!(2), methods::RESOURCES_READ, None)
+        .await;
+    let JsonRpcMessage::Response { error, .. } = invalid else {
+        panic!("expected a response");
+    };
+    assert_eq!(
+        error.expect("missing params is an error").code,
+        error_codes::INVALID_PARAMS
+    );
+}
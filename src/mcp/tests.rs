@@ -12,9 +12,10 @@ mod search_docs_tool_tests {
         let tool = SearchDocsHandler::tool_definition();
 
         assert_eq!(tool.name, "search_docs");
-        assert_eq!(
-            tool.description,
-            Some("Search indexed documentation".to_string())
+        assert!(
+            tool.description
+                .as_deref()
+                .is_some_and(|description| description.starts_with("Search indexed documentation"))
         );
 
         // Verify required parameters
@@ -23,8 +24,21 @@ mod search_docs_tool_tests {
 
         assert!(properties.contains_key("query"));
         assert!(properties.contains_key("site_id"));
+        assert!(properties.contains_key("site_ids"));
         assert!(properties.contains_key("sites_filter"));
+        assert!(properties.contains_key("site_name"));
+        assert!(properties.contains_key("version"));
         assert!(properties.contains_key("limit"));
+        assert!(properties.contains_key("group_by_page"));
+        assert_eq!(schema["properties"]["group_by_page"]["type"], "boolean");
+        assert_eq!(
+            schema["properties"]["mode"]["enum"],
+            serde_json::json!(["semantic", "keyword", "hybrid"])
+        );
+        assert_eq!(schema["properties"]["site_ids"]["type"], "array");
+        assert_eq!(schema["properties"]["min_score"]["type"], "number");
+        assert_eq!(schema["properties"]["min_score"]["minimum"], 0);
+        assert_eq!(schema["properties"]["min_score"]["maximum"], 1);
 
         let required = schema["required"].as_array().expect("has required array");
         assert_eq!(required.len(), 1);
@@ -39,14 +53,14 @@ mod search_docs_tool_tests {
         // Verify query parameter
         let query_prop = &schema["properties"]["query"];
         assert_eq!(query_prop["type"], "string");
-        assert_eq!(query_prop["description"], "Search query");
+        assert_eq!(query_prop["description"], "Text string to search for");
 
         // Verify site_id parameter
         let site_id_prop = &schema["properties"]["site_id"];
         assert_eq!(site_id_prop["type"], "integer");
         assert_eq!(
             site_id_prop["description"],
-            "Optional: Search specific site by ID"
+            "Optional: Search specific site by ID (retrieved from `list_sites` tool)"
         );
 
         // Verify sites_filter parameter
@@ -54,7 +68,7 @@ mod search_docs_tool_tests {
         assert_eq!(sites_filter_prop["type"], "string");
         assert_eq!(
             sites_filter_prop["description"],
-            "Optional: Regex pattern to filter sites (e.g., 'docs.rs')"
+            "Optional: Case-insensitive regex pattern to filter sites by name or URL (e.g., 'docs.rs' or '^tokio'). All matching sites are searched."
         );
 
         // Verify limit parameter
@@ -62,7 +76,7 @@ mod search_docs_tool_tests {
         assert_eq!(limit_prop["type"], "integer");
         assert_eq!(
             limit_prop["description"],
-            "Maximum number of results (default: 10)"
+            "Optional: Maximum number of results (default: 10)"
         );
     }
 }
@@ -78,7 +92,7 @@ mod list_sites_tool_tests {
         assert_eq!(tool.name, "list_sites");
         assert_eq!(
             tool.description,
-            Some("List available documentation sites".to_string())
+            Some("List the documentation sites which have been indexed for searching.".to_string())
         );
 
         // Should have no parameters
@@ -87,3 +101,844 @@ mod list_sites_tool_tests {
         assert!(properties.is_empty());
     }
 }
+
+#[cfg(test)]
+mod get_page_tool_tests {
+    use crate::mcp::tools::GetPageHandler;
+
+    #[test]
+    fn get_page_tool_definition() {
+        let tool = GetPageHandler::tool_definition();
+
+        assert_eq!(tool.name, "get_page");
+
+        let schema = tool.input_schema;
+        let properties = schema["properties"].as_object().expect("has properties");
+
+        assert!(properties.contains_key("url"));
+        assert!(properties.contains_key("site_id"));
+        assert_eq!(schema["properties"]["url"]["type"], "string");
+        assert_eq!(schema["properties"]["site_id"]["type"], "integer");
+
+        let required = schema["required"].as_array().expect("has required array");
+        assert_eq!(required.len(), 1);
+        assert_eq!(required[0], "url");
+    }
+}
+
+#[cfg(test)]
+mod get_context_tool_tests {
+    use crate::mcp::tools::GetContextHandler;
+
+    #[test]
+    fn get_context_tool_definition() {
+        let tool = GetContextHandler::tool_definition();
+
+        assert_eq!(tool.name, "get_context");
+
+        let schema = tool.input_schema;
+        let properties = schema["properties"].as_object().expect("has properties");
+
+        assert!(properties.contains_key("chunk_id"));
+        assert!(properties.contains_key("before"));
+        assert!(properties.contains_key("after"));
+        assert_eq!(schema["properties"]["chunk_id"]["type"], "string");
+        assert_eq!(schema["properties"]["before"]["type"], "integer");
+        assert_eq!(schema["properties"]["after"]["type"], "integer");
+
+        let required = schema["required"].as_array().expect("has required array");
+        assert_eq!(required.len(), 1);
+        assert_eq!(required[0], "chunk_id");
+    }
+}
+
+#[cfg(test)]
+mod indexing_tool_tests {
+    use crate::mcp::tools::{IndexSiteHandler, IndexingStatusHandler};
+
+    #[test]
+    fn index_site_tool_definition() {
+        let tool = IndexSiteHandler::tool_definition();
+
+        assert_eq!(tool.name, "index_site");
+
+        let schema = tool.input_schema;
+        let properties = schema["properties"].as_object().expect("has properties");
+
+        for key in ["url", "name", "version", "base_url"] {
+            assert!(properties.contains_key(key));
+            assert_eq!(schema["properties"][key]["type"], "string");
+        }
+
+        let required = schema["required"].as_array().expect("has required array");
+        assert_eq!(required.len(), 1);
+        assert_eq!(required[0], "url");
+    }
+
+    #[test]
+    fn indexing_status_tool_definition() {
+        let tool = IndexingStatusHandler::tool_definition();
+
+        assert_eq!(tool.name, "indexing_status");
+
+        let schema = tool.input_schema;
+        let properties = schema["properties"].as_object().expect("has properties");
+
+        assert_eq!(properties.len(), 1);
+        assert_eq!(schema["properties"]["job_id"]["type"], "string");
+        assert!(schema.get("required").is_none());
+    }
+}
+
+#[cfg(test)]
+mod page_reassembly_tests {
+    use crate::database::sqlite::models::IndexedChunk;
+    use crate::mcp::pages::{join_chunks, reassemble_page, strip_chunk_overlap};
+
+    fn chunk(index: i64, heading_path: &str, content: &str) -> IndexedChunk {
+        IndexedChunk {
+            id: index + 1,
+            site_id: 1,
+            url: "https://example.com/docs/page".to_string(),
+            page_title: Some("Page".to_string()),
+            heading_path: Some(heading_path.to_string()),
+            chunk_content: content.to_string(),
+            chunk_index: index,
+            vector_id: format!("vector-{}", index),
+            indexed_date: chrono::Utc::now().naive_utc(),
+        }
+    }
+
+    #[test]
+    fn strip_overlap_removes_matching_prefix() {
+        let previous = "The first chunk ends with these words";
+        let current = "ends with these words\n\nThe second chunk starts here";
+
+        assert_eq!(
+            strip_chunk_overlap(previous, current),
+            "The second chunk starts here"
+        );
+    }
+
+    #[test]
+    fn strip_overlap_keeps_unrelated_paragraphs() {
+        let previous = "The first chunk ends here";
+        let current = "A new paragraph\n\nAnother paragraph";
+
+        assert_eq!(strip_chunk_overlap(previous, current), current);
+        assert_eq!(
+            strip_chunk_overlap(previous, "Single paragraph"),
+            "Single paragraph"
+        );
+    }
+
+    #[test]
+    fn reassemble_orders_chunks_and_builds_outline() {
+        let chunks = vec![
+            chunk(2, "Guide > Usage", "Call the function."),
+            chunk(0, "Guide", "Introduction text here"),
+            chunk(1, "Guide", "text here\n\nMore introduction."),
+        ];
+
+        let page = reassemble_page(&chunks).expect("page is reassembled");
+
+        assert_eq!(page.chunk_count, 3);
+        assert_eq!(page.outline, vec!["Guide", "Guide > Usage"]);
+        assert_eq!(
+            page.content,
+            "# Guide\n\nIntroduction text here\n\nMore introduction.\n\n## Usage\n\nCall the function."
+        );
+    }
+
+    #[test]
+    fn reassemble_empty_returns_none() {
+        assert!(reassemble_page(&[]).is_none());
+    }
+
+    #[test]
+    fn join_chunks_marks_gaps() {
+        let joined = join_chunks([
+            (0, "First chunk with tail words"),
+            (1, "tail words\n\nSecond chunk."),
+            (4, "Fifth chunk."),
+        ]);
+
+        assert_eq!(
+            joined,
+            "First chunk with tail words\n\nSecond chunk.\n\n[...]\n\nFifth chunk."
+        );
+        assert_eq!(join_chunks([]), "");
+    }
+}
+
+#[cfg(test)]
+mod version_resolution_tests {
+    use crate::database::sqlite::models::{Site, SiteStatus};
+    use crate::mcp::versions::{
+        VersionRequest, parse_site_version, resolve_version, resolve_versions,
+    };
+
+    fn site(id: i64, name: &str, version: &str) -> Site {
+        let now = chrono::Utc::now().naive_utc();
+        Site {
+            id,
+            index_url: format!("https://example.com/{}/{}/", name, version),
+            base_url: format!("https://example.com/{}/{}/", name, version),
+            name: name.to_string(),
+            version: version.to_string(),
+            indexed_date: Some(now),
+            status: SiteStatus::Completed,
+            progress_percent: 100,
+            total_pages: 1,
+            indexed_pages: 1,
+            error_message: None,
+            created_date: now,
+            last_heartbeat: None,
+            embedding_model: None,
+            embedding_dimension: None,
+            embedding_document_prefix: None,
+        }
+    }
+
+    fn resolved_id(sites: &[Site], version: &str) -> Option<i64> {
+        resolve_version(sites, &VersionRequest::parse(version)).map(|site| site.id)
+    }
+
+    #[test]
+    fn parse_site_version_pads_components() {
+        assert_eq!(
+            parse_site_version("16").map(|v| v.to_string()),
+            Some("16.0.0".to_string())
+        );
+        assert_eq!(
+            parse_site_version("v1.2").map(|v| v.to_string()),
+            Some("1.2.0".to_string())
+        );
+        assert_eq!(
+            parse_site_version("1.0-beta.1").map(|v| v.to_string()),
+            Some("1.0.0-beta.1".to_string())
+        );
+        assert!(parse_site_version("latest").is_none());
+        assert!(parse_site_version("jessie").is_none());
+    }
+
+    #[test]
+    fn parse_version_request() {
+        assert_eq!(VersionRequest::parse("Latest"), VersionRequest::Latest);
+        assert!(matches!(
+            VersionRequest::parse(">=0.8, <0.9"),
+            VersionRequest::Requirement(_)
+        ));
+        assert!(matches!(
+            VersionRequest::parse("v1.2"),
+            VersionRequest::Requirement(_)
+        ));
+        assert_eq!(
+            VersionRequest::parse("jessie"),
+            VersionRequest::Exact("jessie".to_string())
+        );
+    }
+
+    #[test]
+    fn resolves_highest_matching_version() {
+        let sites = vec![
+            site(1, "tokio", "0.8.4"),
+            site(2, "tokio", "1.2.0"),
+            site(3, "tokio", "1.47.1"),
+            site(4, "tokio", "0.9"),
+        ];
+
+        assert_eq!(resolved_id(&sites, "^1.2"), Some(3));
+        assert_eq!(resolved_id(&sites, "1.2"), Some(3));
+        assert_eq!(resolved_id(&sites, "=1.2.0"), Some(2));
+        assert_eq!(resolved_id(&sites, ">=0.8, <0.9"), Some(1));
+        assert_eq!(resolved_id(&sites, "0.9"), Some(4));
+        assert_eq!(resolved_id(&sites, "latest"), Some(3));
+        assert_eq!(resolved_id(&sites, "^2"), None);
+    }
+
+    #[test]
+    fn latest_prefers_latest_site() {
+        let sites = vec![
+            site(1, "react", "16"),
+            site(2, "react", "latest"),
+            site(3, "react", "18.2"),
+        ];
+
+        assert_eq!(resolved_id(&sites, "latest"), Some(2));
+        assert_eq!(resolved_id(&sites, "16"), Some(1));
+    }
+
+    #[test]
+    fn exact_versions_match_non_semver() {
+        let sites = vec![site(1, "debian", "jessie"), site(2, "debian", "bookworm")];
+
+        assert_eq!(resolved_id(&sites, "Bookworm"), Some(2));
+        assert_eq!(resolved_id(&sites, "trixie"), None);
+    }
+
+    #[test]
+    fn resolves_each_site_name_separately() {
+        let sites = vec![
+            site(1, "tokio", "1.2.0"),
+            site(2, "Tokio", "1.47.1"),
+            site(3, "react", "18.2"),
+            site(4, "react", "16"),
+            site(5, "serde", "0.9"),
+        ];
+
+        let ids: Vec<i64> = resolve_versions(&sites, &VersionRequest::parse("latest"))
+            .into_iter()
+            .map(|site| site.id)
+            .collect();
+        assert_eq!(ids, vec![2, 3, 5]);
+
+        let ids: Vec<i64> = resolve_versions(&sites, &VersionRequest::parse("^1"))
+            .into_iter()
+            .map(|site| site.id)
+            .collect();
+        assert_eq!(ids, vec![2]);
+    }
+}
+
+#[cfg(test)]
+mod page_grouping_tests {
+    use crate::database::lancedb::ChunkMetadata;
+    use crate::database::lancedb::vector_store::SearchResult;
+    use crate::mcp::pages::group_results_by_page;
+
+    fn hit(url: &str, index: u32, content: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_metadata: ChunkMetadata {
+                chunk_id: format!("{}#{}", url, index),
+                site_id: "1".to_string(),
+                page_title: "Page".to_string(),
+                page_url: format!("https://example.com/{}", url),
+                heading_path: Some("Guide".to_string()),
+                content: content.to_string(),
+                token_count: 10,
+                chunk_index: index,
+                created_at: String::new(),
+            },
+            similarity_score: score,
+            distance: 1.0 - score,
+            score_breakdown: None,
+        }
+    }
+
+    #[test]
+    fn merges_adjacent_hits_and_strips_overlap() {
+        let results = vec![
+            hit("a", 2, "shared tail\n\nThird chunk.", 0.9),
+            hit("a", 1, "Second chunk with shared tail", 0.8),
+            hit("a", 5, "Sixth chunk.", 0.7),
+            hit("a", 2, "shared tail\n\nThird chunk.", 0.6),
+        ];
+
+        let pages = group_results_by_page(results, 10);
+
+        assert_eq!(pages.len(), 1);
+        let page = &pages[0];
+        assert_eq!(page.chunk_id, "a#2");
+        assert_eq!(page.chunk_index, 2);
+        assert_eq!(page.chunk_indexes, vec![1, 2, 5]);
+        assert_eq!(page.similarity_score, 0.9);
+        assert_eq!(
+            page.content,
+            "Second chunk with shared tail\n\nThird chunk.\n\n[...]\n\nSixth chunk."
+        );
+    }
+
+    #[test]
+    fn fills_limit_with_distinct_pages() {
+        let results = vec![
+            hit("a", 0, "A0", 0.95),
+            hit("a", 1, "A1", 0.94),
+            hit("b", 3, "B3", 0.9),
+            hit("a", 2, "A2", 0.85),
+            hit("c", 0, "C0", 0.8),
+            hit("d", 0, "D0", 0.7),
+        ];
+
+        let pages = group_results_by_page(results, 3);
+
+        let urls: Vec<&str> = pages.iter().map(|p| p.url.as_str()).collect();
+        assert_eq!(
+            urls,
+            vec![
+                "https://example.com/a",
+                "https://example.com/b",
+                "https://example.com/c"
+            ]
+        );
+        assert_eq!(pages[0].content, "A0\n\nA1\n\nA2");
+    }
+}
+
+#[cfg(test)]
+mod context_packing_tests {
+    use std::sync::Arc;
+
+    use crate::embeddings::chunking::ChunkSizer;
+    use crate::embeddings::tests::write_tokenizer;
+    use crate::embeddings::tokenizer::WordPieceTokenizer;
+    use crate::mcp::context::{SectionCandidate, pack_context};
+    use crate::mcp::tools::BuildContextHandler;
+
+    fn candidate(url: &str, heading: &str, renderings: &[&str]) -> SectionCandidate {
+        SectionCandidate {
+            url: format!("https://docs.example.com/{}", url),
+            page_title: Some("Guide".to_string()),
+            heading_path: Some(heading.to_string()),
+            site_name: "Example".to_string(),
+            site_version: "1.0".to_string(),
+            renderings: renderings.iter().map(ToString::to_string).collect(),
+        }
+    }
+
+    fn words(count: usize) -> String {
+        vec!["word"; count].join(" ")
+    }
+
+    #[test]
+    fn build_context_tool_definition() {
+        let tool = BuildContextHandler::tool_definition();
+
+        assert_eq!(tool.name, "build_context");
+
+        let schema = tool.input_schema;
+        assert_eq!(schema["properties"]["query"]["type"], "string");
+        assert_eq!(schema["properties"]["max_tokens"]["type"], "integer");
+        for key in [
+            "mode",
+            "min_score",
+            "site_id",
+            "site_ids",
+            "sites_filter",
+            "site_name",
+            "version",
+        ] {
+            assert!(schema["properties"].get(key).is_some(), "missing {}", key);
+        }
+
+        let required = schema["required"].as_array().expect("has required array");
+        assert_eq!(required.len(), 2);
+        assert!(required.contains(&"query".into()));
+        assert!(required.contains(&"max_tokens".into()));
+    }
+
+    #[test]
+    fn includes_sections_with_citations() {
+        let candidates = vec![
+            candidate("install", "Installation", &["Run the installer."]),
+            candidate("usage", "Usage", &["Call the function."]),
+        ];
+
+        let dir = tempfile::TempDir::new().expect("Failed to create temp dir");
+        write_tokenizer(
+            dir.path(),
+            &["[PAD]", "[UNK]", "[CLS]", "[SEP]", "guide", "run", "the"],
+        );
+        let tokenizer = WordPieceTokenizer::load(&dir.path().join("tokenizer.json"))
+            .expect("Failed to load tokenizer");
+        let sizer = ChunkSizer::new(Some(Arc::new(tokenizer)), None, "");
+
+        let context = pack_context(&candidates, 1000, &sizer);
+
+        assert_eq!(context.sections, 2);
+        assert_eq!(context.omitted, 0);
+        assert_eq!(
+            context.markdown,
+            "## [1] Guide: Installation\n\nRun the installer.\n\n\
+             ## [2] Guide: Usage\n\nCall the function.\n\n\
+             ## Sources\n\n\
+             [1] Guide (Example 1.0): https://docs.example.com/install\n\
+             [2] Guide (Example 1.0): https://docs.example.com/usage"
+        );
+        assert_eq!(context.token_count, sizer.count_tokens(&context.markdown));
+    }
+
+    #[test]
+    fn falls_back_to_smaller_rendering() {
+        let whole_section = words(300);
+        let candidates = vec![candidate(
+            "install",
+            "Installation",
+            &[&whole_section, "Only the matching chunk."],
+        )];
+
+        let context = pack_context(&candidates, 100, &ChunkSizer::default());
+
+        assert_eq!(context.sections, 1);
+        assert!(context.markdown.contains("Only the matching chunk."));
+        assert!(!context.markdown.contains(&whole_section));
+        assert!(context.token_count <= 100);
+    }
+
+    #[test]
+    fn skips_sections_over_budget() {
+        let large = words(300);
+        let candidates = vec![
+            candidate("large", "Large", &[&large]),
+            candidate("small", "Small", &["Fits in the budget."]),
+        ];
+
+        let context = pack_context(&candidates, 100, &ChunkSizer::default());
+
+        assert_eq!(context.sections, 1);
+        assert_eq!(context.omitted, 1);
+        assert!(context.markdown.starts_with("## [1] Guide: Small"));
+        assert!(!context.markdown.contains("docs.example.com/large"));
+        assert!(context.token_count <= 100);
+
+        let empty = pack_context(&candidates, 5, &ChunkSizer::default());
+        assert_eq!(empty.sections, 0);
+        assert_eq!(empty.omitted, 2);
+        assert!(empty.markdown.is_empty());
+    }
+}
+
+#[cfg(test)]
+mod query_cache_tests {
+    use crate::mcp::query_cache::{QueryCacheStats, QueryEmbeddingCache};
+    use crate::mcp::tools::ServerStatsHandler;
+    use anyhow::anyhow;
+    use std::cell::Cell;
+    use std::num::NonZeroUsize;
+
+    fn cache(capacity: usize) -> QueryEmbeddingCache {
+        QueryEmbeddingCache::new(NonZeroUsize::new(capacity).expect("non-zero capacity"))
+    }
+
+    #[test]
+    fn server_stats_tool_definition() {
+        let tool = ServerStatsHandler::tool_definition();
+
+        assert_eq!(tool.name, "server_stats");
+        let properties = tool.input_schema["properties"]
+            .as_object()
+            .expect("has properties");
+        assert!(properties.is_empty());
+    }
+
+    #[tokio::test]
+    async fn reuses_embeddings_per_model() {
+        let cache = cache(4);
+        let calls = Cell::new(0);
+        let generate = |query: &str| {
+            calls.set(calls.get() + 1);
+            Ok(vec![query.len() as f32])
+        };
+
+        let first = cache
+            .get_or_generate("model-a", "async errors", async {
+                generate("async errors")
+            })
+            .await
+            .expect("embedding");
+        let repeated = cache
+            .get_or_generate("model-a", "  async   errors ", async {
+                generate("  async   errors ")
+            })
+            .await
+            .expect("embedding");
+        cache
+            .get_or_generate("model-b", "async errors", async {
+                generate("async errors")
+            })
+            .await
+            .expect("embedding");
+
+        assert_eq!(first, repeated);
+        assert_eq!(calls.get(), 2);
+        assert_eq!(
+            cache.stats(),
+            QueryCacheStats {
+                hits: 1,
+                misses: 2,
+                entries: 2,
+                capacity: 4,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let cache = cache(2);
+        let calls = Cell::new(0);
+        let generate = || {
+            calls.set(calls.get() + 1);
+            Ok(vec![1.0])
+        };
+
+        for query in ["one", "two", "one", "three", "one", "two"] {
+            cache
+                .get_or_generate("model", query, async { generate() })
+                .await
+                .expect("embedding");
+        }
+
+        // "two" was evicted by "three", "one" stayed recently used
+        assert_eq!(calls.get(), 4);
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 4, 2));
+    }
+
+    #[tokio::test]
+    async fn does_not_cache_failures() {
+        let cache = cache(2);
+
+        let failed = cache
+            .get_or_generate("model", "query", async { Err(anyhow!("offline")) })
+            .await;
+        assert!(failed.is_err());
+
+        let embedding = cache
+            .get_or_generate("model", "query", async { Ok(vec![0.5]) })
+            .await
+            .expect("embedding");
+        assert_eq!(embedding, vec![0.5]);
+        assert_eq!(cache.stats().misses, 2);
+        assert_eq!(cache.stats().hits, 0);
+    }
+}
+
+#[cfg(test)]
+mod hybrid_search_tests {
+    use crate::database::lancedb::ChunkMetadata;
+    use crate::database::lancedb::vector_store::SearchResult;
+    use crate::database::sqlite::models::{ChunkMatch, IndexedChunk};
+    use crate::mcp::hybrid::{
+        RRF_K, SearchMode, keyword_result, normalize_bm25, reciprocal_rank_fusion,
+    };
+    use chrono::NaiveDateTime;
+
+    fn hit(chunk_id: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_metadata: ChunkMetadata {
+                chunk_id: chunk_id.to_string(),
+                site_id: "1".to_string(),
+                page_title: "Page".to_string(),
+                page_url: "https://example.com/page".to_string(),
+                heading_path: None,
+                content: format!("Content of {}", chunk_id),
+                token_count: 10,
+                chunk_index: 0,
+                created_at: String::new(),
+            },
+            similarity_score: score,
+            distance: 1.0 - score,
+            score_breakdown: None,
+        }
+    }
+
+    fn chunk_ids(results: &[SearchResult]) -> Vec<&str> {
+        results
+            .iter()
+            .map(|r| r.chunk_metadata.chunk_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn parses_modes() {
+        assert_eq!(SearchMode::parse("semantic"), Some(SearchMode::Semantic));
+        assert_eq!(SearchMode::parse(" Keyword "), Some(SearchMode::Keyword));
+        assert_eq!(SearchMode::parse("HYBRID"), Some(SearchMode::Hybrid));
+        assert_eq!(SearchMode::parse("bm25"), None);
+        assert_eq!(SearchMode::default(), SearchMode::Hybrid);
+        assert!(!SearchMode::Keyword.uses_embeddings());
+        for name in SearchMode::NAMES {
+            assert!(SearchMode::parse(name).is_some());
+        }
+    }
+
+    #[test]
+    fn fusion_favours_chunks_found_by_both_rankings() {
+        let semantic = vec![hit("a", 0.9), hit("b", 0.8), hit("c", 0.7)];
+        let keyword = vec![hit("d", 12.0), hit("c", 8.0)];
+
+        let fused = reciprocal_rank_fusion(vec![semantic, keyword], 10);
+
+        assert_eq!(chunk_ids(&fused), vec!["c", "a", "d", "b"]);
+        // Fused scores are relative to a chunk ranked first in both rankings
+        let expected = (1.0 / (RRF_K + 3.0) + 1.0 / (RRF_K + 2.0)) / (2.0 / (RRF_K + 1.0));
+        assert!((fused[0].similarity_score - expected).abs() < 1e-6);
+        assert!(
+            fused
+                .iter()
+                .all(|result| (0.0..=1.0).contains(&result.similarity_score))
+        );
+        // The semantic copy of a chunk is kept
+        assert_eq!(fused[0].distance, 1.0 - 0.7);
+    }
+
+    #[test]
+    fn fusion_respects_limit_and_empty_rankings() {
+        let fused = reciprocal_rank_fusion(vec![Vec::new(), vec![hit("x", 3.0), hit("y", 2.0)]], 1);
+        assert_eq!(chunk_ids(&fused), vec!["x"]);
+
+        assert!(reciprocal_rank_fusion(vec![Vec::new(), Vec::new()], 10).is_empty());
+    }
+
+    #[test]
+    fn keyword_matches_become_search_results() {
+        let result = keyword_result(ChunkMatch {
+            chunk: IndexedChunk {
+                id: 7,
+                site_id: 3,
+                url: "https://example.com/errors".to_string(),
+                page_title: None,
+                heading_path: Some("Errors".to_string()),
+                chunk_content: "SQLITE_BUSY is returned when the database is locked.".to_string(),
+                chunk_index: 4,
+                vector_id: "vector-7".to_string(),
+                indexed_date: NaiveDateTime::default(),
+            },
+            score: 4.5,
+        });
+
+        let metadata = &result.chunk_metadata;
+        assert_eq!(metadata.chunk_id, "vector-7");
+        assert_eq!(metadata.site_id, "3");
+        assert_eq!(metadata.page_title, "");
+        assert_eq!(metadata.chunk_index, 4);
+        assert!(metadata.token_count > 0);
+        assert_eq!(result.similarity_score, 4.5 / 5.5);
+    }
+
+    #[test]
+    fn bm25_scores_are_normalized() {
+        assert_eq!(normalize_bm25(0.0), 0.0);
+        assert_eq!(normalize_bm25(-2.0), 0.0);
+        assert_eq!(normalize_bm25(1.0), 0.5);
+        assert!(normalize_bm25(3.0) < normalize_bm25(30.0));
+        assert!(normalize_bm25(1000.0) < 1.0);
+    }
+}
+
+#[cfg(test)]
+mod rerank_tests {
+    use crate::config::settings::RerankingConfig;
+    use crate::database::lancedb::ChunkMetadata;
+    use crate::database::lancedb::vector_store::SearchResult;
+    use crate::mcp::rerank::{
+        LEXICAL_RERANKER, MAX_RERANK_CANDIDATES, apply_rerank_scores, lexical_scores,
+        rerank_candidates,
+    };
+
+    fn hit(chunk_id: &str, content: &str, score: f32) -> SearchResult {
+        SearchResult {
+            chunk_metadata: ChunkMetadata {
+                chunk_id: chunk_id.to_string(),
+                site_id: "1".to_string(),
+                page_title: "Tokio".to_string(),
+                page_url: "https://example.com/page".to_string(),
+                heading_path: None,
+                content: content.to_string(),
+                token_count: 10,
+                chunk_index: 0,
+                created_at: String::new(),
+            },
+            similarity_score: score,
+            distance: 1.0 - score,
+            score_breakdown: None,
+        }
+    }
+
+    fn chunk_ids(results: &[SearchResult]) -> Vec<&str> {
+        results
+            .iter()
+            .map(|r| r.chunk_metadata.chunk_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn candidates_are_over_fetched_when_enabled() {
+        let config = RerankingConfig {
+            enabled: true,
+            ..RerankingConfig::default()
+        };
+        assert_eq!(rerank_candidates(&config, 10), 50);
+        assert_eq!(rerank_candidates(&config, 50), MAX_RERANK_CANDIDATES);
+        assert_eq!(rerank_candidates(&config, 500), 500);
+
+        let disabled = RerankingConfig {
+            enabled: false,
+            ..RerankingConfig::default()
+        };
+        assert_eq!(rerank_candidates(&disabled, 10), 10);
+    }
+
+    #[test]
+    fn lexical_scores_weight_rare_terms() {
+        let results = vec![
+            hit("a", "Spawning tasks on the runtime", 0.9),
+            hit("b", "Waiting on several tasks with select", 0.8),
+            hit("c", "Nothing relevant here", 0.7),
+        ];
+
+        let scores = lexical_scores("select between tasks", &results);
+
+        assert!(scores[1] > scores[0]);
+        assert!(scores[0] > scores[2]);
+        assert_eq!(scores[2], 0.0);
+        assert!(scores.iter().all(|score| (0.0..=1.0).contains(score)));
+    }
+
+    #[test]
+    fn lexical_reranking_blends_with_retrieval_scores() {
+        let results = vec![
+            hit("a", "Spawning tasks on the runtime", 0.9),
+            hit("b", "Shutting down the runtime", 0.85),
+            hit("c", "Waiting on several tasks with tokio::select!", 0.8),
+            hit("d", "Nothing relevant here", 0.5),
+        ];
+        let scores = lexical_scores("tokio::select! tasks", &results);
+
+        let reranked = apply_rerank_scores(results, &scores, LEXICAL_RERANKER, 2);
+
+        assert_eq!(chunk_ids(&reranked), vec!["c", "a"]);
+        let breakdown = reranked[0]
+            .score_breakdown
+            .as_ref()
+            .expect("reranked results have a breakdown");
+        assert_eq!(breakdown.reranker, "lexical");
+        assert_eq!(breakdown.retrieval_score, 0.8);
+        assert_eq!(breakdown.rerank_score, scores[2]);
+        let expected = f32::midpoint(0.8, breakdown.rerank_score);
+        assert!((reranked[0].similarity_score - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn model_scores_replace_retrieval_scores() {
+        let results = vec![hit("a", "A", 0.9), hit("b", "B", 0.5)];
+
+        let reranked = apply_rerank_scores(results, &[0.1, 0.7], "bge-reranker", 10);
+
+        assert_eq!(chunk_ids(&reranked), vec!["b", "a"]);
+        assert_eq!(reranked[0].similarity_score, 0.7);
+        let breakdown = reranked[0]
+            .score_breakdown
+            .as_ref()
+            .expect("reranked results have a breakdown");
+        assert_eq!(breakdown.reranker, "bge-reranker");
+        assert_eq!(breakdown.retrieval_score, 0.5);
+    }
+
+    #[test]
+    fn model_logits_are_mapped_to_probabilities() {
+        let results = vec![hit("a", "A", 0.9), hit("b", "B", 0.5)];
+
+        let reranked = apply_rerank_scores(results, &[-3.0, 2.0], "bge-reranker", 10);
+
+        assert_eq!(chunk_ids(&reranked), vec!["b", "a"]);
+        assert!((reranked[0].similarity_score - 1.0 / (1.0 + (-2.0f32).exp())).abs() < 1e-6);
+        assert!(reranked[1].similarity_score > 0.0 && reranked[1].similarity_score < 0.5);
+        // The breakdown keeps the model's raw score
+        let breakdown = reranked[0]
+            .score_breakdown
+            .as_ref()
+            .expect("reranked results have a breakdown");
+        assert_eq!(breakdown.rerank_score, 2.0);
+    }
+}
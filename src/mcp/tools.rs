@@ -3,16 +3,33 @@
 //! This module provides the tool registration and discovery system,
 //! along with concrete tool implementations for documentation search.
 
-use crate::database::lancedb::vector_store::VectorStore;
-use crate::database::sqlite::{Database as SqliteDB, models::SiteStatus};
-use crate::embeddings::ollama::OllamaClient;
+use crate::config::Config;
+use crate::database::lancedb::vector_store::{SearchResult, VectorStore};
+use crate::database::sqlite::{
+    Database as SqliteDB,
+    models::{IndexedChunk, Site, SiteStatus},
+};
+use crate::embeddings::EmbeddingProvider;
+use crate::embeddings::chunking::ChunkSizer;
+use crate::mcp::context::{SectionCandidate, pack_context};
+use crate::mcp::hybrid::{SearchMode, keyword_result, reciprocal_rank_fusion};
+use crate::mcp::jobs::{IndexSiteRequest, IndexingJobs, JobState};
+use crate::mcp::pages::{group_results_by_page, join_chunks, reassemble_page};
+use crate::mcp::query_cache::QueryEmbeddingCache;
+use crate::mcp::reload::Reloadable;
+use crate::mcp::rerank::{
+    LEXICAL_RERANKER, apply_rerank_scores, lexical_scores, rerank_candidates, rerank_document,
+};
+use crate::mcp::versions::{LATEST_VERSION, VersionRequest, resolve_versions};
 use anyhow::{Result, anyhow};
 use async_trait::async_trait;
+use fancy_regex::RegexBuilder;
+use itertools::Itertools;
 use modelcontextprotocol_server::mcp_protocol::tool::{Tool, ToolCallResult, ToolContent};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use std::{collections::HashMap, sync::Arc};
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 /// Tool handler trait for implementing tool execution
 #[async_trait]
@@ -29,11 +46,19 @@ pub struct CallToolParams {
 
 /// Documentation search tool handler
 pub struct SearchDocsHandler {
+    config: Reloadable<Config>,
     sqlite_db: Arc<SqliteDB>,
     vector_store: Arc<VectorStore>,
-    ollama_client: Arc<OllamaClient>,
+    embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+    query_cache: Arc<QueryEmbeddingCache>,
 }
 
+/// Number of chunks fetched per requested result when grouping by page
+const PAGE_GROUP_CANDIDATES_PER_RESULT: usize = 5;
+
+/// Number of chunks fetched from each ranking per result of a hybrid search
+const HYBRID_CANDIDATES_PER_RESULT: usize = 3;
+
 /// List sites tool handler
 pub struct ListSitesHandler {
     sqlite_db: Arc<SqliteDB>,
@@ -42,14 +67,18 @@ pub struct ListSitesHandler {
 impl SearchDocsHandler {
     /// Create a new search docs handler
     pub fn new(
+        config: Reloadable<Config>,
         sqlite_db: Arc<SqliteDB>,
         vector_store: Arc<VectorStore>,
-        ollama_client: Arc<OllamaClient>,
+        embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+        query_cache: Arc<QueryEmbeddingCache>,
     ) -> Self {
         Self {
+            config,
             sqlite_db,
             vector_store,
-            ollama_client,
+            embedder,
+            query_cache,
         }
     }
 
@@ -57,7 +86,7 @@ impl SearchDocsHandler {
     pub fn tool_definition() -> Tool {
         Tool {
             name: "search_docs".to_string(),
-            description: Some("Search indexed documentation. By default this performs a semantic text search of all indexed documentation. This can optionally be filtered via additional parameters.".to_string()),
+            description: Some("Search indexed documentation. By default this combines a semantic search with a keyword search of all indexed documentation, so both paraphrases and exact identifiers are found. This can optionally be filtered via additional parameters.".to_string()),
             input_schema: json!({
                 "type": "object",
                 "properties": {
@@ -69,13 +98,41 @@ impl SearchDocsHandler {
                         "type": "integer",
                         "description": "Optional: Search specific site by ID (retrieved from `list_sites` tool)"
                     },
+                    "site_ids": {
+                        "type": "array",
+                        "items": { "type": "integer" },
+                        "description": "Optional: Search several sites by ID (retrieved from `list_sites` tool)"
+                    },
                     "sites_filter": {
                         "type": "string",
-                        "description": "Optional: Regex pattern to filter sites by name or URL (e.g., 'docs.rs')"
+                        "description": "Optional: Case-insensitive regex pattern to filter sites by name or URL (e.g., 'docs.rs' or '^tokio'). All matching sites are searched."
+                    },
+                    "site_name": {
+                        "type": "string",
+                        "description": "Optional: Exact name of the site to search (case-insensitive, as shown by `list_sites` tool). Only the version selected by `version` is searched."
+                    },
+                    "version": {
+                        "type": "string",
+                        "description": "Optional: Documentation version to search, as a semver requirement (e.g., '^1.2', '>=0.8, <0.9', '16') or 'latest'. Resolves to the best-matching indexed version of each site (default: 'latest' when `site_name` is given)."
                     },
                     "limit": {
                         "type": "integer",
                         "description": "Optional: Maximum number of results (default: 10)"
+                    },
+                    "group_by_page": {
+                        "type": "boolean",
+                        "description": "Optional: Merge hits from the same page into one result with combined text and the best score, so `limit` counts distinct pages (default: false)"
+                    },
+                    "mode": {
+                        "type": "string",
+                        "enum": SearchMode::NAMES,
+                        "description": "Optional: 'semantic' matches meaning, 'keyword' matches exact terms such as identifiers, function names or error codes, and 'hybrid' merges both rankings (default: 'hybrid')"
+                    },
+                    "min_score": {
+                        "type": "number",
+                        "minimum": 0,
+                        "maximum": 1,
+                        "description": "Optional: Minimum relevance from 0 to 1 of the semantic or keyword match of a chunk. Weaker matches are dropped, so an empty result means nothing relevant is indexed (default: 0)"
                     }
                 },
                 "required": ["query"],
@@ -86,6 +143,336 @@ impl SearchDocsHandler {
     }
 }
 
+impl SearchDocsHandler {
+    /// Generate the embedding for a search query, reusing cached embeddings
+    async fn embed_query(&self, query: &str) -> Result<Vec<f32>> {
+        let embedder = self.embedder.get();
+
+        // Cache by the prefixed query, so that changing the prefix on reload
+        // doesn't return embeddings made with the old one
+        let prompted = embedder.prompt().format_query(query);
+        self.query_cache
+            .get_or_generate(embedder.model(), &prompted, async {
+                Ok(embedder.generate_query_embedding(query).await?.embedding)
+            })
+            .await
+    }
+
+    /// Find the chunks matching `query` in the given sites, reranking them
+    /// when enabled in the configuration
+    ///
+    /// Errors are returned as a message for the client.
+    async fn search(
+        &self,
+        query: &str,
+        mode: SearchMode,
+        limit: usize,
+        min_score: f32,
+        site_filter: Option<&[String]>,
+    ) -> std::result::Result<Vec<SearchResult>, String> {
+        let reranking = self.config.get().reranking.clone();
+        let candidates = rerank_candidates(&reranking, limit);
+        let results = self
+            .retrieve(query, mode, candidates, min_score, site_filter)
+            .await?;
+        if !reranking.enabled || results.is_empty() {
+            return Ok(results);
+        }
+
+        if let Some(model) = reranking.model {
+            let documents: Vec<String> = results.iter().map(rerank_document).collect();
+            let scores = self.embedder.get().rerank(&model, query, &documents).await;
+
+            match scores {
+                Ok(scores) => return Ok(apply_rerank_scores(results, &scores, &model, limit)),
+                Err(e) => warn!(
+                    "Reranking with {} failed, falling back to lexical reranking: {:#}",
+                    model, e
+                ),
+            }
+        }
+
+        let scores = lexical_scores(query, &results);
+        Ok(apply_rerank_scores(
+            results,
+            &scores,
+            LEXICAL_RERANKER,
+            limit,
+        ))
+    }
+
+    /// First-stage search for the chunks matching `query` in the given sites
+    ///
+    /// Semantic and keyword matches scoring below `min_score` are dropped
+    /// before the rankings are merged.
+    async fn retrieve(
+        &self,
+        query: &str,
+        mode: SearchMode,
+        limit: usize,
+        min_score: f32,
+        site_filter: Option<&[String]>,
+    ) -> std::result::Result<Vec<SearchResult>, String> {
+        let candidates = match mode {
+            SearchMode::Hybrid => limit.saturating_mul(HYBRID_CANDIDATES_PER_RESULT),
+            SearchMode::Semantic | SearchMode::Keyword => limit,
+        };
+
+        let mut semantic = if mode.uses_embeddings() {
+            let query_embedding = self.embed_query(query).await.map_err(|e| {
+                error!("Failed to generate embedding for query: {}", e);
+                format!("Failed to generate embedding for query: {}", e)
+            })?;
+            self.vector_store
+                .search_similar(&query_embedding, candidates, site_filter)
+                .await
+                .map_err(|e| {
+                    error!("Error performing search: {}", e);
+                    format!("Search error: {}", e)
+                })?
+        } else {
+            Vec::new()
+        };
+
+        let mut keyword: Vec<SearchResult> = if mode == SearchMode::Semantic {
+            Vec::new()
+        } else {
+            let site_ids: Option<Vec<i64>> = site_filter.map(|site_ids| {
+                site_ids
+                    .iter()
+                    .filter_map(|site_id| site_id.parse().ok())
+                    .collect()
+            });
+            self.sqlite_db
+                .search_chunks(query, candidates as i64, site_ids.as_deref())
+                .await
+                .map_err(|e| {
+                    error!("Error performing keyword search: {}", e);
+                    format!("Search error: {}", e)
+                })?
+                .into_iter()
+                .map(keyword_result)
+                .collect()
+        };
+
+        semantic.retain(|result| result.similarity_score >= min_score);
+        keyword.retain(|result| result.similarity_score >= min_score);
+
+        debug!(
+            "{:?} search found {} semantic and {} keyword matches",
+            mode,
+            semantic.len(),
+            keyword.len()
+        );
+
+        Ok(match mode {
+            SearchMode::Semantic => semantic,
+            SearchMode::Keyword => keyword,
+            SearchMode::Hybrid => reciprocal_rank_fusion(vec![semantic, keyword], limit),
+        })
+    }
+
+    /// Parse the `mode` parameter of a tool call
+    fn mode_from_args(
+        args: &HashMap<String, serde_json::Value>,
+    ) -> std::result::Result<SearchMode, String> {
+        let Some(mode) = args.get("mode").and_then(|v| v.as_str()) else {
+            return Ok(SearchMode::default());
+        };
+        SearchMode::parse(mode).ok_or_else(|| {
+            format!(
+                "Invalid mode '{}'. Expected one of: {}",
+                mode,
+                SearchMode::NAMES.join(", ")
+            )
+        })
+    }
+
+    /// Parse the `min_score` parameter of a tool call
+    fn min_score_from_args(
+        args: &HashMap<String, serde_json::Value>,
+    ) -> std::result::Result<f32, String> {
+        let Some(value) = args.get("min_score") else {
+            return Ok(0.0);
+        };
+        match value.as_f64() {
+            Some(min_score) if (0.0..=1.0).contains(&min_score) => Ok(min_score as f32),
+            _ => Err(format!(
+                "Invalid min_score {}. Expected a number from 0 to 1",
+                value
+            )),
+        }
+    }
+
+    /// Resolve the site filtering parameters into the set of site IDs to search
+    ///
+    /// Returns `None` when the search is not restricted to any sites. `site_id` and
+    /// `site_ids` are combined; when `sites_filter` or `site_name` are also given, only
+    /// the explicit IDs that match them are kept. A `version` (which defaults to
+    /// `latest` when `site_name` is given) narrows each documentation set down to
+    /// its best-matching completed site. Errors are returned as a message for the client.
+    async fn resolve_site_filter(
+        &self,
+        site_id: Option<i64>,
+        site_ids: Option<&[i64]>,
+        sites_filter: Option<&str>,
+        site_name: Option<&str>,
+        version: Option<&str>,
+    ) -> std::result::Result<Option<Vec<String>>, String> {
+        let explicit_ids: Option<Vec<i64>> = match (site_id, site_ids) {
+            (None, None) => None,
+            (site_id, site_ids) => Some(
+                site_id
+                    .into_iter()
+                    .chain(site_ids.unwrap_or_default().iter().copied())
+                    .unique()
+                    .collect(),
+            ),
+        };
+
+        if sites_filter.is_none() && site_name.is_none() && version.is_none() {
+            return Ok(explicit_ids.map(|ids| ids.iter().map(|id| id.to_string()).collect()));
+        }
+
+        let regex = sites_filter
+            .map(|filter_pattern| {
+                RegexBuilder::new(filter_pattern)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| {
+                        format!("Invalid sites_filter pattern '{}': {}", filter_pattern, e)
+                    })
+            })
+            .transpose()?;
+
+        let sites = self.sqlite_db.list_sites().await.map_err(|e| {
+            error!("Error listing sites: {}", e);
+            format!("Error listing sites: {}", e)
+        })?;
+
+        let candidates: Vec<Site> = sites
+            .into_iter()
+            .filter(|site| {
+                explicit_ids
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(&site.id))
+            })
+            .filter(|site| {
+                regex.as_ref().is_none_or(|regex| {
+                    regex.is_match(&site.name).unwrap_or(false)
+                        || regex.is_match(&site.index_url).unwrap_or(false)
+                })
+            })
+            .filter(|site| site_name.is_none_or(|name| site.name.eq_ignore_ascii_case(name.trim())))
+            .collect();
+
+        if candidates.is_empty() {
+            return Err(match (sites_filter, site_name) {
+                (Some(filter_pattern), _) => format!(
+                    "No sites found matching pattern '{}'. Use list_sites tool to see available sites.",
+                    filter_pattern
+                ),
+                (None, Some(name)) => format!(
+                    "No site named '{}'. Use list_sites tool to see available sites.",
+                    name
+                ),
+                (None, None) => {
+                    "No sites found. Use list_sites tool to see available sites.".to_string()
+                }
+            });
+        }
+
+        let Some(version) = version.or_else(|| site_name.map(|_| LATEST_VERSION)) else {
+            let matching_ids: Vec<String> =
+                candidates.iter().map(|site| site.id.to_string()).collect();
+            debug!(
+                "sites_filter {:?} matched site IDs: {:?}",
+                sites_filter, matching_ids
+            );
+            return Ok(Some(matching_ids));
+        };
+
+        // Only fully indexed versions are considered, so a newer version that is
+        // still being indexed doesn't shadow a complete one
+        let completed: Vec<Site> = candidates
+            .into_iter()
+            .filter(|site| site.status == SiteStatus::Completed)
+            .collect();
+        let resolved = resolve_versions(&completed, &VersionRequest::parse(version));
+
+        if resolved.is_empty() {
+            let available = completed
+                .iter()
+                .map(|site| format!("{} {}", site.name, site.version))
+                .join(", ");
+            return Err(if available.is_empty() {
+                format!(
+                    "No completed sites to resolve version '{}' against. Use list_sites tool to see available sites.",
+                    version
+                )
+            } else {
+                format!(
+                    "No indexed version matches '{}'. Available versions: {}",
+                    version, available
+                )
+            });
+        }
+
+        debug!(
+            "Version '{}' resolved to sites: {:?}",
+            version,
+            resolved
+                .iter()
+                .map(|site| format!("{} {} (ID {})", site.name, site.version, site.id))
+                .collect::<Vec<_>>()
+        );
+        Ok(Some(
+            resolved.iter().map(|site| site.id.to_string()).collect(),
+        ))
+    }
+
+    /// Resolve the site filtering parameters of a tool call
+    async fn site_filter_from_args(
+        &self,
+        args: &HashMap<String, serde_json::Value>,
+    ) -> std::result::Result<Option<Vec<String>>, String> {
+        let site_id = args.get("site_id").and_then(|v| v.as_i64());
+        let site_ids = args
+            .get("site_ids")
+            .and_then(|v| v.as_array())
+            .map(|ids| ids.iter().filter_map(|id| id.as_i64()).collect::<Vec<_>>());
+        let sites_filter = args.get("sites_filter").and_then(|v| v.as_str());
+        let site_name = args.get("site_name").and_then(|v| v.as_str());
+        let version = args.get("version").and_then(|v| v.as_str());
+
+        debug!(
+            "Site filter: site_id={:?}, site_ids={:?}, sites_filter={:?}, site_name={:?}, version={:?}",
+            site_id, site_ids, sites_filter, site_name, version
+        );
+
+        self.resolve_site_filter(
+            site_id,
+            site_ids.as_deref(),
+            sites_filter,
+            site_name,
+            version,
+        )
+        .await
+    }
+
+    /// Look up the name and version of a result's site
+    async fn site_info(&self, site_id: &str) -> (String, String) {
+        match self
+            .sqlite_db
+            .get_site_by_id(site_id.parse::<i64>().unwrap_or(0))
+            .await
+        {
+            Ok(Some(site)) => (site.name, site.version),
+            Ok(None) | Err(_) => ("Unknown Site".to_string(), "unknown".to_string()),
+        }
+    }
+}
+
 #[async_trait]
 impl ToolHandler for SearchDocsHandler {
     async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
@@ -97,85 +484,61 @@ impl ToolHandler for SearchDocsHandler {
             .and_then(|v| v.as_str())
             .ok_or_else(|| anyhow!("Missing required parameter: query"))?;
 
-        let site_id = args.get("site_id").and_then(|v| v.as_i64());
-        let sites_filter = args.get("sites_filter").and_then(|v| v.as_str());
-
         let limit = args
             .get("limit")
             .and_then(|v| v.as_i64())
             .unwrap_or(10)
             .max(1) as usize;
+        let group_by_page = args
+            .get("group_by_page")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(false);
 
-        debug!(
-            "Searching docs: query='{}', site_id={:?}, sites_filter={:?}, limit={}",
-            query, site_id, sites_filter, limit
-        );
+        // Fetch extra chunks when grouping so the limit can be filled with distinct pages
+        let search_limit = if group_by_page {
+            limit.saturating_mul(PAGE_GROUP_CANDIDATES_PER_RESULT)
+        } else {
+            limit
+        };
 
-        // Generate embedding for the query text
-        let query_embedding = match self.ollama_client.generate_embedding(query) {
-            Ok(result) => result.embedding,
-            Err(e) => {
-                error!("Failed to generate embedding for query: {}", e);
+        let mode = match Self::mode_from_args(&args) {
+            Ok(mode) => mode,
+            Err(message) => {
                 return Ok(ToolCallResult {
-                    content: vec![ToolContent::Text {
-                        text: format!("Failed to generate embedding for query: {}", e),
-                    }],
+                    content: vec![ToolContent::Text { text: message }],
                     is_error: Some(true),
                 });
             }
         };
 
-        // Handle site filtering - convert site_id or sites_filter to format expected by search_similar
-        let site_filter_id = if let Some(id) = site_id {
-            Some(id.to_string())
-        } else if let Some(filter_pattern) = sites_filter {
-            // For sites_filter, we need to get all sites and filter by regex pattern
-            // For simplicity, we'll match against site names
-            match self.sqlite_db.list_sites().await {
-                Ok(sites) => {
-                    // Simple pattern matching - if pattern is contained in site name or URL
-                    let matching_sites: Vec<_> = sites
-                        .into_iter()
-                        .filter(|site| {
-                            site.name.contains(filter_pattern)
-                                || site.index_url.contains(filter_pattern)
-                        })
-                        .collect();
-
-                    if matching_sites.is_empty() {
-                        return Ok(ToolCallResult {
-                            content: vec![ToolContent::Text {
-                                text: format!(
-                                    "No sites found matching pattern '{}'. Use list_sites tool to see available sites.",
-                                    filter_pattern
-                                ),
-                            }],
-                            is_error: Some(true),
-                        });
-                    }
+        let min_score = match Self::min_score_from_args(&args) {
+            Ok(min_score) => min_score,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
+            }
+        };
 
-                    // For now, just use the first matching site ID
-                    // TODO: Enhance vector search to support multiple site IDs
-                    Some(matching_sites[0].id.to_string())
-                }
-                Err(e) => {
-                    error!("Error listing sites for filter '{}': {}", filter_pattern, e);
-                    return Ok(ToolCallResult {
-                        content: vec![ToolContent::Text {
-                            text: format!("Error listing sites: {}", e),
-                        }],
-                        is_error: Some(true),
-                    });
-                }
+        debug!(
+            "Searching docs: query='{}', limit={}, group_by_page={}, mode={:?}, min_score={}",
+            query, limit, group_by_page, mode, min_score
+        );
+
+        let site_filter = match self.site_filter_from_args(&args).await {
+            Ok(site_filter) => site_filter,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
             }
-        } else {
-            None
         };
 
         // Perform the search
         match self
-            .vector_store
-            .search_similar(&query_embedding, limit, site_filter_id.as_deref())
+            .search(query, mode, search_limit, min_score, site_filter.as_deref())
             .await
         {
             Ok(results) => {
@@ -194,29 +557,51 @@ impl ToolHandler for SearchDocsHandler {
                 // Get site information for results
                 let mut formatted_results = Vec::new();
 
-                for result in results {
-                    // Get site details from SQLite
-                    let site_info = match self
-                        .sqlite_db
-                        .get_site_by_id(result.chunk_metadata.site_id.parse::<i64>().unwrap_or(0))
-                        .await
-                    {
-                        Ok(Some(site)) => (site.name, site.version),
-                        Ok(None) => ("Unknown Site".to_string(), "unknown".to_string()),
-                        Err(_) => ("Unknown Site".to_string(), "unknown".to_string()),
-                    };
-
-                    let result_obj = json!({
-                        "content": result.chunk_metadata.content,
-                        "url": result.chunk_metadata.page_url,
-                        "page_title": result.chunk_metadata.page_title,
-                        "heading_path": result.chunk_metadata.heading_path.unwrap_or_else(|| "N/A".to_string()),
-                        "site_name": site_info.0,
-                        "site_version": site_info.1,
-                        "relevance_score": result.similarity_score
-                    });
+                if group_by_page {
+                    for hit in group_results_by_page(results, limit) {
+                        let site_info = self.site_info(&hit.site_id).await;
+
+                        let mut result_obj = json!({
+                            "chunk_id": hit.chunk_id,
+                            "chunk_index": hit.chunk_index,
+                            "chunk_ids": hit.chunk_ids,
+                            "chunk_indexes": hit.chunk_indexes,
+                            "content": hit.content,
+                            "url": hit.url,
+                            "page_title": hit.page_title,
+                            "heading_path": hit.heading_path.unwrap_or_else(|| "N/A".to_string()),
+                            "site_name": site_info.0,
+                            "site_version": site_info.1,
+                            "relevance_score": hit.similarity_score
+                        });
+                        if let Some(breakdown) = hit.score_breakdown {
+                            result_obj["score_breakdown"] = json!(breakdown);
+                        }
+
+                        formatted_results.push(result_obj);
+                    }
+                } else {
+                    for result in results {
+                        // Get site details from SQLite
+                        let site_info = self.site_info(&result.chunk_metadata.site_id).await;
+
+                        let mut result_obj = json!({
+                            "chunk_id": result.chunk_metadata.chunk_id,
+                            "chunk_index": result.chunk_metadata.chunk_index,
+                            "content": result.chunk_metadata.content,
+                            "url": result.chunk_metadata.page_url,
+                            "page_title": result.chunk_metadata.page_title,
+                            "heading_path": result.chunk_metadata.heading_path.unwrap_or_else(|| "N/A".to_string()),
+                            "site_name": site_info.0,
+                            "site_version": site_info.1,
+                            "relevance_score": result.similarity_score
+                        });
+                        if let Some(breakdown) = result.score_breakdown {
+                            result_obj["score_breakdown"] = json!(breakdown);
+                        }
 
-                    formatted_results.push(result_obj);
+                        formatted_results.push(result_obj);
+                    }
                 }
 
                 let response = json!({
@@ -230,15 +615,10 @@ impl ToolHandler for SearchDocsHandler {
                     is_error: Some(false),
                 })
             }
-            Err(e) => {
-                error!("Error performing search: {}", e);
-                Ok(ToolCallResult {
-                    content: vec![ToolContent::Text {
-                        text: format!("Search error: {}", e),
-                    }],
-                    is_error: Some(true),
-                })
-            }
+            Err(message) => Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: message }],
+                is_error: Some(true),
+            }),
         }
     }
 }
@@ -317,3 +697,782 @@ impl ToolHandler for ListSitesHandler {
         }
     }
 }
+
+/// Get page tool handler
+pub struct GetPageHandler {
+    sqlite_db: Arc<SqliteDB>,
+}
+
+impl GetPageHandler {
+    /// Create a new get page handler
+    pub fn new(sqlite_db: Arc<SqliteDB>) -> Self {
+        Self { sqlite_db }
+    }
+
+    /// Create the get_page tool definition
+    pub fn tool_definition() -> Tool {
+        Tool {
+            name: "get_page".to_string(),
+            description: Some("Get the full content of an indexed documentation page as markdown, along with its heading outline. Use this after `search_docs` when you need more than an isolated chunk of a page.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "URL of the page (as returned in `search_docs` results)"
+                    },
+                    "site_id": {
+                        "type": "integer",
+                        "description": "Optional: ID of the site the page belongs to (retrieved from `list_sites` tool). Required if the URL is indexed under multiple sites."
+                    }
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            }),
+            annotations: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for GetPageHandler {
+    async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
+        let args = params.arguments.unwrap_or_default();
+
+        let url = args
+            .get("url")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing required parameter: url"))?;
+        // Fragments are never part of a stored page URL
+        let url = url.split_once('#').map_or(url, |(base, _)| base);
+
+        let site_id = args.get("site_id").and_then(|v| v.as_i64());
+
+        debug!("Getting page: url='{}', site_id={:?}", url, site_id);
+
+        let chunks = match self.sqlite_db.get_chunks_for_page(site_id, url).await {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                error!("Error loading chunks for page '{}': {}", url, e);
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Error loading page: {}", e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let site_ids: Vec<i64> = chunks.iter().map(|c| c.site_id).unique().collect();
+        if site_ids.len() > 1 {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!(
+                        "Page '{}' is indexed under multiple sites ({}). Specify `site_id` to choose one.",
+                        url,
+                        site_ids.iter().join(", ")
+                    ),
+                }],
+                is_error: Some(true),
+            });
+        }
+
+        let Some(page) = reassemble_page(&chunks) else {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!(
+                        "No indexed page found for URL '{}'. Use search_docs to find page URLs.",
+                        url
+                    ),
+                }],
+                is_error: Some(true),
+            });
+        };
+
+        let site_info = match self.sqlite_db.get_site_by_id(page.site_id).await {
+            Ok(Some(site)) => (site.name, site.version),
+            Ok(None) | Err(_) => ("Unknown Site".to_string(), "unknown".to_string()),
+        };
+
+        let response = json!({
+            "url": page.url,
+            "page_title": page.page_title,
+            "site_id": page.site_id,
+            "site_name": site_info.0,
+            "site_version": site_info.1,
+            "chunk_count": page.chunk_count,
+            "outline": page.outline,
+            "content": page.content
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&response)?,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Default number of neighbouring chunks returned on each side by get_context
+const DEFAULT_CONTEXT_CHUNKS: i64 = 2;
+
+/// Maximum number of neighbouring chunks returned on each side by get_context
+const MAX_CONTEXT_CHUNKS: i64 = 20;
+
+/// Get context tool handler
+pub struct GetContextHandler {
+    sqlite_db: Arc<SqliteDB>,
+}
+
+impl GetContextHandler {
+    /// Create a new get context handler
+    pub fn new(sqlite_db: Arc<SqliteDB>) -> Self {
+        Self { sqlite_db }
+    }
+
+    /// Create the get_context tool definition
+    pub fn tool_definition() -> Tool {
+        Tool {
+            name: "get_context".to_string(),
+            description: Some("Expand a `search_docs` result with the chunks immediately before and after it on the same page. Cheaper than `get_page` when an answer spans a section boundary.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "chunk_id": {
+                        "type": "string",
+                        "description": "ID of the chunk to expand (the `chunk_id` of a `search_docs` result)"
+                    },
+                    "before": {
+                        "type": "integer",
+                        "description": format!("Optional: Number of chunks to include before the chunk (default: {}, max: {})", DEFAULT_CONTEXT_CHUNKS, MAX_CONTEXT_CHUNKS)
+                    },
+                    "after": {
+                        "type": "integer",
+                        "description": format!("Optional: Number of chunks to include after the chunk (default: {}, max: {})", DEFAULT_CONTEXT_CHUNKS, MAX_CONTEXT_CHUNKS)
+                    }
+                },
+                "required": ["chunk_id"],
+                "additionalProperties": false
+            }),
+            annotations: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for GetContextHandler {
+    async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
+        let args = params.arguments.unwrap_or_default();
+
+        let chunk_id = args
+            .get("chunk_id")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing required parameter: chunk_id"))?;
+
+        let before = args
+            .get("before")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(DEFAULT_CONTEXT_CHUNKS)
+            .clamp(0, MAX_CONTEXT_CHUNKS);
+        let after = args
+            .get("after")
+            .and_then(|v| v.as_i64())
+            .unwrap_or(DEFAULT_CONTEXT_CHUNKS)
+            .clamp(0, MAX_CONTEXT_CHUNKS);
+
+        debug!(
+            "Getting context: chunk_id='{}', before={}, after={}",
+            chunk_id, before, after
+        );
+
+        let chunk = match self.sqlite_db.get_chunk_by_vector_id(chunk_id).await {
+            Ok(Some(chunk)) => chunk,
+            Ok(None) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!(
+                            "No chunk found with ID '{}'. Use search_docs to find chunk IDs.",
+                            chunk_id
+                        ),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+            Err(e) => {
+                error!("Error loading chunk '{}': {}", chunk_id, e);
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Error loading chunk: {}", e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let window = match self.sqlite_db.get_chunk_window(&chunk, before, after).await {
+            Ok(window) => window,
+            Err(e) => {
+                error!("Error loading context for chunk '{}': {}", chunk_id, e);
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Error loading context: {}", e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let Some(page) = reassemble_page(&window) else {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!("No context found for chunk '{}'", chunk_id),
+                }],
+                is_error: Some(true),
+            });
+        };
+
+        let site_info = match self.sqlite_db.get_site_by_id(chunk.site_id).await {
+            Ok(Some(site)) => (site.name, site.version),
+            Ok(None) | Err(_) => ("Unknown Site".to_string(), "unknown".to_string()),
+        };
+
+        let chunk_indexes: Vec<i64> = window.iter().map(|c| c.chunk_index).collect();
+
+        let response = json!({
+            "chunk_id": chunk.vector_id,
+            "chunk_index": chunk.chunk_index,
+            "url": page.url,
+            "page_title": page.page_title,
+            "site_id": page.site_id,
+            "site_name": site_info.0,
+            "site_version": site_info.1,
+            "first_chunk_index": chunk_indexes.iter().min(),
+            "last_chunk_index": chunk_indexes.iter().max(),
+            "outline": page.outline,
+            "content": page.content
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&response)?,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Number of chunks retrieved as candidates for build_context
+const BUILD_CONTEXT_CANDIDATES: usize = 50;
+
+/// Search parameters shared by build_context
+const SHARED_SEARCH_PARAMS: [&str; 7] = [
+    "mode",
+    "min_score",
+    "site_id",
+    "site_ids",
+    "sites_filter",
+    "site_name",
+    "version",
+];
+
+/// Build context tool handler
+pub struct BuildContextHandler {
+    search: SearchDocsHandler,
+    /// Counts tokens the way chunks were counted when indexing
+    sizer: Reloadable<ChunkSizer>,
+}
+
+impl BuildContextHandler {
+    /// Create a new build context handler
+    pub fn new(
+        config: Reloadable<Config>,
+        sqlite_db: Arc<SqliteDB>,
+        vector_store: Arc<VectorStore>,
+        embedder: Reloadable<Box<dyn EmbeddingProvider>>,
+        query_cache: Arc<QueryEmbeddingCache>,
+        sizer: Reloadable<ChunkSizer>,
+    ) -> Self {
+        Self {
+            search: SearchDocsHandler::new(config, sqlite_db, vector_store, embedder, query_cache),
+            sizer,
+        }
+    }
+
+    /// Create the build_context tool definition
+    pub fn tool_definition() -> Tool {
+        let mut input_schema = json!({
+            "type": "object",
+            "properties": {
+                "query": {
+                    "type": "string",
+                    "description": "Text string to search for"
+                },
+                "max_tokens": {
+                    "type": "integer",
+                    "description": "Token budget for the returned document"
+                }
+            },
+            "required": ["query", "max_tokens"],
+            "additionalProperties": false
+        });
+
+        // Accept the same search mode and site filters as search_docs
+        let search_schema = SearchDocsHandler::tool_definition().input_schema;
+        for param in SHARED_SEARCH_PARAMS {
+            input_schema["properties"][param] = search_schema["properties"][param].clone();
+        }
+
+        Tool {
+            name: "build_context".to_string(),
+            description: Some("Search indexed documentation and assemble the most relevant sections into a single markdown document with numbered citations, fitting within a token budget. Whole sections are preferred, falling back to the matching chunks and their neighbours when a section is too large. Use this instead of guessing a `limit` for `search_docs`.".to_string()),
+            input_schema,
+            annotations: None,
+        }
+    }
+
+    /// Build context candidates from search results, one per section in order
+    /// of the section's best hit
+    async fn section_candidates(&self, results: Vec<SearchResult>) -> Vec<SectionCandidate> {
+        // Group hits by section in order of first appearance
+        let mut sections: Vec<Vec<SearchResult>> = Vec::new();
+        for result in results {
+            let metadata = &result.chunk_metadata;
+            match sections.iter_mut().find(|section| {
+                let first = &section[0].chunk_metadata;
+                first.site_id == metadata.site_id
+                    && first.page_url == metadata.page_url
+                    && first.heading_path == metadata.heading_path
+            }) {
+                Some(section) => section.push(result),
+                None => sections.push(vec![result]),
+            }
+        }
+
+        let mut pages: HashMap<(String, String), Vec<IndexedChunk>> = HashMap::new();
+        let mut candidates = Vec::with_capacity(sections.len());
+
+        for hits in sections {
+            let first = hits[0].chunk_metadata.clone();
+            let page_key = (first.site_id.clone(), first.page_url.clone());
+            if !pages.contains_key(&page_key) {
+                let site_id = first.site_id.parse::<i64>().unwrap_or(0);
+                let chunks = self
+                    .search
+                    .sqlite_db
+                    .get_chunks_for_page(Some(site_id), &first.page_url)
+                    .await
+                    .unwrap_or_else(|e| {
+                        error!("Error loading chunks for page '{}': {}", first.page_url, e);
+                        Vec::new()
+                    });
+                pages.insert(page_key.clone(), chunks);
+            }
+
+            let section_chunks: Vec<&IndexedChunk> = pages[&page_key]
+                .iter()
+                .filter(|chunk| chunk.heading_path == first.heading_path)
+                .sorted_by_key(|chunk| chunk.chunk_index)
+                .collect();
+            let hit_indexes: Vec<i64> = hits
+                .iter()
+                .map(|hit| i64::from(hit.chunk_metadata.chunk_index))
+                .collect();
+            let join = |include: &dyn Fn(i64) -> bool| {
+                join_chunks(
+                    section_chunks
+                        .iter()
+                        .filter(|chunk| include(chunk.chunk_index))
+                        .map(|chunk| (chunk.chunk_index, chunk.chunk_content.as_str())),
+                )
+            };
+
+            let mut renderings: Vec<String> = Vec::new();
+            for rendering in [
+                join(&|_| true),
+                join(&|index| hit_indexes.iter().any(|hit| (index - hit).abs() <= 1)),
+                join(&|index| hit_indexes.contains(&index)),
+            ] {
+                if !rendering.is_empty() && !renderings.contains(&rendering) {
+                    renderings.push(rendering);
+                }
+            }
+            if renderings.is_empty() {
+                // The chunks are missing from SQLite, so fall back to the search hits
+                renderings.push(join_chunks(
+                    hits.iter()
+                        .sorted_by_key(|hit| hit.chunk_metadata.chunk_index)
+                        .dedup_by(|a, b| {
+                            a.chunk_metadata.chunk_index == b.chunk_metadata.chunk_index
+                        })
+                        .map(|hit| {
+                            (
+                                i64::from(hit.chunk_metadata.chunk_index),
+                                hit.chunk_metadata.content.as_str(),
+                            )
+                        }),
+                ));
+            }
+
+            let site_info = self.search.site_info(&first.site_id).await;
+            candidates.push(SectionCandidate {
+                url: first.page_url,
+                page_title: Some(first.page_title).filter(|t| !t.is_empty()),
+                heading_path: first.heading_path,
+                site_name: site_info.0,
+                site_version: site_info.1,
+                renderings,
+            });
+        }
+
+        candidates
+    }
+}
+
+#[async_trait]
+impl ToolHandler for BuildContextHandler {
+    async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
+        let args = params.arguments.unwrap_or_default();
+
+        let query = args
+            .get("query")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| anyhow!("Missing required parameter: query"))?;
+        let max_tokens = args
+            .get("max_tokens")
+            .and_then(|v| v.as_i64())
+            .ok_or_else(|| anyhow!("Missing required parameter: max_tokens"))?;
+        if max_tokens <= 0 {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: "max_tokens must be a positive number".to_string(),
+                }],
+                is_error: Some(true),
+            });
+        }
+
+        let mode = match SearchDocsHandler::mode_from_args(&args) {
+            Ok(mode) => mode,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let min_score = match SearchDocsHandler::min_score_from_args(&args) {
+            Ok(min_score) => min_score,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        debug!(
+            "Building context: query='{}', max_tokens={}, mode={:?}, min_score={}",
+            query, max_tokens, mode, min_score
+        );
+
+        let site_filter = match self.search.site_filter_from_args(&args).await {
+            Ok(site_filter) => site_filter,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let results = match self
+            .search
+            .search(
+                query,
+                mode,
+                BUILD_CONTEXT_CANDIDATES,
+                min_score,
+                site_filter.as_deref(),
+            )
+            .await
+        {
+            Ok(results) => results,
+            Err(message) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text { text: message }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        if results.is_empty() {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: "No relevant documentation found.".to_string(),
+                }],
+                is_error: Some(false),
+            });
+        }
+
+        let candidates = self.section_candidates(results).await;
+        let context = pack_context(&candidates, max_tokens as usize, &self.sizer.get());
+
+        debug!(
+            "Built context with {} sections ({} omitted), ~{} tokens",
+            context.sections, context.omitted, context.token_count
+        );
+
+        if context.sections == 0 {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!(
+                        "No relevant section fits in a budget of {} tokens. Increase max_tokens.",
+                        max_tokens
+                    ),
+                }],
+                is_error: Some(true),
+            });
+        }
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: context.markdown,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Index site tool handler
+pub struct IndexSiteHandler {
+    jobs: IndexingJobs,
+}
+
+impl IndexSiteHandler {
+    /// Create a new index site handler
+    pub fn new(jobs: IndexingJobs) -> Self {
+        Self { jobs }
+    }
+
+    /// Create the index_site tool definition
+    pub fn tool_definition() -> Tool {
+        Tool {
+            name: "index_site".to_string(),
+            description: Some("Crawl and index a documentation site so it can be searched. Use this when the documentation you need is missing from `list_sites`. Indexing runs in the background and can take several minutes; this returns a job ID immediately, which can be passed to `indexing_status` to follow progress.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "url": {
+                        "type": "string",
+                        "description": "Index URL of the documentation site, e.g. 'https://docs.rs/tokio/latest/tokio/'"
+                    },
+                    "name": {
+                        "type": "string",
+                        "description": "Optional: Name for the site (generated from the URL if omitted)"
+                    },
+                    "version": {
+                        "type": "string",
+                        "description": "Optional: Version of the documentation, e.g. '1.47' (default: 'latest')"
+                    },
+                    "base_url": {
+                        "type": "string",
+                        "description": "Optional: Only pages under this URL are crawled (default: the index URL)"
+                    }
+                },
+                "required": ["url"],
+                "additionalProperties": false
+            }),
+            annotations: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for IndexSiteHandler {
+    async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
+        let args = params.arguments.unwrap_or_default();
+        let string_arg = |name: &str| args.get(name).and_then(|v| v.as_str()).map(String::from);
+
+        let request = IndexSiteRequest {
+            url: string_arg("url").ok_or_else(|| anyhow!("Missing required parameter: url"))?,
+            name: string_arg("name"),
+            version: string_arg("version"),
+            base_url: string_arg("base_url"),
+        };
+
+        debug!("Indexing site: url='{}'", request.url);
+
+        let job = match self.jobs.start(request).await {
+            Ok(job) => job,
+            Err(e) => {
+                error!("Error starting indexing job: {:#}", e);
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Error starting indexing job: {:#}", e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        let message = if job.state == JobState::Completed {
+            "Site is already indexed and can be searched."
+        } else {
+            "Indexing started. Use `indexing_status` with this job ID to follow progress."
+        };
+
+        let response = json!({
+            "job_id": job.id,
+            "site_id": job.site_id,
+            "state": job.state,
+            "message": message
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&response)?,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Indexing status tool handler
+pub struct IndexingStatusHandler {
+    jobs: IndexingJobs,
+    sqlite_db: Arc<SqliteDB>,
+}
+
+impl IndexingStatusHandler {
+    /// Create a new indexing status handler
+    pub fn new(jobs: IndexingJobs, sqlite_db: Arc<SqliteDB>) -> Self {
+        Self { jobs, sqlite_db }
+    }
+
+    /// Create the indexing_status tool definition
+    pub fn tool_definition() -> Tool {
+        Tool {
+            name: "indexing_status".to_string(),
+            description: Some("Get the progress of background indexing jobs started with `index_site`. Reports each job's state along with crawl and embedding progress of its site.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {
+                    "job_id": {
+                        "type": "string",
+                        "description": "Optional: ID of the job to report (returned by `index_site`). All jobs are reported if omitted."
+                    }
+                },
+                "additionalProperties": false
+            }),
+            annotations: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for IndexingStatusHandler {
+    async fn handle(&self, params: CallToolParams) -> Result<ToolCallResult> {
+        let args = params.arguments.unwrap_or_default();
+        let job_id = args.get("job_id").and_then(|v| v.as_str());
+
+        debug!("Getting indexing status: job_id={:?}", job_id);
+
+        let jobs = match job_id {
+            Some(job_id) => match self.jobs.get(job_id) {
+                Some(job) => vec![job],
+                None => {
+                    return Ok(ToolCallResult {
+                        content: vec![ToolContent::Text {
+                            text: format!("No indexing job found with ID '{}'", job_id),
+                        }],
+                        is_error: Some(true),
+                    });
+                }
+            },
+            None => self.jobs.list(),
+        };
+
+        let mut job_list = Vec::with_capacity(jobs.len());
+        for job in jobs {
+            let site = match self.sqlite_db.get_site_by_id(job.site_id).await {
+                Ok(site) => site,
+                Err(e) => {
+                    error!("Error loading site {}: {}", job.site_id, e);
+                    None
+                }
+            };
+
+            job_list.push(json!({
+                "job_id": job.id,
+                "state": job.state,
+                "url": job.url,
+                "site_id": job.site_id,
+                "site_name": site.as_ref().map(|s| &s.name),
+                "site_version": site.as_ref().map(|s| &s.version),
+                "site_status": site.as_ref().map(|s| s.status.to_string().to_lowercase()),
+                "progress_percent": site.as_ref().map(|s| s.progress_percent),
+                "indexed_pages": site.as_ref().map(|s| s.indexed_pages),
+                "total_pages": site.as_ref().map(|s| s.total_pages),
+                "error": job.error.or_else(|| site.and_then(|s| s.error_message)),
+                "started_at": job.started_at.format("%Y-%m-%dT%H:%M:%SZ").to_string(),
+                "finished_at": job.finished_at.map(|d| d.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+            }));
+        }
+
+        let response = json!({
+            "jobs": job_list
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&response)?,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
+
+/// Server statistics tool handler
+pub struct ServerStatsHandler {
+    query_cache: Arc<QueryEmbeddingCache>,
+}
+
+impl ServerStatsHandler {
+    /// Create a new server stats handler
+    pub fn new(query_cache: Arc<QueryEmbeddingCache>) -> Self {
+        Self { query_cache }
+    }
+
+    /// Create the server_stats tool definition
+    pub fn tool_definition() -> Tool {
+        Tool {
+            name: "server_stats".to_string(),
+            description: Some("Get runtime statistics of this MCP server, such as hit and miss counts of the query embedding cache. Intended for debugging.".to_string()),
+            input_schema: json!({
+                "type": "object",
+                "properties": {},
+                "additionalProperties": false
+            }),
+            annotations: None,
+        }
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ServerStatsHandler {
+    async fn handle(&self, _params: CallToolParams) -> Result<ToolCallResult> {
+        debug!("Getting server stats");
+
+        let response = json!({
+            "query_embedding_cache": self.query_cache.stats()
+        });
+
+        Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: serde_json::to_string_pretty(&response)?,
+            }],
+            is_error: Some(false),
+        })
+    }
+}
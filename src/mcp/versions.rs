@@ -0,0 +1,122 @@
+//! Version Resolution
+//!
+//! Resolves a requested documentation version, such as `^1.2`, `>=0.8, <0.9`
+//! or `latest`, against the versions indexed for a site.
+
+use itertools::Itertools;
+use semver::{Version, VersionReq};
+
+use crate::database::sqlite::models::Site;
+
+/// Version string meaning "the newest indexed version"
+pub const LATEST_VERSION: &str = "latest";
+
+/// A requested documentation version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionRequest {
+    /// The newest indexed version
+    Latest,
+    /// The highest indexed version satisfying a semver requirement
+    Requirement(VersionReq),
+    /// A version which is not semver, such as a git hash or release name
+    Exact(String),
+}
+
+impl VersionRequest {
+    /// Parse a requested version
+    ///
+    /// Bare versions follow Cargo's rules, so `1.2` means `^1.2`.
+    pub fn parse(version: &str) -> Self {
+        let version = version.trim();
+        if version.eq_ignore_ascii_case(LATEST_VERSION) {
+            return Self::Latest;
+        }
+
+        let requirement = version
+            .strip_prefix(['v', 'V'])
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(version);
+        VersionReq::parse(requirement)
+            .map_or_else(|_| Self::Exact(version.to_string()), Self::Requirement)
+    }
+
+    /// Whether a site's version satisfies this request, ignoring other versions
+    fn matches(&self, site_version: &str) -> bool {
+        match self {
+            Self::Latest => true,
+            Self::Requirement(requirement) => {
+                parse_site_version(site_version).is_some_and(|v| requirement.matches(&v))
+            }
+            Self::Exact(version) => site_version.trim().eq_ignore_ascii_case(version),
+        }
+    }
+}
+
+/// Parse a site's version string as semver
+///
+/// Versions are free-form, so a leading `v` is ignored and missing minor and
+/// patch components are filled in with zero (`16` becomes `16.0.0`).
+pub fn parse_site_version(version: &str) -> Option<Version> {
+    let version = version.trim();
+    let version = version.strip_prefix(['v', 'V']).unwrap_or(version);
+
+    let (core, suffix) = version.split_at(version.find(['-', '+']).unwrap_or(version.len()));
+    let padded = match core.split('.').count() {
+        1 => format!("{}.0.0{}", core, suffix),
+        2 => format!("{}.0{}", core, suffix),
+        _ => version.to_string(),
+    };
+    Version::parse(&padded).ok()
+}
+
+/// Pick the best-matching site for a version request
+///
+/// All sites are assumed to be versions of the same documentation. A site
+/// whose version is literally `latest` tracks the newest docs, so it wins for
+/// [`VersionRequest::Latest`]; otherwise the highest semver version is picked,
+/// falling back to the most recently indexed site when no versions are semver.
+pub fn resolve_version<'a>(
+    sites: impl IntoIterator<Item = &'a Site>,
+    request: &VersionRequest,
+) -> Option<&'a Site> {
+    let sites: Vec<&Site> = sites.into_iter().collect();
+
+    if *request == VersionRequest::Latest
+        && let Some(site) = sites
+            .iter()
+            .find(|site| site.version.trim().eq_ignore_ascii_case(LATEST_VERSION))
+    {
+        return Some(site);
+    }
+
+    let matching: Vec<&Site> = sites
+        .into_iter()
+        .filter(|site| request.matches(&site.version))
+        .collect();
+
+    matching
+        .iter()
+        .filter_map(|site| parse_site_version(&site.version).map(|version| (version, *site)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, site)| site)
+        .or_else(|| {
+            matching
+                .into_iter()
+                .max_by_key(|site| (site.indexed_date, site.id))
+        })
+}
+
+/// Resolve a version request separately for each documentation set
+///
+/// Sites are grouped by name (case-insensitively) and the best match from each
+/// group is returned, ordered by site ID. Groups without a matching version are
+/// left out.
+pub fn resolve_versions<'a>(sites: &'a [Site], request: &VersionRequest) -> Vec<&'a Site> {
+    sites
+        .iter()
+        .into_group_map_by(|site| site.name.to_lowercase())
+        .into_values()
+        .filter_map(|group| resolve_version(group, request))
+        .sorted_by_key(|site| site.id)
+        .collect()
+}